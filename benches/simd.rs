@@ -0,0 +1,74 @@
+//! Compare the vectorized butterflies of the primitive element types with
+//! the scalar default of the `WalshElement` trait, on transforms from
+//! 2^10 elements, which fit in the L1 cache, to 2^24 elements, which are
+//! bound by memory bandwidth. The scalar loops are themselves
+//! auto-vectorized for the baseline target, so the gain depends heavily on
+//! the CPU and on the width of its vector units.
+//!
+//! Run with `cargo bench --bench simd`.
+
+use std::ops::{Add, Sub};
+use std::time::{Duration, Instant};
+
+use fwt::{Ordering, Plan, WalshElement};
+
+// An `f64` that uses the trait's scalar butterflies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scalar(f64);
+
+impl Add for Scalar {
+    type Output = Scalar;
+    fn add(self, rhs: Scalar) -> Scalar {
+        Scalar(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Scalar;
+    fn sub(self, rhs: Scalar) -> Scalar {
+        Scalar(self.0 - rhs.0)
+    }
+}
+
+impl WalshElement for Scalar {}
+
+// The median time of one transform of `v` over several repetitions.
+fn time<T: WalshElement>(plan: &Plan, v: &mut [T]) -> Duration {
+    let repetitions = (1 << 26) / v.len();
+    let mut times: Vec<Duration> = (0..repetitions.clamp(3, 25))
+        .map(|_| {
+            let start = Instant::now();
+            plan.execute(std::hint::black_box(&mut *v)).unwrap();
+            start.elapsed()
+        })
+        .collect();
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>9} {:>12} {:>12} {:>8}",
+        "length", "ordering", "scalar", "vector", "speedup"
+    );
+    for log_length in (10..=24).step_by(2) {
+        let length = 1usize << log_length;
+        let input: Vec<f64> = (0..length)
+            .map(|i| (i as f64 * 0.618).fract() - 0.5)
+            .collect();
+        for ordering in [Ordering::Hadamard, Ordering::Sequency] {
+            let plan = Plan::new(length, ordering).unwrap();
+            let mut scalar: Vec<Scalar> = input.iter().map(|&x| Scalar(x)).collect();
+            let mut vector = input.clone();
+            let (s, v) = (time(&plan, &mut scalar), time(&plan, &mut vector));
+            println!(
+                "{:>6} {:>9} {:>12.3?} {:>12.3?} {:>7.2}x",
+                format!("2^{}", log_length),
+                format!("{:?}", ordering),
+                s,
+                v,
+                s.as_secs_f64() / v.as_secs_f64()
+            );
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! NEON butterflies for `i16`, `Saturating<i16>`, `i32`, `f32` and `f64`
+//! on aarch64, selected at run time like the x86_64 kernels, with the
+//! scalar loop as the fallback.
+
+use std::arch::aarch64::*;
+use std::num::Saturating;
+
+use crate::WalshElement;
+
+/// The vector instructions the butterflies use on this CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Level {
+    Scalar,
+    Neon,
+}
+
+/// Detect whether NEON is available. It is part of the base aarch64
+/// architecture, but some operating systems can disable it, and detection
+/// results are cached by the standard library, so this is cheap to call.
+pub(crate) fn level() -> Level {
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        Level::Neon
+    } else {
+        Level::Scalar
+    }
+}
+
+// Define unsafe butterflies over `$lanes`-element vectors of `$t` using
+// the given load, store, add and subtract intrinsics, finishing the tail
+// with scalar arithmetic.
+macro_rules! vector_butterfly {
+    ($($name:ident, $t:ty, $lanes:literal, $load:ident, $store:ident, $add:ident, $sub:ident;)*) => {$(
+        /// # Safety
+        ///
+        /// The CPU must support NEON.
+        #[target_feature(enable = "neon")]
+        unsafe fn $name(a: &mut [$t], b: &mut [$t], crossed: bool) {
+            let length = a.len().min(b.len());
+            let split = length - length % $lanes;
+            for i in (0..split).step_by($lanes) {
+                // SAFETY: `i + $lanes <= split <= length`, so both
+                // accesses are in bounds.
+                let (pa, pb) = (a.as_mut_ptr().add(i), b.as_mut_ptr().add(i));
+                let (x, y) = ($load(pa), $load(pb));
+                let (sum, difference) = ($add(x, y), $sub(x, y));
+                let (p, q) = if crossed { (difference, sum) } else { (sum, difference) };
+                $store(pa, p);
+                $store(pb, q);
+            }
+            scalar(&mut a[split..length], &mut b[split..length], crossed);
+        }
+    )*};
+}
+
+vector_butterfly! {
+    f64_neon, f64, 2, vld1q_f64, vst1q_f64, vaddq_f64, vsubq_f64;
+    f32_neon, f32, 4, vld1q_f32, vst1q_f32, vaddq_f32, vsubq_f32;
+    i32_neon, i32, 4, vld1q_s32, vst1q_s32, vaddq_s32, vsubq_s32;
+    i16_neon, i16, 8, vld1q_s16, vst1q_s16, vaddq_s16, vsubq_s16;
+    saturating_i16_neon, Saturating<i16>, 8,
+        load_saturating_i16, store_saturating_i16, vqaddq_s16, vqsubq_s16;
+}
+
+// `Saturating<i16>` is a transparent wrapper of `i16`, loaded and stored
+// as one.
+#[target_feature(enable = "neon")]
+unsafe fn load_saturating_i16(p: *const Saturating<i16>) -> int16x8_t {
+    vld1q_s16(p.cast())
+}
+
+#[target_feature(enable = "neon")]
+unsafe fn store_saturating_i16(p: *mut Saturating<i16>, v: int16x8_t) {
+    vst1q_s16(p.cast(), v)
+}
+
+fn scalar<T: WalshElement>(a: &mut [T], b: &mut [T], crossed: bool) {
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        (*x, *y) = if crossed {
+            (*x - *y, *x + *y)
+        } else {
+            (*x + *y, *x - *y)
+        };
+    }
+}
+
+// Define the run-time dispatching butterfly for one element type.
+macro_rules! dispatch {
+    ($($name:ident, $t:ty, $neon:ident;)*) => {$(
+        pub(crate) fn $name(a: &mut [$t], b: &mut [$t], crossed: bool) {
+            match level() {
+                // SAFETY: NEON was detected on this CPU.
+                Level::Neon => unsafe { $neon(a, b, crossed) },
+                Level::Scalar => scalar(a, b, crossed),
+            }
+        }
+    )*};
+}
+
+dispatch! {
+    butterfly_f64, f64, f64_neon;
+    butterfly_f32, f32, f32_neon;
+    butterfly_i32, i32, i32_neon;
+    butterfly_i16, i16, i16_neon;
+    butterfly_saturating_i16, Saturating<i16>, saturating_i16_neon;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check a kernel against the scalar loop on lengths around the
+    // vector widths.
+    fn check<T>(kernel: unsafe fn(&mut [T], &mut [T], bool), make: fn(usize) -> T)
+    where
+        T: WalshElement + PartialEq + std::fmt::Debug,
+    {
+        for length in [0, 1, 3, 4, 5, 8, 9, 33] {
+            let a: Vec<T> = (0..length).map(make).collect();
+            let b: Vec<T> = (0..length).map(|i| make(3 * i + 1)).collect();
+            for crossed in [false, true] {
+                let (mut x, mut y) = (a.clone(), b.clone());
+                // SAFETY: only called when the CPU supports NEON.
+                unsafe { kernel(&mut x, &mut y, crossed) };
+                let (mut p, mut q) = (a.clone(), b.clone());
+                scalar(&mut p, &mut q, crossed);
+                assert_eq!((x, y), (p, q));
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        if level() == Level::Neon {
+            check(f64_neon, |i| (i as f64 * 0.731).sin() * 1e3);
+            check(f32_neon, |i| (i as f32 * 0.731).sin() * 1e3);
+            check(i32_neon, |i| (i as i32 * 7919) % 1000 - 500);
+            check(i16_neon, |i| (i as i16 * 37) % 1000 - 500);
+            check(saturating_i16_neon, |i| {
+                Saturating((i as i16).wrapping_mul(7919))
+            });
+        }
+        let (mut a, mut b) = (vec![1.5f64; 5], vec![0.5f64; 5]);
+        butterfly_f64(&mut a, &mut b, true);
+        assert_eq!((a[4], b[4]), (1.0, 2.0));
+    }
+}
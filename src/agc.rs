@@ -0,0 +1,256 @@
+//! Automatic gain control applied per sequency band.
+
+use crate::{no_alloc, Error, Ordering, Plan};
+
+/// A streaming automatic gain control that normalizes the level of each
+/// sequency band of successive frames independently.
+///
+/// Every frame is transformed to the sequency domain and the RMS level of
+/// each band's time-domain component is tracked by an envelope follower
+/// that rises by the attack fraction and falls by the release fraction of
+/// the difference per frame. Each band is then scaled towards the target
+/// level, with the gain capped at the maximum gain, and the frame is
+/// transformed back.
+/// Processing a frame does not allocate.
+///
+/// # Example
+///
+/// ```
+/// let mut agc = fwt::BandAgc::dyadic(8).unwrap().with_target(1.0);
+/// let mut frame = [4.0, 4.0, 4.0, 4.0, 4.0, 4.0, 4.0, 4.0];
+/// agc.process(&mut frame).unwrap();
+/// for x in frame {
+///     assert!((x - 1.0).abs() < 1e-12);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BandAgc {
+    plan: Plan,
+    edges: Vec<usize>,
+    target: f64,
+    attack: f64,
+    release: f64,
+    max_gain: f64,
+    envelopes: Vec<f64>,
+    gains: Vec<f64>,
+    primed: bool,
+}
+
+impl BandAgc {
+    /// Create a gain control for frames of the given `length`, with bands
+    /// starting at the sequency indices in `edges`. The edges must start
+    /// at 0 and increase strictly, and each band extends to the next edge
+    /// or to the end of the frame.
+    ///
+    /// The defaults are a target level of 1, attack 0.5, release 0.05 and
+    /// a maximum gain of 1000.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2,
+    /// or [`Error::InvalidBands`] if the edges are not as described.
+    pub fn new(length: usize, edges: Vec<usize>) -> Result<BandAgc, Error> {
+        let plan = Plan::new(length, Ordering::Sequency)?;
+        let valid = edges.first() == Some(&0)
+            && edges.windows(2).all(|w| w[0] < w[1])
+            && edges.last().is_some_and(|&e| e < length);
+        if !valid {
+            return Err(Error::InvalidBands);
+        }
+        let bands = edges.len();
+        Ok(BandAgc {
+            plan,
+            edges,
+            target: 1.0,
+            attack: 0.5,
+            release: 0.05,
+            max_gain: 1000.0,
+            envelopes: vec![0.0; bands],
+            gains: vec![1.0; bands],
+            primed: false,
+        })
+    }
+
+    /// Create a gain control with octave bands: sequency 0, then `[1, 2)`,
+    /// `[2, 4)`, `[4, 8)` and so on up to the frame length.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn dyadic(length: usize) -> Result<BandAgc, Error> {
+        let edges = std::iter::once(0)
+            .chain((0..usize::BITS).map(|b| 1 << b).take_while(|&e| e < length))
+            .collect();
+        BandAgc::new(length, edges)
+    }
+
+    /// Return this gain control with the given target RMS level.
+    pub fn with_target(self, target: f64) -> BandAgc {
+        BandAgc { target, ..self }
+    }
+
+    /// Return this gain control with the given attack and release
+    /// fractions, each in `(0, 1]`, applied per frame to rising and
+    /// falling band levels respectively.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attack` or `release` is not in `(0, 1]`.
+    pub fn with_smoothing(self, attack: f64, release: f64) -> BandAgc {
+        BandAgc {
+            attack: checked_fraction(attack),
+            release: checked_fraction(release),
+            ..self
+        }
+    }
+
+    /// Return this gain control with the given maximum gain.
+    pub fn with_max_gain(self, max_gain: f64) -> BandAgc {
+        BandAgc { max_gain, ..self }
+    }
+
+    /// The frame length.
+    pub fn length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The sequency index at which each band starts.
+    pub fn edges(&self) -> &[usize] {
+        &self.edges
+    }
+
+    /// The gains applied to each band of the most recent frame.
+    pub fn gains(&self) -> &[f64] {
+        &self.gains
+    }
+
+    /// Forget the tracked band levels, so that the next frame sets them
+    /// directly.
+    pub fn reset(&mut self) {
+        self.primed = false;
+        self.gains.fill(1.0);
+    }
+
+    /// Apply the gain control to `frame` in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if the frame length differs from
+    /// the configured length, in which case the frame is unchanged.
+    pub fn process(&mut self, frame: &mut [f64]) -> Result<(), Error> {
+        if frame.len() != self.length() {
+            return Err(Error::LengthMismatch {
+                expected: self.length(),
+                found: frame.len(),
+            });
+        }
+        self.plan.execute(frame)?;
+        no_alloc(|| self.apply_gains(frame));
+        self.plan.execute(frame)?;
+        let n = frame.len() as f64;
+        frame.iter_mut().for_each(|x| *x /= n);
+        Ok(())
+    }
+
+    fn apply_gains(&mut self, spectrum: &mut [f64]) {
+        let n = spectrum.len() as f64;
+        for band in 0..self.edges.len() {
+            let start = self.edges[band];
+            let end = self.edges.get(band + 1).copied().unwrap_or(spectrum.len());
+            let coefficients = &mut spectrum[start..end];
+            // By Parseval, the RMS of the band's time-domain component.
+            let level = coefficients.iter().map(|c| c * c).sum::<f64>().sqrt() / n;
+            let envelope = &mut self.envelopes[band];
+            if !self.primed {
+                *envelope = level;
+            } else if level > *envelope {
+                *envelope += self.attack * (level - *envelope);
+            } else {
+                *envelope += self.release * (level - *envelope);
+            }
+            let gain = if *envelope > 0.0 {
+                (self.target / *envelope).min(self.max_gain)
+            } else {
+                self.max_gain
+            };
+            self.gains[band] = gain;
+            coefficients.iter_mut().for_each(|c| *c *= gain);
+        }
+        self.primed = true;
+    }
+}
+
+fn checked_fraction(fraction: f64) -> f64 {
+    assert!(
+        fraction > 0.0 && fraction <= 1.0,
+        "smoothing fraction {fraction} is not in (0, 1]"
+    );
+    fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bands_are_normalized_independently() {
+        let n = 16;
+        let mut agc = BandAgc::new(n, vec![0, 8]).unwrap().with_target(2.0);
+        // A slow component in the low band and a fast one in the high band.
+        let mut frame: Vec<f64> = (0..n)
+            .map(|i| {
+                3.0 * crate::walsh_row(n, 1, Ordering::Sequency).unwrap()[i] as f64
+                    + 0.5 * crate::walsh_row(n, 15, Ordering::Sequency).unwrap()[i] as f64
+            })
+            .collect();
+        agc.process(&mut frame).unwrap();
+        let spectrum = crate::sequency(&frame).unwrap();
+        // Each band's component now has the target RMS of 2.
+        let expected = 2.0 * n as f64;
+        assert!((spectrum[1] - expected).abs() < 1e-9);
+        assert!((spectrum[15] - expected).abs() < 1e-9);
+        assert!((agc.gains()[0] / agc.gains()[1] - 0.5 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_attack_and_release() {
+        let mut agc = BandAgc::new(4, vec![0]).unwrap().with_smoothing(0.5, 0.1);
+        agc.process(&mut [1.0; 4]).unwrap();
+        assert_eq!(agc.gains(), &[1.0]);
+        // A level jump from 1 to 3 moves the envelope halfway, to 2.
+        agc.process(&mut [3.0; 4]).unwrap();
+        assert!((agc.gains()[0] - 0.5).abs() < 1e-12);
+        // A drop back to 1 moves it a tenth of the way, to 1.9.
+        agc.process(&mut [1.0; 4]).unwrap();
+        assert!((agc.gains()[0] - 1.0 / 1.9).abs() < 1e-12);
+        agc.reset();
+        agc.process(&mut [1.0; 4]).unwrap();
+        assert_eq!(agc.gains(), &[1.0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            BandAgc::new(8, vec![1, 4]).unwrap_err(),
+            Error::InvalidBands
+        );
+        assert_eq!(
+            BandAgc::new(8, vec![0, 4, 4]).unwrap_err(),
+            Error::InvalidBands
+        );
+        assert_eq!(
+            BandAgc::new(8, vec![0, 8]).unwrap_err(),
+            Error::InvalidBands
+        );
+        assert_eq!(BandAgc::dyadic(6).unwrap_err(), Error::NotPowerOfTwo(6));
+        assert_eq!(BandAgc::dyadic(8).unwrap().edges(), &[0, 1, 2, 4]);
+        let mut agc = BandAgc::dyadic(8).unwrap();
+        assert!(agc.process(&mut [1.0; 4]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "not in (0, 1]")]
+    fn test_rejects_nan_smoothing() {
+        BandAgc::dyadic(8).unwrap().with_smoothing(0.5, f64::NAN);
+    }
+}
@@ -0,0 +1,113 @@
+//! Machine-checked allocation freedom for real-time code paths.
+//!
+//! With the `alloc-check` feature enabled, debug builds wrap the bodies
+//! of the allocation-free entry points ([`hadamard_in_place`],
+//! [`sequency_in_place`], [`Plan::execute`], and the frame processors)
+//! in [`assert_no_alloc`]. Installing [`CheckedAllocator`] as the global
+//! allocator of a test or debug binary then turns any allocation inside
+//! those paths into a panic, instead of relying on documentation alone.
+//!
+//! ```
+//! use fwt::alloc_check::CheckedAllocator;
+//! use std::alloc::System;
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CheckedAllocator<System> = CheckedAllocator(System);
+//!
+//! let mut v = vec![1.0; 1024];
+//! fwt::hadamard_in_place(&mut v).unwrap();
+//! ```
+//!
+//! [`hadamard_in_place`]: crate::hadamard_in_place
+//! [`sequency_in_place`]: crate::sequency_in_place
+//! [`Plan::execute`]: crate::Plan::execute
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+
+thread_local! {
+    static FORBIDDEN_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static VIOLATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A global allocator wrapper that records allocations made on a thread
+/// while it is inside [`assert_no_alloc`].
+#[derive(Debug, Default)]
+pub struct CheckedAllocator<A>(pub A);
+
+fn record() {
+    let forbidden = FORBIDDEN_DEPTH.try_with(Cell::get).unwrap_or(0) > 0;
+    if forbidden {
+        let _ = VIOLATIONS.try_with(|v| v.set(v.get() + 1));
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CheckedAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record();
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record();
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record();
+        self.0.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+}
+
+/// Run `f`, panicking afterwards if it allocated on the current thread
+/// while [`CheckedAllocator`] was the global allocator. Without that
+/// allocator installed, no allocations are observed and this only runs
+/// `f`.
+///
+/// # Panics
+///
+/// Panics if `f` allocated.
+pub fn assert_no_alloc<R>(f: impl FnOnce() -> R) -> R {
+    let before = VIOLATIONS.with(Cell::get);
+    FORBIDDEN_DEPTH.with(|d| d.set(d.get() + 1));
+    let result = f();
+    FORBIDDEN_DEPTH.with(|d| d.set(d.get() - 1));
+    let allocations = VIOLATIONS.with(Cell::get) - before;
+    assert!(
+        allocations == 0,
+        "{} allocation(s) in an allocation-free path",
+        allocations
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ordering, Plan};
+    use std::alloc::System;
+
+    #[global_allocator]
+    static ALLOCATOR: CheckedAllocator<System> = CheckedAllocator(System);
+
+    #[test]
+    fn test_hot_paths_do_not_allocate() {
+        let mut v: Vec<f64> = (0..4096).map(|x| x as f64).collect();
+        crate::hadamard_in_place(&mut v).unwrap();
+        crate::sequency_in_place(&mut v).unwrap();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let plan = Plan::new(4096, ordering).unwrap();
+            plan.execute(&mut v).unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "allocation-free path")]
+    fn test_allocation_is_detected() {
+        assert_no_alloc(|| vec![0u8; 16].len());
+    }
+}
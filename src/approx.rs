@@ -0,0 +1,208 @@
+//! Approximate comparison of transforms and spectra.
+
+use crate::{kernel, power_of_2, Ordering};
+
+/// How far apart two values may be and still compare equal with
+/// [`approx_eq`]: values `x` and `y` match when `|x - y|` is at most the
+/// absolute tolerance or at most the relative tolerance times the larger
+/// of `|x|` and `|y|`.
+///
+/// NaN matches nothing, and infinities only match themselves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    absolute: f64,
+    relative: f64,
+}
+
+impl Tolerance {
+    /// A tolerance with the given absolute and relative parts.
+    pub fn new(absolute: f64, relative: f64) -> Tolerance {
+        Tolerance { absolute, relative }
+    }
+
+    /// A purely absolute tolerance.
+    pub fn absolute(absolute: f64) -> Tolerance {
+        Tolerance::new(absolute, 0.0)
+    }
+
+    /// A purely relative tolerance.
+    pub fn relative(relative: f64) -> Tolerance {
+        Tolerance::new(0.0, relative)
+    }
+
+    /// The absolute part of the tolerance.
+    pub fn absolute_part(&self) -> f64 {
+        self.absolute
+    }
+
+    /// The relative part of the tolerance.
+    pub fn relative_part(&self) -> f64 {
+        self.relative
+    }
+
+    /// Whether `x` and `y` are equal within this tolerance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::Tolerance;
+    ///
+    /// let tolerance = Tolerance::new(1e-9, 1e-6);
+    /// assert!(tolerance.matches(1e6, 1e6 + 0.5));
+    /// assert!(tolerance.matches(0.0, 1e-10));
+    /// assert!(!tolerance.matches(1.0, 1.01));
+    /// assert!(!tolerance.matches(f64::NAN, f64::NAN));
+    /// ```
+    pub fn matches(&self, x: f64, y: f64) -> bool {
+        if x == y {
+            return true;
+        }
+        if !x.is_finite() || !y.is_finite() {
+            return false;
+        }
+        let difference = (x - y).abs();
+        difference <= self.absolute || difference <= self.relative * x.abs().max(y.abs())
+    }
+}
+
+/// Whether `a` and `b` have the same length and match element by element
+/// within `tolerance`, for comparing computed transforms with expected
+/// ones where exact equality of floating-point results is too brittle.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Tolerance;
+///
+/// let spectrum = fwt::sequency(&[0.1f32, 0.2, 0.3, 0.4]).unwrap();
+/// assert!(fwt::approx_eq(&spectrum, &[1.0, -0.4, 0.0, -0.2], Tolerance::absolute(1e-6)));
+/// assert!(!fwt::approx_eq(&spectrum, &[1.0, -0.4, 0.0], Tolerance::absolute(1e-6)));
+/// ```
+pub fn approx_eq<T, U>(a: &[T], b: &[U], tolerance: Tolerance) -> bool
+where
+    T: Copy + Into<f64>,
+    U: Copy + Into<f64>,
+{
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(&x, &y)| tolerance.matches(x.into(), y.into()))
+}
+
+/// Whether the spectrum `a` in `a_ordering` and the spectrum `b` in
+/// `b_ordering` have the same coefficient for every Walsh function within
+/// `tolerance`. Spectra in different orderings are compared by matching
+/// each sequency index with the Hadamard index of the same function, so
+/// their lengths must be equal powers of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, Tolerance};
+///
+/// let v = [0.5, 1.5, -2.0, 3.25, 0.0, 1.0, 1.0, -4.0];
+/// let sequency = fwt::sequency(&v).unwrap();
+/// let hadamard = fwt::hadamard(&v).unwrap();
+/// let tolerance = Tolerance::relative(1e-12);
+/// assert!(fwt::approx_eq_spectra(&sequency, Ordering::Sequency, &hadamard, Ordering::Hadamard, tolerance));
+/// assert!(!fwt::approx_eq(&sequency, &hadamard, tolerance));
+/// ```
+pub fn approx_eq_spectra<T, U>(
+    a: &[T],
+    a_ordering: Ordering,
+    b: &[U],
+    b_ordering: Ordering,
+    tolerance: Tolerance,
+) -> bool
+where
+    T: Copy + Into<f64>,
+    U: Copy + Into<f64>,
+{
+    if a_ordering == b_ordering {
+        return approx_eq(a, b, tolerance);
+    }
+    let length = a.len();
+    if b.len() != length || !power_of_2(length) {
+        return false;
+    }
+    let (sequency, hadamard): (Vec<f64>, Vec<f64>) = match a_ordering {
+        Ordering::Sequency => (to_f64(a), to_f64(b)),
+        Ordering::Hadamard => (to_f64(b), to_f64(a)),
+    };
+    sequency.iter().enumerate().all(|(s, &x)| {
+        let y = hadamard[kernel::sequency_to_hadamard(s, length)];
+        tolerance.matches(x, y)
+    })
+}
+
+fn to_f64<T: Copy + Into<f64>>(v: &[T]) -> Vec<f64> {
+    v.iter().map(|&x| x.into()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tolerance_rules() {
+        let absolute = Tolerance::absolute(0.1);
+        assert!(absolute.matches(1.0, 1.0625));
+        assert!(!absolute.matches(1000.0, 1000.2));
+        let relative = Tolerance::relative(1e-3);
+        assert!(relative.matches(1000.0, 1000.9));
+        assert!(!relative.matches(0.0, 1e-300));
+        assert!(relative.matches(-2.0, -2.001));
+        assert!(!relative.matches(2.0, -2.0));
+        assert!(relative.matches(f64::INFINITY, f64::INFINITY));
+        assert!(!relative.matches(f64::INFINITY, f64::NEG_INFINITY));
+        assert!(!Tolerance::absolute(f64::INFINITY).matches(f64::NAN, 0.0));
+        assert_eq!(relative.relative_part(), 1e-3);
+        assert_eq!(relative.absolute_part(), 0.0);
+    }
+
+    #[test]
+    fn test_spectra_across_orderings() {
+        let v: Vec<i32> = (0..64).map(|i| (i * 13) % 7 - 3).collect();
+        let sequency = crate::sequency(&v).unwrap();
+        let hadamard = crate::hadamard(&v).unwrap();
+        let exact = Tolerance::absolute(0.0);
+        for (a, a_ordering, b, b_ordering) in [
+            (&sequency, Ordering::Sequency, &hadamard, Ordering::Hadamard),
+            (&hadamard, Ordering::Hadamard, &sequency, Ordering::Sequency),
+            (&hadamard, Ordering::Hadamard, &hadamard, Ordering::Hadamard),
+        ] {
+            assert!(approx_eq_spectra(a, a_ordering, b, b_ordering, exact));
+        }
+        let mut perturbed: Vec<f32> = hadamard.iter().map(|&c| c as f32).collect();
+        perturbed[5] += 0.5;
+        assert!(!approx_eq_spectra(
+            &sequency,
+            Ordering::Sequency,
+            &perturbed,
+            Ordering::Hadamard,
+            exact
+        ));
+        assert!(approx_eq_spectra(
+            &sequency,
+            Ordering::Sequency,
+            &perturbed,
+            Ordering::Hadamard,
+            Tolerance::absolute(0.5)
+        ));
+        let odd = [1.0; 6];
+        assert!(!approx_eq_spectra(
+            &odd,
+            Ordering::Sequency,
+            &odd,
+            Ordering::Hadamard,
+            exact
+        ));
+        assert!(approx_eq_spectra(
+            &odd,
+            Ordering::Sequency,
+            &odd,
+            Ordering::Sequency,
+            exact
+        ));
+    }
+}
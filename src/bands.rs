@@ -0,0 +1,260 @@
+//! Octave-like summaries of sequency spectra.
+
+use crate::{Error, Ordering, Plan};
+
+/// The content of one band of a frame, as reported by
+/// [`BandAnalyzer::analyze`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandSummary {
+    /// The first sequency in the band.
+    pub start: usize,
+    /// One past the last sequency in the band.
+    pub end: usize,
+    /// The geometric center of the band, `√(start · end)`, or 0 for the
+    /// band holding only sequency 0, in sequency units or, with a sample
+    /// rate, zero crossings per second.
+    pub center: f64,
+    /// A short label for the center: `"DC"` for sequency 0, otherwise the
+    /// center to two or three significant figures, with a `k` or `M`
+    /// suffix for thousands or millions.
+    pub label: String,
+    /// The mean square of the band's time-domain component.
+    pub power: f64,
+    /// [`power`](Self::power) in decibels, `10 log10(power)`, which is
+    /// `-inf` for a silent band.
+    pub level_db: f64,
+    /// The fraction of the frame's total power in the band, or 0 if the
+    /// frame is silent.
+    pub fraction: f64,
+}
+
+/// An analyzer that summarizes the sequency content of frames by bands
+/// doubling in width with sequency, the Walsh counterpart of octave and
+/// constant-Q analysis, giving a compact logarithmic view of a spectrum.
+///
+/// The bands are sequency 0, then the octaves `[1, 2)`, `[2, 4)`,
+/// `[4, 8)` and so on up to the frame length, each optionally split into
+/// several bands whose edges are spaced geometrically, rounded to whole
+/// sequencies. Splits too fine for the integer sequencies of the low
+/// octaves are merged, so low octaves may have fewer bands.
+///
+/// # Example
+///
+/// ```
+/// let analyzer = fwt::BandAnalyzer::new(8).unwrap();
+/// let summary = analyzer.analyze(&[1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0]).unwrap();
+/// let labels: Vec<&str> = summary.iter().map(|band| band.label.as_str()).collect();
+/// assert_eq!(labels, ["DC", "1.41", "2.83", "5.66"]);
+/// // The square wave with three sign changes lies in the band [2, 4).
+/// assert_eq!(summary[2].fraction, 1.0);
+/// assert_eq!(summary[2].level_db, 0.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BandAnalyzer {
+    plan: Plan,
+    bands_per_octave: usize,
+    sample_rate: Option<f64>,
+    edges: Vec<usize>,
+}
+
+impl BandAnalyzer {
+    /// Create an analyzer for frames of `length` samples, with one band
+    /// per octave.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn new(length: usize) -> Result<BandAnalyzer, Error> {
+        let plan = Plan::new(length, Ordering::Sequency)?;
+        Ok(BandAnalyzer {
+            edges: edges(length, 1),
+            plan,
+            bands_per_octave: 1,
+            sample_rate: None,
+        })
+    }
+
+    /// Return this analyzer splitting each octave into
+    /// `bands_per_octave` bands, at least 1.
+    pub fn with_bands_per_octave(self, bands_per_octave: usize) -> BandAnalyzer {
+        let bands_per_octave = bands_per_octave.max(1);
+        BandAnalyzer {
+            edges: edges(self.plan.length(), bands_per_octave),
+            bands_per_octave,
+            ..self
+        }
+    }
+
+    /// Return this analyzer reporting band centers in zero crossings per
+    /// second for frames sampled at `sample_rate` samples per second. A
+    /// frame of `n` samples lasts `n / sample_rate` seconds, so sequency
+    /// `s` is `s · sample_rate / n` crossings per second.
+    pub fn with_sample_rate(self, sample_rate: f64) -> BandAnalyzer {
+        BandAnalyzer {
+            sample_rate: Some(sample_rate),
+            ..self
+        }
+    }
+
+    /// The frame length.
+    pub fn length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The number of bands per octave requested.
+    pub fn bands_per_octave(&self) -> usize {
+        self.bands_per_octave
+    }
+
+    /// The sample rate, if one was given.
+    pub fn sample_rate(&self) -> Option<f64> {
+        self.sample_rate
+    }
+
+    /// The sequency at which each band starts; each band extends to the
+    /// next edge or to the end of the frame.
+    pub fn edges(&self) -> &[usize] {
+        &self.edges
+    }
+
+    /// Summarize the sequency content of `frame` by band.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if the length of `frame` is not
+    /// that of the analyzer.
+    pub fn analyze(&self, frame: &[f64]) -> Result<Vec<BandSummary>, Error> {
+        let mut spectrum = frame.to_vec();
+        self.plan.execute(&mut spectrum)?;
+        let length = self.length();
+        // By Parseval's theorem the mean square of the component made of
+        // the coefficients `c` is the sum of `c²` divided by `n²`.
+        let scale = ((length * length) as f64).recip();
+        let total: f64 = spectrum.iter().map(|c| c * c).sum::<f64>() * scale;
+        let ends = self.edges.iter().skip(1).copied().chain([length]);
+        Ok(self
+            .edges
+            .iter()
+            .zip(ends)
+            .map(|(&start, end)| {
+                let power = spectrum[start..end].iter().map(|c| c * c).sum::<f64>() * scale;
+                let center = self.to_rate(((start * end) as f64).sqrt());
+                BandSummary {
+                    start,
+                    end,
+                    center,
+                    label: if start == 0 {
+                        "DC".to_string()
+                    } else {
+                        label(center)
+                    },
+                    power,
+                    level_db: 10.0 * power.log10(),
+                    fraction: if total == 0.0 { 0.0 } else { power / total },
+                }
+            })
+            .collect())
+    }
+
+    fn to_rate(&self, sequency: f64) -> f64 {
+        match self.sample_rate {
+            Some(rate) => sequency * rate / self.length() as f64,
+            None => sequency,
+        }
+    }
+}
+
+// The band edges for frames of `length`, with `per_octave` bands in each
+// octave.
+fn edges(length: usize, per_octave: usize) -> Vec<usize> {
+    let mut edges = vec![0];
+    let mut low = 1;
+    while low < length {
+        for i in 0..per_octave {
+            let edge = (low as f64 * 2f64.powf(i as f64 / per_octave as f64)).round() as usize;
+            if edge > *edges.last().unwrap() && edge < 2 * low {
+                edges.push(edge);
+            }
+        }
+        low *= 2;
+    }
+    edges
+}
+
+// Format `x` to three significant figures, or two from 100 up, with an
+// SI suffix.
+fn label(x: f64) -> String {
+    let (value, suffix) = if x >= 1e6 {
+        (x / 1e6, "M")
+    } else if x >= 1e3 {
+        (x / 1e3, "k")
+    } else {
+        (x, "")
+    };
+    let decimals = if value >= 100.0 {
+        0
+    } else if value >= 10.0 {
+        1
+    } else {
+        2
+    };
+    format!("{value:.decimals$}{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_band_edges() {
+        assert_eq!(edges(1, 1), [0]);
+        assert_eq!(edges(16, 1), [0, 1, 2, 4, 8]);
+        assert_eq!(edges(32, 3), [0, 1, 2, 3, 4, 5, 6, 8, 10, 13, 16, 20, 25]);
+        let analyzer = BandAnalyzer::new(1024).unwrap().with_bands_per_octave(0);
+        assert_eq!(analyzer.bands_per_octave(), 1);
+        assert_eq!(analyzer.edges().len(), 11);
+    }
+
+    #[test]
+    fn test_powers_sum_to_total() {
+        let frame: Vec<f64> = (0..256).map(|i| ((i * 37) % 23) as f64 - 11.0).collect();
+        let analyzer = BandAnalyzer::new(256).unwrap().with_bands_per_octave(2);
+        let summary = analyzer.analyze(&frame).unwrap();
+        let mean_square = frame.iter().map(|x| x * x).sum::<f64>() / 256.0;
+        let total: f64 = summary.iter().map(|band| band.power).sum();
+        assert!((total - mean_square).abs() < 1e-9 * mean_square);
+        let fractions: f64 = summary.iter().map(|band| band.fraction).sum();
+        assert!((fractions - 1.0).abs() < 1e-12);
+        for pair in summary.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+            assert!(pair[0].center < pair[1].center);
+        }
+        assert_eq!(summary.last().unwrap().end, 256);
+    }
+
+    #[test]
+    fn test_labels_and_rates() {
+        assert_eq!(label(1.414), "1.41");
+        assert_eq!(label(22.6), "22.6");
+        assert_eq!(label(181.0), "181");
+        assert_eq!(label(2828.4), "2.83k");
+        assert_eq!(label(1.5e6), "1.50M");
+        let analyzer = BandAnalyzer::new(1024).unwrap().with_sample_rate(48_000.0);
+        assert_eq!(analyzer.sample_rate(), Some(48_000.0));
+        let summary = analyzer.analyze(&[0.0; 1024]).unwrap();
+        // The top band, [512, 1024), is centered on 724 sequencies, or
+        // 33.9 thousand crossings per second.
+        let top = summary.last().unwrap();
+        assert_eq!(top.label, "33.9k");
+        assert_eq!(top.fraction, 0.0);
+        assert_eq!(top.level_db, f64::NEG_INFINITY);
+        assert_eq!(
+            analyzer.analyze(&[0.0; 8]).unwrap_err(),
+            Error::LengthMismatch {
+                expected: 1024,
+                found: 8
+            }
+        );
+        assert_eq!(BandAnalyzer::new(3).unwrap_err(), Error::NotPowerOfTwo(3));
+    }
+}
@@ -0,0 +1,125 @@
+//! Walsh spectra of Boolean functions given as bit-packed truth tables.
+
+/// Return the Walsh–Hadamard spectrum of the Boolean function of `n_vars`
+/// variables whose truth table is packed into `truth_table`, or `None` if
+/// the table has the wrong number of words.
+///
+/// Bit `x % 64` of word `x / 64` holds `f(x)`. Entry `a` of the result,
+/// in Hadamard (natural) order, is `Σ (-1)^(f(x) ^ popcount(a & x))` over
+/// all inputs `x`, which is the Hadamard transform of the ±1 form of `f`.
+/// A function of fewer than 6 variables occupies the low bits of a single
+/// word.
+///
+/// The spectrum restricted to the 6 variables inside a word is computed
+/// with one XOR and popcount per coefficient, and the remaining variables
+/// are combined with butterflies over whole words, so the ±1 expansion is
+/// never materialized.
+///
+/// # Example
+///
+/// ```
+/// // f(x1, x0) = x0 AND x1
+/// let spectrum = fwt::walsh_spectrum_packed(&[0b1000], 2);
+/// assert_eq!(spectrum, Some(vec![2, 2, 2, -2]));
+/// assert_eq!(fwt::walsh_spectrum_packed(&[0, 0], 6), None);
+/// ```
+pub fn walsh_spectrum_packed(truth_table: &[u64], n_vars: u32) -> Option<Vec<i64>> {
+    let low_vars = n_vars.min(6);
+    let words = 1usize.checked_shl(n_vars - low_vars)?;
+    if truth_table.len() != words {
+        return None;
+    }
+    let per_word = 1usize << low_vars;
+    let mask = if low_vars == 6 {
+        u64::MAX
+    } else {
+        (1u64 << per_word) - 1
+    };
+    let linear = linear_functions();
+    let mut spectrum = Vec::with_capacity(words * per_word);
+    for &word in truth_table {
+        for l in &linear[..per_word] {
+            let disagreements = ((word ^ l) & mask).count_ones() as i64;
+            spectrum.push(per_word as i64 - 2 * disagreements);
+        }
+    }
+    let length = spectrum.len();
+    let mut lag = per_word;
+    while lag < length {
+        let offset = lag << 1;
+        for group in (0..length).step_by(offset) {
+            for j in group..group + lag {
+                let k = j + lag;
+                (spectrum[j], spectrum[k]) = (spectrum[j] + spectrum[k], spectrum[j] - spectrum[k]);
+            }
+        }
+        lag = offset;
+    }
+    Some(spectrum)
+}
+
+// Truth tables of the 64 linear functions x -> popcount(a & x) mod 2 of
+// six variables.
+fn linear_functions() -> [u64; 64] {
+    let mut tables = [0u64; 64];
+    for (a, table) in tables.iter_mut().enumerate() {
+        for x in 0..64 {
+            if (a & x).count_ones() & 1 == 1 {
+                *table |= 1 << x;
+            }
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(truth_table: &[u64], n_vars: u32) -> Vec<i64> {
+        let signs: Vec<i64> = (0..1usize << n_vars)
+            .map(|x| {
+                if truth_table[x / 64] >> (x % 64) & 1 == 1 {
+                    -1
+                } else {
+                    1
+                }
+            })
+            .collect();
+        crate::hadamard(&signs).unwrap()
+    }
+
+    #[test]
+    fn test_matches_expanded_transform() {
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        for n_vars in 0..=10u32 {
+            let words = 1usize << n_vars.saturating_sub(6);
+            let table: Vec<u64> = (0..words)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state
+                })
+                .collect();
+            assert_eq!(
+                walsh_spectrum_packed(&table, n_vars),
+                Some(expanded(&table, n_vars)),
+                "n_vars = {}",
+                n_vars
+            );
+        }
+    }
+
+    #[test]
+    fn test_bent_function_is_flat() {
+        // x0·x1 ^ x2·x3 is bent, so every coefficient has magnitude 4.
+        let table = (0..16).fold(0u64, |t, x: u64| {
+            let f = (x & 1) & (x >> 1 & 1) ^ (x >> 2 & 1) & (x >> 3 & 1);
+            t | f << x
+        });
+        let spectrum = walsh_spectrum_packed(&[table], 4).unwrap();
+        assert!(spectrum.iter().all(|c| c.abs() == 4));
+        assert_eq!(walsh_spectrum_packed(&[], 70), None);
+    }
+}
@@ -0,0 +1,254 @@
+//! A bounded, least-recently-used cache of plans for servers handling
+//! many transform sizes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{Error, Ordering, Plan, WalshElement};
+
+/// Counters describing the activity of a [`PlanCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Requests served by a cached plan.
+    pub hits: u64,
+    /// Requests that had to build a plan.
+    pub misses: u64,
+    /// Plans dropped to stay within the memory budget.
+    pub evictions: u64,
+    /// Plans currently cached.
+    pub entries: usize,
+    /// Bytes currently charged against the budget.
+    pub bytes: usize,
+}
+
+/// A thread-safe cache of [`Plan`]s keyed by length and ordering, holding
+/// at most a fixed number of bytes and evicting the least recently used
+/// plans to make room.
+///
+/// Each plan is charged its [`heap_bytes`](Plan::heap_bytes) plus the
+/// size of the plan itself. A plan larger than the whole budget is built
+/// and returned but never cached. Plans are shared as [`Arc`]s, so an
+/// evicted plan stays valid for callers still holding it.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, PlanCache};
+///
+/// let cache = PlanCache::new(1 << 20);
+/// let mut v = [1.0, 0.0, 0.0, 0.0];
+/// cache.execute(&mut v, Ordering::Sequency).unwrap();
+/// cache.execute(&mut v, Ordering::Sequency).unwrap();
+/// assert_eq!(v, [4.0, 0.0, 0.0, 0.0]);
+/// let stats = cache.stats();
+/// assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+/// ```
+#[derive(Debug)]
+pub struct PlanCache {
+    budget: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<(usize, Ordering), Entry>,
+    clock: u64,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct Entry {
+    plan: Arc<Plan>,
+    bytes: usize,
+    last_used: u64,
+}
+
+impl PlanCache {
+    /// Create an empty cache holding at most `budget` bytes of plans.
+    pub fn new(budget: usize) -> PlanCache {
+        PlanCache {
+            budget,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// The most bytes of plans the cache holds.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Return the plan for transforms of the given length and ordering,
+    /// building and caching it if it is not cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn get(&self, length: usize, ordering: Ordering) -> Result<Arc<Plan>, Error> {
+        let key = (length, ordering);
+        {
+            let mut inner = self.lock();
+            inner.clock += 1;
+            let clock = inner.clock;
+            if let Some(entry) = inner.entries.get_mut(&key) {
+                entry.last_used = clock;
+                let plan = Arc::clone(&entry.plan);
+                inner.stats.hits += 1;
+                return Ok(plan);
+            }
+            inner.stats.misses += 1;
+        }
+        // Build outside the lock, so other sizes are served meanwhile.
+        let plan = Arc::new(Plan::new(length, ordering)?);
+        let bytes = plan.heap_bytes() + std::mem::size_of::<Plan>();
+        if bytes > self.budget {
+            return Ok(plan);
+        }
+        let mut inner = self.lock();
+        if let Some(entry) = inner.entries.get(&key) {
+            // Another thread cached it first.
+            return Ok(Arc::clone(&entry.plan));
+        }
+        while inner.stats.bytes + bytes > self.budget {
+            inner.evict_oldest();
+        }
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.entries.insert(
+            key,
+            Entry {
+                plan: Arc::clone(&plan),
+                bytes,
+                last_used,
+            },
+        );
+        inner.stats.entries += 1;
+        inner.stats.bytes += bytes;
+        Ok(plan)
+    }
+
+    /// Replace the contents of `v` with its transform in the given
+    /// ordering, using the cached plan for its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a
+    /// power of 2.
+    pub fn execute<T>(&self, v: &mut [T], ordering: Ordering) -> Result<(), Error>
+    where
+        T: WalshElement,
+    {
+        self.get(v.len(), ordering)?.execute(v)
+    }
+
+    /// The cache's counters and current occupancy.
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    /// Drop every cached plan, keeping the counters.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.stats.entries = 0;
+        inner.stats.bytes = 0;
+    }
+
+    // A panic while holding the lock cannot leave the cache inconsistent,
+    // since every update is completed before anything can panic.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Inner {
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(&key, _)| key);
+        if let Some(entry) = oldest.and_then(|key| self.entries.remove(&key)) {
+            self.stats.entries -= 1;
+            self.stats.bytes -= entry.bytes;
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charge(length: usize) -> usize {
+        Plan::new(length, Ordering::Sequency).unwrap().heap_bytes() + std::mem::size_of::<Plan>()
+    }
+
+    #[test]
+    fn test_hits_misses_and_sharing() {
+        let cache = PlanCache::new(1 << 20);
+        let a = cache.get(64, Ordering::Sequency).unwrap();
+        let b = cache.get(64, Ordering::Sequency).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        cache.get(64, Ordering::Hadamard).unwrap();
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                evictions: 0,
+                entries: 2,
+                bytes: charge(64) + std::mem::size_of::<Plan>(),
+            }
+        );
+        assert_eq!(
+            cache.get(6, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(6))
+        );
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().bytes, 0);
+        assert_eq!(cache.stats().misses, 3);
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = PlanCache::new(charge(64) + charge(128) + charge(256) - 1);
+        cache.get(64, Ordering::Sequency).unwrap();
+        cache.get(128, Ordering::Sequency).unwrap();
+        cache.get(64, Ordering::Sequency).unwrap();
+        cache.get(256, Ordering::Sequency).unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.evictions, stats.entries), (1, 2));
+        assert_eq!(stats.bytes, charge(64) + charge(256));
+        assert!(stats.bytes <= cache.budget());
+        // 64 was used more recently than 128, so it survived.
+        cache.get(64, Ordering::Sequency).unwrap();
+        assert_eq!(cache.stats().hits, 2);
+
+        let tiny = PlanCache::new(charge(1024) - 1);
+        let plan = tiny.get(1024, Ordering::Sequency).unwrap();
+        assert_eq!(plan.length(), 1024);
+        assert_eq!(tiny.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_concurrent_use() {
+        let cache = PlanCache::new(1 << 16);
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let cache = &cache;
+                scope.spawn(move || {
+                    for i in 0..200 {
+                        let length = 1 << ((i * 7 + t) % 12);
+                        let mut v = vec![1i64; length];
+                        cache.execute(&mut v, Ordering::Sequency).unwrap();
+                        assert_eq!(v[0], length as i64);
+                    }
+                });
+            }
+        });
+        let stats = cache.stats();
+        assert_eq!(stats.hits + stats.misses, 800);
+        assert!(stats.bytes <= cache.budget());
+    }
+}
@@ -0,0 +1,153 @@
+//! Walsh transforms of [`candle_core`] tensors.
+//!
+//! [`WalshOp`] is a candle custom operation that transforms every lane
+//! along the last dimension of a tensor, so a batch of signals stored as
+//! the rows of a matrix (or any higher-rank tensor) is transformed in one
+//! call. The CPU path reads the tensor's storage directly and writes the
+//! result into a single new buffer. Because the Walsh matrices are
+//! symmetric, the backward pass applies the same operation to the
+//! incoming gradient, which makes the op usable inside structured linear
+//! layers such as ACDC or Fastfood.
+//!
+//! Enable with the `candle` feature.
+
+use candle_core::{bail, CpuStorage, CustomOp1, Layout, Shape, Tensor};
+
+use crate::{Ordering, Plan, WalshElement};
+
+/// A candle operation that transforms the last dimension of a tensor.
+///
+/// # Example
+///
+/// ```
+/// use candle_core::{Device, Tensor};
+/// use fwt::candle::WalshOp;
+/// use fwt::Ordering;
+///
+/// let x = Tensor::new(&[[1f32, 0., 0., 0.], [0., 1., 0., 0.]], &Device::Cpu).unwrap();
+/// let y = x.apply_op1(WalshOp::new(Ordering::Hadamard)).unwrap();
+/// assert_eq!(
+///     y.to_vec2::<f32>().unwrap(),
+///     vec![vec![1., 1., 1., 1.], vec![1., -1., 1., -1.]]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalshOp {
+    ordering: Ordering,
+    normalized: bool,
+}
+
+impl WalshOp {
+    /// An unscaled transform with the given ordering.
+    pub fn new(ordering: Ordering) -> WalshOp {
+        WalshOp {
+            ordering,
+            normalized: false,
+        }
+    }
+
+    /// Scale the transform by `1/sqrt(n)`, making it orthonormal and
+    /// therefore its own inverse.
+    pub fn normalized(self) -> WalshOp {
+        WalshOp {
+            normalized: true,
+            ..self
+        }
+    }
+
+    fn apply<T>(
+        &self,
+        data: &[T],
+        layout: &Layout,
+        scale: impl Fn(T, usize) -> T,
+    ) -> candle_core::Result<Vec<T>>
+    where
+        T: WalshElement,
+    {
+        let Some((start, end)) = layout.contiguous_offsets() else {
+            bail!("walsh-hadamard requires a contiguous tensor")
+        };
+        let length = layout.dims().last().copied().unwrap_or(1);
+        let plan = Plan::new(length, self.ordering).map_err(candle_core::Error::msg)?;
+        let mut output = data[start..end].to_vec();
+        for lane in output.chunks_exact_mut(length) {
+            plan.execute(lane).map_err(candle_core::Error::msg)?;
+        }
+        if self.normalized {
+            for x in output.iter_mut() {
+                *x = scale(*x, length);
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl CustomOp1 for WalshOp {
+    fn name(&self) -> &'static str {
+        "walsh-hadamard"
+    }
+
+    fn cpu_fwd(
+        &self,
+        storage: &CpuStorage,
+        layout: &Layout,
+    ) -> candle_core::Result<(CpuStorage, Shape)> {
+        let output = match storage {
+            CpuStorage::F32(data) => {
+                CpuStorage::F32(self.apply(data, layout, |x, n| x / (n as f32).sqrt())?)
+            }
+            CpuStorage::F64(data) => {
+                CpuStorage::F64(self.apply(data, layout, |x, n| x / (n as f64).sqrt())?)
+            }
+            _ => bail!("walsh-hadamard supports only f32 and f64 tensors"),
+        };
+        Ok((output, layout.shape().clone()))
+    }
+
+    fn bwd(
+        &self,
+        _arg: &Tensor,
+        _res: &Tensor,
+        grad_res: &Tensor,
+    ) -> candle_core::Result<Option<Tensor>> {
+        Ok(Some(grad_res.contiguous()?.apply_op1_no_bwd(self)?))
+    }
+}
+
+/// Transform every lane along the last dimension of `tensor`, making the
+/// tensor contiguous first if necessary.
+pub fn walsh_last_dim(tensor: &Tensor, ordering: Ordering) -> candle_core::Result<Tensor> {
+    tensor.contiguous()?.apply_op1(WalshOp::new(ordering))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::{Device, Var};
+
+    #[test]
+    fn test_batched_matches_slices() {
+        let data: Vec<f64> = (0..32).map(|x| ((x * 7 + 3) % 11) as f64).collect();
+        let t = Tensor::from_vec(data.clone(), (2, 2, 8), &Device::Cpu).unwrap();
+        let out = walsh_last_dim(&t, Ordering::Sequency).unwrap();
+        let out = out.flatten_all().unwrap().to_vec1::<f64>().unwrap();
+        for (lane, result) in data.chunks(8).zip(out.chunks(8)) {
+            assert_eq!(crate::sequency(lane).unwrap(), result);
+        }
+        let bad = Tensor::zeros((2, 3), candle_core::DType::F32, &Device::Cpu).unwrap();
+        assert!(walsh_last_dim(&bad, Ordering::Hadamard).is_err());
+    }
+
+    #[test]
+    fn test_backward_is_transform() {
+        let x = Var::new(&[1f64, 2., 3., 4.], &Device::Cpu).unwrap();
+        let y = x
+            .apply_op1(WalshOp::new(Ordering::Sequency).normalized())
+            .unwrap();
+        let weights = Tensor::new(&[1f64, 0., 0., 0.], &Device::Cpu).unwrap();
+        let loss = (y * weights).unwrap().sum_all().unwrap();
+        let grads = loss.backward().unwrap();
+        let g = grads.get(&x).unwrap().to_vec1::<f64>().unwrap();
+        assert_eq!(g, vec![0.5, 0.5, 0.5, 0.5]);
+    }
+}
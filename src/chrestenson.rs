@@ -0,0 +1,158 @@
+//! The Vilenkin–Chrestenson transform, the radix-`p` generalization of
+//! the Walsh–Hadamard transform.
+//!
+//! For a length `n = p^m`, write indices in base `p` as `j = Σ jᵢ pⁱ`.
+//! The Chrestenson functions in natural (Kronecker) ordering are
+//!
+//! `χ_k(j) = ω^(Σ kᵢ jᵢ)`, with `ω = e^(−2πi/p)`,
+//!
+//! the tensor product of `m` copies of the `p`-point DFT matrix. For
+//! `p = 2` they are the Hadamard-ordered Walsh functions. The transform
+//! is computed by the [`Kronecker`] engine in `m` stages of `p`-point
+//! DFTs, for O(*n* *m* *p*) work.
+
+use crate::{Complex, Error, Kronecker};
+
+/// Return the Chrestenson transform of `input` with radix `p`, in natural
+/// ordering.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidRadix`] if `p < 2`, or
+/// [`Error::NotPowerOfRadix`] if the input length is not a power of `p`.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Complex;
+///
+/// // The transform of a ternary impulse at index 1 is 1, ω, ω².
+/// let input = [0.0, 1.0, 0.0].map(Complex::from);
+/// let spectrum = fwt::chrestenson(&input, 3).unwrap();
+/// assert!((spectrum[1].arg() + 2.0 * std::f64::consts::PI / 3.0).abs() < 1e-12);
+///
+/// // Radix 2 is the Hadamard transform.
+/// let v = [1.0, 2.0, 3.0, 4.0].map(Complex::from);
+/// assert_eq!(fwt::chrestenson(&v, 2).unwrap(), fwt::hadamard(&v).unwrap());
+/// ```
+pub fn chrestenson(input: &[Complex], p: usize) -> Result<Vec<Complex>, Error> {
+    transform(input, p, false)
+}
+
+/// Return the inverse Chrestenson transform of `spectrum` with radix `p`,
+/// so that `inverse_chrestenson(chrestenson(v, p), p)` recovers `v` up to
+/// rounding.
+///
+/// # Errors
+///
+/// As for [`chrestenson`].
+///
+/// # Example
+///
+/// ```
+/// use fwt::Complex;
+///
+/// let v: Vec<Complex> = (0..9).map(|i| Complex::new(i as f64, 1.0)).collect();
+/// let spectrum = fwt::chrestenson(&v, 3).unwrap();
+/// let back = fwt::inverse_chrestenson(&spectrum, 3).unwrap();
+/// for (a, b) in back.iter().zip(&v) {
+///     assert!((*a - *b).abs() < 1e-12);
+/// }
+/// ```
+pub fn inverse_chrestenson(spectrum: &[Complex], p: usize) -> Result<Vec<Complex>, Error> {
+    let n = spectrum.len() as f64;
+    let mut v = transform(spectrum, p, true)?;
+    for x in v.iter_mut() {
+        *x = Complex::new(x.re / n, x.im / n);
+    }
+    Ok(v)
+}
+
+fn transform(input: &[Complex], p: usize, inverse: bool) -> Result<Vec<Complex>, Error> {
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let dft = (0..p * p)
+        .map(|i| root_of_unity(i / p * (i % p) % p, p, sign))
+        .collect();
+    let mut v = input.to_vec();
+    Kronecker::new(p, dft)?.apply_in_place(&mut v)?;
+    Ok(v)
+}
+
+// e^(sign·2πik/p), exact at the quarter turns so that radix 2 and 4 need
+// no rounding.
+fn root_of_unity(k: usize, p: usize, sign: f64) -> Complex {
+    if (4 * k).is_multiple_of(p) {
+        match 4 * k / p {
+            0 => Complex::new(1.0, 0.0),
+            1 => Complex::new(0.0, sign),
+            2 => Complex::new(-1.0, 0.0),
+            _ => Complex::new(0.0, -sign),
+        }
+    } else {
+        Complex::from_polar(1.0, sign * 2.0 * std::f64::consts::PI * k as f64 / p as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Direct evaluation of the digit-product definition.
+    fn naive(input: &[Complex], p: usize) -> Vec<Complex> {
+        let n = input.len();
+        (0..n)
+            .map(|k| {
+                input
+                    .iter()
+                    .enumerate()
+                    .fold(Complex::default(), |acc, (j, &x)| {
+                        let (mut a, mut b, mut exponent) = (k, j, 0);
+                        while a > 0 || b > 0 {
+                            exponent += (a % p) * (b % p);
+                            a /= p;
+                            b /= p;
+                        }
+                        let angle = -2.0 * std::f64::consts::PI * (exponent % p) as f64 / p as f64;
+                        acc + x * Complex::from_polar(1.0, angle)
+                    })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_definition() {
+        for (p, n) in [(3, 1), (3, 27), (4, 16), (5, 25), (7, 49)] {
+            let v: Vec<Complex> = (0..n)
+                .map(|i| Complex::new((i % 5) as f64, (i % 3) as f64 - 1.0))
+                .collect();
+            let fast = chrestenson(&v, p).unwrap();
+            for (a, b) in fast.iter().zip(naive(&v, p)) {
+                assert!((*a - b).abs() < 1e-9);
+            }
+            let back = inverse_chrestenson(&fast, p).unwrap();
+            for (a, b) in back.iter().zip(&v) {
+                assert!((*a - *b).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let v = [Complex::default(); 8];
+        assert_eq!(chrestenson(&v, 1), Err(Error::InvalidRadix(1)));
+        assert_eq!(
+            chrestenson(&v, 3),
+            Err(Error::NotPowerOfRadix {
+                length: 8,
+                radix: 3
+            })
+        );
+        assert_eq!(
+            chrestenson(&[], 3),
+            Err(Error::NotPowerOfRadix {
+                length: 0,
+                radix: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Synchronous framing of sample chunks into transformed frames.
+
+use crate::{Error, Ordering, Plan, WalshElement};
+
+/// Transforms a stream of samples delivered in chunks of any size, frame
+/// by frame, for capture loops that hand over whatever samples each
+/// callback or read produced.
+///
+/// Samples are buffered until a whole frame of the plan's length is
+/// available. Successive frames start [`hop`](Self::hop) samples apart,
+/// by default the frame length: a shorter hop overlaps the frames, and a
+/// longer one skips samples between them. The spectra of the frames
+/// completed by each chunk are returned by [`push`](Self::push) as an
+/// iterator, or passed to a callback by [`push_with`](Self::push_with),
+/// which does not allocate once the buffers have grown to their working
+/// size.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{ChunkedFwt, Ordering};
+///
+/// let mut chunked = ChunkedFwt::new(4, Ordering::Sequency).unwrap().with_hop(2);
+/// assert_eq!(chunked.push(&[1, 1, 1]).count(), 0);
+/// let spectra: Vec<Vec<i32>> = chunked.push(&[1, 0, 0, 0]).collect();
+/// assert_eq!(spectra, vec![vec![4, 0, 0, 0], vec![2, 2, 0, 0]]);
+/// assert_eq!(chunked.buffered(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkedFwt<T> {
+    plan: Plan,
+    hop: usize,
+    samples: Vec<T>,
+    // The index in `samples` where the next frame starts, which may be
+    // past the end when the hop skips samples not yet received.
+    start: usize,
+    frame: Vec<T>,
+}
+
+impl<T: WalshElement> ChunkedFwt<T> {
+    /// Create a framer for frames of `frame_length` samples, transformed
+    /// in the given ordering, without overlap.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `frame_length` is not a power
+    /// of 2.
+    pub fn new(frame_length: usize, ordering: Ordering) -> Result<ChunkedFwt<T>, Error> {
+        Ok(ChunkedFwt {
+            plan: Plan::new(frame_length, ordering)?,
+            hop: frame_length,
+            samples: Vec::new(),
+            start: 0,
+            frame: Vec::with_capacity(frame_length),
+        })
+    }
+
+    /// Return this framer starting successive frames `hop` samples apart,
+    /// at least 1.
+    pub fn with_hop(self, hop: usize) -> ChunkedFwt<T> {
+        ChunkedFwt {
+            hop: hop.max(1),
+            ..self
+        }
+    }
+
+    /// The length of each frame.
+    pub fn frame_length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The number of samples between the starts of successive frames.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// The ordering of the spectra.
+    pub fn ordering(&self) -> Ordering {
+        self.plan.ordering()
+    }
+
+    /// The number of samples received from the start of the next frame
+    /// on, which is less than the frame length once the frames pushed
+    /// have been consumed.
+    pub fn buffered(&self) -> usize {
+        self.samples.len().saturating_sub(self.start)
+    }
+
+    /// Discard the buffered samples, so that the next frame starts with
+    /// the next sample pushed.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.start = 0;
+    }
+
+    /// Append `chunk` to the stream and return an iterator over the
+    /// spectra of the frames now complete. Frames the iterator is not
+    /// advanced to stay buffered and are returned after the next push.
+    pub fn push(&mut self, chunk: &[T]) -> ChunkedSpectra<'_, T> {
+        self.append(chunk);
+        ChunkedSpectra { chunked: self }
+    }
+
+    /// Append `chunk` to the stream and call `f` with the spectrum of
+    /// each frame now complete, in order.
+    pub fn push_with<F>(&mut self, chunk: &[T], mut f: F)
+    where
+        F: FnMut(&[T]),
+    {
+        self.append(chunk);
+        while self.next_frame() {
+            f(&self.frame);
+        }
+    }
+
+    fn append(&mut self, chunk: &[T]) {
+        // Drop the samples before the next frame, keeping any skip still
+        // to come.
+        let consumed = self.start.min(self.samples.len());
+        self.samples.drain(..consumed);
+        self.start -= consumed;
+        self.samples.extend_from_slice(chunk);
+    }
+
+    // Transform the next frame into `self.frame` if it is complete.
+    fn next_frame(&mut self) -> bool {
+        let length = self.frame_length();
+        let Some(frame) = self.samples.get(self.start..self.start + length) else {
+            return false;
+        };
+        self.frame.clear();
+        self.frame.extend_from_slice(frame);
+        self.plan
+            .execute(&mut self.frame)
+            .expect("frames have the plan's length");
+        self.start += self.hop;
+        true
+    }
+}
+
+impl<T: WalshElement + Default> ChunkedFwt<T> {
+    /// Return the spectrum of the buffered samples padded with zeros to a
+    /// frame, or `None` if no samples are buffered, and discard them. Use
+    /// at the end of the stream after the complete frames are consumed.
+    pub fn flush(&mut self) -> Option<Vec<T>> {
+        if self.buffered() == 0 {
+            return None;
+        }
+        let mut frame = self.samples[self.start..].to_vec();
+        frame.truncate(self.frame_length());
+        frame.resize(self.frame_length(), T::default());
+        self.plan
+            .execute(&mut frame)
+            .expect("frames have the plan's length");
+        self.reset();
+        Some(frame)
+    }
+}
+
+/// The spectra of the frames completed by a chunk, returned by
+/// [`ChunkedFwt::push`].
+#[derive(Debug)]
+pub struct ChunkedSpectra<'a, T> {
+    chunked: &'a mut ChunkedFwt<T>,
+}
+
+impl<T: WalshElement> Iterator for ChunkedSpectra<'_, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        self.chunked
+            .next_frame()
+            .then(|| self.chunked.frame.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(length: usize) -> Vec<i64> {
+        (0..length as i64).map(|i| (i * 7919) % 101 - 50).collect()
+    }
+
+    #[test]
+    fn test_frames_ignore_chunk_boundaries() {
+        let input = signal(100);
+        let plan = Plan::new(8, Ordering::Sequency).unwrap();
+        for hop in [8, 3, 11] {
+            let expected: Vec<Vec<i64>> = (0..)
+                .map(|k| k * hop)
+                .take_while(|&s| s + 8 <= input.len())
+                .map(|s| plan.transform(&input[s..s + 8]).unwrap())
+                .collect();
+            for chunk in [1, 5, 8, 100] {
+                let mut chunked = ChunkedFwt::new(8, Ordering::Sequency)
+                    .unwrap()
+                    .with_hop(hop);
+                let spectra: Vec<Vec<i64>> = input
+                    .chunks(chunk)
+                    .flat_map(|c| chunked.push(c).collect::<Vec<_>>())
+                    .collect();
+                assert_eq!(spectra, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_callback_matches_iterator() {
+        let input = signal(37);
+        let mut by_iterator = ChunkedFwt::new(4, Ordering::Hadamard).unwrap().with_hop(2);
+        let mut by_callback = by_iterator.clone();
+        let mut expected = Vec::new();
+        let mut spectra = Vec::new();
+        for chunk in input.chunks(6) {
+            expected.extend(by_iterator.push(chunk));
+            by_callback.push_with(chunk, |s| spectra.push(s.to_vec()));
+        }
+        assert_eq!(spectra, expected);
+        assert_eq!(spectra.len(), 17);
+        // An unconsumed frame stays buffered for the next push.
+        let mut chunked = ChunkedFwt::new(2, Ordering::Hadamard).unwrap();
+        assert_eq!(chunked.push(&[1, 2, 3, 4]).next(), Some(vec![3, -1]));
+        assert_eq!(chunked.push(&[5]).collect::<Vec<_>>(), vec![vec![7, -1]]);
+    }
+
+    #[test]
+    fn test_flush_pads_the_final_frame() {
+        let mut chunked = ChunkedFwt::new(4, Ordering::Sequency).unwrap();
+        assert_eq!(chunked.push(&[1, 1, 1, 1, 2]).count(), 1);
+        assert_eq!(chunked.buffered(), 1);
+        assert_eq!(chunked.flush(), Some(vec![2, 2, 2, 2]));
+        assert_eq!(chunked.flush(), None);
+        assert_eq!(
+            ChunkedFwt::<f64>::new(6, Ordering::Sequency).unwrap_err(),
+            Error::NotPowerOfTwo(6)
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Fully unrolled kernels ("codelets") for transforms of up to 64
+//! elements, whose cost in the iterative kernels is mostly loop overhead.
+//!
+//! The codelet for each length splits its array into halves handled by
+//! the codelet of half the length, plus one stage of butterflies between
+//! them. Every length and index is a constant, so after inlining each
+//! codelet is straight-line code. Each element receives the additions of
+//! the radix-2 kernels in the same order, so results are bit-identical to
+//! them.
+
+use crate::{Ordering, WalshElement};
+
+/// The largest length with a codelet.
+pub(crate) const MAX_LENGTH: usize = 64;
+
+/// Replace the contents of `v` with its transform in `ordering`, with
+/// the codelet for its length, which must be a power of 2 no greater than
+/// [`MAX_LENGTH`]. The sequency codelets gather their operands from
+/// bit-reversed positions as they load them, without a separate pass.
+pub(crate) fn transform<T: WalshElement>(v: &mut [T], ordering: Ordering) {
+    match ordering {
+        Ordering::Sequency => dispatch::<T, true>(v, ordering),
+        Ordering::Hadamard => dispatch::<T, false>(v, ordering),
+    }
+}
+
+/// Apply the butterfly stages of `ordering` to `v`, bit-reversed for
+/// sequency, with the codelet for its length, as in [`transform`].
+pub(crate) fn stages<T: WalshElement>(v: &mut [T], ordering: Ordering) {
+    dispatch::<T, false>(v, ordering);
+}
+
+fn dispatch<T: WalshElement, const GATHER: bool>(v: &mut [T], ordering: Ordering) {
+    match v.len() {
+        0 | 1 => {}
+        2 => run::<_, _, GATHER>(v, ordering, codelet_2),
+        4 => run::<_, _, GATHER>(v, ordering, codelet_4),
+        8 => run::<_, _, GATHER>(v, ordering, codelet_8),
+        16 => run::<_, _, GATHER>(v, ordering, codelet_16),
+        32 => run::<_, _, GATHER>(v, ordering, codelet_32),
+        64 => run::<_, _, GATHER>(v, ordering, codelet_64),
+        length => unreachable!("no codelet for length {length}"),
+    }
+}
+
+// Run `codelet` on `v`, or if `GATHER` on a copy of `v` loaded from
+// bit-reversed positions.
+#[inline(always)]
+fn run<T: WalshElement, const N: usize, const GATHER: bool>(
+    v: &mut [T],
+    ordering: Ordering,
+    codelet: impl Fn(&mut [T; N], Ordering, bool),
+) {
+    let v: &mut [T; N] = v.try_into().expect("the length matches the codelet");
+    if GATHER {
+        let reversed = const { reversal::<N>() };
+        let mut x = *v;
+        for (xi, &r) in x.iter_mut().zip(&reversed) {
+            *xi = v[r];
+        }
+        codelet(&mut x, ordering, false);
+        *v = x;
+    } else {
+        codelet(v, ordering, false);
+    }
+}
+
+// The bit-reversal permutation of `0..N`, for a power of 2 `N > 1`.
+const fn reversal<const N: usize>() -> [usize; N] {
+    let mut table = [0; N];
+    let mut i = 0;
+    while i < N {
+        table[i] = i.reverse_bits() >> (usize::BITS - N.trailing_zeros());
+        i += 1;
+    }
+    table
+}
+
+// Butterfly `a[i]` with `b[i]` for every `i`, exchanging the outputs if
+// `crossed`.
+#[inline(always)]
+fn butterfly<T: WalshElement, const N: usize>(a: &mut [T; N], b: &mut [T; N], crossed: bool) {
+    for i in 0..N {
+        let (p, q) = (a[i], b[i]);
+        (a[i], b[i]) = if crossed {
+            (p - q, p + q)
+        } else {
+            (p + q, p - q)
+        };
+    }
+}
+
+#[inline(always)]
+fn codelet_1<T: WalshElement>(_: &mut [T; 1], _: Ordering, _: bool) {}
+
+// Define the codelet `$name` for length `$n` in terms of the codelet
+// `$half` for half the length. Hadamard stages run from the smallest lag,
+// so the halves are transformed first; sequency stages run from the
+// largest, and take the parity of the enclosing group, `odd`, for the
+// first, while the halves are the even and odd groups of the next.
+macro_rules! codelets {
+    ($($name:ident, $half:ident, $n:literal;)*) => {$(
+        #[inline(always)]
+        fn $name<T: WalshElement>(x: &mut [T; $n], ordering: Ordering, odd: bool) {
+            let (low, high) = x.split_at_mut($n / 2);
+            let low: &mut [T; $n / 2] = low.try_into().unwrap();
+            let high: &mut [T; $n / 2] = high.try_into().unwrap();
+            match ordering {
+                Ordering::Hadamard => {
+                    $half(low, ordering, false);
+                    $half(high, ordering, false);
+                    butterfly(low, high, false);
+                }
+                Ordering::Sequency => {
+                    butterfly(low, high, odd);
+                    $half(low, ordering, false);
+                    $half(high, ordering, true);
+                }
+            }
+        }
+    )*};
+}
+
+codelets! {
+    codelet_2, codelet_1, 2;
+    codelet_4, codelet_2, 4;
+    codelet_8, codelet_4, 8;
+    codelet_16, codelet_8, 16;
+    codelet_32, codelet_16, 32;
+    codelet_64, codelet_32, 64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel;
+
+    #[test]
+    fn test_codelets_match_radix2_bit_exact() {
+        let mut length = 1;
+        while length <= MAX_LENGTH {
+            let input: Vec<f64> = (0..length)
+                .map(|x| ((x * 37 + 11) % 101) as f64 / 7.3)
+                .collect();
+            let mut expected = input.clone();
+            kernel::hadamard_from(&mut expected, 1);
+            let mut v = input.clone();
+            stages(&mut v, Ordering::Hadamard);
+            assert!(v
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+
+            let mut expected = input.clone();
+            kernel::bit_reverse(&mut expected);
+            let mut v = expected.clone();
+            kernel::sequency_stages_from(&mut expected, length, 0);
+            stages(&mut v, Ordering::Sequency);
+            assert!(v
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+            length *= 2;
+        }
+        let mut v = [7i16];
+        stages(&mut v, Ordering::Sequency);
+        assert_eq!(v, [7]);
+        for length in [1, 2, 4, 8, 16, 32, 64] {
+            let input: Vec<i32> = (0..length).map(|x| (x * 7919) % 1001 - 500).collect();
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let mut v = input.clone();
+                transform(&mut v, ordering);
+                assert_eq!(v, crate::naive::transform(&input, ordering).unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::{Normalize, WalshElement};
+
+/// A complex number with `f64` parts, as needed by the
+/// [`chrestenson`](crate::chrestenson) transform.
+///
+/// It also implements [`WalshElement`], so complex signals can be passed
+/// to the radix-2 transforms directly.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Complex;
+///
+/// let i = Complex::new(0.0, 1.0);
+/// assert_eq!(i * i, Complex::new(-1.0, 0.0));
+/// assert_eq!(fwt::hadamard(&[i, Complex::new(1.0, 0.0)]).unwrap()[1], Complex::new(-1.0, 1.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    /// The real part.
+    pub re: f64,
+    /// The imaginary part.
+    pub im: f64,
+}
+
+impl Complex {
+    /// Construct `re + im·i`.
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// Construct the complex number with the given magnitude and phase
+    /// in radians.
+    pub fn from_polar(magnitude: f64, phase: f64) -> Complex {
+        let (sin, cos) = phase.sin_cos();
+        Complex::new(magnitude * cos, magnitude * sin)
+    }
+
+    /// The complex conjugate.
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    /// The magnitude `|z|`.
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// The phase in radians, in `(−π, π]`.
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Complex {
+        Complex::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Complex {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl WalshElement for Complex {}
+
+impl Normalize for Complex {
+    fn normalize(self, length: usize) -> Option<Self> {
+        let length = length as f64;
+        Some(Complex::new(self.re / length, self.im / length))
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.im.is_sign_negative() {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Complex::new(1.0, 2.0);
+        let b = Complex::new(3.0, -1.0);
+        assert_eq!(a + b, Complex::new(4.0, 1.0));
+        assert_eq!(a - b, Complex::new(-2.0, 3.0));
+        assert_eq!(a * b, Complex::new(5.0, 5.0));
+        assert_eq!(-a, Complex::new(-1.0, -2.0));
+        assert_eq!(a.conj(), Complex::new(1.0, -2.0));
+        assert_eq!(Complex::new(3.0, 4.0).abs(), 5.0);
+        assert_eq!(a.normalize(2), Some(Complex::new(0.5, 1.0)));
+        assert_eq!(b.to_string(), "3-1i");
+        let z = Complex::from_polar(2.0, 0.5);
+        assert!((z.abs() - 2.0).abs() < 1e-15 && (z.arg() - 0.5).abs() < 1e-15);
+    }
+}
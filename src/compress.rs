@@ -0,0 +1,347 @@
+//! Lossy compression of signals by keeping their largest Walsh
+//! coefficients.
+
+use crate::{kernel, no_alloc, power_of_2, Error, Ordering, Plan};
+
+/// Selected coefficients of a sequency spectrum with their indices, and
+/// the length of the spectrum, as returned by [`compress_topk`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseSpectrum {
+    length: usize,
+    // Sequency indices in increasing order, with their coefficients.
+    entries: Vec<(usize, f64)>,
+}
+
+impl SparseSpectrum {
+    /// The length of the full spectrum and of the signal it describes.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The kept coefficients as `(sequency, coefficient)` pairs, in
+    /// increasing order of sequency. The coefficients are unnormalized,
+    /// as returned by [`sequency`](crate::sequency).
+    pub fn entries(&self) -> &[(usize, f64)] {
+        &self.entries
+    }
+
+    /// Return the full sequency spectrum, with zeros for the coefficients
+    /// not kept.
+    pub fn to_dense(&self) -> Vec<f64> {
+        let mut spectrum = vec![0.0; self.length];
+        for &(s, c) in &self.entries {
+            spectrum[s] = c;
+        }
+        spectrum
+    }
+}
+
+/// Return the `k` coefficients of the sequency spectrum of `v` with the
+/// largest magnitudes, or all of them if `v` has no more than `k`. Ties
+/// are broken in favor of the lower sequency. Since the transform
+/// preserves energy up to a factor of the length, these give the best
+/// approximation of `v` by `k` Walsh functions in the least-squares
+/// sense, rebuilt by [`reconstruct`].
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// let v = [5, 5, 5, 5, 1, 1, 1, 2];
+/// let sparse = fwt::compress_topk(&v, 2).unwrap();
+/// assert_eq!(sparse.entries(), [(0, 25.0), (1, 15.0)]);
+/// let approximation = fwt::reconstruct(&sparse);
+/// assert_eq!(approximation, [5.0, 5.0, 5.0, 5.0, 1.25, 1.25, 1.25, 1.25]);
+/// ```
+pub fn compress_topk<T>(v: &[T], k: usize) -> Result<SparseSpectrum, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let spectrum = crate::spectrum::coefficients(v, Ordering::Sequency)?;
+    let mut order: Vec<usize> = (0..spectrum.len()).collect();
+    by_magnitude(&mut order, &spectrum);
+    order.truncate(k);
+    order.sort_unstable();
+    Ok(SparseSpectrum {
+        length: spectrum.len(),
+        entries: order.into_iter().map(|s| (s, spectrum[s])).collect(),
+    })
+}
+
+/// Return the signal approximated by the coefficients of `sparse`, the
+/// inverse transform of its spectrum with the missing coefficients zero.
+///
+/// # Example
+///
+/// ```
+/// let v = [3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0];
+/// let sparse = fwt::compress_topk(&v, 8).unwrap();
+/// assert_eq!(fwt::reconstruct(&sparse), v);
+/// ```
+pub fn reconstruct(sparse: &SparseSpectrum) -> Vec<f64> {
+    let mut signal = sparse.to_dense();
+    let scale = (sparse.length as f64).recip();
+    signal.iter_mut().for_each(|x| *x *= scale);
+    Plan::new(sparse.length, Ordering::Sequency)
+        .expect("sparse spectra have a power of 2 length")
+        .execute(&mut signal)
+        .expect("the spectrum has the plan's length");
+    signal
+}
+
+/// The order in which [`progressive_order`] sends coefficients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Progression {
+    /// Largest magnitude first, ties by increasing sequency, so that
+    /// every prefix is the best approximation with that many
+    /// coefficients, as from [`compress_topk`].
+    Magnitude,
+    /// Increasing sequency, coarse structure before fine detail, so that
+    /// the receiver needs no indices to place the coefficients.
+    Sequency,
+}
+
+/// Return the sequency spectrum of `v` as `(sequency, coefficient)`
+/// pairs in the order of `progression`, for progressive transmission:
+/// feeding any prefix to a [`ProgressiveDecoder`] reconstructs an
+/// approximation of `v` that improves with every coefficient, and the
+/// whole sequence reconstructs `v`.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{ProgressiveDecoder, Progression};
+///
+/// let v = [5, 5, 5, 5, 1, 1, 1, 2];
+/// let order = fwt::progressive_order(&v, Progression::Magnitude).unwrap();
+/// assert_eq!(&order[..2], [(0, 25.0), (1, 15.0)]);
+/// let mut decoder = ProgressiveDecoder::new(8).unwrap();
+/// decoder.receive_all(&order[..2]);
+/// assert_eq!(decoder.signal(), [5.0, 5.0, 5.0, 5.0, 1.25, 1.25, 1.25, 1.25]);
+/// decoder.receive_all(&order[2..]);
+/// assert_eq!(decoder.signal(), [5.0, 5.0, 5.0, 5.0, 1.0, 1.0, 1.0, 2.0]);
+/// ```
+pub fn progressive_order<T>(v: &[T], progression: Progression) -> Result<Vec<(usize, f64)>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let spectrum = crate::spectrum::coefficients(v, Ordering::Sequency)?;
+    let mut order: Vec<usize> = (0..spectrum.len()).collect();
+    if progression == Progression::Magnitude {
+        by_magnitude(&mut order, &spectrum);
+    }
+    Ok(order.into_iter().map(|s| (s, spectrum[s])).collect())
+}
+
+/// Rebuilds a signal from sequency coefficients arriving one at a time,
+/// keeping the approximation from the coefficients so far up to date.
+///
+/// Each coefficient adds its Walsh function to the signal, in O(n)
+/// operations without allocating, so that an approximation is available
+/// after every coefficient instead of only after a full inverse
+/// transform. See [`progressive_order`].
+#[derive(Debug, Clone)]
+pub struct ProgressiveDecoder {
+    signal: Vec<f64>,
+    received: usize,
+}
+
+impl ProgressiveDecoder {
+    /// Create a decoder for signals of `length` samples, starting from
+    /// silence.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn new(length: usize) -> Result<ProgressiveDecoder, Error> {
+        if !power_of_2(length) {
+            return Err(Error::NotPowerOfTwo(length));
+        }
+        Ok(ProgressiveDecoder {
+            signal: vec![0.0; length],
+            received: 0,
+        })
+    }
+
+    /// The length of the signal.
+    pub fn length(&self) -> usize {
+        self.signal.len()
+    }
+
+    /// The number of coefficients received.
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// The approximation of the signal from the coefficients received.
+    pub fn signal(&self) -> &[f64] {
+        &self.signal
+    }
+
+    /// Add the unnormalized `coefficient` of sequency `s` to the signal
+    /// and return the new approximation. A coefficient received twice is
+    /// added twice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not less than the length.
+    pub fn receive(&mut self, s: usize, coefficient: f64) -> &[f64] {
+        let length = self.length();
+        assert!(s < length, "sequency {s} out of range for length {length}");
+        let row = kernel::sequency_to_hadamard(s, length);
+        let amplitude = coefficient / length as f64;
+        no_alloc(|| {
+            for (j, x) in self.signal.iter_mut().enumerate() {
+                if (row & j).count_ones().is_multiple_of(2) {
+                    *x += amplitude;
+                } else {
+                    *x -= amplitude;
+                }
+            }
+        });
+        self.received += 1;
+        &self.signal
+    }
+
+    /// Receive each `(sequency, coefficient)` pair of `entries` in turn,
+    /// and return the new approximation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a sequency is not less than the length.
+    pub fn receive_all(&mut self, entries: &[(usize, f64)]) -> &[f64] {
+        for &(s, c) in entries {
+            self.receive(s, c);
+        }
+        &self.signal
+    }
+
+    /// Return to silence, as if the decoder were new.
+    pub fn reset(&mut self) {
+        self.signal.fill(0.0);
+        self.received = 0;
+    }
+}
+
+// Sort sequencies by decreasing magnitude of their coefficients, ties by
+// increasing sequency.
+fn by_magnitude(order: &mut [usize], spectrum: &[f64]) {
+    order.sort_by(|&a, &b| {
+        spectrum[b]
+            .abs()
+            .total_cmp(&spectrum[a].abs())
+            .then(a.cmp(&b))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(length: usize) -> Vec<f64> {
+        (0..length)
+            .map(|i| ((i * 7919) % 101) as f64 - 50.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_topk_keeps_largest_coefficients() {
+        let v = signal(64);
+        let spectrum = crate::sequency(&v).unwrap();
+        let sparse = compress_topk(&v, 10).unwrap();
+        assert_eq!(sparse.length(), 64);
+        assert_eq!(sparse.entries().len(), 10);
+        assert!(sparse.entries().windows(2).all(|w| w[0].0 < w[1].0));
+        let smallest_kept = sparse
+            .entries()
+            .iter()
+            .map(|(_, c)| c.abs())
+            .fold(f64::INFINITY, f64::min);
+        let dense = sparse.to_dense();
+        for (s, &c) in spectrum.iter().enumerate() {
+            if dense[s] == 0.0 {
+                assert!(c.abs() <= smallest_kept);
+            } else {
+                assert_eq!(dense[s], c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_falls_as_k_grows() {
+        let v = signal(128);
+        let error = |k: usize| -> f64 {
+            let approximation = reconstruct(&compress_topk(&v, k).unwrap());
+            approximation
+                .iter()
+                .zip(&v)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum()
+        };
+        let errors: Vec<f64> = [0, 1, 4, 16, 64, 128].into_iter().map(error).collect();
+        assert!(errors.windows(2).all(|w| w[1] <= w[0]));
+        assert!(errors[5] < 1e-18);
+        // The error is the energy of the dropped coefficients.
+        let spectrum = crate::sequency(&v).unwrap();
+        let kept = compress_topk(&v, 16).unwrap().to_dense();
+        let dropped: f64 = spectrum
+            .iter()
+            .zip(&kept)
+            .map(|(c, k)| (c - k).powi(2))
+            .sum();
+        assert!((errors[3] - dropped / 128.0).abs() < 1e-9 * dropped);
+    }
+
+    #[test]
+    fn test_progressive_prefixes_improve() {
+        let v = signal(64);
+        let error = |a: &[f64]| -> f64 { a.iter().zip(&v).map(|(a, b)| (a - b).powi(2)).sum() };
+        for progression in [Progression::Magnitude, Progression::Sequency] {
+            let order = progressive_order(&v, progression).unwrap();
+            let mut decoder = ProgressiveDecoder::new(64).unwrap();
+            let mut last = error(decoder.signal());
+            for (i, &(s, c)) in order.iter().enumerate() {
+                let now = error(decoder.receive(s, c));
+                assert!(now <= last + 1e-9);
+                last = now;
+                if progression == Progression::Magnitude {
+                    let best = reconstruct(&compress_topk(&v, i + 1).unwrap());
+                    assert!((now - error(&best)).abs() < 1e-6);
+                } else {
+                    assert_eq!(s, i);
+                }
+            }
+            assert!(last < 1e-18);
+            assert_eq!(decoder.received(), 64);
+            decoder.reset();
+            assert_eq!((decoder.received(), decoder.signal()), (0, &[0.0; 64][..]));
+        }
+        assert_eq!(
+            ProgressiveDecoder::new(10).unwrap_err(),
+            Error::NotPowerOfTwo(10)
+        );
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(compress_topk(&[1.0, 2.0], 5).unwrap().entries().len(), 2);
+        let empty = compress_topk(&[4, 2], 0).unwrap();
+        assert_eq!(reconstruct(&empty), [0.0, 0.0]);
+        // Equal magnitudes favor the lower sequency.
+        let ties = compress_topk(&[1, 0, 0, 0], 2).unwrap();
+        assert_eq!(ties.entries(), [(0, 1.0), (1, 1.0)]);
+        assert_eq!(
+            compress_topk(&[1.0; 3], 1).unwrap_err(),
+            Error::NotPowerOfTwo(3)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Low-latency streaming dyadic filtering with long kernels.
+
+use crate::{no_alloc, Ordering, Plan};
+
+/// A streaming filter that applies a kernel of any length to a signal in
+/// blocks no longer than a maximum latency, by partitioned dyadic
+/// convolution.
+///
+/// The stream is cut into blocks of `B` samples, the largest power of 2
+/// no greater than the maximum latency plus 1 or, if smaller, the kernel
+/// length rounded up to a power of 2, and the kernel into
+/// partitions `h_0, h_1, …` of `B` samples, the last padded with zeros.
+/// Output block `j` is the sum over the partitions of the
+/// [dyadic convolution](crate::dyadic_convolution) of `h_p` with input
+/// block `j - p`, so a kernel no longer than a block filters each block
+/// independently, and each later partition adds the response to an
+/// earlier block. With a block of 1 sample this is ordinary linear
+/// convolution, and with a block as long as the kernel it is the dyadic
+/// convolution of successive frames.
+///
+/// The head partition is applied as each block completes, while the tail
+/// partitions are accumulated in the Walsh domain from the spectra of
+/// earlier blocks, kept in a delay line, before the block arrives. The
+/// work per block is two transforms of `B` samples and one product per
+/// partition, so the cost per sample grows with the kernel length over
+/// the block length. Every partition has the head's length: the block
+/// length fixes which samples the dyadic products combine, so longer tail
+/// blocks would change the output rather than only the cost.
+///
+/// The output lags the input by [`latency`](Self::latency) samples, `B -
+/// 1`. Processing does not allocate.
+///
+/// # Example
+///
+/// ```
+/// // An echo of 3 samples delay, at half the level, within a latency of 1.
+/// let mut convolver = fwt::PartitionedConvolver::new(&[1.0, 0.0, 0.0, 0.5], 1);
+/// assert_eq!(convolver.block_length(), 2);
+/// assert_eq!(convolver.latency(), 1);
+/// let mut samples = [1.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+/// convolver.process(&mut samples);
+/// // The impulse comes out 1 sample late and its echo 3 samples after it.
+/// assert_eq!(samples, [0.0, 1.0, 0.0, 0.0, 0.5, 0.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PartitionedConvolver {
+    plan: Plan,
+    // The Hadamard spectra of the kernel partitions, divided by the block
+    // length so that the inverse transform needs no scaling.
+    partitions: Vec<Vec<f64>>,
+    // The spectra of the latest input blocks, the newest at `newest`.
+    history: Vec<Vec<f64>>,
+    newest: usize,
+    // The tail partitions' contribution to the spectrum of the next block.
+    tail: Vec<f64>,
+    input: Vec<f64>,
+    output: Vec<f64>,
+    position: usize,
+}
+
+impl PartitionedConvolver {
+    /// Create a convolver for `kernel` whose output lags its input by at
+    /// most `max_latency` samples. An empty kernel silences the stream.
+    ///
+    /// The maximum latency chooses the block length, and the block length
+    /// decides which samples each dyadic product combines, so **the
+    /// output depends on `max_latency`**, not only its delay and cost: a
+    /// kernel longer than a block filters the signal differently for each
+    /// block length. Keep `max_latency` fixed wherever outputs must match.
+    /// Latencies of at least the kernel length rounded up to a power of 2,
+    /// less 1, all give that block length and the same filter.
+    pub fn new(kernel: &[f64], max_latency: usize) -> PartitionedConvolver {
+        let widest = 1 << (usize::BITS - 1 - max_latency.saturating_add(1).leading_zeros());
+        let length = widest.min(kernel.len().next_power_of_two());
+        let plan = Plan::new(length, Ordering::Hadamard).expect("the block length is a power of 2");
+        let scale = (length as f64).recip();
+        let mut partitions: Vec<Vec<f64>> = kernel
+            .chunks(length)
+            .map(|chunk| {
+                let mut spectrum = chunk.to_vec();
+                spectrum.resize(length, 0.0);
+                plan.execute(&mut spectrum)
+                    .expect("partitions fill a block");
+                spectrum.iter_mut().for_each(|x| *x *= scale);
+                spectrum
+            })
+            .collect();
+        if partitions.is_empty() {
+            partitions.push(vec![0.0; length]);
+        }
+        let history = vec![vec![0.0; length]; partitions.len()];
+        PartitionedConvolver {
+            plan,
+            partitions,
+            history,
+            newest: 0,
+            tail: vec![0.0; length],
+            input: vec![0.0; length],
+            output: vec![0.0; length],
+            position: 0,
+        }
+    }
+
+    /// The block length `B`, the length of every kernel partition.
+    pub fn block_length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The number of kernel partitions, the head included.
+    pub fn partitions(&self) -> usize {
+        self.partitions.len()
+    }
+
+    /// The number of samples by which the output lags the input, one less
+    /// than the block length.
+    pub fn latency(&self) -> usize {
+        self.block_length() - 1
+    }
+
+    /// Forget the input so far, as if the convolver were new.
+    pub fn reset(&mut self) {
+        for spectrum in &mut self.history {
+            spectrum.fill(0.0);
+        }
+        self.tail.fill(0.0);
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.position = 0;
+    }
+
+    /// Replace each sample of `samples`, the next part of the stream, of
+    /// any length, with the output [`latency`](Self::latency) samples
+    /// earlier.
+    pub fn process(&mut self, samples: &mut [f64]) {
+        no_alloc(|| {
+            for x in samples {
+                self.input[self.position] = *x;
+                self.position += 1;
+                if self.position == self.input.len() {
+                    self.position = 0;
+                    self.complete_block();
+                }
+                *x = self.output[self.position];
+            }
+        })
+    }
+
+    // Compute the output of the block just completed, then the tail of the
+    // next one.
+    fn complete_block(&mut self) {
+        let count = self.partitions.len();
+        self.newest = (self.newest + 1) % count;
+        let spectrum = &mut self.history[self.newest];
+        spectrum.copy_from_slice(&self.input);
+        self.plan.execute(spectrum).expect("blocks fill the plan");
+        for ((y, &h), (&x, &t)) in self
+            .output
+            .iter_mut()
+            .zip(&self.partitions[0])
+            .zip(spectrum.iter().zip(&self.tail))
+        {
+            *y = h * x + t;
+        }
+        self.plan
+            .execute(&mut self.output)
+            .expect("blocks fill the plan");
+        // The next block's tail pairs partition p with the block p - 1
+        // before the one just completed.
+        self.tail.fill(0.0);
+        for (p, partition) in self.partitions.iter().enumerate().skip(1) {
+            let block = &self.history[(self.newest + count + 1 - p) % count];
+            for ((t, &h), &x) in self.tail.iter_mut().zip(partition).zip(block) {
+                *t += h * x;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The output of a convolver with blocks of `length` samples, computed
+    // directly from its definition without delay.
+    fn reference(kernel: &[f64], input: &[f64], length: usize) -> Vec<f64> {
+        let pad = |v: &[f64]| {
+            let mut v = v.to_vec();
+            v.resize(v.len().next_multiple_of(length), 0.0);
+            v
+        };
+        let (kernel, input) = (pad(kernel), pad(input));
+        let mut output = vec![0.0; input.len()];
+        for (j, out) in output.chunks_mut(length).enumerate() {
+            for (p, h) in kernel.chunks(length).enumerate().take(j + 1) {
+                let x = &input[(j - p) * length..(j - p + 1) * length];
+                let y = crate::dyadic_convolution(h, x).unwrap();
+                out.iter_mut().zip(y).for_each(|(o, y)| *o += y);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_matches_partitioned_definition() {
+        let kernel: Vec<f64> = (0..37).map(|i| ((i * 13) % 7) as f64 - 3.0).collect();
+        let input: Vec<f64> = (0..200).map(|i| ((i * 29) % 11) as f64 - 5.0).collect();
+        for max_latency in [0, 1, 6, 7, 40, 100] {
+            let mut convolver = PartitionedConvolver::new(&kernel, max_latency);
+            let length = convolver.block_length();
+            assert!(convolver.latency() <= max_latency);
+            assert!(2 * length > max_latency + 1);
+            assert_eq!(convolver.partitions(), 37usize.div_ceil(length));
+            let mut output = input.clone();
+            // Feed the stream in uneven pieces.
+            let (a, b) = output.split_at_mut(13);
+            convolver.process(a);
+            convolver.process(b);
+            let expected = reference(&kernel, &input, length);
+            let delay = convolver.latency();
+            assert!(output[..delay].iter().all(|&y| y == 0.0));
+            for (y, e) in output[delay..].iter().zip(&expected) {
+                assert!((y - e).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_sample_blocks_convolve_linearly() {
+        let mut convolver = PartitionedConvolver::new(&[1.0, 2.0, 3.0], 0);
+        assert_eq!(convolver.latency(), 0);
+        let mut samples = [1.0, 1.0, 0.0, 0.0, 0.0];
+        convolver.process(&mut samples);
+        assert_eq!(samples, [1.0, 3.0, 5.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn test_reset_and_empty_kernel() {
+        let mut convolver = PartitionedConvolver::new(&[0.5; 20], 3);
+        let mut first = [1.0, -2.0, 3.0, 0.5, 2.0, 1.0, 0.0, 4.0, 1.0];
+        let mut second = first;
+        convolver.process(&mut first);
+        convolver.reset();
+        convolver.process(&mut second);
+        assert_eq!(first, second);
+        let mut silent = PartitionedConvolver::new(&[], usize::MAX);
+        assert_eq!(silent.partitions(), 1);
+        assert_eq!(silent.latency(), 0);
+        let mut samples = [1.0; 8];
+        silent.process(&mut samples);
+        assert_eq!(samples, [0.0; 8]);
+        // Latencies beyond the kernel length give the same filter.
+        let mut widest = PartitionedConvolver::new(&[1.0, 2.0, 3.0], usize::MAX);
+        let mut exact = PartitionedConvolver::new(&[1.0, 2.0, 3.0], 3);
+        assert_eq!(widest.block_length(), 4);
+        let mut a = [1.0, -1.0, 2.0, 0.0, 3.0, 1.0, 0.0, 0.0];
+        let mut b = a;
+        widest.process(&mut a);
+        exact.process(&mut b);
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,68 @@
+// Walsh transforms of batches of contiguous signals of `length` floats.
+
+#define CHUNK 2048u
+
+// Apply the stages with lags below the chunk length to every chunk of
+// every signal, one chunk per block, in shared memory.
+extern "C" __global__ void local_stages(float *data, unsigned length, unsigned signals) {
+    __shared__ float tile[CHUNK];
+    unsigned chunk = min(length, CHUNK);
+    unsigned long long group = blockIdx.x;
+    if (group >= (unsigned long long)signals * (length / chunk)) {
+        return;
+    }
+    float *base = data + group * chunk;
+    for (unsigned i = threadIdx.x; i < chunk; i += blockDim.x) {
+        tile[i] = base[i];
+    }
+    __syncthreads();
+    for (unsigned lag = 1; lag < chunk; lag <<= 1) {
+        for (unsigned i = threadIdx.x; i < chunk / 2; i += blockDim.x) {
+            unsigned j = (i / lag) * 2 * lag + i % lag;
+            float a = tile[j];
+            float b = tile[j + lag];
+            tile[j] = a + b;
+            tile[j + lag] = a - b;
+        }
+        __syncthreads();
+    }
+    for (unsigned i = threadIdx.x; i < chunk; i += blockDim.x) {
+        base[i] = tile[i];
+    }
+}
+
+// Apply the stage with lag `lag` to every signal, one butterfly per
+// thread.
+extern "C" __global__ void global_stage(float *data, unsigned length, unsigned signals, unsigned lag) {
+    unsigned long long i = (unsigned long long)blockIdx.x * blockDim.x + threadIdx.x;
+    unsigned half = length / 2;
+    if (i >= (unsigned long long)signals * half) {
+        return;
+    }
+    float *base = data + (i / half) * length;
+    unsigned k = i % half;
+    unsigned j = (k / lag) * 2 * lag + k % lag;
+    float a = base[j];
+    float b = base[j + lag];
+    base[j] = a + b;
+    base[j + lag] = a - b;
+}
+
+// Write the sequency ordering of the Hadamard-ordered signals of `data`
+// to `out`: sequency `s` is at the bit reversal of the Gray code of `s`.
+extern "C" __global__ void gather_sequency(
+    const float *data,
+    float *out,
+    unsigned length,
+    unsigned log_length,
+    unsigned signals
+) {
+    unsigned long long i = (unsigned long long)blockIdx.x * blockDim.x + threadIdx.x;
+    if (i >= (unsigned long long)signals * length) {
+        return;
+    }
+    unsigned long long base = (i / length) * length;
+    unsigned s = i % length;
+    unsigned h = __brev(s ^ (s >> 1)) >> (32 - log_length);
+    out[base + s] = data[base + h];
+}
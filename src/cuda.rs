@@ -0,0 +1,457 @@
+//! Walsh transforms on NVIDIA GPUs with CUDA, through [`cudarc`].
+//!
+//! A [`Cuda`] holds a device context with the transform kernels compiled
+//! for it by NVRTC, and creates [`CudaPlan`]s for batches of 1D
+//! transforms. A plan splits a batch held in host memory into chunks and
+//! pipelines them over several CUDA streams, each with its own pinned
+//! (page-locked) staging buffer and device buffers, so transfers of one
+//! chunk overlap the transforms of others. The buffers are kept for reuse
+//! by later calls. Data already on the device, such as the output of an
+//! existing CUDA pipeline, is transformed in place on its own stream by
+//! [`CudaPlan::execute_device`].
+//!
+//! The kernels are those of the [`gpu`](crate::gpu) module: the stages
+//! with small lags run in shared memory, a chunk of up to 2048 elements
+//! per block, then one launch per remaining stage, and the sequency
+//! ordering is gathered from the Hadamard ordering. Transforms are of
+//! `f32` data, and agree with the CPU transforms up to rounding.
+//!
+//! The CUDA driver and NVRTC libraries are loaded when a context is
+//! created, so building needs no CUDA toolkit.
+//!
+//! Enable with the `cuda` feature.
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use cudarc::driver::{
+    CudaContext, CudaFunction, CudaSlice, CudaStream, LaunchConfig, PinnedHostSlice, PushKernelArg,
+};
+
+use crate::{power_of_2, Error, Ordering};
+
+const KERNELS: &str = include_str!("cuda.cu");
+// The threads per block and chunk length of the kernels.
+const THREADS: usize = 256;
+const CHUNK: usize = 2048;
+// The size of the chunks of a host batch, and the default number of
+// streams they are spread over.
+const CHUNK_BYTES: usize = 32 << 20;
+const DEFAULT_STREAMS: usize = 4;
+
+/// Errors reported by CUDA transforms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CudaError {
+    /// The CUDA driver or NVRTC library is not installed, or there is no
+    /// device with the requested ordinal.
+    NoDevice,
+    /// A CUDA driver call failed.
+    Driver(String),
+    /// The kernels failed to compile.
+    Compile(String),
+    /// A plan's length or the data was invalid, as reported by the CPU
+    /// transforms.
+    Shape(Error),
+}
+
+impl fmt::Display for CudaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CudaError::NoDevice => write!(f, "no CUDA device is available"),
+            CudaError::Driver(message) => write!(f, "CUDA driver error: {}", message),
+            CudaError::Compile(message) => write!(f, "CUDA kernels failed to compile: {}", message),
+            CudaError::Shape(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CudaError {}
+
+impl From<Error> for CudaError {
+    fn from(error: Error) -> CudaError {
+        CudaError::Shape(error)
+    }
+}
+
+impl From<cudarc::driver::DriverError> for CudaError {
+    fn from(error: cudarc::driver::DriverError) -> CudaError {
+        CudaError::Driver(error.to_string())
+    }
+}
+
+/// A CUDA device context with the transform kernels loaded.
+#[derive(Debug, Clone)]
+pub struct Cuda {
+    context: Arc<CudaContext>,
+    kernels: Kernels,
+}
+
+#[derive(Debug, Clone)]
+struct Kernels {
+    local_stages: CudaFunction,
+    global_stage: CudaFunction,
+    gather_sequency: CudaFunction,
+}
+
+impl Cuda {
+    /// Open the device with the given `ordinal` and compile the kernels
+    /// for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CudaError::NoDevice`] if the CUDA libraries or the
+    /// device are missing, [`CudaError::Compile`] if the kernels do not
+    /// compile, or [`CudaError::Driver`] if the driver fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::cuda::Cuda;
+    /// use fwt::Ordering;
+    ///
+    /// let Ok(cuda) = Cuda::new(0) else { return };
+    /// let mut plan = cuda.plan(4, Ordering::Sequency).unwrap();
+    /// let mut v = [1.0, 1.0, -1.0, -1.0, 1.0, 2.0, 3.0, 4.0];
+    /// plan.execute(&mut v).unwrap();
+    /// assert_eq!(v, [0.0, 4.0, 0.0, 0.0, 10.0, -4.0, 0.0, -2.0]);
+    /// ```
+    pub fn new(ordinal: usize) -> Result<Cuda, CudaError> {
+        // SAFETY: probing for the libraries only attempts to load them.
+        let present = unsafe {
+            cudarc::driver::sys::is_culib_present() && cudarc::nvrtc::sys::is_culib_present()
+        };
+        if !present {
+            return Err(CudaError::NoDevice);
+        }
+        let count = CudaContext::device_count()?;
+        if ordinal >= count.max(0) as usize {
+            return Err(CudaError::NoDevice);
+        }
+        let context = CudaContext::new(ordinal)?;
+        let ptx =
+            cudarc::nvrtc::compile_ptx(KERNELS).map_err(|e| CudaError::Compile(e.to_string()))?;
+        let module = context.load_module(ptx)?;
+        let kernels = Kernels {
+            local_stages: module.load_function("local_stages")?,
+            global_stage: module.load_function("global_stage")?,
+            gather_sequency: module.load_function("gather_sequency")?,
+        };
+        Ok(Cuda { context, kernels })
+    }
+
+    /// The device context, for allocating device data to pass to
+    /// [`CudaPlan::execute_device`] or sharing with other CUDA code.
+    pub fn context(&self) -> &Arc<CudaContext> {
+        &self.context
+    }
+
+    /// Create a plan for transforms of `length` elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CudaError::Shape`] with [`Error::NotPowerOfTwo`] if
+    /// `length` is not a power of 2, or with [`Error::LengthMismatch`] if
+    /// it is 2^32 or more, more than the kernels index.
+    pub fn plan(&self, length: usize, ordering: Ordering) -> Result<CudaPlan, CudaError> {
+        if !power_of_2(length) {
+            return Err(Error::NotPowerOfTwo(length).into());
+        }
+        if u32::try_from(length).is_err() {
+            return Err(Error::LengthMismatch {
+                expected: u32::MAX as usize,
+                found: length,
+            }
+            .into());
+        }
+        Ok(CudaPlan {
+            cuda: self.clone(),
+            length,
+            ordering,
+            streams: DEFAULT_STREAMS,
+            slots: Vec::new(),
+        })
+    }
+}
+
+/// A plan for CUDA transforms of batches of signals of a fixed length,
+/// created by [`Cuda::plan`].
+#[derive(Debug)]
+pub struct CudaPlan {
+    cuda: Cuda,
+    length: usize,
+    ordering: Ordering,
+    streams: usize,
+    slots: Vec<Slot>,
+}
+
+// The buffers of one stream of a plan, and the range of the batch whose
+// spectra it is computing.
+#[derive(Debug)]
+struct Slot {
+    stream: Arc<CudaStream>,
+    pinned: PinnedHostSlice<f32>,
+    data: CudaSlice<f32>,
+    scratch: CudaSlice<f32>,
+    pending: Option<Range<usize>>,
+}
+
+impl CudaPlan {
+    /// The length of each signal.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The ordering of the transforms.
+    pub fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    /// Return this plan spreading host batches over `streams` CUDA
+    /// streams, at least 1. The default is 4.
+    pub fn with_streams(self, streams: usize) -> CudaPlan {
+        CudaPlan {
+            streams: streams.max(1),
+            slots: Vec::new(),
+            ..self
+        }
+    }
+
+    /// The number of streams host batches are spread over.
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    /// Replace each signal in `v`, which holds any whole number of them
+    /// back to back, with its transform. The batch is copied through the
+    /// plan's pinned buffers in chunks of about 32 MiB, pipelined over its
+    /// streams.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CudaError::Shape`] with [`Error::LengthMismatch`] if the
+    /// length of `v` is not a multiple of the plan's length (reporting the
+    /// next multiple as expected), or [`CudaError::Driver`] if the driver
+    /// fails.
+    pub fn execute(&mut self, v: &mut [f32]) -> Result<(), CudaError> {
+        if !v.len().is_multiple_of(self.length) {
+            return Err(Error::LengthMismatch {
+                expected: v.len().next_multiple_of(self.length),
+                found: v.len(),
+            }
+            .into());
+        }
+        if v.is_empty() {
+            return Ok(());
+        }
+        let chunk = (CHUNK_BYTES / 4).max(self.length).min(v.len());
+        if self.slots.first().is_some_and(|s| s.data.len() < chunk) {
+            self.slots.clear();
+        }
+        while self.slots.len() < self.streams {
+            self.slots.push(self.slot(chunk)?);
+        }
+        let starts = (0..v.len()).step_by(chunk);
+        for (start, i) in starts.zip((0..self.streams).cycle()) {
+            let range = start..(start + chunk).min(v.len());
+            finish(&mut self.slots[i], v)?;
+            let slot = &mut self.slots[i];
+            slot.pinned.as_mut_slice()?[..range.len()].copy_from_slice(&v[range.clone()]);
+            slot.stream.memcpy_htod(&slot.pinned, &mut slot.data)?;
+            let signals = range.len() / self.length;
+            enqueue(
+                &self.cuda.kernels,
+                &slot.stream,
+                &mut slot.data,
+                &mut slot.scratch,
+                self.length,
+                self.ordering,
+                signals,
+            )?;
+            slot.stream.memcpy_dtoh(&slot.data, &mut slot.pinned)?;
+            slot.pending = Some(range);
+        }
+        for slot in &mut self.slots {
+            finish(slot, v)?;
+        }
+        Ok(())
+    }
+
+    /// Replace each signal in the device buffer `data`, which holds any
+    /// whole number of them back to back, with its transform, enqueued on
+    /// the buffer's stream without synchronizing, so it can form part of
+    /// an existing CUDA pipeline.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CudaError::Shape`] with [`Error::LengthMismatch`] if the
+    /// length of `data` is not a multiple of the plan's length, or
+    /// [`CudaError::Driver`] if the driver fails.
+    pub fn execute_device(&self, data: &mut CudaSlice<f32>) -> Result<(), CudaError> {
+        if !data.len().is_multiple_of(self.length) {
+            return Err(Error::LengthMismatch {
+                expected: data.len().next_multiple_of(self.length),
+                found: data.len(),
+            }
+            .into());
+        }
+        let stream = data.stream().clone();
+        let mut scratch = match self.ordering {
+            Ordering::Sequency => stream.alloc_zeros::<f32>(data.len())?,
+            Ordering::Hadamard => stream.alloc_zeros::<f32>(0)?,
+        };
+        let signals = data.len() / self.length;
+        enqueue(
+            &self.cuda.kernels,
+            &stream,
+            data,
+            &mut scratch,
+            self.length,
+            self.ordering,
+            signals,
+        )
+    }
+
+    fn slot(&self, chunk: usize) -> Result<Slot, CudaError> {
+        let stream = self.cuda.context.new_stream()?;
+        // SAFETY: the pinned buffer is only read after it is filled.
+        let pinned = unsafe { self.cuda.context.alloc_pinned::<f32>(chunk) }?;
+        let data = stream.alloc_zeros::<f32>(chunk)?;
+        let scratch = match self.ordering {
+            Ordering::Sequency => stream.alloc_zeros::<f32>(chunk)?,
+            Ordering::Hadamard => stream.alloc_zeros::<f32>(0)?,
+        };
+        Ok(Slot {
+            stream,
+            pinned,
+            data,
+            scratch,
+            pending: None,
+        })
+    }
+}
+
+// Wait for the spectra a slot is computing and copy them into `v`.
+fn finish(slot: &mut Slot, v: &mut [f32]) -> Result<(), CudaError> {
+    if let Some(range) = slot.pending.take() {
+        let n = range.len();
+        v[range].copy_from_slice(&slot.pinned.as_slice()?[..n]);
+    }
+    Ok(())
+}
+
+// Enqueue on `stream` the transforms of the first `signals` signals of
+// `length` elements in `data`, using `scratch`, of at least the same
+// length, for the sequency ordering.
+fn enqueue(
+    kernels: &Kernels,
+    stream: &Arc<CudaStream>,
+    data: &mut CudaSlice<f32>,
+    scratch: &mut CudaSlice<f32>,
+    length: usize,
+    ordering: Ordering,
+    signals: usize,
+) -> Result<(), CudaError> {
+    if signals == 0 || length == 1 {
+        return Ok(());
+    }
+    let config = |blocks: usize| LaunchConfig {
+        grid_dim: (blocks as u32, 1, 1),
+        block_dim: (THREADS as u32, 1, 1),
+        shared_mem_bytes: 0,
+    };
+    let (n, count) = (length as u32, signals as u32);
+    let chunk = length.min(CHUNK);
+    // SAFETY: each launch passes the arguments its kernel declares, and
+    // the kernels only touch the first `signals * length` elements of
+    // `data` and `scratch`, which hold at least that many.
+    unsafe {
+        stream
+            .launch_builder(&kernels.local_stages)
+            .arg(&mut *data)
+            .arg(&n)
+            .arg(&count)
+            .launch(config(signals * (length / chunk)))?;
+        let mut lag = chunk as u32;
+        while lag < n {
+            stream
+                .launch_builder(&kernels.global_stage)
+                .arg(&mut *data)
+                .arg(&n)
+                .arg(&count)
+                .arg(&lag)
+                .launch(config((signals * length / 2).div_ceil(THREADS)))?;
+            lag <<= 1;
+        }
+        if ordering == Ordering::Sequency {
+            let log_length = length.trailing_zeros();
+            stream
+                .launch_builder(&kernels.gather_sequency)
+                .arg(&*data)
+                .arg(&mut *scratch)
+                .arg(&n)
+                .arg(&log_length)
+                .arg(&count)
+                .launch(config((signals * length).div_ceil(THREADS)))?;
+        }
+    }
+    if ordering == Ordering::Sequency {
+        let used = signals * length;
+        stream.memcpy_dtod(&scratch.slice(..used), &mut data.slice_mut(..used))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_cpu_transforms() {
+        let Ok(cuda) = Cuda::new(0) else {
+            return;
+        };
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            for log_length in [0, 1, 5, 11, 12, 16] {
+                let length = 1 << log_length;
+                let mut plan = cuda.plan(length, ordering).unwrap().with_streams(2);
+                let input: Vec<f32> = (0..5 * length)
+                    .map(|i| ((i * 37) % 23) as f32 - 11.0)
+                    .collect();
+                let mut v = input.clone();
+                plan.execute(&mut v).unwrap();
+                let cpu = crate::Plan::new(length, ordering).unwrap();
+                for (signal, spectrum) in input.chunks(length).zip(v.chunks(length)) {
+                    assert_eq!(spectrum, cpu.transform(signal).unwrap());
+                }
+                let mut data = cuda.context().default_stream().clone_htod(&input).unwrap();
+                plan.execute_device(&mut data).unwrap();
+                let back = cuda.context().default_stream().clone_dtoh(&data).unwrap();
+                assert_eq!(back, v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let Ok(cuda) = Cuda::new(0) else {
+            assert!(Cuda::new(usize::MAX).is_err());
+            return;
+        };
+        assert_eq!(
+            cuda.plan(6, Ordering::Sequency).unwrap_err(),
+            CudaError::Shape(Error::NotPowerOfTwo(6))
+        );
+        let mut plan = cuda.plan(8, Ordering::Hadamard).unwrap();
+        assert_eq!(plan.streams(), 4);
+        assert_eq!(
+            plan.execute(&mut [0.0; 12]),
+            Err(CudaError::Shape(Error::LengthMismatch {
+                expected: 16,
+                found: 12
+            }))
+        );
+        plan.execute(&mut []).unwrap();
+    }
+}
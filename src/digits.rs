@@ -0,0 +1,214 @@
+//! Walsh spectra of the digits of integer sequences.
+//!
+//! Write each value `x` in base `b`, which is 2 or 4, so that its digit at
+//! `position` is `d = (x / b^position) mod b`. A digit is mapped to ±1 by
+//! a character `c` between 1 and `b - 1`, read as a mask of the digit's
+//! bits: `(-1)^popcount(d & c)`. In base 2 the only character is 1, giving
+//! `(-1)^d`; in base 4, characters 1 and 2 give the sign of the low and
+//! high bit of the digit and 3 the parity of both. These are all the
+//! nontrivial ±1 characters of base-`b` digits, so a digit sequence is
+//! equidistributed exactly when every character averages to 0 over it,
+//! and the Walsh spectrum of a character's signal shows which dyadic
+//! patterns in the index the digit follows, as in the analysis of digital
+//! sequences for quasi-Monte Carlo integration.
+
+use crate::{power_of_2, Error, Ordering, Plan};
+
+/// Return the ±1 signal of the digit at `position` of each of `values`
+/// in base `base` under the character `character`: for each value `x`,
+/// `(-1)^popcount(d & character)` where `d = (x / base^position) mod
+/// base`.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidCharacter`] if `base` is not 2 or 4 or
+/// `character` is not between 1 and `base - 1`.
+///
+/// # Example
+///
+/// ```
+/// // The lowest binary digit of 0, 1, 2, 3 alternates.
+/// assert_eq!(fwt::digit_signal(&[0, 1, 2, 3], 2, 0, 1).unwrap(), [1, -1, 1, -1]);
+/// // The base-4 digits of 0, 5, 10, 15 are 0, 1, 2, 3; character 3 is
+/// // the parity of both bits of the digit.
+/// assert_eq!(fwt::digit_signal(&[0, 5, 10, 15], 4, 0, 3).unwrap(), [1, -1, -1, 1]);
+/// ```
+pub fn digit_signal(
+    values: &[u64],
+    base: u64,
+    position: u32,
+    character: u64,
+) -> Result<Vec<i64>, Error> {
+    check_character(base, character)?;
+    // Digits beyond the width of a `u64` are 0.
+    let shift = position.checked_mul(base.trailing_zeros());
+    Ok(values
+        .iter()
+        .map(|&x| {
+            let digit = shift.and_then(|s| x.checked_shr(s)).unwrap_or(0) & (base - 1);
+            if (digit & character).count_ones().is_multiple_of(2) {
+                1
+            } else {
+                -1
+            }
+        })
+        .collect())
+}
+
+/// Return the Walsh transform in `ordering` of the
+/// [`digit_signal`] of `values`. Coefficient 0 is the imbalance of the
+/// character over the whole sequence, and each other coefficient its
+/// correlation with a Walsh function of the index.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidCharacter`] as for [`digit_signal`], or
+/// [`Error::NotPowerOfTwo`] if the length of `values` is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // The n-th point of the van der Corput sequence has the binary digits
+/// // of n reversed, so its leading digit follows the lowest digit of n:
+/// // a single Walsh function of the index.
+/// let points: Vec<u64> = (0..8u64).map(|n| n.reverse_bits() >> 61).collect();
+/// let spectrum = fwt::digit_spectrum(&points, 2, 2, 1, Ordering::Sequency).unwrap();
+/// assert_eq!(spectrum, [0, 0, 0, 0, 0, 0, 0, 8]);
+/// ```
+pub fn digit_spectrum(
+    values: &[u64],
+    base: u64,
+    position: u32,
+    character: u64,
+    ordering: Ordering,
+) -> Result<Vec<i64>, Error> {
+    check_character(base, character)?;
+    if !power_of_2(values.len()) {
+        return Err(Error::NotPowerOfTwo(values.len()));
+    }
+    let mut signal = digit_signal(values, base, position, character)?;
+    Plan::new(values.len(), ordering)?.execute(&mut signal)?;
+    Ok(signal)
+}
+
+/// Return the [`digit_spectrum`] of `values` for each character of
+/// `base`, in order from character 1 to `base - 1`. The digit at
+/// `position` is equidistributed over `values` exactly when coefficient 0
+/// of every spectrum is 0.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidCharacter`] if `base` is not 2 or 4, or
+/// [`Error::NotPowerOfTwo`] if the length of `values` is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // Every base-4 digit appears once, so each character is balanced.
+/// let spectra = fwt::digit_spectra(&[3, 1, 0, 2], 4, 0, Ordering::Hadamard).unwrap();
+/// assert_eq!(spectra.len(), 3);
+/// assert!(spectra.iter().all(|spectrum| spectrum[0] == 0));
+/// ```
+pub fn digit_spectra(
+    values: &[u64],
+    base: u64,
+    position: u32,
+    ordering: Ordering,
+) -> Result<Vec<Vec<i64>>, Error> {
+    check_character(base, 1)?;
+    (1..base)
+        .map(|character| digit_spectrum(values, base, position, character, ordering))
+        .collect()
+}
+
+fn check_character(base: u64, character: u64) -> Result<(), Error> {
+    if (base == 2 || base == 4) && (1..base).contains(&character) {
+        Ok(())
+    } else {
+        Err(Error::InvalidCharacter { base, character })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals_follow_digits() {
+        let values: Vec<u64> = (0..64).collect();
+        for position in 0..3 {
+            for character in 1..4 {
+                let signal = digit_signal(&values, 4, position, character).unwrap();
+                for (&x, &s) in values.iter().zip(&signal) {
+                    let digit = (x >> (2 * position)) % 4;
+                    let expected = match (character, digit) {
+                        (1, 1 | 3) | (2, 2 | 3) | (3, 1 | 2) => -1,
+                        _ => 1,
+                    };
+                    assert_eq!(
+                        s, expected,
+                        "x {x} position {position} character {character}"
+                    );
+                }
+            }
+        }
+        // Base-4 digits are pairs of binary digits.
+        assert_eq!(
+            digit_signal(&values, 4, 1, 2).unwrap(),
+            digit_signal(&values, 2, 3, 1).unwrap()
+        );
+        // Digits beyond the width of the values are 0.
+        assert_eq!(digit_signal(&[u64::MAX], 2, 64, 1).unwrap(), [1]);
+        assert_eq!(digit_signal(&[u64::MAX], 4, 31, 3).unwrap(), [1]);
+        assert_eq!(digit_signal(&[u64::MAX], 4, u32::MAX, 1).unwrap(), [1]);
+    }
+
+    #[test]
+    fn test_spectra_of_counting_sequence() {
+        // The binary digit j of the index n is the Rademacher function of
+        // bit j, a single Hadamard-ordered Walsh function.
+        let values: Vec<u64> = (0..32).collect();
+        for position in 0..5 {
+            let spectrum = digit_spectrum(&values, 2, position, 1, Ordering::Hadamard).unwrap();
+            for (k, &c) in spectrum.iter().enumerate() {
+                assert_eq!(c, if k == 1 << position { 32 } else { 0 });
+            }
+        }
+        // Digit 5 is constant over the first 32 values, so it is
+        // unbalanced.
+        let spectra = digit_spectra(&values, 2, 5, Ordering::Sequency).unwrap();
+        assert_eq!(
+            spectra,
+            vec![{
+                let mut s = vec![0; 32];
+                s[0] = 32;
+                s
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        for (base, character) in [(3, 1), (8, 1), (2, 0), (2, 2), (4, 4)] {
+            assert_eq!(
+                digit_signal(&[1], base, 0, character),
+                Err(Error::InvalidCharacter { base, character })
+            );
+        }
+        assert_eq!(
+            digit_spectra(&[1, 2, 3], 2, 0, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            digit_spectra(&[1, 2], 16, 0, Ordering::Sequency),
+            Err(Error::InvalidCharacter {
+                base: 16,
+                character: 1
+            })
+        );
+    }
+}
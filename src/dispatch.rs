@@ -0,0 +1,795 @@
+//! Automatic choice of the backend that runs a batch of transforms.
+
+use std::any::TypeId;
+use std::io;
+use std::ops::{Add, Sub};
+use std::path::PathBuf;
+#[cfg(feature = "gpu")]
+use std::sync::{Mutex, PoisonError};
+
+use std::time::{Duration, Instant};
+
+use crate::format::{self, Artifact};
+use crate::{power_of_2, Determinism, Error, Ordering, PlanCache, WalshElement};
+
+// The plan memory a dispatcher keeps cached.
+const CACHE_BYTES: usize = 16 << 20;
+
+// Without calibration data, batches of at least this many elements run on
+// multiple threads by default, and batches of `f32` of at least `GPU_ELEMENTS` on
+// the GPU, whose transfers outweigh its speed for anything smaller.
+const PARALLEL_ELEMENTS: usize = 1 << 16;
+const GPU_ELEMENTS: usize = 1 << 22;
+
+// Batches of at least `TRANSPOSED_BATCH` signals of lengths in
+// `TRANSPOSED_LENGTHS` run transposed. Shorter signals already run
+// unrolled kernels that beat the cost of transposing, and longer ones
+// fill vectors without it.
+const TRANSPOSED_LENGTHS: std::ops::RangeInclusive<usize> = 128..=1 << 11;
+const TRANSPOSED_BATCH: usize = 64;
+
+// The lengths and batch sizes `TuningProfile::measure` times, from short
+// signals in large batches to single long ones, and the least time it
+// spends on each.
+const CALIBRATION_SIZES: [(usize, usize); 9] = [
+    (1 << 4, 1),
+    (1 << 4, 1 << 12),
+    (1 << 8, 1),
+    (1 << 8, 1 << 8),
+    (1 << 12, 1),
+    (1 << 12, 1 << 6),
+    (1 << 16, 1),
+    (1 << 16, 1 << 4),
+    (1 << 20, 1),
+];
+const CALIBRATION_TIME: Duration = Duration::from_millis(5);
+
+/// The implementations a [`Dispatcher`] chooses between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The serial kernels with plain scalar butterflies.
+    Scalar,
+    /// The serial kernels with the vectorized butterflies of the element
+    /// type, as used by [`Plan::execute`](crate::Plan::execute). For
+    /// types without vectorized butterflies this is the same as
+    /// [`Backend::Scalar`].
+    Simd,
+    /// The kernels run on the rayon thread pool, one signal per task for
+    /// batches and divided between threads for single signals. Only
+    /// available with the `parallel` feature.
+    Parallel,
+    /// The serial kernels run across the batch transposed, one signal per
+    /// vector lane, as by
+    /// [`Plan::execute_transposed`](crate::Plan::execute_transposed).
+    Transposed,
+    /// The compute shaders of the `gpu` module, for `f32` only. Only
+    /// available with the `gpu` feature once a device is given with
+    /// `Dispatcher::with_gpu`.
+    Gpu,
+}
+
+/// A measured cost of running transforms of one size on one backend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// The backend measured.
+    pub backend: Backend,
+    /// The length of each signal.
+    pub length: usize,
+    /// The number of signals transformed together.
+    pub batch: usize,
+    /// The time taken per element, in nanoseconds.
+    pub nanos_per_element: f64,
+}
+
+/// Calibration data for a [`Dispatcher`]: the measured costs of the
+/// backends at representative sizes on one machine.
+///
+/// The cost of a backend at other sizes is estimated from its nearest
+/// measurement, taking the distance between sizes as the sum of the
+/// differences of the base-2 logarithms of their lengths and batch sizes.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Backend, Measurement, TuningProfile};
+///
+/// let mut profile = TuningProfile::new();
+/// for (length, nanos) in [(64, 1.0), (1 << 20, 3.0)] {
+///     profile.record(Measurement { backend: Backend::Simd, length, batch: 1, nanos_per_element: nanos });
+/// }
+/// assert_eq!(profile.estimate(Backend::Simd, 128, 4), Some(1.0));
+/// assert_eq!(profile.estimate(Backend::Simd, 1 << 18, 1), Some(3.0));
+/// assert_eq!(profile.estimate(Backend::Scalar, 128, 4), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TuningProfile {
+    measurements: Vec<Measurement>,
+}
+
+impl TuningProfile {
+    /// Create a profile without measurements.
+    pub fn new() -> TuningProfile {
+        TuningProfile::default()
+    }
+
+    /// Time transforms of `f32` signals on every backend available to
+    /// `dispatcher`, at representative lengths from 2^4 to 2^20 and batch
+    /// sizes of up to 2^16 elements, and return the measurements. This
+    /// takes a fraction of a second, or longer on a slow GPU.
+    ///
+    /// Only `f32` is timed, and a dispatcher applies the measurements to
+    /// every element type, so for types of other sizes, or without the
+    /// same vector kernels, they are only an approximation.
+    pub fn measure(dispatcher: &Dispatcher) -> TuningProfile {
+        let mut profile = TuningProfile::new();
+        for (length, batch) in CALIBRATION_SIZES {
+            let elements = length * batch;
+            // Repeated transforms overflow to infinities and NaNs, which
+            // cost no more than finite values.
+            let mut v: Vec<f32> = (0..elements).map(|i| (i % 7) as f32 - 3.0).collect();
+            for backend in dispatcher.available::<f32>() {
+                let run = |v: &mut [f32]| {
+                    dispatcher
+                        .execute_on(backend, v, length, Ordering::Hadamard)
+                        .expect("calibration sizes are powers of 2")
+                };
+                // The first run builds plans and buffers.
+                if run(&mut v) != backend {
+                    continue;
+                }
+                let start = Instant::now();
+                let mut runs = 0;
+                while runs < 3 || start.elapsed() < CALIBRATION_TIME {
+                    run(&mut v);
+                    runs += 1;
+                }
+                profile.record(Measurement {
+                    backend,
+                    length,
+                    batch,
+                    nanos_per_element: start.elapsed().as_nanos() as f64 / (runs * elements) as f64,
+                });
+            }
+        }
+        profile
+    }
+
+    /// Add `measurement`, replacing any earlier one of the same backend,
+    /// length and batch size.
+    pub fn record(&mut self, measurement: Measurement) {
+        let same = |m: &Measurement| {
+            (m.backend, m.length, m.batch)
+                == (measurement.backend, measurement.length, measurement.batch)
+        };
+        match self.measurements.iter_mut().find(|m| same(m)) {
+            Some(m) => *m = measurement,
+            None => self.measurements.push(measurement),
+        }
+    }
+
+    /// The measurements in the order they were first recorded.
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    /// The estimated time per element, in nanoseconds, of transforming
+    /// `batch` signals of `length` elements on `backend`, or `None` if the
+    /// backend was never measured.
+    pub fn estimate(&self, backend: Backend, length: usize, batch: usize) -> Option<f64> {
+        let log = |n: usize| (n.max(1) as f64).log2();
+        self.measurements
+            .iter()
+            .filter(|m| m.backend == backend)
+            .min_by(|a, b| {
+                let distance = |m: &Measurement| {
+                    (log(m.length) - log(length)).abs() + (log(m.batch) - log(batch)).abs()
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+            .map(|m| m.nanos_per_element)
+    }
+}
+
+/// Somewhere to keep a [`TuningProfile`] between runs of a program, for
+/// [`calibrate`].
+///
+/// A [`PathBuf`] stores the profile in a file in the
+/// [`format`](crate::format) container. Other stores, such as a database
+/// or a key-value service, implement this trait.
+pub trait ProfileStore {
+    /// Return the stored profile, or `None` if there is none yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reading the store, or of kind
+    /// [`io::ErrorKind::InvalidData`] if it does not hold a profile.
+    fn load(&mut self) -> io::Result<Option<TuningProfile>>;
+
+    /// Store `profile`, replacing any stored before.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error writing the store.
+    fn save(&mut self, profile: &TuningProfile) -> io::Result<()>;
+}
+
+impl ProfileStore for PathBuf {
+    fn load(&mut self) -> io::Result<Option<TuningProfile>> {
+        let bytes = match std::fs::read(&*self) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let profile = format::read(&mut bytes.as_slice())?.to_profile();
+        profile
+            .map(Some)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a tuning profile"))
+    }
+
+    fn save(&mut self, profile: &TuningProfile) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        format::write(&mut bytes, &Artifact::profile(profile))?;
+        std::fs::write(&*self, bytes)
+    }
+}
+
+/// Return the tuning profile kept in `store`, or on the first run, when
+/// the store is empty, [measure](TuningProfile::measure) the backends
+/// available to `dispatcher` and save the profile in `store` before
+/// returning it. Give the profile to the dispatcher with
+/// [`Dispatcher::with_profile`], much as FFTW imports wisdom, so that
+/// later runs choose backends from this machine's measurements without
+/// timing them again.
+///
+/// The measurements are of `f32` transforms, and the dispatcher applies
+/// them to every element type, as described for
+/// [`TuningProfile::measure`].
+///
+/// # Errors
+///
+/// Returns any error loading or saving the profile.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// let dispatcher = fwt::Dispatcher::new();
+/// let mut store = PathBuf::from("fwt-profile.bin");
+/// let profile = fwt::calibrate(&dispatcher, &mut store)?;
+/// let dispatcher = dispatcher.with_profile(profile);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn calibrate<S>(dispatcher: &Dispatcher, store: &mut S) -> io::Result<TuningProfile>
+where
+    S: ProfileStore + ?Sized,
+{
+    if let Some(profile) = store.load()? {
+        return Ok(profile);
+    }
+    let profile = TuningProfile::measure(dispatcher);
+    store.save(&profile)?;
+    Ok(profile)
+}
+
+/// Runs batches of transforms on whichever available backend should be
+/// fastest for their size, so that callers get good performance without
+/// choosing kernels and thresholds for each machine.
+///
+/// With a [`TuningProfile`], the dispatcher picks the available backend
+/// with the lowest estimated cost among those the profile measured.
+/// Otherwise, or if the profile measured none of them, it uses built-in
+/// thresholds: the GPU for `f32` batches of at least 2^22 elements, the
+/// thread pool for batches of at least
+/// [`parallel_threshold`](Self::parallel_threshold) elements, 2^16 by
+/// default, holding several signals or one long one, batches of at least
+/// 64 signals of 128 to 2048 elements transposed, and the vectorized
+/// serial kernels for the rest. Plans are kept in a [`PlanCache`] between
+/// calls.
+///
+/// Every backend computes the same transform; floating-point results may
+/// differ between them in the last bits. A dispatcher with
+/// [`Determinism::Strict`] runs every batch on [`Backend::Scalar`] with
+/// strict plans, for results bit-identical to
+/// [`hadamard`](crate::hadamard) and [`sequency`](crate::sequency).
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Backend, Dispatcher, Ordering};
+///
+/// let dispatcher = Dispatcher::new();
+/// let mut batch = [1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+/// let backend = dispatcher.execute(&mut batch, 4, Ordering::Sequency).unwrap();
+/// assert_eq!(backend, Backend::Simd);
+/// assert_eq!(batch, [1.0, 1.0, 1.0, 1.0, 4.0, 0.0, 0.0, 0.0]);
+/// ```
+#[derive(Debug)]
+pub struct Dispatcher {
+    cache: PlanCache,
+    profile: Option<TuningProfile>,
+    parallel_threshold: usize,
+    determinism: Determinism,
+    #[cfg(feature = "parallel")]
+    pool: crate::parallel::Pool,
+    #[cfg(feature = "gpu")]
+    gpu: Option<crate::gpu::Gpu>,
+    // The most recently used GPU plan, reused while the length and
+    // ordering are unchanged.
+    #[cfg(feature = "gpu")]
+    gpu_plan: Mutex<Option<crate::gpu::GpuPlan>>,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Dispatcher {
+        Dispatcher::new()
+    }
+}
+
+impl Dispatcher {
+    /// Create a dispatcher using the built-in thresholds and no GPU.
+    pub fn new() -> Dispatcher {
+        Dispatcher {
+            cache: PlanCache::new(CACHE_BYTES),
+            profile: None,
+            parallel_threshold: PARALLEL_ELEMENTS,
+            determinism: Determinism::default(),
+            #[cfg(feature = "parallel")]
+            pool: crate::parallel::Pool::default(),
+            #[cfg(feature = "gpu")]
+            gpu: None,
+            #[cfg(feature = "gpu")]
+            gpu_plan: Mutex::new(None),
+        }
+    }
+
+    /// Return this dispatcher choosing backends from the measurements in
+    /// `profile`.
+    pub fn with_profile(self, profile: TuningProfile) -> Dispatcher {
+        Dispatcher {
+            profile: Some(profile),
+            ..self
+        }
+    }
+
+    /// The calibration data the dispatcher uses, if any.
+    pub fn profile(&self) -> Option<&TuningProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Return this dispatcher using the thread pool, when no profile
+    /// decides, for batches of at least `elements` elements.
+    pub fn with_parallel_threshold(self, elements: usize) -> Dispatcher {
+        Dispatcher {
+            parallel_threshold: elements,
+            ..self
+        }
+    }
+
+    /// The smallest batch, in elements, run on the thread pool without a
+    /// profile, 2^16 by default.
+    pub fn parallel_threshold(&self) -> usize {
+        self.parallel_threshold
+    }
+
+    /// Return this dispatcher with the given [`Determinism`] requirement.
+    pub fn with_determinism(self, determinism: Determinism) -> Dispatcher {
+        Dispatcher {
+            determinism,
+            ..self
+        }
+    }
+
+    /// The reproducibility requirement of this dispatcher.
+    pub fn determinism(&self) -> Determinism {
+        self.determinism
+    }
+
+    /// Return this dispatcher running [`Backend::Parallel`] on `pool`
+    /// instead of the current rayon thread pool.
+    ///
+    /// Enable with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(self, pool: std::sync::Arc<rayon::ThreadPool>) -> Dispatcher {
+        Dispatcher {
+            pool: crate::parallel::Pool::new(pool),
+            ..self
+        }
+    }
+
+    /// Return this dispatcher running [`Backend::Parallel`] on a pool of
+    /// its own with `threads` threads, or rayon's default number if
+    /// `threads` is 0.
+    ///
+    /// Enable with the `parallel` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system cannot create the threads.
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(self, threads: usize) -> Dispatcher {
+        Dispatcher {
+            pool: crate::parallel::Pool::with_threads(threads),
+            ..self
+        }
+    }
+
+    /// Return this dispatcher able to run `f32` batches on `gpu`.
+    ///
+    /// Enable with the `gpu` feature.
+    #[cfg(feature = "gpu")]
+    pub fn with_gpu(self, gpu: crate::gpu::Gpu) -> Dispatcher {
+        Dispatcher {
+            gpu: Some(gpu),
+            gpu_plan: Mutex::new(None),
+            ..self
+        }
+    }
+
+    /// The backends available for elements of type `T`, in order of
+    /// preference when nothing else distinguishes them.
+    pub fn available<T: 'static>(&self) -> Vec<Backend> {
+        let mut backends = vec![Backend::Simd, Backend::Scalar, Backend::Transposed];
+        if cfg!(feature = "parallel") {
+            backends.push(Backend::Parallel);
+        }
+        if self.has_gpu() && TypeId::of::<T>() == TypeId::of::<f32>() {
+            backends.push(Backend::Gpu);
+        }
+        backends
+    }
+
+    /// The backend [`execute`](Self::execute) uses for `batch` signals of
+    /// `length` elements of type `T`.
+    pub fn choose<T: 'static>(&self, length: usize, batch: usize) -> Backend {
+        if self.determinism == Determinism::Strict {
+            return Backend::Scalar;
+        }
+        let available = self.available::<T>();
+        let measured = self.profile.as_ref().and_then(|profile| {
+            available
+                .iter()
+                .filter_map(|&b| profile.estimate(b, length, batch).map(|cost| (b, cost)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+        });
+        if let Some((backend, _)) = measured {
+            return backend;
+        }
+        let elements = length.saturating_mul(batch);
+        if available.contains(&Backend::Gpu) && elements >= GPU_ELEMENTS {
+            Backend::Gpu
+        } else if available.contains(&Backend::Parallel)
+            && elements >= self.parallel_threshold
+            && (batch > 1 || length >= self.parallel_threshold)
+        {
+            Backend::Parallel
+        } else if TRANSPOSED_LENGTHS.contains(&length) && batch >= TRANSPOSED_BATCH {
+            Backend::Transposed
+        } else {
+            Backend::Simd
+        }
+    }
+
+    /// Replace each signal in `v`, which holds any whole number of
+    /// signals of `length` elements back to back, with its transform, on
+    /// the backend chosen by [`choose`](Self::choose). Returns the backend
+    /// used.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2,
+    /// or [`Error::LengthMismatch`] if the length of `v` is not a multiple
+    /// of it, reporting the next multiple as expected.
+    pub fn execute<T>(
+        &self,
+        v: &mut [T],
+        length: usize,
+        ordering: Ordering,
+    ) -> Result<Backend, Error>
+    where
+        T: WalshElement + Send + Sync + 'static,
+    {
+        check(v, length)?;
+        let backend = self.choose::<T>(length, v.len() / length);
+        self.execute_on(backend, v, length, ordering)
+    }
+
+    /// Transform the signals of `v` as by [`execute`](Self::execute), but
+    /// on `backend`, or on [`Backend::Simd`] if `backend` is not
+    /// available for `T`, the GPU fails, or [`Backend::Transposed`] is
+    /// given a single signal. A strict dispatcher always uses
+    /// [`Backend::Scalar`]. Returns the backend used.
+    ///
+    /// # Errors
+    ///
+    /// As for [`execute`](Self::execute).
+    pub fn execute_on<T>(
+        &self,
+        backend: Backend,
+        v: &mut [T],
+        length: usize,
+        ordering: Ordering,
+    ) -> Result<Backend, Error>
+    where
+        T: WalshElement + Send + Sync + 'static,
+    {
+        check(v, length)?;
+        let plan = self.cache.get(length, ordering)?;
+        if self.determinism == Determinism::Strict {
+            for signal in v.chunks_exact_mut(length) {
+                plan.execute_strict(signal)?;
+            }
+            return Ok(Backend::Scalar);
+        }
+        match backend {
+            Backend::Scalar => {
+                for signal in as_scalar(v).chunks_exact_mut(length) {
+                    plan.execute(signal)?;
+                }
+                return Ok(Backend::Scalar);
+            }
+            Backend::Transposed if v.len() > length => {
+                plan.execute_transposed(v)?;
+                return Ok(Backend::Transposed);
+            }
+            #[cfg(feature = "parallel")]
+            Backend::Parallel => {
+                use rayon::prelude::*;
+                self.pool.install(|| {
+                    if v.len() == length {
+                        plan.execute_parallel_from(v, 0)
+                    } else {
+                        v.par_chunks_exact_mut(length)
+                            .try_for_each(|s| plan.execute(s))
+                    }
+                })?;
+                return Ok(Backend::Parallel);
+            }
+            #[cfg(feature = "gpu")]
+            Backend::Gpu if self.available::<T>().contains(&Backend::Gpu) => {
+                // SAFETY: `T` is `f32`, as `available` checked.
+                let v32 = unsafe {
+                    std::slice::from_raw_parts_mut(v.as_mut_ptr().cast::<f32>(), v.len())
+                };
+                if self.execute_gpu(v32, length, ordering) {
+                    return Ok(Backend::Gpu);
+                }
+            }
+            _ => {}
+        }
+        for signal in v.chunks_exact_mut(length) {
+            plan.execute(signal)?;
+        }
+        Ok(Backend::Simd)
+    }
+
+    fn has_gpu(&self) -> bool {
+        #[cfg(feature = "gpu")]
+        return self.gpu.is_some();
+        #[cfg(not(feature = "gpu"))]
+        false
+    }
+
+    // Transform `v` on the GPU, returning whether it succeeded.
+    #[cfg(feature = "gpu")]
+    fn execute_gpu(&self, v: &mut [f32], length: usize, ordering: Ordering) -> bool {
+        let Some(gpu) = &self.gpu else {
+            return false;
+        };
+        let mut cached = self.gpu_plan.lock().unwrap_or_else(PoisonError::into_inner);
+        let reusable = cached
+            .as_ref()
+            .is_some_and(|p| p.cols() == length && p.ordering() == ordering);
+        if !reusable {
+            *cached = gpu.plan(length, ordering).ok();
+        }
+        cached.as_mut().is_some_and(|plan| plan.execute(v).is_ok())
+    }
+}
+
+fn check<T>(v: &[T], length: usize) -> Result<(), Error> {
+    if !power_of_2(length) {
+        return Err(Error::NotPowerOfTwo(length));
+    }
+    if !v.len().is_multiple_of(length) {
+        return Err(Error::LengthMismatch {
+            expected: v.len().next_multiple_of(length),
+            found: v.len(),
+        });
+    }
+    Ok(())
+}
+
+// An element whose butterflies are the plain scalar loops of
+// `WalshElement`, whatever the vectorized butterflies of `T`.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub(crate) struct Scalar<T>(T);
+
+impl<T: Add<Output = T>> Add for Scalar<T> {
+    type Output = Scalar<T>;
+    fn add(self, rhs: Scalar<T>) -> Scalar<T> {
+        Scalar(self.0 + rhs.0)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Scalar<T> {
+    type Output = Scalar<T>;
+    fn sub(self, rhs: Scalar<T>) -> Scalar<T> {
+        Scalar(self.0 - rhs.0)
+    }
+}
+
+impl<T: WalshElement> WalshElement for Scalar<T> {}
+
+pub(crate) fn as_scalar<T>(v: &mut [T]) -> &mut [Scalar<T>] {
+    // SAFETY: `Scalar<T>` is a transparent wrapper of `T`.
+    unsafe { std::slice::from_raw_parts_mut(v.as_mut_ptr().cast(), v.len()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Plan;
+
+    fn measurement(backend: Backend, length: usize, batch: usize, nanos: f64) -> Measurement {
+        Measurement {
+            backend,
+            length,
+            batch,
+            nanos_per_element: nanos,
+        }
+    }
+
+    #[test]
+    fn test_every_backend_matches_plan() {
+        let dispatcher = Dispatcher::new();
+        for (length, batch) in [(1, 3), (16, 5), (256, 1), (1 << 12, 2)] {
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let input: Vec<f64> = (0..length * batch)
+                    .map(|i| ((i * 37) % 23) as f64 - 11.0)
+                    .collect();
+                let plan = Plan::new(length, ordering).unwrap();
+                let expected: Vec<f64> = input
+                    .chunks(length)
+                    .flat_map(|s| plan.transform(s).unwrap())
+                    .collect();
+                for backend in dispatcher.available::<f64>() {
+                    let mut v = input.clone();
+                    let used = dispatcher
+                        .execute_on(backend, &mut v, length, ordering)
+                        .unwrap();
+                    let single = backend == Backend::Transposed && batch == 1;
+                    assert_eq!(used, if single { Backend::Simd } else { backend });
+                    assert_eq!(v, expected);
+                }
+                let mut v = input.clone();
+                dispatcher.execute(&mut v, length, ordering).unwrap();
+                assert_eq!(v, expected);
+            }
+        }
+        // Without a device the GPU falls back to the vectorized kernels.
+        let mut v = [1.0f32, 2.0];
+        let used = dispatcher.execute_on(Backend::Gpu, &mut v, 2, Ordering::Hadamard);
+        assert_eq!(used, Ok(Backend::Simd));
+        assert_eq!(v, [3.0, -1.0]);
+    }
+
+    #[test]
+    fn test_profile_drives_choice() {
+        let mut profile = TuningProfile::new();
+        profile.record(measurement(Backend::Simd, 1 << 4, 1, 2.0));
+        profile.record(measurement(Backend::Scalar, 1 << 4, 1, 1.0));
+        profile.record(measurement(Backend::Simd, 1 << 20, 1, 1.0));
+        profile.record(measurement(Backend::Scalar, 1 << 20, 1, 5.0));
+        // A later measurement of the same size replaces the earlier one.
+        profile.record(measurement(Backend::Scalar, 1 << 20, 1, 4.0));
+        assert_eq!(profile.measurements().len(), 4);
+        assert_eq!(profile.estimate(Backend::Scalar, 1 << 19, 1), Some(4.0));
+        let dispatcher = Dispatcher::new().with_profile(profile);
+        assert_eq!(dispatcher.choose::<f64>(8, 1), Backend::Scalar);
+        assert_eq!(dispatcher.choose::<f64>(1 << 16, 2), Backend::Simd);
+        let mut v = [1.0, 1.0];
+        assert_eq!(
+            dispatcher.execute(&mut v, 2, Ordering::Sequency),
+            Ok(Backend::Scalar)
+        );
+        assert_eq!(v, [2.0, 0.0]);
+        // A strict dispatcher overrides the profile and the thresholds.
+        let strict = dispatcher.with_determinism(Determinism::Strict);
+        assert_eq!(strict.choose::<f64>(1 << 16, 2), Backend::Scalar);
+        assert_eq!(strict.choose::<f64>(256, 64), Backend::Scalar);
+        assert_eq!(
+            Dispatcher::new().choose::<f64>(256, 64),
+            Backend::Transposed
+        );
+        let input: Vec<f64> = (0..1 << 16).map(|i| (i % 13) as f64 / 7.3).collect();
+        let mut v = input.clone();
+        let used = strict.execute_on(Backend::Transposed, &mut v, 1 << 8, Ordering::Sequency);
+        assert_eq!(used, Ok(Backend::Scalar));
+        let expected: Vec<f64> = input
+            .chunks(1 << 8)
+            .flat_map(|s| crate::sequency(s).unwrap())
+            .collect();
+        assert!(v
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
+
+    // A store in memory that counts its saves.
+    #[derive(Default)]
+    struct Memory {
+        profile: Option<TuningProfile>,
+        saves: usize,
+    }
+
+    impl ProfileStore for Memory {
+        fn load(&mut self) -> io::Result<Option<TuningProfile>> {
+            Ok(self.profile.clone())
+        }
+
+        fn save(&mut self, profile: &TuningProfile) -> io::Result<()> {
+            self.profile = Some(profile.clone());
+            self.saves += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_calibrate_measures_once_and_persists() {
+        let dispatcher = Dispatcher::new();
+        let mut memory = Memory::default();
+        let profile = calibrate(&dispatcher, &mut memory).unwrap();
+        let backends = dispatcher.available::<f32>();
+        // Single signals are not measured transposed.
+        let single = CALIBRATION_SIZES.iter().filter(|s| s.1 == 1).count();
+        assert_eq!(
+            profile.measurements().len(),
+            CALIBRATION_SIZES.len() * backends.len() - single
+        );
+        assert!(profile
+            .measurements()
+            .iter()
+            .all(|m| m.nanos_per_element > 0.0 && backends.contains(&m.backend)));
+        assert_eq!(calibrate(&dispatcher, &mut memory).unwrap(), profile);
+        assert_eq!(memory.saves, 1);
+        let mut path = std::env::temp_dir().join(format!("fwt-profile-{}", std::process::id()));
+        assert_eq!(path.load().unwrap(), None);
+        path.save(&profile).unwrap();
+        assert_eq!(calibrate(&dispatcher, &mut path).unwrap(), profile);
+        std::fs::write(&path, b"not a profile").unwrap();
+        assert!(path.load().is_err());
+        std::fs::remove_file(&path).unwrap();
+        let dispatcher = dispatcher.with_profile(profile);
+        assert!(backends.contains(&dispatcher.choose::<f32>(64, 100)));
+    }
+
+    #[test]
+    fn test_thresholds_and_errors() {
+        let dispatcher = Dispatcher::new();
+        assert_eq!(dispatcher.choose::<f32>(64, 10), Backend::Simd);
+        let large = if cfg!(feature = "parallel") {
+            Backend::Parallel
+        } else {
+            Backend::Simd
+        };
+        assert_eq!(dispatcher.choose::<f32>(64, 10_000), large);
+        assert_eq!(dispatcher.choose::<i32>(1 << 20, 1), large);
+        assert_eq!(dispatcher.choose::<f64>(256, 64), Backend::Transposed);
+        assert_eq!(dispatcher.choose::<f64>(256, 63), Backend::Simd);
+        assert!(!dispatcher.available::<f32>().contains(&Backend::Gpu));
+        let mut v = [0.0; 12];
+        assert_eq!(
+            dispatcher.execute(&mut v, 8, Ordering::Sequency),
+            Err(Error::LengthMismatch {
+                expected: 16,
+                found: 12
+            })
+        );
+        assert_eq!(
+            dispatcher.execute(&mut v, 6, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(6))
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Sequency-domain downmixing of synchronized multi-sensor channels.
+
+use crate::{linalg, power_of_2, sequency, Error};
+
+/// Per-coefficient weights that combine several synchronized sensor
+/// channels into one output, in the manner of a beamformer operating in
+/// the sequency domain.
+///
+/// Each sequency coefficient of the output is a weighted sum of the same
+/// coefficient across channels. Weights are fit from calibration
+/// recordings by ridge-regularized least squares against a target signal,
+/// so that the downmix enhances the target signature while suppressing
+/// whatever the channels do not share with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Downmixer {
+    length: usize,
+    channels: usize,
+    weights: Vec<f64>,
+}
+
+impl Downmixer {
+    /// Fit weights from calibration `recordings`, each a set of equally
+    /// long channels, and the matching `targets` the downmix should
+    /// reproduce. For every coefficient `k` the weights minimize
+    ///
+    /// `Σ |Σ_c w[k][c] X_c[k] − Y[k]|² + ridge · Σ_c w[k][c]²`
+    ///
+    /// over the recordings, where `X_c` and `Y` are the unnormalized
+    /// sequency transforms of channel `c` and the target. A positive
+    /// `ridge` keeps coefficients that carry no calibration energy well
+    /// defined (their weights are zero).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if the signal length is not a
+    /// power of 2, [`Error::LengthMismatch`] if the recordings, targets,
+    /// or channels disagree in count or length, and [`Error::Singular`]
+    /// if there are no recordings or some coefficient's weights are not
+    /// uniquely determined.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // Two sensors see the target with different gains plus opposite
+    /// // interference; the fit learns to cancel the interference.
+    /// let target = vec![1.0, -2.0, 0.5, 3.0];
+    /// let noise = vec![0.3, 0.1, -0.2, 0.4];
+    /// let a: Vec<f64> = target.iter().zip(&noise).map(|(t, n)| t + n).collect();
+    /// let b: Vec<f64> = target.iter().zip(&noise).map(|(t, n)| 2.0 * t - n).collect();
+    ///
+    /// let mixer = fwt::Downmixer::fit(&[vec![a.clone(), b.clone()]], &[target.clone()], 1e-9)
+    ///     .unwrap();
+    /// let out = mixer.apply(&[a, b]).unwrap();
+    /// for (o, t) in out.iter().zip(&target) {
+    ///     assert!((o - t).abs() < 0.05);
+    /// }
+    /// ```
+    pub fn fit(
+        recordings: &[Vec<Vec<f64>>],
+        targets: &[Vec<f64>],
+        ridge: f64,
+    ) -> Result<Downmixer, Error> {
+        if recordings.len() != targets.len() {
+            return Err(Error::LengthMismatch {
+                expected: recordings.len(),
+                found: targets.len(),
+            });
+        }
+        let first = recordings.first().ok_or(Error::Singular)?;
+        let channels = first.len();
+        let length = targets[0].len();
+        let spectra = recordings
+            .iter()
+            .zip(targets)
+            .map(|(recording, target)| {
+                if recording.len() != channels {
+                    return Err(Error::LengthMismatch {
+                        expected: channels,
+                        found: recording.len(),
+                    });
+                }
+                let x = transform_channels(recording, length)?;
+                let y = transform_channels(std::slice::from_ref(target), length)?;
+                Ok((x, y))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut weights = Vec::with_capacity(length * channels);
+        for k in 0..length {
+            let mut gram = vec![0.0; channels * channels];
+            let mut rhs = vec![0.0; channels];
+            for (x, y) in &spectra {
+                for i in 0..channels {
+                    let xi = x[i * length + k];
+                    rhs[i] += xi * y[k];
+                    for j in 0..channels {
+                        gram[i * channels + j] += xi * x[j * length + k];
+                    }
+                }
+            }
+            for i in 0..channels {
+                gram[i * channels + i] += ridge;
+            }
+            weights.extend(linalg::solve(gram, rhs).ok_or(Error::Singular)?);
+        }
+        Ok(Downmixer {
+            length,
+            channels,
+            weights,
+        })
+    }
+
+    /// Create a downmixer from explicit weights, stored coefficient by
+    /// coefficient with `channels` weights each.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2,
+    /// or [`Error::LengthMismatch`] if `weights` does not hold
+    /// `length * channels` values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // Average two channels at every coefficient.
+    /// let mixer = fwt::Downmixer::from_weights(4, 2, vec![0.5; 8]).unwrap();
+    /// let out = mixer.apply(&[vec![1.0, 2.0, 3.0, 4.0], vec![3.0, 2.0, 1.0, 0.0]]).unwrap();
+    /// assert_eq!(out, vec![2.0, 2.0, 2.0, 2.0]);
+    /// ```
+    pub fn from_weights(
+        length: usize,
+        channels: usize,
+        weights: Vec<f64>,
+    ) -> Result<Downmixer, Error> {
+        if !power_of_2(length) {
+            return Err(Error::NotPowerOfTwo(length));
+        }
+        if weights.len() != length * channels {
+            return Err(Error::LengthMismatch {
+                expected: length * channels,
+                found: weights.len(),
+            });
+        }
+        Ok(Downmixer {
+            length,
+            channels,
+            weights,
+        })
+    }
+
+    /// The signal length the weights apply to.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The number of input channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The channel weights for sequency coefficient `k`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is not less than the length.
+    pub fn weights(&self, k: usize) -> &[f64] {
+        &self.weights[k * self.channels..(k + 1) * self.channels]
+    }
+
+    /// Combine synchronized `channels` into a single signal.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if the number of channels or any
+    /// channel's length differs from the fitted configuration.
+    pub fn apply(&self, channels: &[Vec<f64>]) -> Result<Vec<f64>, Error> {
+        if channels.len() != self.channels {
+            return Err(Error::LengthMismatch {
+                expected: self.channels,
+                found: channels.len(),
+            });
+        }
+        let x = transform_channels(channels, self.length)?;
+        let mixed: Vec<f64> = (0..self.length)
+            .map(|k| {
+                self.weights(k)
+                    .iter()
+                    .enumerate()
+                    .map(|(c, w)| w * x[c * self.length + k])
+                    .sum()
+            })
+            .collect();
+        let n = self.length as f64;
+        Ok(sequency(&mixed)
+            .expect("length is a power of 2")
+            .into_iter()
+            .map(|y| y / n)
+            .collect())
+    }
+}
+
+// The sequency transforms of `channels`, concatenated channel by channel.
+fn transform_channels(channels: &[Vec<f64>], length: usize) -> Result<Vec<f64>, Error> {
+    let mut out = Vec::with_capacity(channels.len() * length);
+    for channel in channels {
+        if channel.len() != length {
+            return Err(Error::LengthMismatch {
+                expected: length,
+                found: channel.len(),
+            });
+        }
+        out.extend(sequency(channel).ok_or(Error::NotPowerOfTwo(length))?);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_recovers_known_weights() {
+        // The target is a known per-coefficient mix of three channels.
+        let n = 8;
+        let truth: Vec<f64> = (0..n * 3)
+            .map(|i| ((i * 5) % 7) as f64 / 3.0 - 1.0)
+            .collect();
+        let reference = Downmixer::from_weights(n, 3, truth.clone()).unwrap();
+        let recordings: Vec<Vec<Vec<f64>>> = (0..6)
+            .map(|r| {
+                (0..3)
+                    .map(|c| {
+                        (0..n)
+                            .map(|i| (((r * 31 + c * 17 + i * 13) % 19) as f64) - 9.0)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let targets: Vec<Vec<f64>> = recordings
+            .iter()
+            .map(|r| reference.apply(r).unwrap())
+            .collect();
+        let fitted = Downmixer::fit(&recordings, &targets, 0.0).unwrap();
+        for (w, t) in fitted.weights.iter().zip(&truth) {
+            assert!((w - t).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Downmixer::fit(&[], &[], 1.0), Err(Error::Singular));
+        assert_eq!(
+            Downmixer::fit(&[vec![vec![1.0; 3]]], &[vec![1.0; 3]], 1.0),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            Downmixer::fit(&[vec![vec![1.0; 4]]], &[], 1.0),
+            Err(Error::LengthMismatch {
+                expected: 1,
+                found: 0
+            })
+        );
+        // One recording cannot determine two weights per coefficient.
+        assert_eq!(
+            Downmixer::fit(&[vec![vec![1.0; 4], vec![2.0; 4]]], &[vec![1.0; 4]], 0.0),
+            Err(Error::Singular)
+        );
+        let mixer = Downmixer::from_weights(4, 2, vec![0.5; 8]).unwrap();
+        assert!(mixer.apply(&[vec![1.0; 4]]).is_err());
+        assert!(mixer.apply(&[vec![1.0; 4], vec![1.0; 2]]).is_err());
+        assert_eq!(
+            Downmixer::from_weights(3, 1, vec![1.0; 3]),
+            Err(Error::NotPowerOfTwo(3))
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Operations on sequences under dyadic (XOR) index arithmetic.
+
+use std::ops::{Mul, Neg};
+
+use crate::{kernel, power_of_2, Error, Normalize, Ordering, Plan, WalshElement};
+
+/// Return the dyadic (XOR) convolution of `a` and `b`, whose `k`-th entry
+/// is the sum of `a[i] * b[j]` over all `i ^ j == k`.
+///
+/// The result is computed with forward Walsh transforms of both inputs,
+/// a pointwise product, and a scaled inverse transform. Integer inputs
+/// produce exact integer results.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if the inputs differ in length,
+/// [`Error::NotPowerOfTwo`] if their length is not a power of 2, and
+/// [`Error::InexactScaling`] if an integer result cannot be scaled
+/// exactly, which only happens if intermediate values overflowed.
+///
+/// # Example
+///
+/// ```
+/// let a = [1, 2, 3, 4];
+/// let b = [1, 0, 0, 1];
+/// assert_eq!(fwt::dyadic_convolution(&a, &b), Ok(vec![5, 5, 5, 5]));
+/// assert_eq!(
+///     fwt::dyadic_convolution(&[1.0, 2.0], &[1.0]),
+///     Err(fwt::Error::LengthMismatch { expected: 2, found: 1 })
+/// );
+/// ```
+pub fn dyadic_convolution<T>(a: &[T], b: &[T]) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Mul<Output = T> + Normalize,
+{
+    dyadic_correlation(a, b)
+}
+
+/// Return the dyadic cross-correlation of `a` and `b`, whose `tau`-th
+/// entry is the sum of `a[i] * b[i ^ tau]` over all `i`.
+///
+/// Because XOR is its own inverse, the dyadic correlation coincides with
+/// the dyadic convolution; this name is provided for code-acquisition and
+/// similarity-search code where correlation is the natural reading. Use
+/// [`dyadic_correlation_with_plan`] to reuse a [`Plan`] across calls.
+///
+/// # Errors
+///
+/// As for [`dyadic_convolution`].
+///
+/// # Example
+///
+/// ```
+/// let code = [1, -1, -1, 1, 1, 1, -1, -1];
+/// let shifted: Vec<i32> = (0..8).map(|i| code[i ^ 5]).collect();
+/// let r = fwt::dyadic_correlation(&code, &shifted).unwrap();
+/// assert_eq!(r[5], 8);
+/// ```
+pub fn dyadic_correlation<T>(a: &[T], b: &[T]) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Mul<Output = T> + Normalize,
+{
+    if b.len() != a.len() {
+        return Err(Error::LengthMismatch {
+            expected: a.len(),
+            found: b.len(),
+        });
+    }
+    let plan = Plan::new(a.len(), Ordering::Hadamard)?;
+    dyadic_correlation_with_plan(&plan, a, b)
+}
+
+/// As [`dyadic_correlation`], but using an existing `plan` for the
+/// transforms. Any ordering may be used, since the pointwise product does
+/// not depend on the order of the coefficients.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if either input does not have the
+/// plan's length, and [`Error::InexactScaling`] as for
+/// [`dyadic_convolution`].
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, Plan};
+///
+/// let plan = Plan::new(4, Ordering::Sequency).unwrap();
+/// let r = fwt::dyadic_correlation_with_plan(&plan, &[1.0, 2.0, 0.0, 0.0], &[0.0, 1.0, 0.0, 0.0]);
+/// assert_eq!(r, Ok(vec![2.0, 1.0, 0.0, 0.0]));
+/// ```
+pub fn dyadic_correlation_with_plan<T>(plan: &Plan, a: &[T], b: &[T]) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Mul<Output = T> + Normalize,
+{
+    let mut fa = plan.transform(a)?;
+    let fb = plan.transform(b)?;
+    for (x, &y) in fa.iter_mut().zip(fb.iter()) {
+        *x = *x * y;
+    }
+    plan.execute(&mut fa)?;
+    let length = plan.length();
+    fa.into_iter()
+        .map(|x| x.normalize(length).ok_or(Error::InexactScaling))
+        .collect()
+}
+
+/// Return the dyadic autocorrelation of `v`, whose `tau`-th entry is the
+/// sum of `v[i] * v[i ^ tau]` over all `i`.
+///
+/// This needs one fewer transform than correlating `v` with itself: the
+/// spectrum is computed once, squared, and transformed back.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2, and [`Error::InexactScaling`] as for [`dyadic_convolution`].
+///
+/// # Example
+///
+/// ```
+/// let v = [1, 1, -1, -1, 1, 1, -1, -1];
+/// assert_eq!(
+///     fwt::dyadic_autocorrelation(&v),
+///     Ok(vec![8, 8, -8, -8, 8, 8, -8, -8])
+/// );
+/// ```
+pub fn dyadic_autocorrelation<T>(v: &[T]) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Mul<Output = T> + Normalize,
+{
+    let plan = Plan::new(v.len(), Ordering::Hadamard)?;
+    let mut spectrum = plan.transform(v)?;
+    for x in spectrum.iter_mut() {
+        *x = *x * *x;
+    }
+    plan.execute(&mut spectrum)?;
+    let length = plan.length();
+    spectrum
+        .into_iter()
+        .map(|x| x.normalize(length).ok_or(Error::InexactScaling))
+        .collect()
+}
+
+/// Return `v` permuted by the dyadic shift `tau`, so that entry `i` of
+/// the result is `v[i ^ tau]`. Returns `None` if the length of `v` is not
+/// a power of 2 or `tau` is not a valid index.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(fwt::dyadic_shift(&[1, 2, 3, 4], 1), Some(vec![2, 1, 4, 3]));
+/// assert_eq!(fwt::dyadic_shift(&[1, 2, 3, 4], 4), None);
+/// ```
+pub fn dyadic_shift<T>(v: &[T], tau: usize) -> Option<Vec<T>>
+where
+    T: Copy,
+{
+    if power_of_2(v.len()) && tau < v.len() {
+        Some((0..v.len()).map(|i| v[i ^ tau]).collect())
+    } else {
+        None
+    }
+}
+
+/// Apply the dyadic shift `tau` to a signal given its `spectrum` in the
+/// stated `ordering`, without leaving the Walsh domain.
+///
+/// By the shift theorem, shifting a signal by `tau` multiplies the
+/// coefficient of the Walsh function with Hadamard index `k` by
+/// `(-1)^popcount(k & tau)` and leaves its magnitude unchanged, so the
+/// result equals the transform of [`dyadic_shift`] applied to the signal.
+/// Returns `None` under the same conditions as [`dyadic_shift`].
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let v = [1, 2, 3, 4, 5, 6, 7, 8];
+/// let shifted = fwt::dyadic_shift(&v, 6).unwrap();
+/// let spectrum = fwt::sequency(&v).unwrap();
+/// assert_eq!(
+///     fwt::dyadic_shift_spectrum(&spectrum, 6, Ordering::Sequency),
+///     fwt::sequency(&shifted)
+/// );
+/// ```
+pub fn dyadic_shift_spectrum<T>(spectrum: &[T], tau: usize, ordering: Ordering) -> Option<Vec<T>>
+where
+    T: WalshElement + Neg<Output = T>,
+{
+    let length = spectrum.len();
+    if !power_of_2(length) || tau >= length {
+        return None;
+    }
+    Some(
+        spectrum
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                let k = match ordering {
+                    Ordering::Sequency => kernel::sequency_to_hadamard(i, length),
+                    Ordering::Hadamard => i,
+                };
+                if (k & tau).count_ones() & 1 == 1 {
+                    -x
+                } else {
+                    x
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Return the Gibbs dyadic derivative of the sampled function `v`.
+///
+/// The Gibbs derivative is the dyadic analogue of differentiation: the
+/// Walsh functions are its eigenfunctions, and the coefficient of the
+/// Walsh function with Paley (dyadic) index `k` is multiplied by `k`.
+/// It is computed directly from the samples as
+///
+/// `D v(x) = Σ_r 2^(r-1) · (v(x) - v(x ^ (n >> (r + 1))))`,
+///
+/// in O(*n* log(*n*)) time, where `n` is the length of `v`.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// // The Paley-ordered Walsh function with index 1 is an eigenfunction
+/// // with eigenvalue 1.
+/// let wal1 = [1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0];
+/// assert_eq!(fwt::gibbs_derivative(&wal1), Ok(wal1.to_vec()));
+/// ```
+pub fn gibbs_derivative(v: &[f64]) -> Result<Vec<f64>, Error> {
+    let length = v.len();
+    if !power_of_2(length) {
+        return Err(Error::NotPowerOfTwo(length));
+    }
+    let mut result = vec![0.0; length];
+    let mut weight = 0.5;
+    let mut stride = length >> 1;
+    while stride > 0 {
+        for (x, r) in result.iter_mut().enumerate() {
+            *r += weight * (v[x] - v[x ^ stride]);
+        }
+        weight *= 2.0;
+        stride >>= 1;
+    }
+    Ok(result)
+}
+
+/// Return the dyadic integral of `v`, the inverse of
+/// [`gibbs_derivative`].
+///
+/// The derivative annihilates constants, so the integral is determined
+/// only up to an additive constant; the result is chosen to have zero
+/// mean. Likewise, only the zero-mean part of `v` has an antiderivative,
+/// so the mean of `v` is ignored.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// let v = [3.0, -1.0, 0.5, 2.0, -4.5, 1.0, 0.0, -1.0];
+/// let derivative = fwt::gibbs_derivative(&v).unwrap();
+/// let recovered = fwt::gibbs_integral(&derivative).unwrap();
+/// assert!(recovered.iter().zip(&v).all(|(a, b)| (a - b).abs() < 1e-12));
+/// ```
+pub fn gibbs_integral(v: &[f64]) -> Result<Vec<f64>, Error> {
+    let length = v.len();
+    let plan = Plan::new(length, Ordering::Hadamard)?;
+    let mut spectrum = plan.transform(v)?;
+    for (h, c) in spectrum.iter_mut().enumerate() {
+        let paley = kernel::reverse_index(h, length);
+        *c = if paley == 0 {
+            0.0
+        } else {
+            *c / (paley * length) as f64
+        };
+    }
+    plan.execute(&mut spectrum)?;
+    Ok(spectrum)
+}
+
+/// Return the logical (Boolean) derivative of the truth table `f` with
+/// respect to variable `var`, which is true at `x` exactly when flipping
+/// bit `var` of `x` changes the value of `f`. Returns `None` if the
+/// length of `f` is not a power of 2 or `var` is not one of its
+/// variables.
+///
+/// # Example
+///
+/// ```
+/// // f(x1, x0) = x0 AND x1 depends on x0 only when x1 is set.
+/// let f = [false, false, false, true];
+/// assert_eq!(
+///     fwt::logical_derivative(&f, 0),
+///     Some(vec![false, false, true, true])
+/// );
+/// ```
+pub fn logical_derivative(f: &[bool], var: u32) -> Option<Vec<bool>> {
+    let length = f.len();
+    let bit = 1usize.checked_shl(var)?;
+    if !power_of_2(length) || bit >= length {
+        return None;
+    }
+    Some((0..length).map(|x| f[x] ^ f[x ^ bit]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dyadic_convolution() {
+        let a: Vec<i64> = (0..16).map(|x| (x * 7 + 3) % 11 - 5).collect();
+        let b: Vec<i64> = (0..16).map(|x| (x * 5 + 1) % 13 - 6).collect();
+        let mut expected = vec![0; 16];
+        for i in 0..16 {
+            for j in 0..16 {
+                expected[i ^ j] += a[i] * b[j];
+            }
+        }
+        assert_eq!(dyadic_convolution(&a, &b), Ok(expected.clone()));
+        let af: Vec<f64> = a.iter().map(|&x| x as f64).collect();
+        let bf: Vec<f64> = b.iter().map(|&x| x as f64).collect();
+        let expected: Vec<f64> = expected.iter().map(|&x| x as f64).collect();
+        assert_eq!(dyadic_convolution(&af, &bf), Ok(expected));
+    }
+
+    #[test]
+    fn test_dyadic_convolution_errors() {
+        assert_eq!(
+            dyadic_convolution(&[1, 2, 3], &[1, 2, 3]),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            dyadic_convolution::<i32>(&[], &[]),
+            Err(Error::NotPowerOfTwo(0))
+        );
+        assert_eq!(
+            dyadic_convolution(&[1, 2], &[1, 2, 3, 4]),
+            Err(Error::LengthMismatch {
+                expected: 2,
+                found: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_dyadic_correlation_finds_shift() {
+        let code: Vec<f64> = (0..32)
+            .map(|i| if (i * 13 + 5) % 7 < 3 { 1.0 } else { -1.0 })
+            .collect();
+        let plan = Plan::new(32, Ordering::Sequency).unwrap();
+        for tau in [0, 3, 17, 31] {
+            let shifted: Vec<f64> = (0..32).map(|i| code[i ^ tau]).collect();
+            let r = dyadic_correlation_with_plan(&plan, &code, &shifted).unwrap();
+            let peak = (0..32).max_by(|&i, &j| r[i].total_cmp(&r[j])).unwrap();
+            assert_eq!(peak, tau);
+            assert_eq!(r[tau], 32.0);
+        }
+        let plan = Plan::new(16, Ordering::Hadamard).unwrap();
+        assert_eq!(
+            dyadic_correlation_with_plan(&plan, &code, &code),
+            Err(Error::LengthMismatch {
+                expected: 16,
+                found: 32
+            })
+        );
+    }
+
+    #[test]
+    fn test_dyadic_autocorrelation() {
+        let v: Vec<i64> = (0..16).map(|x| (x * 7 + 3) % 11 - 5).collect();
+        assert_eq!(dyadic_autocorrelation(&v), dyadic_correlation(&v, &v));
+        let energy: i64 = v.iter().map(|x| x * x).sum();
+        assert_eq!(dyadic_autocorrelation(&v).unwrap()[0], energy);
+        assert_eq!(dyadic_autocorrelation(&[1.0; 6]), Err(Error::NotPowerOfTwo(6)));
+    }
+
+    #[test]
+    fn test_shift_theorem() {
+        let v: Vec<f64> = (0..32).map(|x| ((x * 7 + 3) % 11) as f64 - 5.0).collect();
+        for tau in 0..32 {
+            let shifted = dyadic_shift(&v, tau).unwrap();
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let plan = Plan::new(32, ordering).unwrap();
+                let spectrum = plan.transform(&v).unwrap();
+                assert_eq!(
+                    dyadic_shift_spectrum(&spectrum, tau, ordering),
+                    plan.transform(&shifted).ok()
+                );
+            }
+        }
+        assert_eq!(dyadic_shift(&[1, 2, 3], 1), None);
+        assert_eq!(dyadic_shift_spectrum(&[1, 2], 2, Ordering::Hadamard), None);
+    }
+
+    #[test]
+    fn test_gibbs_eigenvalues_are_paley_indices() {
+        let n = 16;
+        for h in 0..n {
+            let mut unit = vec![0.0; n];
+            unit[h] = 1.0;
+            let wal = Plan::new(n, Ordering::Hadamard).unwrap().transform(&unit).unwrap();
+            let paley = kernel::reverse_index(h, n) as f64;
+            let expected: Vec<f64> = wal.iter().map(|w| paley * w).collect();
+            assert_eq!(gibbs_derivative(&wal), Ok(expected));
+        }
+        assert_eq!(gibbs_derivative(&[1.0]), Ok(vec![0.0]));
+        assert_eq!(gibbs_integral(&[1.0, 2.0, 3.0]), Err(Error::NotPowerOfTwo(3)));
+    }
+
+    #[test]
+    fn test_logical_derivative() {
+        // Parity depends on every variable everywhere.
+        let parity: Vec<bool> = (0..8u32).map(|x| x.count_ones() % 2 == 1).collect();
+        for var in 0..3 {
+            assert_eq!(logical_derivative(&parity, var), Some(vec![true; 8]));
+        }
+        assert_eq!(logical_derivative(&parity, 3), None);
+        assert_eq!(logical_derivative(&parity, 200), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(12i32.normalize(4), Some(3));
+        assert_eq!(13i32.normalize(4), None);
+        assert_eq!(1i8.normalize(256), None);
+        assert_eq!(1.0f32.normalize(4), Some(0.25));
+    }
+}
@@ -0,0 +1,201 @@
+use std::num::{Saturating, Wrapping};
+use std::ops::{Add, Sub};
+
+/// The element types that the transforms accept.
+///
+/// Walsh transforms only ever add and subtract elements, so any `Copy`
+/// type closed under `+` and `-` can be transformed once it implements
+/// this trait. Implementations are provided for the signed integer and
+/// floating-point primitives and for [`Wrapping`] and [`Saturating`]
+/// integers; user types such as clamped fixed-point numbers, intervals,
+/// dual numbers, or modular integers only need an empty `impl`.
+///
+/// Unsigned primitives are deliberately excluded because the transform of
+/// almost any input has negative entries. Use `Wrapping<u32>` and friends
+/// for arithmetic modulo a power of 2.
+///
+/// On x86_64, `i32`, `f32` and `f64` override the butterfly methods with
+/// AVX-512 or AVX2 loops chosen at run time from the features of the CPU,
+/// and on aarch64 with NEON loops, falling back to scalar loops. On wasm32
+/// built with the `simd128` target feature they use SIMD128 loops. With the
+/// nightly-only `simd` feature they use `std::simd` loops instead, on every
+/// target. Either way they compute exactly the same values as long as no
+/// sum overflows.
+///
+/// `i16` and `Saturating<i16>`, common for raw ADC samples and pixels,
+/// are vectorized the same way, with AVX2 on x86_64. `Saturating<i16>`
+/// uses saturating vector instructions. To keep the full range of 16-bit
+/// input, transform it into a wider type with
+/// [`Plan::transform_widened`](crate::Plan::transform_widened).
+///
+/// The result of an `i16` or `i32` transform that overflows is
+/// unspecified: the vector loops wrap, while the scalar loops that
+/// handle short slices and the ends of long ones panic in debug builds,
+/// so the outcome depends on the length and the CPU. Use `Wrapping<i16>`
+/// or `Wrapping<i32>` for arithmetic that wraps at every length, or
+/// `Saturating<i16>` for arithmetic that saturates.
+///
+/// `f32` has vector butterflies of its own rather than sharing those of
+/// `f64`, such as 8 lanes with AVX2 and 16 with AVX-512 on x86_64: twice
+/// as many as `f64`, so single precision transforms need half the vector
+/// operations. To
+/// keep inverse transforms in `f32`, use
+/// [`Plan::execute_inverse`](crate::Plan::execute_inverse) or
+/// [`scale_in_place`](crate::scale_in_place) rather than
+/// [`scale`](crate::scale), which widens to `f64`.
+///
+/// # Example
+///
+/// ```
+/// use std::ops::{Add, Sub};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// struct Clamped(i8);
+///
+/// impl Add for Clamped {
+///     type Output = Clamped;
+///     fn add(self, rhs: Clamped) -> Clamped {
+///         Clamped((self.0 + rhs.0).clamp(-100, 100))
+///     }
+/// }
+///
+/// impl Sub for Clamped {
+///     type Output = Clamped;
+///     fn sub(self, rhs: Clamped) -> Clamped {
+///         Clamped((self.0 - rhs.0).clamp(-100, 100))
+///     }
+/// }
+///
+/// impl fwt::WalshElement for Clamped {}
+///
+/// let v = [Clamped(60), Clamped(60)];
+/// assert_eq!(fwt::hadamard(&v), Some(vec![Clamped(100), Clamped(0)]));
+/// ```
+pub trait WalshElement: Copy + Add<Output = Self> + Sub<Output = Self> {
+    /// Replace each pair `(a[i], b[i])` with `(a[i] + b[i], a[i] - b[i])`.
+    /// The slices have the same length. Types may override this with a
+    /// vectorized loop, which must compute exactly the values of the
+    /// default.
+    fn butterfly(a: &mut [Self], b: &mut [Self]) {
+        for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+            (*x, *y) = (*x + *y, *x - *y);
+        }
+    }
+
+    /// Replace each pair `(a[i], b[i])` with `(a[i] - b[i], a[i] + b[i])`,
+    /// the butterfly with exchanged outputs that the sequency ordering
+    /// uses in odd groups, under the same rules as
+    /// [`butterfly`](WalshElement::butterfly).
+    fn butterfly_crossed(a: &mut [Self], b: &mut [Self]) {
+        for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+            (*x, *y) = (*x - *y, *x + *y);
+        }
+    }
+}
+
+macro_rules! walsh_element {
+    ($($t:ty),*) => {$(
+        impl WalshElement for $t {}
+    )*};
+}
+
+macro_rules! walsh_element_wrapping {
+    ($($t:ty),*) => {$(
+        impl WalshElement for Wrapping<$t> {}
+    )*};
+}
+
+macro_rules! walsh_element_saturating {
+    ($($t:ty),*) => {$(
+        impl WalshElement for Saturating<$t> {}
+    )*};
+}
+
+#[cfg(feature = "simd")]
+macro_rules! walsh_element_simd {
+    ($($t:ty => $lanes:literal),*) => {$(
+        impl WalshElement for $t {
+            fn butterfly(a: &mut [$t], b: &mut [$t]) {
+                crate::simd::butterfly::<$t, $lanes>(a, b, false);
+            }
+
+            fn butterfly_crossed(a: &mut [$t], b: &mut [$t]) {
+                crate::simd::butterfly::<$t, $lanes>(a, b, true);
+            }
+        }
+    )*};
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "simd")))]
+use crate::x86 as arch;
+
+#[cfg(all(target_arch = "aarch64", not(feature = "simd")))]
+use crate::aarch64 as arch;
+
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "simd")
+))]
+use crate::wasm as arch;
+
+#[cfg(all(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    ),
+    not(feature = "simd")
+))]
+macro_rules! walsh_element_arch {
+    ($($t:ty => $butterfly:ident),*) => {$(
+        impl WalshElement for $t {
+            fn butterfly(a: &mut [$t], b: &mut [$t]) {
+                arch::$butterfly(a, b, false);
+            }
+
+            fn butterfly_crossed(a: &mut [$t], b: &mut [$t]) {
+                arch::$butterfly(a, b, true);
+            }
+        }
+    )*};
+}
+
+walsh_element!(i8, i64, i128, isize);
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    feature = "simd"
+)))]
+walsh_element!(i16, i32, f32, f64);
+#[cfg(all(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    ),
+    not(feature = "simd")
+))]
+walsh_element_arch!(
+    i16 => butterfly_i16,
+    i32 => butterfly_i32,
+    f32 => butterfly_f32,
+    f64 => butterfly_f64,
+    Saturating<i16> => butterfly_saturating_i16
+);
+#[cfg(feature = "simd")]
+walsh_element_simd!(i16 => 16, i32 => 8, f32 => 8, f64 => 4);
+walsh_element_wrapping!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+// `std::simd` has no saturating operators, so `Saturating<i16>` is scalar
+// with the `simd` feature.
+#[cfg(not(all(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    ),
+    not(feature = "simd")
+)))]
+walsh_element_saturating!(i16);
+walsh_element_saturating!(i8, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
@@ -0,0 +1,64 @@
+use std::fmt;
+
+/// Errors reported by the operations in this crate that return a
+/// `Result`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// An input length that must be a power of 2 was not.
+    NotPowerOfTwo(usize),
+    /// Two inputs that must have the same length did not.
+    LengthMismatch { expected: usize, found: usize },
+    /// Dividing an integer result by the transform length would have
+    /// discarded a remainder.
+    InexactScaling,
+    /// A modulus was out of range or did not admit the required inverse.
+    InvalidModulus(u64),
+    /// A radix was less than 2.
+    InvalidRadix(usize),
+    /// An input length that must be a power of the radix was not.
+    NotPowerOfRadix { length: usize, radix: usize },
+    /// Band edges were not strictly increasing from 0 within the
+    /// transform length.
+    InvalidBands,
+    /// Sketches built with different parameters were combined.
+    IncompatibleSketches,
+    /// A fitting problem did not determine a unique solution.
+    Singular,
+    /// An axis was out of range for an array's shape or was listed more
+    /// than once.
+    InvalidAxis(usize),
+    /// A digit character was not a ±1 character of the base: the base
+    /// must be 2 or 4 and the character between 1 and the base less 1.
+    InvalidCharacter { base: u64, character: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotPowerOfTwo(n) => write!(f, "length {} is not a power of 2", n),
+            Error::LengthMismatch { expected, found } => {
+                write!(f, "expected length {}, found {}", expected, found)
+            }
+            Error::InexactScaling => write!(f, "result is not exactly divisible by the length"),
+            Error::InvalidModulus(p) => write!(f, "{} is not a usable modulus", p),
+            Error::InvalidRadix(p) => write!(f, "radix {} is less than 2", p),
+            Error::NotPowerOfRadix { length, radix } => {
+                write!(f, "length {} is not a power of {}", length, radix)
+            }
+            Error::InvalidBands => write!(f, "band edges are not valid for the length"),
+            Error::IncompatibleSketches => write!(f, "the sketches are not compatible"),
+            Error::Singular => write!(f, "the system has no unique solution"),
+            Error::InvalidAxis(axis) => write!(f, "axis {} is out of range or repeated", axis),
+            Error::InvalidCharacter { base, character } => {
+                write!(
+                    f,
+                    "{} is not a ±1 character of base-{} digits",
+                    character, base
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,594 @@
+//! Frame-by-frame transforms of raw sample files, memory-mapped and
+//! processed in parallel.
+//!
+//! The input is a headerless file of little-endian samples, split into
+//! consecutive frames of a fixed power-of-2 length; a final partial frame
+//! is padded with zeros. The output is a [`format`](crate::format)
+//! container holding one spectrum per frame as a row, so it can be read
+//! back with [`format::read`](crate::format::read) or mapped directly,
+//! with the spectra starting at byte 32.
+//!
+//! Enable with the `mmap` feature.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::format::{self, Kind, Payload};
+use crate::{Error, Ordering, Plan};
+
+const HEADER_BYTES: usize = 32;
+
+/// The encoding of the samples in an input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleFormat {
+    /// 32-bit IEEE floats, transformed to `f32` spectra.
+    F32,
+    /// 16-bit signed integers, transformed exactly to `i32` spectra.
+    I16,
+}
+
+impl SampleFormat {
+    fn input_bytes(self) -> usize {
+        match self {
+            SampleFormat::F32 => 4,
+            SampleFormat::I16 => 2,
+        }
+    }
+
+    fn output_type_code(self) -> u8 {
+        match self {
+            SampleFormat::F32 => Payload::F32(Vec::new()).type_code(),
+            SampleFormat::I16 => Payload::I32(Vec::new()).type_code(),
+        }
+    }
+}
+
+/// What [`FileTransform::run`] processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileReport {
+    /// The number of frames transformed.
+    pub frames: usize,
+    /// The number of zeros appended to complete the last frame.
+    pub padding: usize,
+    /// The number of frames already done by an earlier, interrupted run
+    /// with checkpoints, which this run skipped.
+    pub resumed_from: usize,
+}
+
+/// A reusable description of a file transform: the frame length,
+/// ordering, sample format and number of worker threads.
+///
+/// # Example
+///
+/// ```no_run
+/// use fwt::file::{FileTransform, SampleFormat};
+/// use fwt::Ordering;
+///
+/// let report = FileTransform::new(1024, Ordering::Sequency)
+///     .unwrap()
+///     .with_sample_format(SampleFormat::I16)
+///     .run("recording.raw", "spectra.fwt")
+///     .unwrap();
+/// println!("{} frames", report.frames);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTransform {
+    plan: Plan,
+    sample_format: SampleFormat,
+    threads: usize,
+    checkpoint_interval: Option<usize>,
+}
+
+impl FileTransform {
+    /// Create a transform of `f32` frames of `frame_length` samples in
+    /// the given ordering, using one thread per available CPU.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `frame_length` is not a power
+    /// of 2.
+    pub fn new(frame_length: usize, ordering: Ordering) -> Result<FileTransform, Error> {
+        Ok(FileTransform {
+            plan: Plan::new(frame_length, ordering)?,
+            sample_format: SampleFormat::F32,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            checkpoint_interval: None,
+        })
+    }
+
+    /// Return this transform reading samples in the given format.
+    pub fn with_sample_format(self, sample_format: SampleFormat) -> FileTransform {
+        FileTransform {
+            sample_format,
+            ..self
+        }
+    }
+
+    /// Return this transform using `threads` worker threads, at least 1.
+    pub fn with_threads(self, threads: usize) -> FileTransform {
+        FileTransform {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    /// The number of samples per frame.
+    pub fn frame_length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The ordering of the spectra.
+    pub fn ordering(&self) -> Ordering {
+        self.plan.ordering()
+    }
+
+    /// The format of the input samples.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of worker threads.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Return this transform saving its progress every `frames` frames,
+    /// so that an interrupted [`run`](FileTransform::run) can resume.
+    ///
+    /// With checkpoints, spectra are written to `<output>.partial` and
+    /// progress is recorded in a small text manifest, `<output>.progress`,
+    /// which is replaced atomically after the spectra it covers have been
+    /// flushed to disk. A later run with the same input, output and
+    /// settings continues after the last recorded frame. When every frame
+    /// is done the partial file is renamed to `output` and the manifest is
+    /// removed, so `output` only ever appears complete. The input must not
+    /// change between runs.
+    pub fn with_checkpoint_interval(self, frames: usize) -> FileTransform {
+        FileTransform {
+            checkpoint_interval: Some(frames.max(1)),
+            ..self
+        }
+    }
+
+    /// The number of frames between checkpoints, if checkpoints are
+    /// enabled.
+    pub fn checkpoint_interval(&self) -> Option<usize> {
+        self.checkpoint_interval
+    }
+
+    /// Transform every frame of the sample file at `input`, writing the
+    /// spectra to a container at `output`, which is created or replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if the
+    /// input is not a whole number of samples, if the output would hold
+    /// more elements than the container format allows, or if `i16` frames
+    /// are longer than 2^16 samples, whose spectra could overflow `i32`.
+    /// Propagates errors from opening, mapping and writing the files.
+    pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input: P,
+        output: Q,
+    ) -> io::Result<FileReport> {
+        let length = self.frame_length();
+        if self.sample_format == SampleFormat::I16 && length > 1 << 16 {
+            return Err(invalid_input("i16 frames are limited to 2^16 samples"));
+        }
+        let source = File::open(input)?;
+        let bytes = usize::try_from(source.metadata()?.len())
+            .map_err(|_| invalid_input("input too large to map"))?;
+        if bytes % self.sample_format.input_bytes() != 0 {
+            return Err(invalid_input("input is not a whole number of samples"));
+        }
+        let samples = bytes / self.sample_format.input_bytes();
+        let frames = samples.div_ceil(length);
+        let count = u32::try_from(frames * length)
+            .map_err(|_| invalid_input("output too large for the container format"))?;
+        let header = format::header(
+            Kind::Spectrum,
+            Some(self.ordering()),
+            self.sample_format.output_type_code(),
+            frames as u64,
+            length as u64,
+            count,
+        );
+        let total = (HEADER_BYTES + frames * length * 4) as u64;
+        // SAFETY: the files are opened by this function and are not
+        // expected to be modified by other processes while mapped.
+        let map = match frames {
+            0 => None,
+            _ => Some(unsafe { Mmap::map(&source)? }),
+        };
+        let input = map.as_deref().unwrap_or(&[]);
+        let output = output.as_ref();
+        let padding = frames * length - samples;
+
+        let Some(interval) = self.checkpoint_interval else {
+            let mut out = create_mapped(output, total, true)?;
+            out[..HEADER_BYTES].copy_from_slice(&header);
+            self.transform_frames(input, &mut out[HEADER_BYTES..], 0);
+            out.flush()?;
+            return Ok(FileReport {
+                frames,
+                padding,
+                resumed_from: 0,
+            });
+        };
+
+        let partial = sibling(output, "partial");
+        let progress = sibling(output, "progress");
+        let mut manifest = Manifest {
+            input_bytes: bytes,
+            frame_length: length,
+            ordering: self.ordering(),
+            sample_format: self.sample_format,
+            frames_done: 0,
+        };
+        let resumed_from = match (Manifest::load(&progress), fs::metadata(&partial)) {
+            (Some(saved), Ok(meta)) if saved.continues(&manifest) && meta.len() == total => {
+                saved.frames_done.min(frames)
+            }
+            _ => 0,
+        };
+        let mut out = create_mapped(&partial, total, resumed_from == 0)?;
+        out[..HEADER_BYTES].copy_from_slice(&header);
+        let frame_bytes = length * 4;
+        manifest.frames_done = resumed_from;
+        while manifest.frames_done < frames {
+            let (start, end) = (
+                manifest.frames_done,
+                (manifest.frames_done + interval).min(frames),
+            );
+            let range = HEADER_BYTES + start * frame_bytes..HEADER_BYTES + end * frame_bytes;
+            self.transform_frames(input, &mut out[range.clone()], start);
+            out.flush_range(0, range.end)?;
+            manifest.frames_done = end;
+            manifest.save(&progress)?;
+        }
+        out.flush()?;
+        drop(out);
+        fs::rename(&partial, output)?;
+        match fs::remove_file(&progress) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        Ok(FileReport {
+            frames,
+            padding,
+            resumed_from,
+        })
+    }
+
+    // Transform the frames of the raw samples `input`, starting with frame
+    // `first`, into the spectrum bytes `output`, splitting the frames
+    // evenly between the threads.
+    fn transform_frames(&self, input: &[u8], output: &mut [u8], first: usize) {
+        let length = self.frame_length();
+        let frame_bytes = length * 4;
+        let frames = output.len() / frame_bytes;
+        if frames == 0 {
+            return;
+        }
+        let per_thread = frames.div_ceil(self.threads);
+        std::thread::scope(|scope| {
+            for (i, chunk) in output.chunks_mut(per_thread * frame_bytes).enumerate() {
+                scope.spawn(move || {
+                    let first = first + i * per_thread;
+                    match self.sample_format {
+                        SampleFormat::F32 => self.worker::<f32, 4>(input, chunk, first),
+                        SampleFormat::I16 => self.worker::<i32, 2>(input, chunk, first),
+                    }
+                });
+            }
+        });
+    }
+
+    // Transform the frames starting with frame `first` into `output`,
+    // decoding `N`-byte input samples into a scratch frame of `T`.
+    fn worker<T: Sample, const N: usize>(&self, input: &[u8], output: &mut [u8], first: usize) {
+        let length = self.frame_length();
+        let mut frame = vec![T::default(); length];
+        for (k, out) in output.chunks_exact_mut(length * 4).enumerate() {
+            let start = ((first + k) * length * N).min(input.len());
+            let end = (start + length * N).min(input.len());
+            let samples = input[start..end].chunks_exact(N);
+            let filled = samples.len();
+            for (x, bytes) in frame.iter_mut().zip(samples) {
+                *x = T::decode(bytes);
+            }
+            frame[filled..].fill(T::default());
+            self.plan.execute(&mut frame).expect("frame matches plan");
+            for (x, bytes) in frame.iter().zip(out.chunks_exact_mut(4)) {
+                bytes.copy_from_slice(&x.encode());
+            }
+        }
+    }
+}
+
+// A spectrum element type with its input decoding and output encoding.
+trait Sample: crate::WalshElement + Default + Send {
+    fn decode(bytes: &[u8]) -> Self;
+    fn encode(self) -> [u8; 4];
+}
+
+impl Sample for f32 {
+    fn decode(bytes: &[u8]) -> f32 {
+        f32::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn encode(self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+}
+
+impl Sample for i32 {
+    fn decode(bytes: &[u8]) -> i32 {
+        i32::from(i16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn encode(self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+}
+
+// The progress of a checkpointed run, saved as `key value` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Manifest {
+    input_bytes: usize,
+    frame_length: usize,
+    ordering: Ordering,
+    sample_format: SampleFormat,
+    frames_done: usize,
+}
+
+impl Manifest {
+    // Whether `self` records progress on the same job as `other`.
+    fn continues(&self, other: &Manifest) -> bool {
+        Manifest {
+            frames_done: other.frames_done,
+            ..self.clone()
+        } == *other
+    }
+
+    fn load(path: &Path) -> Option<Manifest> {
+        let text = fs::read_to_string(path).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != "fwt-progress 1" {
+            return None;
+        }
+        let mut field = |key: &str| -> Option<String> {
+            let (k, v) = lines.next()?.split_once(' ')?;
+            (k == key).then(|| v.to_string())
+        };
+        Some(Manifest {
+            input_bytes: field("input_bytes")?.parse().ok()?,
+            frame_length: field("frame_length")?.parse().ok()?,
+            ordering: match field("ordering")?.as_str() {
+                "sequency" => Ordering::Sequency,
+                "hadamard" => Ordering::Hadamard,
+                _ => return None,
+            },
+            sample_format: match field("sample_format")?.as_str() {
+                "f32" => SampleFormat::F32,
+                "i16" => SampleFormat::I16,
+                _ => return None,
+            },
+            frames_done: field("frames_done")?.parse().ok()?,
+        })
+    }
+
+    // Replace the manifest at `path` atomically, via a temporary file.
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let text = format!(
+            "fwt-progress 1\ninput_bytes {}\nframe_length {}\nordering {}\nsample_format {}\nframes_done {}\n",
+            self.input_bytes,
+            self.frame_length,
+            match self.ordering {
+                Ordering::Sequency => "sequency",
+                Ordering::Hadamard => "hadamard",
+            },
+            match self.sample_format {
+                SampleFormat::F32 => "f32",
+                SampleFormat::I16 => "i16",
+            },
+            self.frames_done,
+        );
+        let temporary = sibling(path, "tmp");
+        let mut file = File::create(&temporary)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)
+    }
+}
+
+// `path` with `.suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Open or create the file at `path` with exactly `length` bytes,
+// discarding any existing contents if `truncate`, and map it.
+fn create_mapped(path: &Path, length: u64, truncate: bool) -> io::Result<MmapMut> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(truncate)
+        .open(path)?;
+    file.set_len(length)?;
+    // SAFETY: the file is opened by this function and is not expected to
+    // be modified by other processes while mapped.
+    unsafe { MmapMut::map_mut(&file) }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn scratch(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fwt-file-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_f32_frames_match_plan() {
+        let samples: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.37).sin()).collect();
+        let (input, output) = (scratch("f32.raw"), scratch("f32.fwt"));
+        let raw: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+        std::fs::write(&input, raw).unwrap();
+        for threads in [1, 3] {
+            let transform = FileTransform::new(64, Ordering::Sequency)
+                .unwrap()
+                .with_threads(threads);
+            let report = transform.run(&input, &output).unwrap();
+            assert_eq!(
+                report,
+                FileReport {
+                    frames: 16,
+                    padding: 24,
+                    resumed_from: 0
+                }
+            );
+            let artifact = format::read(&mut File::open(&output).unwrap()).unwrap();
+            assert_eq!(artifact.shape(), (16, 64));
+            assert_eq!(artifact.ordering(), Some(Ordering::Sequency));
+            let Payload::F32(spectra) = artifact.payload() else {
+                panic!("wrong payload type");
+            };
+            let mut padded = samples.clone();
+            padded.resize(1024, 0.0);
+            for (frame, spectrum) in padded.chunks(64).zip(spectra.chunks(64)) {
+                assert_eq!(crate::sequency(frame).unwrap(), spectrum);
+            }
+        }
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_i16_frames_are_exact() {
+        let samples: Vec<i16> = (0..512)
+            .map(|i| if i % 3 == 0 { i16::MAX } else { i16::MIN })
+            .collect();
+        let (input, output) = (scratch("i16.raw"), scratch("i16.fwt"));
+        let raw: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+        std::fs::write(&input, raw).unwrap();
+        let transform = FileTransform::new(256, Ordering::Hadamard)
+            .unwrap()
+            .with_sample_format(SampleFormat::I16);
+        assert_eq!(transform.run(&input, &output).unwrap().frames, 2);
+        let artifact = format::read(&mut File::open(&output).unwrap()).unwrap();
+        let wide: Vec<i32> = samples.iter().map(|&x| i32::from(x)).collect();
+        let expected: Vec<i32> = wide
+            .chunks(256)
+            .flat_map(|f| crate::hadamard(f).unwrap())
+            .collect();
+        assert_eq!(artifact.payload(), &Payload::I32(expected));
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_checkpointed_run_resumes() {
+        let samples: Vec<f32> = (0..4096).map(|i| (i as f32 * 0.11).cos()).collect();
+        let (input, output, reference) =
+            (scratch("ck.raw"), scratch("ck.fwt"), scratch("ck-ref.fwt"));
+        let raw: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+        std::fs::write(&input, raw).unwrap();
+        let plain = FileTransform::new(128, Ordering::Sequency)
+            .unwrap()
+            .with_threads(2);
+        plain.run(&input, &reference).unwrap();
+        let expected = std::fs::read(&reference).unwrap();
+
+        let checkpointed = plain.clone().with_checkpoint_interval(5);
+        assert_eq!(checkpointed.run(&input, &output).unwrap().resumed_from, 0);
+        assert_eq!(std::fs::read(&output).unwrap(), expected);
+        let (partial, progress) = (sibling(&output, "partial"), sibling(&output, "progress"));
+        assert!(!partial.exists() && !progress.exists());
+
+        // Simulate a run interrupted after 10 of the 32 frames: frames
+        // after the checkpoint are recomputed, frames before it are kept.
+        let mut interrupted = expected.clone();
+        interrupted[HEADER_BYTES..HEADER_BYTES + 4].copy_from_slice(&7f32.to_le_bytes());
+        interrupted[HEADER_BYTES + 10 * 512..].fill(0xff);
+        std::fs::write(&partial, &interrupted).unwrap();
+        let manifest = Manifest {
+            input_bytes: 4096 * 4,
+            frame_length: 128,
+            ordering: Ordering::Sequency,
+            sample_format: SampleFormat::F32,
+            frames_done: 10,
+        };
+        manifest.save(&progress).unwrap();
+        assert_eq!(Manifest::load(&progress), Some(manifest.clone()));
+        assert_eq!(checkpointed.run(&input, &output).unwrap().resumed_from, 10);
+        let resumed = std::fs::read(&output).unwrap();
+        assert_eq!(resumed[HEADER_BYTES..HEADER_BYTES + 4], 7f32.to_le_bytes());
+        assert_eq!(resumed[HEADER_BYTES + 4..], expected[HEADER_BYTES + 4..]);
+
+        // A manifest for different settings is ignored.
+        std::fs::write(&partial, &interrupted).unwrap();
+        Manifest {
+            frame_length: 64,
+            ..manifest
+        }
+        .save(&progress)
+        .unwrap();
+        assert_eq!(checkpointed.run(&input, &output).unwrap().resumed_from, 0);
+        assert_eq!(std::fs::read(&output).unwrap(), expected);
+
+        for path in [input, output, reference] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_empty_and_invalid_inputs() {
+        let (input, output) = (scratch("empty.raw"), scratch("empty.fwt"));
+        std::fs::write(&input, []).unwrap();
+        let transform = FileTransform::new(8, Ordering::Sequency).unwrap();
+        assert_eq!(
+            transform.run(&input, &output).unwrap(),
+            FileReport {
+                frames: 0,
+                padding: 0,
+                resumed_from: 0
+            }
+        );
+        assert_eq!(
+            format::read(&mut File::open(&output).unwrap())
+                .unwrap()
+                .shape(),
+            (0, 8)
+        );
+
+        std::fs::write(&input, [0u8; 6]).unwrap();
+        let error = transform.run(&input, &output).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let wide = FileTransform::new(1 << 17, Ordering::Sequency)
+            .unwrap()
+            .with_sample_format(SampleFormat::I16);
+        assert_eq!(
+            wide.run(&input, &output).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            FileTransform::new(6, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(6))
+        );
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+}
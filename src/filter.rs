@@ -0,0 +1,537 @@
+//! Filters applied to the Walsh spectra of signals and images.
+//!
+//! The 1D filters select sequencies of a signal: they transform it,
+//! weight each coefficient by a gain depending on its sequency, and
+//! transform back.
+//!
+//! The 2D filters work on the sequency spectrum of a `rows × cols`
+//! image, divided into dyadic bands: the coefficient at row sequency `u`
+//! and column sequency `v` lies in band `octave(max(u, v))`, where
+//! `octave(0) = 0` and `octave(s) = ⌊log2 s⌋ + 1`. Band 0 is the DC
+//! coefficient, and each later band is the L-shaped ring of coefficients
+//! whose larger sequency lies in the next octave, so the bands step from
+//! coarse structure to fine detail like the levels of a wavelet
+//! decomposition.
+
+use crate::{ifwt2, kernel, power_of_2, Error, Ordering, WalshElement};
+
+/// Return the mean energy per coefficient in each dyadic band of the
+/// sequency spectrum of the `rows × cols` row-major `image`, from band 0,
+/// the DC coefficient, up to band `log2(max(rows, cols))`. The profile of
+/// a reference image can serve as the target of [`equalize_2d`].
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `image` does not hold
+/// `rows * cols` elements, or [`Error::NotPowerOfTwo`] if either
+/// dimension is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// // Vertical stripes one pixel wide have all their energy at column
+/// // sequency 3, in band 2, which holds 4 coefficients of a 2 × 4 image.
+/// let stripes = [1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0];
+/// let energies = fwt::band_energies_2d(&stripes, 2, 4).unwrap();
+/// assert_eq!(energies, [0.0, 0.0, 16.0]);
+/// ```
+pub fn band_energies_2d(image: &[f64], rows: usize, cols: usize) -> Result<Vec<f64>, Error> {
+    let spectrum = crate::fwt2(image, rows, cols, Ordering::Sequency)?;
+    let (energies, _) = band_totals(&spectrum, rows, cols);
+    Ok(energies)
+}
+
+/// Return the `rows × cols` row-major `image` with the energy of each
+/// dyadic band of its sequency spectrum rescaled towards the profile
+/// `target`, the Walsh-domain counterpart of histogram equalization for
+/// quick contrast enhancement.
+///
+/// `target` gives the desired relative energy per coefficient of each
+/// band, as returned by [`band_energies_2d`]. Its overall scale does not
+/// matter: the bands other than DC are rescaled so that their total
+/// energy is unchanged and their mean energies per coefficient are in the
+/// proportions of `target`. A flat target whitens the spectrum, which
+/// brings out fine detail, and the profile of a reference image matches
+/// its texture. Each band is multiplied by the gain that achieves this
+/// raised to the power `strength`, so 0 leaves the image unchanged and 1
+/// equalizes fully. The DC coefficient, the mean brightness, is never
+/// changed, nor are bands that are silent or whose target is not
+/// positive. `strength` should be between 0 and 1.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `image` does not hold
+/// `rows * cols` elements or `target` does not have one entry per band,
+/// `log2(max(rows, cols)) + 1`, or [`Error::NotPowerOfTwo`] if either
+/// dimension is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// // A smooth ramp with a faint fine texture.
+/// let image: Vec<f64> = (0..64)
+///     .map(|i| (i / 8) as f64 + if i % 2 == 0 { 0.05 } else { -0.05 })
+///     .collect();
+/// let before = fwt::band_energies_2d(&image, 8, 8).unwrap();
+/// let flat = [1.0; 4];
+/// let equalized = fwt::equalize_2d(&image, 8, 8, &flat, 1.0).unwrap();
+/// let after = fwt::band_energies_2d(&equalized, 8, 8).unwrap();
+/// // The finest band, which holds the texture, is boosted.
+/// assert!(after[3] > 10.0 * before[3]);
+/// assert!((after[0] - before[0]).abs() < 1e-9);
+/// ```
+pub fn equalize_2d(
+    image: &[f64],
+    rows: usize,
+    cols: usize,
+    target: &[f64],
+    strength: f64,
+) -> Result<Vec<f64>, Error> {
+    let mut spectrum = crate::fwt2(image, rows, cols, Ordering::Sequency)?;
+    let (energies, counts) = band_totals(&spectrum, rows, cols);
+    if target.len() != energies.len() {
+        return Err(Error::LengthMismatch {
+            expected: energies.len(),
+            found: target.len(),
+        });
+    }
+    let adjusted = |b: usize| energies[b] > 0.0 && target[b] > 0.0;
+    let (total, weight) =
+        (1..energies.len())
+            .filter(|&b| adjusted(b))
+            .fold((0.0, 0.0), |(total, weight), b| {
+                let count = counts[b] as f64;
+                (total + energies[b] * count, weight + target[b] * count)
+            });
+    let gains: Vec<f64> = (0..energies.len())
+        .map(|b| {
+            if b > 0 && adjusted(b) {
+                (total / weight * target[b] / energies[b]).powf(strength / 2.0)
+            } else {
+                1.0
+            }
+        })
+        .collect();
+    for (i, x) in spectrum.iter_mut().enumerate() {
+        *x *= gains[band(i / cols, i % cols)];
+    }
+    ifwt2(&spectrum, rows, cols, Ordering::Sequency)
+}
+
+/// Return `v` with only the Walsh components of sequency `low` up to but
+/// not including `high` kept, by transforming it, zeroing the other
+/// coefficients and transforming back.
+///
+/// An empty band, `low >= high`, silences the signal, and a band
+/// reaching past the length of `v` keeps every sequency from `low` on.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// // A step has components of sequency 0 and 1; keep only sequency 1.
+/// let filtered = fwt::bandpass(&[3, 3, 1, 1], 1, 2).unwrap();
+/// assert_eq!(filtered, [1.0, 1.0, -1.0, -1.0]);
+/// ```
+pub fn bandpass<T>(v: &[T], low: usize, high: usize) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    filter_sequencies(v, |s| if (low..high).contains(&s) { 1.0 } else { 0.0 })
+}
+
+/// Zero the coefficients of `spectrum`, a transform in the given
+/// ordering, outside sequencies `low` up to but not including `high`, in
+/// place, the spectrum-domain step of [`bandpass`] for callers already
+/// holding a spectrum.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `spectrum` is not a
+/// power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let mut spectrum = [4, 3, 2, 1];
+/// fwt::bandpass_spectrum(&mut spectrum, Ordering::Hadamard, 1, 3).unwrap();
+/// // Hadamard index 1 holds sequency 3 and index 3 holds sequency 2.
+/// assert_eq!(spectrum, [0, 0, 2, 1]);
+/// ```
+pub fn bandpass_spectrum<T>(
+    spectrum: &mut [T],
+    ordering: Ordering,
+    low: usize,
+    high: usize,
+) -> Result<(), Error>
+where
+    T: WalshElement + Default,
+{
+    let length = spectrum.len();
+    if !power_of_2(length) {
+        return Err(Error::NotPowerOfTwo(length));
+    }
+    for (i, x) in spectrum.iter_mut().enumerate() {
+        if !(low..high).contains(&sequency(i, length, ordering)) {
+            *x = T::default();
+        }
+    }
+    Ok(())
+}
+
+/// Return `v` with the Walsh components of sequency below `cutoff` kept
+/// and those above attenuated.
+///
+/// With a `rolloff` of 0 the cutoff is hard, as in [`bandpass`] from
+/// sequency 0. Otherwise the gain falls smoothly along a raised cosine
+/// over the `rolloff` sequencies from `cutoff` on, to 0 at
+/// `cutoff + rolloff`, so that the reconstruction has fewer of the
+/// blocky artifacts of discarding coefficients abruptly.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// let impulse = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 8.0];
+/// // A hard cutoff at sequency 2 spreads the impulse over half the signal.
+/// let hard = fwt::lowpass(&impulse, 2, 0).unwrap();
+/// assert_eq!(hard, [0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 2.0, 2.0]);
+/// // A rolloff keeps part of the next sequencies, sharpening the peak.
+/// let smooth = fwt::lowpass(&impulse, 2, 4).unwrap();
+/// assert!(smooth[7] > 2.0 && smooth[7] < 8.0);
+/// ```
+pub fn lowpass<T>(v: &[T], cutoff: usize, rolloff: usize) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    filter_sequencies(v, |s| lowpass_gain(s, cutoff, rolloff))
+}
+
+/// Return `v` with the Walsh components of sequency below `cutoff`
+/// removed and those above kept, the complement of [`lowpass`] with the
+/// same parameters: the two outputs sum to `v`. The gain rises along a
+/// raised cosine over the `rolloff` sequencies from `cutoff` on, to 1 at
+/// `cutoff + rolloff`.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// // Removing sequency 0 removes the mean.
+/// let v = [3.0, 1.0, 3.0, 1.0];
+/// assert_eq!(fwt::highpass(&v, 1, 0).unwrap(), [1.0, -1.0, 1.0, -1.0]);
+/// ```
+pub fn highpass<T>(v: &[T], cutoff: usize, rolloff: usize) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    filter_sequencies(v, |s| 1.0 - lowpass_gain(s, cutoff, rolloff))
+}
+
+// The gain of a low-pass filter at sequency `s`: 1 below `cutoff`, 0 from
+// `cutoff + rolloff` on, and half a raised cosine between.
+fn lowpass_gain(s: usize, cutoff: usize, rolloff: usize) -> f64 {
+    if s < cutoff {
+        1.0
+    } else if s >= cutoff.saturating_add(rolloff) {
+        0.0
+    } else {
+        let x = (s - cutoff + 1) as f64 / (rolloff as f64 + 1.0);
+        0.5 + 0.5 * (std::f64::consts::PI * x).cos()
+    }
+}
+
+/// How [`denoise`] shrinks the coefficients of a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThresholdMode {
+    /// Keep coefficients whose amplitude exceeds the threshold and zero
+    /// the rest.
+    Hard,
+    /// Zero coefficients whose amplitude does not exceed the threshold
+    /// and shrink the rest towards 0 by the threshold, which avoids the
+    /// jumps of hard thresholding at the cost of some bias.
+    Soft,
+}
+
+/// Return `v` denoised by thresholding its Walsh coefficients: each
+/// component of amplitude at most `threshold` is removed, and with
+/// [`ThresholdMode::Soft`] the amplitudes of the others are reduced by
+/// `threshold`.
+///
+/// The amplitudes are those of [`amplitude_spectrum`](crate::amplitude_spectrum),
+/// the coefficients divided by the length, so that a component
+/// `a · wal(k, t)` has amplitude `|a|`; for white noise of standard
+/// deviation σ in `n` samples each amplitude has a standard deviation of
+/// σ/√n. The mean, at sequency 0, is never thresholded. Few Walsh
+/// components describe a piecewise-constant signal, so thresholding
+/// removes noise while keeping its steps sharp.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::ThresholdMode;
+///
+/// let noisy = [1.1, 0.9, 1.0, 1.0, -1.0, -0.9, -1.1, -1.0];
+/// let clean = fwt::denoise(&noisy, 0.1, ThresholdMode::Hard).unwrap();
+/// assert_eq!(clean, [1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0]);
+/// ```
+pub fn denoise<T>(v: &[T], threshold: f64, mode: ThresholdMode) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let mut spectrum = crate::spectrum::coefficients(v, Ordering::Hadamard)?;
+    let scale = (v.len() as f64).recip();
+    for x in &mut spectrum[1..] {
+        let amplitude = x.abs() * scale;
+        *x = if amplitude <= threshold {
+            0.0
+        } else {
+            match mode {
+                ThresholdMode::Hard => *x * scale,
+                ThresholdMode::Soft => (amplitude - threshold).copysign(*x),
+            }
+        };
+    }
+    spectrum[0] *= scale;
+    crate::Plan::new(v.len(), Ordering::Hadamard)?.execute(&mut spectrum)?;
+    Ok(spectrum)
+}
+
+// Return `v` with each component of sequency `s` scaled by `gain(s)`.
+fn filter_sequencies<T, F>(v: &[T], gain: F) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+    F: Fn(usize) -> f64,
+{
+    let mut spectrum = crate::spectrum::coefficients(v, Ordering::Sequency)?;
+    let scale = (v.len() as f64).recip();
+    for (s, x) in spectrum.iter_mut().enumerate() {
+        *x *= gain(s) * scale;
+    }
+    crate::Plan::new(v.len(), Ordering::Sequency)?.execute(&mut spectrum)?;
+    Ok(spectrum)
+}
+
+// The sequency of coefficient `i` of a transform of `length` in
+// `ordering`.
+fn sequency(i: usize, length: usize, ordering: Ordering) -> usize {
+    match ordering {
+        Ordering::Sequency => i,
+        Ordering::Hadamard => kernel::hadamard_to_sequency(i, length),
+    }
+}
+
+// The dyadic band of the coefficient at sequencies `u` and `v`.
+fn band(u: usize, v: usize) -> usize {
+    (usize::BITS - u.max(v).leading_zeros()) as usize
+}
+
+// The mean energy per coefficient and the number of coefficients of each
+// dyadic band of a sequency spectrum.
+fn band_totals(spectrum: &[f64], rows: usize, cols: usize) -> (Vec<f64>, Vec<usize>) {
+    let bands = band(rows - 1, cols - 1) + 1;
+    let mut energies = vec![0.0; bands];
+    let mut counts = vec![0; bands];
+    for (i, x) in spectrum.iter().enumerate() {
+        let b = band(i / cols, i % cols);
+        energies[b] += x * x;
+        counts[b] += 1;
+    }
+    for (e, &count) in energies.iter_mut().zip(&counts) {
+        *e /= count as f64;
+    }
+    (energies, counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn test_bandpass_keeps_band_components() {
+        let v: Vec<f64> = (0..64).map(|i| ((i * 7919) % 101) as f64 - 50.0).collect();
+        let spectrum = crate::sequency(&v).unwrap();
+        let filtered = bandpass(&v, 5, 20).unwrap();
+        let kept = crate::sequency(&filtered).unwrap();
+        for (s, (k, x)) in kept.iter().zip(&spectrum).enumerate() {
+            let expected = if (5..20).contains(&s) { *x } else { 0.0 };
+            assert!((k - expected).abs() < 1e-9);
+        }
+        // Complementary bands sum to the signal.
+        let low = bandpass(&v, 0, 5).unwrap();
+        let high = bandpass(&v, 20, usize::MAX).unwrap();
+        for i in 0..64 {
+            assert!((low[i] + filtered[i] + high[i] - v[i]).abs() < 1e-9);
+        }
+        assert_eq!(bandpass(&v, 9, 9).unwrap(), [0.0; 64]);
+        assert_eq!(bandpass(&[1.0; 3], 0, 1), Err(Error::NotPowerOfTwo(3)));
+    }
+
+    #[test]
+    fn test_bandpass_spectrum_in_either_ordering() {
+        let v: Vec<i32> = (0..32).map(|i| (i * 37) % 23 - 11).collect();
+        for length in [1, 2, 32] {
+            for h in 0..length {
+                let s = kernel::hadamard_to_sequency(h, length);
+                assert_eq!(kernel::sequency_to_hadamard(s, length), h);
+            }
+        }
+        let mut by_sequency = crate::sequency(&v).unwrap();
+        let mut by_hadamard = crate::hadamard(&v).unwrap();
+        bandpass_spectrum(&mut by_sequency, Ordering::Sequency, 3, 11).unwrap();
+        bandpass_spectrum(&mut by_hadamard, Ordering::Hadamard, 3, 11).unwrap();
+        let reordered: Vec<i32> = (0..32)
+            .map(|s| by_hadamard[kernel::sequency_to_hadamard(s, 32)])
+            .collect();
+        assert_eq!(reordered, by_sequency);
+        let filtered = bandpass(&v, 3, 11).unwrap();
+        let expected = crate::sequency(&by_sequency).unwrap();
+        for (f, e) in filtered.iter().zip(expected) {
+            assert!((f - f64::from(e) / 32.0).abs() < 1e-9);
+        }
+        assert_eq!(
+            bandpass_spectrum(&mut [0; 6], Ordering::Sequency, 0, 1),
+            Err(Error::NotPowerOfTwo(6))
+        );
+    }
+
+    #[test]
+    fn test_lowpass_and_highpass_are_complementary() {
+        let v: Vec<f64> = (0..128).map(|i| ((i * 7919) % 101) as f64 - 50.0).collect();
+        for (cutoff, rolloff) in [(0, 0), (10, 0), (10, 7), (100, 50)] {
+            let low = lowpass(&v, cutoff, rolloff).unwrap();
+            let high = highpass(&v, cutoff, rolloff).unwrap();
+            for i in 0..128 {
+                assert!((low[i] + high[i] - v[i]).abs() < 1e-9);
+            }
+        }
+        let hard = lowpass(&v, 10, 0).unwrap();
+        let band = bandpass(&v, 0, 10).unwrap();
+        assert!(hard.iter().zip(&band).all(|(a, b)| (a - b).abs() < 1e-9));
+        // The gain falls monotonically through the transition band.
+        let gains: Vec<f64> = (0..20).map(|s| lowpass_gain(s, 8, 5)).collect();
+        assert_eq!(&gains[..8], [1.0; 8]);
+        assert_eq!(&gains[13..], [0.0; 7]);
+        assert!(gains[7..14].windows(2).all(|w| w[0] > w[1]));
+        assert!(gains[8..13].iter().all(|&g| g > 0.0 && g < 1.0));
+        assert_eq!(highpass(&[0.0; 5], 1, 1), Err(Error::NotPowerOfTwo(5)));
+        // Unbounded cutoffs and rolloffs pass everything.
+        assert_eq!(highpass(&[1.0; 8], 4, usize::MAX).unwrap(), [0.0; 8]);
+        let all = lowpass(&v, usize::MAX, usize::MAX).unwrap();
+        assert!(all.iter().zip(&v).all(|(a, b)| (a - b).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_denoise_shrinks_small_components() {
+        let n = 256;
+        let steps: Vec<f64> = (0..n).map(|i| [2.0, -1.0, 3.0, 0.5][i / 64]).collect();
+        let mut rng = SplitMix64::new(7);
+        let noisy: Vec<f64> = steps.iter().map(|x| x + 0.05 * rng.normal()).collect();
+        let error = |v: &[f64]| -> f64 { v.iter().zip(&steps).map(|(a, b)| (a - b).powi(2)).sum() };
+        let hard = denoise(&noisy, 0.015, ThresholdMode::Hard).unwrap();
+        let soft = denoise(&noisy, 0.015, ThresholdMode::Soft).unwrap();
+        assert!(error(&hard) < 0.1 * error(&noisy));
+        // Soft thresholding also shrinks the steps, by the threshold.
+        assert!(error(&soft) < 0.5 * error(&noisy));
+        // No threshold keeps the signal, and a huge one leaves the mean.
+        let kept = denoise(&noisy, 0.0, ThresholdMode::Soft).unwrap();
+        assert!(kept.iter().zip(&noisy).all(|(a, b)| (a - b).abs() < 1e-9));
+        let mean = noisy.iter().sum::<f64>() / n as f64;
+        let flat = denoise(&noisy, 1e9, ThresholdMode::Hard).unwrap();
+        assert!(flat.iter().all(|x| (x - mean).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_soft_threshold_shrinks_amplitudes() {
+        // 3 · wal(1) + 0.5 · wal(2) over 4 samples.
+        let v = [3.5, 2.5, -3.5, -2.5];
+        let soft = denoise(&v, 1.0, ThresholdMode::Soft).unwrap();
+        assert_eq!(soft, [2.0, 2.0, -2.0, -2.0]);
+        let hard = denoise(&v, 1.0, ThresholdMode::Hard).unwrap();
+        assert_eq!(hard, [3.0, 3.0, -3.0, -3.0]);
+        assert_eq!(
+            denoise(&[1.0; 12], 1.0, ThresholdMode::Hard),
+            Err(Error::NotPowerOfTwo(12))
+        );
+    }
+
+    #[test]
+    fn test_bands_partition_the_spectrum() {
+        assert_eq!(band(0, 0), 0);
+        assert_eq!(band(1, 0), 1);
+        assert_eq!(band(3, 2), 2);
+        assert_eq!(band(4, 7), 3);
+        let image: Vec<f64> = (0..128).map(|i| ((i * 29) % 17) as f64 - 8.0).collect();
+        let energies = band_energies_2d(&image, 8, 16).unwrap();
+        assert_eq!(energies.len(), 5);
+        // Band b of an 8 × 16 image has 3 · 4^(b-1) coefficients up to
+        // band 3, and the band 4 half-ring holds the remaining 64.
+        let counts = [1.0, 3.0, 12.0, 48.0, 64.0];
+        let total: f64 = energies.iter().zip(counts).map(|(e, c)| e * c).sum();
+        let energy: f64 = image.iter().map(|x| x * x).sum::<f64>() * 128.0;
+        assert!((total - energy).abs() < 1e-9 * energy);
+    }
+
+    #[test]
+    fn test_equalize_matches_target_profile() {
+        let image: Vec<f64> = (0..256)
+            .map(|i| ((i % 16) as f64 * 0.3).sin() + (i / 16) as f64 * 0.2)
+            .collect();
+        let target = [0.0, 16.0, 8.0, 4.0, 2.0];
+        let before = band_energies_2d(&image, 16, 16).unwrap();
+        let equalized = equalize_2d(&image, 16, 16, &target, 1.0).unwrap();
+        let after = band_energies_2d(&equalized, 16, 16).unwrap();
+        assert!((after[0] - before[0]).abs() < 1e-9);
+        for b in 2..5 {
+            assert!((after[b] / after[1] - target[b] / target[1]).abs() < 1e-9);
+        }
+        let ac = |e: &[f64]| e[1] * 3.0 + e[2] * 12.0 + e[3] * 48.0 + e[4] * 192.0;
+        assert!((ac(&after) - ac(&before)).abs() < 1e-9 * ac(&before));
+        // Zero strength leaves the image unchanged.
+        let unchanged = equalize_2d(&image, 16, 16, &target, 0.0).unwrap();
+        assert!(unchanged
+            .iter()
+            .zip(&image)
+            .all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_equalize_errors_and_silent_bands() {
+        let constant = [2.0; 16];
+        assert_eq!(
+            equalize_2d(&constant, 4, 4, &[1.0; 3], 1.0),
+            Ok(constant.to_vec())
+        );
+        assert_eq!(
+            equalize_2d(&constant, 4, 4, &[1.0; 4], 1.0),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            band_energies_2d(&[1.0; 6], 2, 3),
+            Err(Error::NotPowerOfTwo(3))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Short digests of arrays that tolerate numerical jitter.
+
+use crate::{Ordering, Plan};
+
+// The number of lowest-sequency coefficients in a fingerprint.
+const COEFFICIENTS: usize = 16;
+
+/// Return a 64-bit fingerprint of `values` that ignores changes smaller
+/// than `resolution`, for detecting when products derived from a large
+/// array need to be recomputed.
+///
+/// The array is zero-padded to a power-of-2 length `n` and transformed in
+/// sequency order. The features hashed are the length of `values`, its
+/// 16 lowest-sequency coefficients divided by `n`, and the RMS level
+/// `sqrt(sum of squares) / n` of each dyadic sequency band (the mean,
+/// then sequencies `[1, 2)`, `[2, 4)`, `[4, 8)` and so on), each rounded
+/// to a multiple of `resolution`, which should be positive. Since the
+/// transform divided by `n` is an average, changing every value by at
+/// most `e` changes each feature by at most `e`. Two arrays within
+/// jitter `e` of each other therefore have the same fingerprint unless a
+/// feature lies within `e` of a rounding boundary, which becomes unlikely
+/// as `e` shrinks relative to `resolution`; fingerprints are a hint to
+/// recompute, not a proof of equality. Non-finite features are hashed by
+/// their bits.
+///
+/// Transforms are bit-identical across platforms, and the hash is
+/// specified rather than randomized, so the fingerprint of an array is
+/// the same on every platform and in every process.
+///
+/// # Example
+///
+/// ```
+/// let data: Vec<f64> = (0..4096).map(|i| (i as f64 * 0.01).sin()).collect();
+/// let jittered: Vec<f64> = data.iter().map(|x| x + 1e-12).collect();
+/// assert_eq!(fwt::fingerprint(&data, 1e-6), fwt::fingerprint(&jittered, 1e-6));
+///
+/// let mut changed = data.clone();
+/// changed[100] += 1.0;
+/// assert_ne!(fwt::fingerprint(&data, 1e-6), fwt::fingerprint(&changed, 1e-6));
+/// ```
+pub fn fingerprint(values: &[f64], resolution: f64) -> u64 {
+    let length = values.len().next_power_of_two();
+    let mut spectrum = values.to_vec();
+    spectrum.resize(length, 0.0);
+    Plan::new(length, Ordering::Sequency)
+        .and_then(|plan| plan.execute(&mut spectrum))
+        .expect("the padded length is a power of 2");
+    let scale = (length as f64).recip();
+
+    let mut hash = Fnv::new();
+    hash.write(values.len() as u64);
+    let quantize = |x: f64| {
+        if x.is_finite() {
+            (x / resolution).round() as i64 as u64
+        } else {
+            x.to_bits()
+        }
+    };
+    for &c in spectrum.iter().take(COEFFICIENTS) {
+        hash.write(quantize(c * scale));
+    }
+    let mut start = 0;
+    while start < length {
+        let end = (2 * start).max(1);
+        let energy: f64 = spectrum[start..end].iter().map(|c| c * c).sum();
+        hash.write(quantize(energy.sqrt() * scale));
+        start = end;
+    }
+    hash.finish()
+}
+
+// The 64-bit FNV-1a hash, which unlike the standard library's hashers is
+// specified, so fingerprints are reproducible.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, word: u64) {
+        for byte in word.to_le_bytes() {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn test_jitter_is_tolerated() {
+        let mut rng = SplitMix64::new(5);
+        let data: Vec<f64> = (0..10_000)
+            .map(|i| (i as f64 * 0.003).cos() * 3.0 + (i % 7) as f64)
+            .collect();
+        let digest = fingerprint(&data, 1e-3);
+        let mut agreeing = 0;
+        for _ in 0..20 {
+            let jittered: Vec<f64> = data
+                .iter()
+                .map(|x| x + (rng.below(2001) as f64 - 1000.0) * 1e-12)
+                .collect();
+            agreeing += usize::from(fingerprint(&jittered, 1e-3) == digest);
+        }
+        assert_eq!(agreeing, 20);
+        assert_eq!(fingerprint(&data, 1e-3), digest);
+    }
+
+    #[test]
+    fn test_changes_are_detected() {
+        let data: Vec<f64> = (0..1000).map(|i| ((i * 31) % 17) as f64).collect();
+        let digest = fingerprint(&data, 1e-6);
+        let mut shifted = data.clone();
+        shifted.rotate_left(1);
+        assert_ne!(fingerprint(&shifted, 1e-6), digest);
+        assert_ne!(fingerprint(&data[..999], 1e-6), digest);
+        let mut padded = data.clone();
+        padded.push(0.0);
+        assert_ne!(fingerprint(&padded, 1e-6), digest);
+        let mut high = data.clone();
+        for (i, x) in high.iter_mut().enumerate() {
+            *x += if i % 2 == 0 { 0.01 } else { -0.01 };
+        }
+        assert_ne!(fingerprint(&high, 1e-6), digest);
+        // The same change is below a coarse resolution.
+        assert_eq!(fingerprint(&high, 1.0), fingerprint(&data, 1.0));
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(fingerprint(&[], 1.0), fingerprint(&[], 1.0));
+        assert_ne!(fingerprint(&[], 1.0), fingerprint(&[0.0], 1.0));
+        assert_ne!(fingerprint(&[f64::NAN], 1.0), fingerprint(&[0.0], 1.0));
+        assert_eq!(
+            fingerprint(&[f64::INFINITY, 1.0], 1.0),
+            fingerprint(&[f64::INFINITY, 1.0], 1.0)
+        );
+    }
+}
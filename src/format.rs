@@ -0,0 +1,758 @@
+//! A small, versioned binary container for persisting transform artifacts.
+//!
+//! Every container starts with a fixed 32-byte little-endian header:
+//!
+//! | offset | size | field                                             |
+//! |-------:|-----:|---------------------------------------------------|
+//! | 0      | 4    | magic bytes `FWT\0`                               |
+//! | 4      | 2    | format version                                    |
+//! | 6      | 1    | [`Kind`] of artifact                              |
+//! | 7      | 1    | ordering: 0 none, 1 sequency, 2 Hadamard          |
+//! | 8      | 1    | element type of the payload                       |
+//! | 9      | 3    | reserved, zero                                    |
+//! | 12     | 8    | rows                                              |
+//! | 20     | 8    | columns                                           |
+//! | 28     | 4    | number of payload elements                        |
+//!
+//! followed by the payload elements in little-endian byte order. Spectra
+//! are stored as a single row and design matrices in row-major order. A
+//! plan stores its length as the column count and its algorithm and
+//! determinism settings as a two-element `u8` payload. An SRHT summary
+//! stores its sketch dimensions as the shape and a `u8` payload of the
+//! matrix row count, seed and rows seen as `u64` values followed by the
+//! sketch as `f64` values. A tuning profile stores one row per
+//! measurement of four `f64` values: the backend (0 scalar, 1 SIMD, 2
+//! parallel, 3 GPU, 4 transposed), the length, the batch size and the
+//! nanoseconds per element. Wisdom stores its number of choices as the
+//! row count and a `u8` payload of one record per choice: the length as a
+//! `u64`, the ordering, algorithm and backend codes as one byte each, and
+//! the element type name as a `u16` byte count followed by its UTF-8
+//! bytes.
+//!
+//! Version 1 holds plans, spectra and design matrices; version 2 added
+//! sketches, tuning profiles and wisdom. Each container records the
+//! earliest version that can hold its kind, so plans, spectra and design
+//! matrices are still written as version 1 and stay readable by older
+//! releases, while a release that predates a kind rejects it as a newer
+//! version rather than as a malformed file. Readers accept every version
+//! up to [`VERSION`], so artifacts written today remain readable by later
+//! releases of this crate.
+
+use std::io::{self, Read, Write};
+
+use crate::{
+    Algorithm, Backend, Determinism, Measurement, Ordering, Plan, Srht, SrhtSummary, TuningProfile,
+    Wisdom, WisdomEntry,
+};
+
+/// The format version written by this release.
+pub const VERSION: u16 = 2;
+
+const MAGIC: [u8; 4] = *b"FWT\0";
+
+/// The largest plan length or sketched matrix row count that
+/// [`Artifact::to_plan`] and [`Artifact::to_sketch`] rebuild. Rebuilding
+/// allocates in proportion to these values, so larger ones are treated
+/// as malformed rather than trusted from a container that may have been
+/// crafted.
+pub const MAX_DIMENSION: u64 = 1 << 28;
+
+/// What an artifact contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// The parameters of a [`Plan`].
+    Plan,
+    /// Transform coefficients.
+    Spectrum,
+    /// A ±1 design matrix, such as a Hadamard design of experiments.
+    DesignMatrix,
+    /// An [`SrhtSummary`].
+    Sketch,
+    /// A [`TuningProfile`].
+    Profile,
+    /// The kernel choices of a [`Planner`](crate::Planner), as
+    /// [`Wisdom`].
+    Wisdom,
+}
+
+/// The payload of an artifact, tagged with its element type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    /// Unsigned bytes, element type 0, also used for the encoded
+    /// parameters of plans, sketches and wisdom.
+    U8(Vec<u8>),
+    /// 8-bit integers, element type 1, such as ±1 design matrices.
+    I8(Vec<i8>),
+    /// 16-bit integers, element type 2.
+    I16(Vec<i16>),
+    /// 32-bit integers, element type 3.
+    I32(Vec<i32>),
+    /// 64-bit integers, element type 4.
+    I64(Vec<i64>),
+    /// 32-bit floats, element type 5.
+    F32(Vec<f32>),
+    /// 64-bit floats, element type 6.
+    F64(Vec<f64>),
+}
+
+impl Payload {
+    /// The number of elements in the payload.
+    pub fn len(&self) -> usize {
+        match self {
+            Payload::U8(v) => v.len(),
+            Payload::I8(v) => v.len(),
+            Payload::I16(v) => v.len(),
+            Payload::I32(v) => v.len(),
+            Payload::I64(v) => v.len(),
+            Payload::F32(v) => v.len(),
+            Payload::F64(v) => v.len(),
+        }
+    }
+
+    /// Whether the payload has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn type_code(&self) -> u8 {
+        match self {
+            Payload::U8(_) => 0,
+            Payload::I8(_) => 1,
+            Payload::I16(_) => 2,
+            Payload::I32(_) => 3,
+            Payload::I64(_) => 4,
+            Payload::F32(_) => 5,
+            Payload::F64(_) => 6,
+        }
+    }
+}
+
+macro_rules! put_all {
+    ($out:expr, $v:expr) => {
+        for x in $v {
+            $out.extend_from_slice(&x.to_le_bytes());
+        }
+    };
+}
+
+/// A persisted plan, spectrum, design matrix, sketch, tuning profile or
+/// wisdom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    kind: Kind,
+    ordering: Option<Ordering>,
+    rows: u64,
+    cols: u64,
+    payload: Payload,
+}
+
+impl Artifact {
+    /// An artifact holding the coefficients of a spectrum in the given
+    /// ordering.
+    pub fn spectrum(payload: Payload, ordering: Ordering) -> Artifact {
+        Artifact {
+            kind: Kind::Spectrum,
+            ordering: Some(ordering),
+            rows: 1,
+            cols: payload.len() as u64,
+            payload,
+        }
+    }
+
+    /// An artifact holding a `rows` × `cols` design matrix in row-major
+    /// order, or `None` if the entries do not fill the matrix.
+    pub fn design_matrix(rows: usize, cols: usize, entries: Vec<i8>) -> Option<Artifact> {
+        if rows.checked_mul(cols)? != entries.len() {
+            return None;
+        }
+        Some(Artifact {
+            kind: Kind::DesignMatrix,
+            ordering: None,
+            rows: rows as u64,
+            cols: cols as u64,
+            payload: Payload::I8(entries),
+        })
+    }
+
+    /// An artifact holding the parameters of `plan`.
+    pub fn plan(plan: &Plan) -> Artifact {
+        let algorithm = algorithm_code(plan.algorithm());
+        let determinism = match plan.determinism() {
+            Determinism::Fast => 0,
+            Determinism::Strict => 1,
+        };
+        Artifact {
+            kind: Kind::Plan,
+            ordering: Some(plan.ordering()),
+            rows: 1,
+            cols: plan.length() as u64,
+            payload: Payload::U8(vec![algorithm, determinism]),
+        }
+    }
+
+    /// Rebuild the plan stored in this artifact, or return `None` if the
+    /// artifact does not hold a valid plan.
+    pub fn to_plan(&self) -> Option<Plan> {
+        let (Kind::Plan, Some(ordering), Payload::U8(params)) =
+            (self.kind, self.ordering, &self.payload)
+        else {
+            return None;
+        };
+        let algorithm = algorithm_from_code(*params.first()?)?;
+        let determinism = match params.get(1)? {
+            0 => Determinism::Fast,
+            1 => Determinism::Strict,
+            _ => return None,
+        };
+        let length = usize::try_from(dimension(self.cols)?).ok()?;
+        let plan = Plan::new(length, ordering).ok()?;
+        Some(plan.with_algorithm(algorithm).with_determinism(determinism))
+    }
+
+    /// An artifact holding an SRHT summary.
+    pub fn sketch(summary: &SrhtSummary) -> Artifact {
+        let mut bytes = Vec::with_capacity(24 + 8 * summary.sketch().len());
+        put_all!(
+            bytes,
+            [
+                summary.rows() as u64,
+                summary.seed(),
+                summary.rows_seen() as u64
+            ]
+        );
+        put_all!(bytes, summary.sketch());
+        Artifact {
+            kind: Kind::Sketch,
+            ordering: None,
+            rows: summary.sketch_rows() as u64,
+            cols: summary.cols() as u64,
+            payload: Payload::U8(bytes),
+        }
+    }
+
+    /// Rebuild the SRHT summary stored in this artifact, or return `None`
+    /// if the artifact does not hold a valid summary.
+    pub fn to_sketch(&self) -> Option<SrhtSummary> {
+        let (Kind::Sketch, Payload::U8(bytes)) = (self.kind, &self.payload) else {
+            return None;
+        };
+        if bytes.len() < 24 || bytes.len() % 8 != 0 {
+            return None;
+        }
+        let words: Vec<[u8; 8]> = bytes
+            .chunks_exact(8)
+            .map(|c| c.try_into().unwrap())
+            .collect();
+        let rows = usize::try_from(dimension(u64::from_le_bytes(words[0]))?).ok()?;
+        let seed = u64::from_le_bytes(words[1]);
+        let rows_seen = usize::try_from(u64::from_le_bytes(words[2])).ok()?;
+        let sketch = words[3..].iter().map(|&w| f64::from_le_bytes(w)).collect();
+        let srht = Srht::new(rows, usize::try_from(self.rows).ok()?, seed).ok()?;
+        let cols = usize::try_from(dimension(self.cols)?).ok()?;
+        SrhtSummary::from_parts(&srht, cols, rows_seen, sketch).ok()
+    }
+
+    /// An artifact holding the measurements of a tuning profile.
+    pub fn profile(profile: &TuningProfile) -> Artifact {
+        let rows = profile
+            .measurements()
+            .iter()
+            .flat_map(|m| {
+                [
+                    backend_code(m.backend) as f64,
+                    m.length as f64,
+                    m.batch as f64,
+                    m.nanos_per_element,
+                ]
+            })
+            .collect();
+        Artifact {
+            kind: Kind::Profile,
+            ordering: None,
+            rows: profile.measurements().len() as u64,
+            cols: 4,
+            payload: Payload::F64(rows),
+        }
+    }
+
+    /// Rebuild the tuning profile stored in this artifact, or return
+    /// `None` if the artifact does not hold a valid profile.
+    pub fn to_profile(&self) -> Option<TuningProfile> {
+        let (Kind::Profile, 4, Payload::F64(values)) = (self.kind, self.cols, &self.payload) else {
+            return None;
+        };
+        let mut profile = TuningProfile::new();
+        for row in values.chunks_exact(4) {
+            let backend = match row[0] {
+                0.0 => Backend::Scalar,
+                1.0 => Backend::Simd,
+                2.0 => Backend::Parallel,
+                3.0 => Backend::Gpu,
+                4.0 => Backend::Transposed,
+                _ => return None,
+            };
+            let size = |x: f64| (x >= 1.0 && x.fract() == 0.0).then_some(x as usize);
+            profile.record(Measurement {
+                backend,
+                length: size(row[1])?,
+                batch: size(row[2])?,
+                nanos_per_element: row[3],
+            });
+        }
+        Some(profile)
+    }
+
+    /// An artifact holding the kernel choices of `wisdom`.
+    pub fn wisdom(wisdom: &Wisdom) -> Artifact {
+        let mut bytes = Vec::new();
+        for e in wisdom.entries() {
+            // Type names are far shorter than 64 KiB.
+            let name = &e.element.as_bytes()[..e.element.len().min(u16::MAX as usize)];
+            bytes.extend_from_slice(&(e.length as u64).to_le_bytes());
+            bytes.extend_from_slice(&[
+                ordering_code(Some(e.ordering)),
+                algorithm_code(e.algorithm),
+                backend_code(e.backend),
+            ]);
+            bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(name);
+        }
+        Artifact {
+            kind: Kind::Wisdom,
+            ordering: None,
+            rows: wisdom.entries().len() as u64,
+            cols: 1,
+            payload: Payload::U8(bytes),
+        }
+    }
+
+    /// Rebuild the wisdom stored in this artifact, or return `None` if the
+    /// artifact does not hold valid wisdom.
+    pub fn to_wisdom(&self) -> Option<Wisdom> {
+        let (Kind::Wisdom, Payload::U8(bytes)) = (self.kind, &self.payload) else {
+            return None;
+        };
+        let mut wisdom = Wisdom::new();
+        let mut rest = bytes.as_slice();
+        for _ in 0..self.rows {
+            let (fixed, tail) = rest.split_at_checked(13)?;
+            let length =
+                usize::try_from(u64::from_le_bytes(fixed[..8].try_into().unwrap())).ok()?;
+            let ordering = ordering_from_code(fixed[8]).ok()??;
+            let algorithm = algorithm_from_code(fixed[9])?;
+            let backend = backend_from_code(fixed[10])?;
+            let count = u16::from_le_bytes([fixed[11], fixed[12]]) as usize;
+            let (name, tail) = tail.split_at_checked(count)?;
+            wisdom.record(WisdomEntry {
+                element: String::from_utf8(name.to_vec()).ok()?,
+                length,
+                ordering,
+                algorithm,
+                backend,
+            });
+            rest = tail;
+        }
+        rest.is_empty().then_some(wisdom)
+    }
+
+    /// What the artifact contains.
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// The ordering of a spectrum or plan.
+    pub fn ordering(&self) -> Option<Ordering> {
+        self.ordering
+    }
+
+    /// The number of rows and columns.
+    pub fn shape(&self) -> (u64, u64) {
+        (self.rows, self.cols)
+    }
+
+    /// The stored elements.
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    /// Consume the artifact, returning its elements.
+    pub fn into_payload(self) -> Payload {
+        self.payload
+    }
+}
+
+macro_rules! take_all {
+    ($reader:expr, $t:ty, $count:expr) => {{
+        let size = std::mem::size_of::<$t>();
+        let len = $count
+            .checked_mul(size)
+            .ok_or_else(|| invalid("payload too large"))?;
+        read_bytes($reader, len)?
+            .chunks_exact(size)
+            .map(|c| <$t>::from_le_bytes(c.try_into().unwrap()))
+            .collect::<Vec<$t>>()
+    }};
+}
+
+/// Write `artifact` to `writer`, in the earliest format version that can
+/// hold its kind.
+///
+/// # Example
+///
+/// ```
+/// use fwt::format::{self, Artifact, Payload};
+/// use fwt::Ordering;
+///
+/// let spectrum = fwt::sequency(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+/// let artifact = Artifact::spectrum(Payload::F64(spectrum), Ordering::Sequency);
+/// let mut bytes = Vec::new();
+/// format::write(&mut bytes, &artifact).unwrap();
+/// assert_eq!(format::read(&mut bytes.as_slice()).unwrap(), artifact);
+/// ```
+pub fn write<W: Write>(writer: &mut W, artifact: &Artifact) -> io::Result<()> {
+    let count = u32::try_from(artifact.payload.len())
+        .map_err(|_| invalid("payload too large for the format"))?;
+    let mut out = Vec::with_capacity(32);
+    out.extend_from_slice(&header(
+        artifact.kind,
+        artifact.ordering,
+        artifact.payload.type_code(),
+        artifact.rows,
+        artifact.cols,
+        count,
+    ));
+    match &artifact.payload {
+        Payload::U8(v) => out.extend_from_slice(v),
+        Payload::I8(v) => put_all!(out, v),
+        Payload::I16(v) => put_all!(out, v),
+        Payload::I32(v) => put_all!(out, v),
+        Payload::I64(v) => put_all!(out, v),
+        Payload::F32(v) => put_all!(out, v),
+        Payload::F64(v) => put_all!(out, v),
+    }
+    writer.write_all(&out)
+}
+
+// The container header for a payload of `count` elements of the given
+// element type code.
+pub(crate) fn header(
+    kind: Kind,
+    ordering: Option<Ordering>,
+    type_code: u8,
+    rows: u64,
+    cols: u64,
+    count: u32,
+) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[0..4].copy_from_slice(&MAGIC);
+    out[4..6].copy_from_slice(&kind_version(kind).to_le_bytes());
+    out[6] = match kind {
+        Kind::Plan => 0,
+        Kind::Spectrum => 1,
+        Kind::DesignMatrix => 2,
+        Kind::Sketch => 3,
+        Kind::Profile => 4,
+        Kind::Wisdom => 5,
+    };
+    out[7] = ordering_code(ordering);
+    out[8] = type_code;
+    out[12..20].copy_from_slice(&rows.to_le_bytes());
+    out[20..28].copy_from_slice(&cols.to_le_bytes());
+    out[28..32].copy_from_slice(&count.to_le_bytes());
+    out
+}
+
+/// Read an artifact written by any format version up to [`VERSION`].
+///
+/// # Errors
+///
+/// Returns an error of kind [`io::ErrorKind::InvalidData`] if the data is
+/// not a container, comes from a newer format version, or is malformed,
+/// and propagates errors from `reader`.
+pub fn read<R: Read>(reader: &mut R) -> io::Result<Artifact> {
+    let mut header = [0u8; 32];
+    reader.read_exact(&mut header)?;
+    if header[0..4] != MAGIC {
+        return Err(invalid("not an fwt container"));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version == 0 || version > VERSION {
+        return Err(invalid("unsupported container version"));
+    }
+    let kind = match header[6] {
+        0 => Kind::Plan,
+        1 => Kind::Spectrum,
+        2 => Kind::DesignMatrix,
+        3 => Kind::Sketch,
+        4 => Kind::Profile,
+        5 => Kind::Wisdom,
+        _ => return Err(invalid("unknown artifact kind")),
+    };
+    if kind_version(kind) > version {
+        return Err(invalid("artifact kind newer than its container version"));
+    }
+    let ordering = ordering_from_code(header[7]).map_err(|_| invalid("unknown ordering"))?;
+    let rows = u64::from_le_bytes(header[12..20].try_into().unwrap());
+    let cols = u64::from_le_bytes(header[20..28].try_into().unwrap());
+    let count = u32::from_le_bytes(header[28..32].try_into().unwrap()) as usize;
+    let payload = match header[8] {
+        0 => Payload::U8(read_bytes(reader, count)?),
+        1 => Payload::I8(take_all!(reader, i8, count)),
+        2 => Payload::I16(take_all!(reader, i16, count)),
+        3 => Payload::I32(take_all!(reader, i32, count)),
+        4 => Payload::I64(take_all!(reader, i64, count)),
+        5 => Payload::F32(take_all!(reader, f32, count)),
+        6 => Payload::F64(take_all!(reader, f64, count)),
+        _ => return Err(invalid("unknown element type")),
+    };
+    let parameterized = matches!(kind, Kind::Plan | Kind::Sketch | Kind::Wisdom);
+    if !parameterized && rows.checked_mul(cols) != Some(count as u64) {
+        return Err(invalid("payload does not match shape"));
+    }
+    Ok(Artifact {
+        kind,
+        ordering,
+        rows,
+        cols,
+        payload,
+    })
+}
+
+// Read exactly `len` bytes. The buffer grows with the bytes actually
+// read rather than being sized from the untrusted header, so a header
+// claiming a huge payload fails at the end of the data instead of
+// exhausting memory.
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "payload shorter than its header claims",
+        ));
+    }
+    Ok(bytes)
+}
+
+// The format version that introduced `kind`.
+fn kind_version(kind: Kind) -> u16 {
+    match kind {
+        Kind::Plan | Kind::Spectrum | Kind::DesignMatrix => 1,
+        Kind::Sketch | Kind::Profile | Kind::Wisdom => 2,
+    }
+}
+
+// `value` if it is at most `MAX_DIMENSION`.
+fn dimension(value: u64) -> Option<u64> {
+    (value <= MAX_DIMENSION).then_some(value)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn ordering_code(ordering: Option<Ordering>) -> u8 {
+    match ordering {
+        None => 0,
+        Some(Ordering::Sequency) => 1,
+        Some(Ordering::Hadamard) => 2,
+    }
+}
+
+fn ordering_from_code(code: u8) -> Result<Option<Ordering>, ()> {
+    match code {
+        0 => Ok(None),
+        1 => Ok(Some(Ordering::Sequency)),
+        2 => Ok(Some(Ordering::Hadamard)),
+        _ => Err(()),
+    }
+}
+
+fn algorithm_code(algorithm: Algorithm) -> u8 {
+    match algorithm {
+        Algorithm::Radix2 => 2,
+        Algorithm::Radix4 => 4,
+        Algorithm::Radix8 => 8,
+        Algorithm::Recursive => 0,
+        Algorithm::FourStep => 1,
+        Algorithm::Tiled => 3,
+    }
+}
+
+fn algorithm_from_code(code: u8) -> Option<Algorithm> {
+    match code {
+        2 => Some(Algorithm::Radix2),
+        4 => Some(Algorithm::Radix4),
+        8 => Some(Algorithm::Radix8),
+        0 => Some(Algorithm::Recursive),
+        1 => Some(Algorithm::FourStep),
+        3 => Some(Algorithm::Tiled),
+        _ => None,
+    }
+}
+
+fn backend_code(backend: Backend) -> u8 {
+    match backend {
+        Backend::Scalar => 0,
+        Backend::Simd => 1,
+        Backend::Parallel => 2,
+        Backend::Gpu => 3,
+        Backend::Transposed => 4,
+    }
+}
+
+fn backend_from_code(code: u8) -> Option<Backend> {
+    match code {
+        0 => Some(Backend::Scalar),
+        1 => Some(Backend::Simd),
+        2 => Some(Backend::Parallel),
+        3 => Some(Backend::Gpu),
+        4 => Some(Backend::Transposed),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(artifact: &Artifact) -> Artifact {
+        let mut bytes = Vec::new();
+        write(&mut bytes, artifact).unwrap();
+        read(&mut bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let plan = Plan::new(64, Ordering::Hadamard)
+            .unwrap()
+            .with_algorithm(Algorithm::Radix4)
+            .with_determinism(Determinism::Strict);
+        assert_eq!(round_trip(&Artifact::plan(&plan)).to_plan(), Some(plan));
+        let design = Artifact::design_matrix(2, 2, vec![1, 1, 1, -1]).unwrap();
+        assert_eq!(round_trip(&design), design);
+        for payload in [
+            Payload::I16(vec![-3, 7]),
+            Payload::I32(vec![i32::MIN, 0]),
+            Payload::I64(vec![i64::MAX, -1]),
+            Payload::F32(vec![0.5, -0.25]),
+        ] {
+            let spectrum = Artifact::spectrum(payload, Ordering::Sequency);
+            assert_eq!(round_trip(&spectrum), spectrum);
+        }
+        assert_eq!(Artifact::design_matrix(2, 3, vec![1; 4]), None);
+        let srht = Srht::new(10, 4, 99).unwrap();
+        let summary = srht.summarize(&[0.5, -1.0, 2.0, 0.25], 3, 2).unwrap();
+        assert_eq!(
+            round_trip(&Artifact::sketch(&summary)).to_sketch(),
+            Some(summary)
+        );
+        assert_eq!(design.to_sketch(), None);
+        let mut profile = TuningProfile::new();
+        profile.record(Measurement {
+            backend: Backend::Parallel,
+            length: 1 << 16,
+            batch: 8,
+            nanos_per_element: 0.75,
+        });
+        assert_eq!(
+            round_trip(&Artifact::profile(&profile)).to_profile(),
+            Some(profile)
+        );
+        assert_eq!(design.to_profile(), None);
+    }
+
+    #[test]
+    fn test_layout_is_stable() {
+        let artifact = Artifact::spectrum(Payload::I16(vec![1, -2]), Ordering::Hadamard);
+        let mut bytes = Vec::new();
+        write(&mut bytes, &artifact).unwrap();
+        assert_eq!(
+            bytes,
+            [
+                b'F', b'W', b'T', 0, 1, 0, 1, 2, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0,
+                0, 0, 0, 2, 0, 0, 0, 1, 0, 0xFE, 0xFF
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let artifact = Artifact::spectrum(Payload::F64(vec![1.0]), Ordering::Sequency);
+        let mut bytes = Vec::new();
+        write(&mut bytes, &artifact).unwrap();
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(
+            read(&mut future.as_slice()).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        let mut garbage = bytes.clone();
+        garbage[0] = b'X';
+        assert!(read(&mut garbage.as_slice()).is_err());
+        assert!(read(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_versions_follow_kinds() {
+        let mut profile = TuningProfile::new();
+        profile.record(Measurement {
+            backend: Backend::Simd,
+            length: 64,
+            batch: 1,
+            nanos_per_element: 0.5,
+        });
+        let mut bytes = Vec::new();
+        write(&mut bytes, &Artifact::profile(&profile)).unwrap();
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 2);
+        assert!(read(&mut bytes.as_slice()).is_ok());
+        // A version 1 container cannot hold a kind added in version 2.
+        bytes[4] = 1;
+        let error = read(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("newer"));
+    }
+
+    #[test]
+    fn test_huge_dimensions_are_rejected() {
+        let plan = Plan::new(8, Ordering::Sequency).unwrap();
+        let mut artifact = Artifact::plan(&plan);
+        artifact.cols = 1 << 40;
+        assert_eq!(artifact.to_plan(), None);
+        let srht = Srht::new(10, 4, 99).unwrap();
+        let summary = srht.summarize(&[0.5, -1.0, 2.0, 0.25], 3, 2).unwrap();
+        let mut artifact = Artifact::sketch(&summary);
+        let Payload::U8(bytes) = &mut artifact.payload else {
+            unreachable!()
+        };
+        bytes[..8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        assert_eq!(artifact.to_sketch(), None);
+        // A corrupted column count survives reading but not rebuilding.
+        let mut artifact = Artifact::sketch(&summary);
+        artifact.cols = u32::MAX as u64;
+        let mut bytes = Vec::new();
+        write(&mut bytes, &artifact).unwrap();
+        let read_back = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read_back.to_sketch(), None);
+    }
+
+    #[test]
+    fn test_huge_counts_fail_without_allocating() {
+        let mut bytes = header(
+            Kind::Spectrum,
+            Some(Ordering::Sequency),
+            6,
+            1,
+            u32::MAX as u64,
+            u32::MAX,
+        )
+        .to_vec();
+        bytes.extend_from_slice(&1.0f64.to_le_bytes());
+        assert_eq!(
+            read(&mut bytes.as_slice()).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let short = header(Kind::Spectrum, None, 0, 1, u32::MAX as u64, u32::MAX);
+        assert!(read(&mut short.as_slice()).is_err());
+    }
+}
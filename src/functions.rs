@@ -0,0 +1,232 @@
+//! Individual Walsh functions.
+//!
+//! The continuous Walsh functions are defined on `[0, 1)` and extended
+//! with period 1. With `t = 0.t₁t₂t₃…` in binary and `g = k ^ (k >> 1)`
+//! the Gray code of `k`,
+//!
+//! `wal(k, t) = (−1)^(g₀t₁ + g₁t₂ + g₂t₃ + …)`
+//!
+//! where `gⱼ` is bit `j` of `g`. Sampling at `t = i / n` reproduces row
+//! `k` of the sequency-ordered Walsh matrix of size `n`.
+
+use crate::{kernel, power_of_2, Ordering};
+
+/// Return the `k`-th Walsh function of length `n` in the given ordering
+/// as a ±1 sequence, without materializing the rest of the matrix.
+/// Returns `None` if `n` is not a power of 2 or `k` is not less than `n`.
+///
+/// In sequency ordering the row has exactly `k` sign changes.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// assert_eq!(
+///     fwt::walsh_row(8, 3, Ordering::Sequency),
+///     Some(vec![1, 1, -1, -1, 1, 1, -1, -1])
+/// );
+/// assert_eq!(
+///     fwt::walsh_row(8, 3, Ordering::Hadamard),
+///     Some(vec![1, -1, -1, 1, 1, -1, -1, 1])
+/// );
+/// assert_eq!(fwt::walsh_row(8, 8, Ordering::Hadamard), None);
+/// ```
+pub fn walsh_row(n: usize, k: usize, ordering: Ordering) -> Option<Vec<i8>> {
+    if !power_of_2(n) || k >= n {
+        return None;
+    }
+    let row = match ordering {
+        Ordering::Sequency => kernel::sequency_to_hadamard(k, n),
+        Ordering::Hadamard => k,
+    };
+    Some(
+        (0..n)
+            .map(|j| {
+                if (row & j).count_ones().is_multiple_of(2) {
+                    1
+                } else {
+                    -1
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Evaluate the Walsh function of sequency `k` at time `t`, where `t` is
+/// taken modulo 1. The function has `k` sign changes on `[0, 1)`.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{wal, walsh_row, Ordering};
+///
+/// assert_eq!(wal(3, 0.3), -1);
+/// assert_eq!(wal(3, 1.3), -1);
+/// let samples: Vec<i8> = (0..8).map(|i| wal(5, i as f64 / 8.0)).collect();
+/// assert_eq!(Some(samples), walsh_row(8, 5, Ordering::Sequency));
+/// ```
+pub fn wal(k: usize, t: f64) -> i8 {
+    let mut gray = k ^ (k >> 1);
+    // Doubling and subtracting 1 are exact, so the digits of t are too.
+    let mut x = t.rem_euclid(1.0);
+    let mut negative = false;
+    while gray != 0 {
+        x *= 2.0;
+        let digit = x >= 1.0;
+        if digit {
+            x -= 1.0;
+        }
+        negative ^= digit && gray & 1 == 1;
+        gray >>= 1;
+    }
+    if negative {
+        -1
+    } else {
+        1
+    }
+}
+
+/// Evaluate the even (cosine-like) Walsh function `cal(k, t) = wal(2k, t)`.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(fwt::cal(1, 0.1), 1);
+/// assert_eq!(fwt::cal(1, 0.3), -1);
+/// assert_eq!(fwt::cal(1, 0.8), 1);
+/// ```
+pub fn cal(k: usize, t: f64) -> i8 {
+    wal(2 * k, t)
+}
+
+/// Evaluate the odd (sine-like) Walsh function `sal(k, t) = wal(2k − 1, t)`.
+///
+/// # Panics
+///
+/// Panics if `k` is 0, for which no `sal` function exists.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(fwt::sal(1, 0.3), 1);
+/// assert_eq!(fwt::sal(1, 0.7), -1);
+/// ```
+pub fn sal(k: usize, t: f64) -> i8 {
+    assert!(k > 0, "sal(0, t) is not defined");
+    wal(2 * k - 1, t)
+}
+
+/// Return the Rademacher function `r_k(t)`, the sign of `sin(2^k π t)`,
+/// sampled at the `n` points `t = i / n`. By convention `r_0` is the
+/// constant 1. Samples falling on a zero of the sine take the value of
+/// the interval they begin, so `r_k` is `(−1)` raised to the `k`-th
+/// binary digit of `t`.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(fwt::rademacher(0, 4), vec![1, 1, 1, 1]);
+/// assert_eq!(fwt::rademacher(1, 4), vec![1, 1, -1, -1]);
+/// assert_eq!(fwt::rademacher(2, 8), vec![1, 1, -1, -1, 1, 1, -1, -1]);
+/// ```
+pub fn rademacher(k: u32, n: usize) -> Vec<i8> {
+    (0..n)
+        .map(|i| {
+            if k == 0 {
+                return 1;
+            }
+            // Scaling by a power of 2 and the remainder are both exact.
+            let scaled = (i as f64 / n as f64) * 2f64.powi(k as i32);
+            if scaled % 2.0 >= 1.0 {
+                -1
+            } else {
+                1
+            }
+        })
+        .collect()
+}
+
+/// Return the indices of the Rademacher functions whose product is the
+/// Walsh function of sequency `k`, in increasing order. These are
+/// `j + 1` for each set bit `j` of the Gray code of `k`; the empty
+/// product is `wal(0, t) = 1`.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{rademacher, rademacher_factors, walsh_row, Ordering};
+///
+/// assert_eq!(rademacher_factors(6), vec![1, 3]);
+/// let product: Vec<i8> = rademacher(1, 8)
+///     .iter()
+///     .zip(rademacher(3, 8))
+///     .map(|(a, b)| a * b)
+///     .collect();
+/// assert_eq!(Some(product), walsh_row(8, 6, Ordering::Sequency));
+/// ```
+pub fn rademacher_factors(k: usize) -> Vec<u32> {
+    let gray = k ^ (k >> 1);
+    (0..usize::BITS)
+        .filter(|j| gray >> j & 1 == 1)
+        .map(|j| j + 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walsh_row_matches_transform_of_unit_vector() {
+        for n in [1, 2, 16, 64] {
+            for k in 0..n {
+                let mut unit = vec![0i8; n];
+                unit[k] = 1;
+                // Both Walsh matrices are symmetric.
+                assert_eq!(walsh_row(n, k, Ordering::Sequency), crate::sequency(&unit));
+                assert_eq!(walsh_row(n, k, Ordering::Hadamard), crate::hadamard(&unit));
+            }
+        }
+        assert_eq!(walsh_row(6, 0, Ordering::Sequency), None);
+    }
+
+    #[test]
+    fn test_walsh_rows_are_rademacher_products() {
+        let n = 64;
+        for k in 0..n {
+            let product = rademacher_factors(k)
+                .into_iter()
+                .fold(vec![1i8; n], |acc, j| {
+                    acc.iter()
+                        .zip(rademacher(j, n))
+                        .map(|(a, b)| a * b)
+                        .collect()
+                });
+            assert_eq!(Some(product), walsh_row(n, k, Ordering::Sequency));
+        }
+        // Beyond the sampling resolution every sample starts a positive
+        // half-period.
+        assert_eq!(rademacher(10, 8), vec![1; 8]);
+    }
+
+    #[test]
+    fn test_wal_sign_changes_and_symmetry() {
+        let n = 1024;
+        for k in 0..64 {
+            let samples: Vec<i8> = (0..n)
+                .map(|i| wal(k, (i as f64 + 0.5) / n as f64))
+                .collect();
+            let changes = samples.windows(2).filter(|w| w[0] != w[1]).count();
+            assert_eq!(changes, k);
+            // cal is even and sal is odd about t = 1/2.
+            for i in 0..n {
+                let t = (i as f64 + 0.5) / n as f64;
+                assert_eq!(cal(k, t), cal(k, 1.0 - t));
+                if k > 0 {
+                    assert_eq!(sal(k, t), -sal(k, 1.0 - t));
+                }
+            }
+        }
+    }
+}
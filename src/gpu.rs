@@ -0,0 +1,561 @@
+//! Walsh transforms on the GPU with wgpu compute shaders.
+//!
+//! A [`Gpu`] holds a device and the compiled shaders, and creates
+//! [`GpuPlan`]s for 1D transforms or 2D transforms of row-major images. A
+//! plan transforms any number of signals or images in one call, for
+//! throughput on large batches, and keeps the device buffers it allocates
+//! for reuse by later calls. Batches too large for the device's buffers
+//! are split into several submissions.
+//!
+//! Each lane is transformed by a shader that applies the stages with
+//! small lags in workgroup memory, a chunk of up to 2048 elements per
+//! workgroup, then one dispatch per remaining stage, and for the sequency
+//! ordering gathers the result from the Hadamard ordering. Columns of 2D
+//! transforms are transformed in place, with strided accesses.
+//!
+//! Transforms are of `f32` data. Results agree with the CPU transforms up
+//! to rounding, and exactly when every partial sum is exact in `f32`, as
+//! for small integers.
+//!
+//! Enable with the `gpu` feature.
+
+use std::fmt;
+use std::num::NonZeroU64;
+use std::sync::{mpsc, Arc};
+
+use crate::{power_of_2, Error, Ordering};
+
+const SHADER: &str = include_str!("gpu.wgsl");
+// The workgroup size and chunk length of the shader.
+const THREADS: usize = 256;
+const CHUNK: usize = 2048;
+// The size of the shader's parameter block, and the most blocks one
+// submission uses: for each of two axes, the local stages, up to 21
+// global stages and the gather.
+const PARAMS_BYTES: u64 = 32;
+const MAX_PASSES: u64 = 48;
+// The most workgroups in one dimension of a dispatch.
+const MAX_GROUPS: usize = 65535;
+
+/// Errors reported by GPU transforms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GpuError {
+    /// No adapter was available.
+    NoAdapter,
+    /// The device could not be created or failed during a transform.
+    Device(String),
+    /// A plan's shape or the data was invalid, as reported by the CPU
+    /// transforms.
+    Shape(Error),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter => write!(f, "no GPU adapter is available"),
+            GpuError::Device(message) => write!(f, "GPU device error: {}", message),
+            GpuError::Shape(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+impl From<Error> for GpuError {
+    fn from(error: Error) -> GpuError {
+        GpuError::Shape(error)
+    }
+}
+
+/// A GPU device with the transform shaders compiled for it.
+#[derive(Debug, Clone)]
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipelines: Arc<Pipelines>,
+}
+
+#[derive(Debug)]
+struct Pipelines {
+    layout: wgpu::BindGroupLayout,
+    local_stages: wgpu::ComputePipeline,
+    global_stage: wgpu::ComputePipeline,
+    gather_sequency: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// Open the default high-performance adapter, blocking until the
+    /// device is ready.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GpuError::NoAdapter`] if there is no adapter, or
+    /// [`GpuError::Device`] if the device cannot be created.
+    pub fn new() -> Result<Gpu, GpuError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|_| GpuError::NoAdapter)?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("fwt"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .map_err(|e| GpuError::Device(e.to_string()))?;
+        Ok(Gpu::with_device(device, queue))
+    }
+
+    /// Use a device and queue opened by the caller, such as those of a
+    /// renderer, so transforms can share its buffers' device.
+    pub fn with_device(device: wgpu::Device, queue: wgpu::Queue) -> Gpu {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("fwt"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let storage = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("fwt"),
+            entries: &[
+                storage(0),
+                storage(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(PARAMS_BYTES),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("fwt"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let pipelines = Pipelines {
+            local_stages: pipeline("local_stages"),
+            global_stage: pipeline("global_stage"),
+            gather_sequency: pipeline("gather_sequency"),
+            layout,
+        };
+        Gpu {
+            device,
+            queue,
+            pipelines: Arc::new(pipelines),
+        }
+    }
+
+    /// Create a plan for 1D transforms of `length` elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GpuError::Shape`] with [`Error::NotPowerOfTwo`] if
+    /// `length` is not a power of 2, or [`GpuError::Device`] if one
+    /// signal does not fit in a device buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::gpu::Gpu;
+    /// use fwt::Ordering;
+    ///
+    /// let Ok(gpu) = Gpu::new() else { return };
+    /// let mut plan = gpu.plan(4, Ordering::Sequency).unwrap();
+    /// // Two signals in one batch.
+    /// let mut v = [1.0, 1.0, -1.0, -1.0, 1.0, 2.0, 3.0, 4.0];
+    /// plan.execute(&mut v).unwrap();
+    /// assert_eq!(v, [0.0, 4.0, 0.0, 0.0, 10.0, -4.0, 0.0, -2.0]);
+    /// ```
+    pub fn plan(&self, length: usize, ordering: Ordering) -> Result<GpuPlan, GpuError> {
+        self.plan_2d(1, length, ordering)
+    }
+
+    /// Create a plan for 2D transforms of `rows × cols` row-major images,
+    /// as computed by [`fwt2`](crate::fwt2).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GpuError::Shape`] with [`Error::NotPowerOfTwo`] if
+    /// either dimension is not a power of 2, or [`GpuError::Device`] if
+    /// one image does not fit in a device buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::gpu::Gpu;
+    /// use fwt::Ordering;
+    ///
+    /// let Ok(gpu) = Gpu::new() else { return };
+    /// let image: Vec<f32> = (0..64 * 32).map(|i| (i % 5) as f32).collect();
+    /// let mut v = image.clone();
+    /// gpu.plan_2d(64, 32, Ordering::Hadamard).unwrap().execute(&mut v).unwrap();
+    /// assert_eq!(v, fwt::fwt2(&image, 64, 32, Ordering::Hadamard).unwrap());
+    /// ```
+    pub fn plan_2d(
+        &self,
+        rows: usize,
+        cols: usize,
+        ordering: Ordering,
+    ) -> Result<GpuPlan, GpuError> {
+        for extent in [cols, rows] {
+            if !power_of_2(extent) {
+                return Err(Error::NotPowerOfTwo(extent).into());
+            }
+        }
+        let limits = self.device.limits();
+        let max_bytes = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+        let image_bytes = (rows * cols * 4) as u64;
+        if image_bytes > max_bytes {
+            return Err(GpuError::Device(format!(
+                "an image of {} bytes exceeds the buffer limit of {} bytes",
+                image_bytes, max_bytes
+            )));
+        }
+        Ok(GpuPlan {
+            gpu: self.clone(),
+            rows,
+            cols,
+            ordering,
+            max_images: (max_bytes / image_bytes) as usize,
+            buffers: None,
+        })
+    }
+}
+
+/// A plan for GPU transforms of batches of signals or images of a fixed
+/// shape, created by [`Gpu::plan`] or [`Gpu::plan_2d`].
+#[derive(Debug)]
+pub struct GpuPlan {
+    gpu: Gpu,
+    rows: usize,
+    cols: usize,
+    ordering: Ordering,
+    max_images: usize,
+    buffers: Option<Buffers>,
+}
+
+#[derive(Debug)]
+struct Buffers {
+    images: usize,
+    data: wgpu::Buffer,
+    scratch: wgpu::Buffer,
+    staging: wgpu::Buffer,
+    params: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+// One line of the axes transformed by a plan, as seen by the shader.
+#[derive(Clone, Copy)]
+struct Axis {
+    length: usize,
+    stride: usize,
+    lanes: usize,
+    lane_step: usize,
+}
+
+impl GpuPlan {
+    /// The number of rows of each image, 1 for 1D plans.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of columns of each image, or the length of each signal.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The ordering of the transforms.
+    pub fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    /// The number of images or signals the plan's device buffers hold,
+    /// 0 before the first transform. Buffers grow to the largest batch
+    /// transformed, up to the device's limit.
+    pub fn capacity(&self) -> usize {
+        self.buffers.as_ref().map_or(0, |b| b.images)
+    }
+
+    /// Replace each image or signal in `v`, which holds any whole number
+    /// of them back to back, with its transform.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GpuError::Shape`] with [`Error::LengthMismatch`] if the
+    /// length of `v` is not a multiple of the image size (reporting the
+    /// next multiple as expected), or [`GpuError::Device`] if the device
+    /// fails.
+    pub fn execute(&mut self, v: &mut [f32]) -> Result<(), GpuError> {
+        let image = self.rows * self.cols;
+        if !v.len().is_multiple_of(image) {
+            return Err(Error::LengthMismatch {
+                expected: v.len().next_multiple_of(image),
+                found: v.len(),
+            }
+            .into());
+        }
+        for batch in v.chunks_mut(self.max_images * image) {
+            self.execute_batch(batch)?;
+        }
+        Ok(())
+    }
+
+    fn execute_batch(&mut self, v: &mut [f32]) -> Result<(), GpuError> {
+        let images = v.len() / (self.rows * self.cols);
+        let bytes = (v.len() * 4) as u64;
+        let mut axes = vec![Axis {
+            length: self.cols,
+            stride: 1,
+            lanes: self.rows,
+            lane_step: self.cols,
+        }];
+        if self.rows > 1 {
+            axes.push(Axis {
+                length: self.rows,
+                stride: self.cols,
+                lanes: self.cols,
+                lane_step: 1,
+            });
+        }
+        let (image, ordering) = (self.rows * self.cols, self.ordering);
+        let gpu = self.gpu.clone();
+        let (device, queue, pipelines) = (&gpu.device, &gpu.queue, &gpu.pipelines);
+        let buffers = self.buffers(images);
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        queue.write_buffer(&buffers.data, 0, bytemuck::cast_slice(v));
+
+        // Each pass reads its parameters from its own block.
+        let mut params = Vec::new();
+        let mut encoder = device.create_command_encoder(&Default::default());
+        for axis in axes.into_iter().filter(|a| a.length > 1) {
+            let total_lanes = axis.lanes * images;
+            let block = |lag: usize, params: &mut Vec<u8>| {
+                let offset = params.len() as u32;
+                let values = [
+                    axis.length,
+                    axis.length.trailing_zeros() as usize,
+                    axis.stride,
+                    axis.lanes,
+                    axis.lane_step,
+                    image,
+                    total_lanes,
+                    lag,
+                ];
+                for value in values {
+                    params.extend_from_slice(&(value as u32).to_le_bytes());
+                }
+                params.resize(params.len().next_multiple_of(alignment as usize), 0);
+                offset
+            };
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            let chunk = axis.length.min(CHUNK);
+            pass.set_pipeline(&pipelines.local_stages);
+            pass.set_bind_group(0, &buffers.bind_group, &[block(0, &mut params)]);
+            dispatch(&mut pass, total_lanes * (axis.length / chunk));
+            let mut lag = chunk;
+            pass.set_pipeline(&pipelines.global_stage);
+            while lag < axis.length {
+                pass.set_bind_group(0, &buffers.bind_group, &[block(lag, &mut params)]);
+                dispatch(&mut pass, (total_lanes * axis.length / 2).div_ceil(THREADS));
+                lag <<= 1;
+            }
+            if ordering == Ordering::Sequency {
+                pass.set_pipeline(&pipelines.gather_sequency);
+                pass.set_bind_group(0, &buffers.bind_group, &[block(0, &mut params)]);
+                dispatch(&mut pass, (total_lanes * axis.length).div_ceil(THREADS));
+                drop(pass);
+                encoder.copy_buffer_to_buffer(&buffers.scratch, 0, &buffers.data, 0, bytes);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&buffers.data, 0, &buffers.staging, 0, bytes);
+        queue.write_buffer(&buffers.params, 0, &params);
+        queue.submit([encoder.finish()]);
+
+        let slice = buffers.staging.slice(..bytes);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| GpuError::Device(e.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|e| GpuError::Device(e.to_string()))?
+            .map_err(|e| GpuError::Device(e.to_string()))?;
+        v.copy_from_slice(bytemuck::cast_slice(&slice.get_mapped_range()));
+        buffers.staging.unmap();
+        Ok(())
+    }
+
+    // The plan's buffers, grown if needed to hold `images`.
+    fn buffers(&mut self, images: usize) -> &Buffers {
+        if self.capacity() < images {
+            self.buffers = None;
+        }
+        let gpu = &self.gpu;
+        let bytes = (images * self.rows * self.cols * 4) as u64;
+        self.buffers.get_or_insert_with(|| {
+            let buffer = |label, usage| {
+                gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size: bytes,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            };
+            let data = buffer(
+                "fwt data",
+                wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            );
+            let scratch = buffer(
+                "fwt scratch",
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            );
+            let staging = buffer(
+                "fwt staging",
+                wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            );
+            let alignment = gpu.device.limits().min_uniform_buffer_offset_alignment as u64;
+            let params = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("fwt params"),
+                size: MAX_PASSES * PARAMS_BYTES.next_multiple_of(alignment),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("fwt"),
+                layout: &gpu.pipelines.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: data.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: scratch.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &params,
+                            offset: 0,
+                            size: NonZeroU64::new(PARAMS_BYTES),
+                        }),
+                    },
+                ],
+            });
+            Buffers {
+                images,
+                data,
+                scratch,
+                staging,
+                params,
+                bind_group,
+            }
+        })
+    }
+}
+
+// Dispatch `groups` workgroups, folding them into rows of at most
+// `MAX_GROUPS`.
+fn dispatch(pass: &mut wgpu::ComputePass<'_>, groups: usize) {
+    let x = groups.clamp(1, MAX_GROUPS);
+    pass.dispatch_workgroups(x as u32, groups.div_ceil(x) as u32, 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_cpu_transforms() {
+        let Ok(gpu) = Gpu::new() else {
+            return;
+        };
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            for log_length in [0, 1, 5, 11, 13] {
+                let length = 1 << log_length;
+                let mut plan = gpu.plan(length, ordering).unwrap();
+                let input: Vec<f32> = (0..3 * length)
+                    .map(|i| ((i * 37) % 23) as f32 - 11.0)
+                    .collect();
+                let mut v = input.clone();
+                plan.execute(&mut v).unwrap();
+                for (signal, spectrum) in input.chunks(length).zip(v.chunks(length)) {
+                    let expected = crate::Plan::new(length, ordering)
+                        .unwrap()
+                        .transform(signal)
+                        .unwrap();
+                    assert_eq!(spectrum, expected, "{ordering:?} length {length}");
+                }
+            }
+            for (rows, cols) in [(2, 1), (4, 8), (4096, 2), (2, 4096)] {
+                let mut plan = gpu.plan_2d(rows, cols, ordering).unwrap();
+                let input: Vec<f32> = (0..2 * rows * cols)
+                    .map(|i| ((i * 7) % 13) as f32 - 6.0)
+                    .collect();
+                let mut v = input.clone();
+                plan.execute(&mut v).unwrap();
+                for (image, spectrum) in input.chunks(rows * cols).zip(v.chunks(rows * cols)) {
+                    assert_eq!(spectrum, crate::fwt2(image, rows, cols, ordering).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let Ok(gpu) = Gpu::new() else {
+            return;
+        };
+        let mut plan = gpu.plan(8, Ordering::Hadamard).unwrap();
+        assert_eq!(plan.capacity(), 0);
+        let mut v = vec![1.0; 8 * 4];
+        plan.execute(&mut v).unwrap();
+        assert_eq!(plan.capacity(), 4);
+        let mut w = [1.0; 8];
+        plan.execute(&mut w).unwrap();
+        assert_eq!(w, [8.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(plan.capacity(), 4);
+        plan.execute(&mut []).unwrap();
+        assert_eq!(
+            plan.execute(&mut [0.0; 12]),
+            Err(GpuError::Shape(Error::LengthMismatch {
+                expected: 16,
+                found: 12
+            }))
+        );
+        assert_eq!(
+            gpu.plan_2d(3, 4, Ordering::Hadamard).unwrap_err(),
+            GpuError::Shape(Error::NotPowerOfTwo(3))
+        );
+    }
+}
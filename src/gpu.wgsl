@@ -0,0 +1,107 @@
+// Walsh transforms of the lanes of a batch of row-major images.
+//
+// A lane is the sequence of `length` elements starting at `lane_base`
+// and `stride` apart: a row of an image for `stride` 1, or a column for
+// `stride` equal to the row length. The lanes of each image are
+// `lane_step` apart, and the images `image_size` apart.
+
+struct Params {
+    length: u32,
+    log_length: u32,
+    stride: u32,
+    lanes: u32,
+    lane_step: u32,
+    image_size: u32,
+    total_lanes: u32,
+    lag: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@group(0) @binding(1) var<storage, read_write> scratch: array<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const THREADS: u32 = 256u;
+// The elements of a lane transformed in workgroup memory at once.
+const CHUNK: u32 = 2048u;
+
+var<workgroup> tile: array<f32, CHUNK>;
+
+fn at(lane: u32, position: u32) -> u32 {
+    let image = lane / params.lanes;
+    let line = lane % params.lanes;
+    return image * params.image_size + line * params.lane_step + position * params.stride;
+}
+
+// Apply the stages with lags below the chunk length to every chunk of
+// every lane, one chunk per workgroup.
+@compute @workgroup_size(256)
+fn local_stages(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+    @builtin(local_invocation_index) thread: u32,
+) {
+    let chunk = min(params.length, CHUNK);
+    let chunks = params.length / chunk;
+    let group = group_id.x + group_id.y * groups.x;
+    if group >= params.total_lanes * chunks {
+        return;
+    }
+    let lane = group / chunks;
+    let start = (group % chunks) * chunk;
+    for (var i = thread; i < chunk; i += THREADS) {
+        tile[i] = data[at(lane, start + i)];
+    }
+    workgroupBarrier();
+    for (var lag = 1u; lag < chunk; lag <<= 1u) {
+        for (var i = thread; i < chunk / 2u; i += THREADS) {
+            let j = (i / lag) * 2u * lag + i % lag;
+            let a = tile[j];
+            let b = tile[j + lag];
+            tile[j] = a + b;
+            tile[j + lag] = a - b;
+        }
+        workgroupBarrier();
+    }
+    for (var i = thread; i < chunk; i += THREADS) {
+        data[at(lane, start + i)] = tile[i];
+    }
+}
+
+// Apply the stage with lag `params.lag` to every lane, one butterfly per
+// invocation.
+@compute @workgroup_size(256)
+fn global_stage(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let i = id.x + id.y * groups.x * THREADS;
+    let half = params.length / 2u;
+    if i >= params.total_lanes * half {
+        return;
+    }
+    let lane = i / half;
+    let k = i % half;
+    let lag = params.lag;
+    let j = (k / lag) * 2u * lag + k % lag;
+    let a = data[at(lane, j)];
+    let b = data[at(lane, j + lag)];
+    data[at(lane, j)] = a + b;
+    data[at(lane, j + lag)] = a - b;
+}
+
+// Write the sequency ordering of the Hadamard-ordered lanes of `data` to
+// `scratch`: sequency `s` is at the bit reversal of the Gray code of `s`.
+@compute @workgroup_size(256)
+fn gather_sequency(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let i = id.x + id.y * groups.x * THREADS;
+    if i >= params.total_lanes * params.length {
+        return;
+    }
+    let lane = i / params.length;
+    let s = i % params.length;
+    let h = reverseBits(s ^ (s >> 1u)) >> (32u - params.log_length);
+    scratch[at(lane, s)] = data[at(lane, h)];
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use crate::{Normalize, WalshElement};
+
+/// A closed interval of `f64` values with outward-rounded arithmetic.
+///
+/// Every sum and difference is widened by rounding its lower bound down
+/// and its upper bound up, so the result of a transform of intervals is a
+/// rigorous enclosure of the transform of every vector of points drawn
+/// from the input intervals, floating-point error included. This lets
+/// measurement uncertainty be carried through a transform.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Interval;
+///
+/// let input = [Interval::with_radius(1.0, 0.1), Interval::point(2.0)];
+/// let spectrum = fwt::hadamard(&input).unwrap();
+/// assert!(spectrum[0].contains(3.0) && spectrum[0].contains(3.1));
+/// assert!(spectrum[1].contains(-1.0) && !spectrum[1].contains(-0.8));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    /// Construct the interval `[lo, hi]`, or `None` if either bound is
+    /// NaN or `lo > hi`.
+    pub fn new(lo: f64, hi: f64) -> Option<Interval> {
+        if lo <= hi {
+            Some(Interval { lo, hi })
+        } else {
+            None
+        }
+    }
+
+    /// The degenerate interval containing only `x`.
+    pub fn point(x: f64) -> Interval {
+        Interval { lo: x, hi: x }
+    }
+
+    /// An interval enclosing `[mid - radius, mid + radius]`.
+    pub fn with_radius(mid: f64, radius: f64) -> Interval {
+        let radius = radius.abs();
+        Interval {
+            lo: (mid - radius).next_down(),
+            hi: (mid + radius).next_up(),
+        }
+    }
+
+    /// The lower bound.
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    /// The upper bound.
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    /// The midpoint of the interval.
+    pub fn midpoint(&self) -> f64 {
+        self.lo / 2.0 + self.hi / 2.0
+    }
+
+    /// The width of the interval, rounded up.
+    pub fn width(&self) -> f64 {
+        (self.hi - self.lo).next_up()
+    }
+
+    /// Determine whether `x` lies in the interval.
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Interval) -> Interval {
+        Interval {
+            lo: (self.lo + rhs.lo).next_down(),
+            hi: (self.hi + rhs.hi).next_up(),
+        }
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Interval) -> Interval {
+        Interval {
+            lo: (self.lo - rhs.hi).next_down(),
+            hi: (self.hi - rhs.lo).next_up(),
+        }
+    }
+}
+
+impl WalshElement for Interval {}
+
+impl Normalize for Interval {
+    fn normalize(self, length: usize) -> Option<Self> {
+        let length = length as f64;
+        Some(Interval {
+            lo: (self.lo / length).next_down(),
+            hi: (self.hi / length).next_up(),
+        })
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hadamard, sequency};
+
+    #[test]
+    fn test_transform_encloses_point_results() {
+        let mids: Vec<f64> = (0..64).map(|x| ((x * 37 + 11) % 101) as f64 / 7.0).collect();
+        let input: Vec<Interval> = mids.iter().map(|&m| Interval::with_radius(m, 1e-3)).collect();
+        let enclosure = sequency(&input).unwrap();
+        for shift in [-1e-3, 0.0, 1e-3] {
+            let points: Vec<f64> = mids.iter().map(|m| m + shift).collect();
+            let exact = sequency(&points).unwrap();
+            for (e, x) in enclosure.iter().zip(&exact) {
+                assert!(e.contains(*x), "{} does not contain {}", e, x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_encloses_input() {
+        let mids: Vec<f64> = (0..16).map(|x| 0.1 * x as f64).collect();
+        let input: Vec<Interval> = mids.iter().map(|&m| Interval::point(m)).collect();
+        let twice = hadamard(&hadamard(&input).unwrap()).unwrap();
+        for (e, m) in twice.iter().zip(&mids) {
+            let scaled = e.normalize(16).unwrap();
+            assert!(scaled.contains(*m));
+            assert!(scaled.width() < 1e-12);
+        }
+        assert_eq!(Interval::new(2.0, 1.0), None);
+        assert_eq!(Interval::new(f64::NAN, 1.0), None);
+    }
+}
@@ -0,0 +1,729 @@
+//! In-place butterfly kernels shared by the public transforms and plans.
+//! Callers are responsible for checking that lengths are powers of 2.
+
+use std::ops::Range;
+
+use crate::{codelet, Ordering, WalshElement};
+
+// Index the mutable slice `$v` at `$i` as a place. Every use is inside a
+// loop that asserts before it starts that all the indices it forms are
+// within `$v`, so with the `unchecked` feature the bounds check repeated
+// on each access, which the optimizer cannot always remove, is skipped.
+#[cfg(not(feature = "unchecked"))]
+macro_rules! at {
+    ($v:expr, $i:expr) => {
+        $v[$i]
+    };
+}
+
+#[cfg(feature = "unchecked")]
+macro_rules! at {
+    ($v:expr, $i:expr) => {
+        // SAFETY: the enclosing loop has asserted that `$i` is in bounds.
+        *unsafe { $v.get_unchecked_mut($i) }
+    };
+}
+
+/// Apply the Hadamard-ordered butterfly stages to `v`.
+pub(crate) fn hadamard<T>(v: &mut [T])
+where
+    T: WalshElement,
+{
+    if v.len() <= codelet::MAX_LENGTH {
+        return codelet::stages(v, Ordering::Hadamard);
+    }
+    hadamard_from(v, 1);
+}
+
+/// Apply the Hadamard-ordered butterfly stages with lags of at least
+/// `first_lag` to `v`. For a power-of-2 `first_lag` this transforms each
+/// of the `first_lag` interleaved subsequences of `v` independently.
+pub(crate) fn hadamard_from<T>(v: &mut [T], first_lag: usize)
+where
+    T: WalshElement,
+{
+    let length = v.len();
+    let mut lag = first_lag;
+    while lag < length {
+        for group in (0..length).step_by(lag << 1) {
+            butterfly_slices(v, group, group + lag, lag, false);
+        }
+        lag <<= 1;
+    }
+}
+
+// Below this many elements, butterflies over slices are done by the
+// scalar loops of the gathering passes.
+const MIN_SLICE: usize = 8;
+
+// The number of elements per slice in the fused passes, so that the
+// 2^fused slices of a block stay in the L1 cache.
+const FUSED_CHUNK: usize = 256;
+
+// Butterfly the `len` elements starting at `j` with those starting at
+// `k > j`, exchanging the outputs if `crossed`.
+#[inline]
+fn butterfly_slices<T: WalshElement>(v: &mut [T], j: usize, k: usize, len: usize, crossed: bool) {
+    if len < MIN_SLICE {
+        assert!(j + len <= k && k + len <= v.len());
+        for i in j..j + len {
+            let (a, b) = (at!(v, i), at!(v, i + k - j));
+            (at!(v, i), at!(v, i + k - j)) = if crossed {
+                (a - b, a + b)
+            } else {
+                (a + b, a - b)
+            };
+        }
+        return;
+    }
+    let (head, tail) = v.split_at_mut(k);
+    let (a, b) = (&mut head[j..j + len], &mut tail[..len]);
+    if crossed {
+        T::butterfly_crossed(a, b);
+    } else {
+        T::butterfly(a, b);
+    }
+}
+
+/// Apply the Hadamard-ordered butterfly stages to `v`, fusing up to
+/// `max_fused` (at most 3) consecutive stages into each pass over the
+/// data. Fused passes perform exactly the additions of the radix-2
+/// stages they replace, in the same order, so results are bit-identical
+/// to [`hadamard`] while the data is swept fewer times.
+pub(crate) fn hadamard_fused<T>(v: &mut [T], max_fused: u32)
+where
+    T: WalshElement,
+{
+    let length = v.len();
+    let mut lag = 1;
+    while lag < length {
+        let remaining = (length / lag).trailing_zeros();
+        match remaining.min(max_fused) {
+            3 => hadamard_pass8(v, lag),
+            2 => hadamard_pass4(v, lag),
+            _ => hadamard_pass2(v, lag),
+        }
+        lag <<= remaining.min(max_fused).max(1);
+    }
+}
+
+fn hadamard_pass2<T: WalshElement>(v: &mut [T], lag: usize) {
+    for group in (0..v.len()).step_by(lag << 1) {
+        butterfly_slices(v, group, group + lag, lag, false);
+    }
+}
+
+fn hadamard_pass4<T: WalshElement>(v: &mut [T], lag: usize) {
+    if lag >= MIN_SLICE {
+        return fused_slices(v, lag << 2, 2, Ordering::Hadamard, 0);
+    }
+    assert!(v.len().is_multiple_of(lag << 2));
+    for group in (0..v.len()).step_by(lag << 2) {
+        for j in group..group + lag {
+            let (a, b, c, d) = (
+                at!(v, j),
+                at!(v, j + lag),
+                at!(v, j + 2 * lag),
+                at!(v, j + 3 * lag),
+            );
+            let (ab, a_b, cd, c_d) = (a + b, a - b, c + d, c - d);
+            at!(v, j) = ab + cd;
+            at!(v, j + lag) = a_b + c_d;
+            at!(v, j + 2 * lag) = ab - cd;
+            at!(v, j + 3 * lag) = a_b - c_d;
+        }
+    }
+}
+
+fn hadamard_pass8<T: WalshElement>(v: &mut [T], lag: usize) {
+    if lag >= MIN_SLICE {
+        return fused_slices(v, lag << 3, 3, Ordering::Hadamard, 0);
+    }
+    assert!(v.len().is_multiple_of(lag << 3));
+    for group in (0..v.len()).step_by(lag << 3) {
+        for j in group..group + lag {
+            let mut x = [at!(v, j); 8];
+            for (i, xi) in x.iter_mut().enumerate().skip(1) {
+                *xi = at!(v, j + i * lag);
+            }
+            for half in [1, 2, 4] {
+                for base in (0..8).step_by(half << 1) {
+                    for i in base..base + half {
+                        (x[i], x[i + half]) = (x[i] + x[i + half], x[i] - x[i + half]);
+                    }
+                }
+            }
+            for (i, &xi) in x.iter().enumerate() {
+                at!(v, j + i * lag) = xi;
+            }
+        }
+    }
+}
+
+// Apply `fused` stages to every group of `offset` elements as butterflies
+// between whole slices, the form of the gathering passes used when the
+// stride between interacting elements is long enough to vectorize. Each
+// group's 2^fused slices of stride elements are processed in chunks
+// that fit in cache, applying every fused stage to a chunk before moving
+// on, so the data is still swept once per pass. Hadamard stages run with
+// increasing lags; sequency stages run with decreasing lags, the first
+// taking its parity from the group, numbered from `first_group`.
+fn fused_slices<T: WalshElement>(
+    v: &mut [T],
+    offset: usize,
+    fused: u32,
+    ordering: Ordering,
+    first_group: usize,
+) {
+    let width = 1usize << fused;
+    let stride = offset / width;
+    for (group, start) in (0..v.len()).step_by(offset).enumerate() {
+        for chunk in (0..stride).step_by(FUSED_CHUNK) {
+            let len = FUSED_CHUNK.min(stride - chunk);
+            let at = |i: usize| start + i * stride + chunk;
+            match ordering {
+                Ordering::Hadamard => {
+                    let mut half = 1;
+                    while half < width {
+                        for base in (0..width).step_by(half << 1) {
+                            for i in base..base + half {
+                                butterfly_slices(v, at(i), at(i + half), len, false);
+                            }
+                        }
+                        half <<= 1;
+                    }
+                }
+                Ordering::Sequency => {
+                    let mut local_offset = width;
+                    while local_offset > 1 {
+                        let lag = local_offset >> 1;
+                        for (local_group, base) in (0..width).step_by(local_offset).enumerate() {
+                            let odd = if local_offset == width {
+                                (first_group + group) & 1 == 1
+                            } else {
+                                local_group & 1 == 1
+                            };
+                            for i in base..base + lag {
+                                butterfly_slices(v, at(i), at(i + lag), len, odd);
+                            }
+                        }
+                        local_offset = lag;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reorder `v` by reversing the bits of each index.
+pub(crate) fn bit_reverse<T>(v: &mut [T]) {
+    #[cfg(not(feature = "unchecked"))]
+    for (i, j) in bit_reverse_swaps(v.len()) {
+        v.swap(i, j);
+    }
+    #[cfg(feature = "unchecked")]
+    {
+        assert!(v.len() <= 1 || crate::power_of_2(v.len()));
+        let p = v.as_mut_ptr();
+        for (i, j) in bit_reverse_swaps(v.len()) {
+            // SAFETY: the bit-reversal swaps of a power-of-2 length are
+            // within it.
+            unsafe { std::ptr::swap(p.add(i), p.add(j)) };
+        }
+    }
+}
+
+/// Return the index pairs that must be exchanged to bit-reverse a slice
+/// of the given length.
+pub(crate) fn bit_reverse_swaps(length: usize) -> impl Iterator<Item = (usize, usize)> {
+    let mut j = 0;
+    (0..length.saturating_sub(2)).filter_map(move |i| {
+        let swap = if i < j { Some((i, j)) } else { None };
+        let mut k = length >> 1;
+        while k <= j {
+            j -= k;
+            k >>= 1;
+        }
+        j += k;
+        swap
+    })
+}
+
+/// Apply the sequency-ordered butterfly stages to a bit-reversed `v`.
+pub(crate) fn sequency_stages<T>(v: &mut [T])
+where
+    T: WalshElement,
+{
+    if v.len() <= codelet::MAX_LENGTH {
+        return codelet::stages(v, Ordering::Sequency);
+    }
+    sequency_stages_from(v, v.len(), 0);
+}
+
+/// Return the sequency ordering transform of `input` without a separate
+/// bit-reversal pass: the first stage gathers its operands directly from
+/// their bit-reversed positions, which always form the adjacent pair
+/// `2r, 2r + 1`. The arithmetic is identical to [`bit_reverse`] followed
+/// by [`sequency_stages`], so results are bit-exact.
+pub(crate) fn sequency_gathered<T>(input: &[T]) -> Vec<T>
+where
+    T: WalshElement,
+{
+    let length = input.len();
+    if length <= codelet::MAX_LENGTH {
+        let mut v = input.to_vec();
+        codelet::transform(&mut v, Ordering::Sequency);
+        return v;
+    }
+    let lag = length >> 1;
+    let mut v: Vec<T> = (0..length)
+        .map(|i| {
+            let r = reverse_index(i & (lag - 1), lag) << 1;
+            if i < lag {
+                input[r] + input[r + 1]
+            } else {
+                input[r] - input[r + 1]
+            }
+        })
+        .collect();
+    sequency_stages_from(&mut v, lag, 0);
+    v
+}
+
+/// Apply the sequency stages whose butterflies span at most `offset`
+/// elements to `v`, the `block`th of the equal blocks of a longer
+/// bit-reversed slice, so that each group takes its parity from its
+/// position in the whole slice.
+pub(crate) fn sequency_stages_from<T>(v: &mut [T], mut offset: usize, block: usize)
+where
+    T: WalshElement,
+{
+    let length = v.len();
+    while offset > 1 {
+        let lag = offset >> 1;
+        let first_group = block * (length / offset);
+        for (group, start) in (0..length).step_by(offset).enumerate() {
+            butterfly_slices(v, start, start + lag, lag, (first_group + group) & 1 == 1);
+        }
+        offset = lag;
+    }
+}
+
+/// Reorder the rows of `v`, viewed as rows of `width` elements, by
+/// reversing the bits of each row index.
+pub(crate) fn bit_reverse_rows<T>(v: &mut [T], width: usize) {
+    for (i, j) in bit_reverse_swaps(v.len() / width) {
+        let (head, tail) = v.split_at_mut(j * width);
+        head[i * width..(i + 1) * width].swap_with_slice(&mut tail[..width]);
+    }
+}
+
+/// Apply the butterfly stages of `ordering` down the columns of `v`,
+/// viewed as a power-of-2 number of rows of `width` elements, touching
+/// only the columns in `columns`; the last row may end after them. Each
+/// butterfly combines two row segments element by element, so the
+/// innermost loop stays contiguous, and each column receives exactly the
+/// additions of [`hadamard`] or [`sequency_stages`], so results are
+/// bit-identical to transforming the columns one at a time. Sequency
+/// ordering expects the rows to have been bit-reversed already, and takes
+/// group parities as in [`sequency_stages_from`] with `block`.
+pub(crate) fn stages_across_rows<T>(
+    v: &mut [T],
+    width: usize,
+    columns: Range<usize>,
+    ordering: Ordering,
+    block: usize,
+) where
+    T: WalshElement,
+{
+    let rows = v.len().div_ceil(width);
+    let butterfly = |v: &mut [T], j: usize, k: usize, swap: bool| {
+        let (head, tail) = v.split_at_mut(k * width);
+        let a = &mut head[j * width + columns.start..j * width + columns.end];
+        let b = &mut tail[columns.clone()];
+        if swap {
+            T::butterfly_crossed(a, b);
+        } else {
+            T::butterfly(a, b);
+        }
+    };
+    match ordering {
+        Ordering::Hadamard => {
+            let mut lag = 1;
+            while lag < rows {
+                for group in (0..rows).step_by(lag << 1) {
+                    for j in group..group + lag {
+                        butterfly(v, j, j + lag, false);
+                    }
+                }
+                lag <<= 1;
+            }
+        }
+        Ordering::Sequency => {
+            let mut offset = rows;
+            while offset > 1 {
+                let lag = offset >> 1;
+                let first_group = block * (rows / offset);
+                for group in 0..rows / offset {
+                    for i in 0..lag {
+                        let j = i + group * offset;
+                        butterfly(v, j, j + lag, (first_group + group) & 1 == 1);
+                    }
+                }
+                offset = lag;
+            }
+        }
+    }
+}
+
+/// Reverse the low `log2(length)` bits of `i`.
+pub(crate) fn reverse_index(i: usize, length: usize) -> usize {
+    if length <= 1 {
+        0
+    } else {
+        i.reverse_bits() >> (length.leading_zeros() + 1)
+    }
+}
+
+/// Map a sequency-ordered coefficient index to the Hadamard-ordered index
+/// of the same Walsh function.
+pub(crate) fn sequency_to_hadamard(s: usize, length: usize) -> usize {
+    reverse_index(s ^ (s >> 1), length)
+}
+
+/// Map a Hadamard-ordered coefficient index to the sequency-ordered index
+/// of the same Walsh function, the inverse of [`sequency_to_hadamard`].
+pub(crate) fn hadamard_to_sequency(h: usize, length: usize) -> usize {
+    let mut s = reverse_index(h, length);
+    let mut shift = 1;
+    while shift < usize::BITS {
+        s ^= s >> shift;
+        shift <<= 1;
+    }
+    s
+}
+
+/// Apply the sequency-ordered butterfly stages to a bit-reversed `v`,
+/// fusing up to `max_fused` (at most 3) consecutive stages into each
+/// pass, with the same bit-exactness guarantee as [`hadamard_fused`].
+/// As in [`sequency_stages_from`], `v` is the `block`th block of a longer
+/// slice, or the whole slice if `block` is 0.
+pub(crate) fn sequency_stages_fused<T>(v: &mut [T], max_fused: u32, block: usize)
+where
+    T: WalshElement,
+{
+    let mut offset = v.len();
+    while offset > 1 {
+        let fused = offset.trailing_zeros().min(max_fused).max(1);
+        sequency_pass(v, offset, fused, block * (v.len() / offset));
+        offset >>= fused;
+    }
+}
+
+// Butterfly `x[i]` with `x[i + lag]`, exchanging the outputs in groups of
+// odd parity as the sequency ordering requires.
+fn sequency_butterfly<T: WalshElement>(x: &mut [T], i: usize, lag: usize, odd: bool) {
+    let (a, b) = (x[i], x[i + lag]);
+    (x[i], x[i + lag]) = if odd { (a - b, a + b) } else { (a + b, a - b) };
+}
+
+// Apply `fused` sequency stages, starting with the stage whose groups have
+// size `offset`, numbered from `first_group`, gathering each set of
+// 2^fused interacting elements into a local array.
+fn sequency_pass<T: WalshElement>(v: &mut [T], offset: usize, fused: u32, first_group: usize) {
+    let width = 1usize << fused;
+    let stride = offset / width;
+    if stride >= MIN_SLICE && fused > 1 {
+        return fused_slices(v, offset, fused, Ordering::Sequency, first_group);
+    }
+    assert!(v.len().is_multiple_of(offset));
+    for (group, start) in (0..v.len()).step_by(offset).enumerate() {
+        for j in start..start + stride {
+            let mut x = [at!(v, j); 8];
+            for (i, xi) in x.iter_mut().enumerate().take(width).skip(1) {
+                *xi = at!(v, j + i * stride);
+            }
+            let mut local_offset = width;
+            let mut first = true;
+            while local_offset > 1 {
+                let lag = local_offset >> 1;
+                for (local_group, base) in (0..width).step_by(local_offset).enumerate() {
+                    // The first stage's parity is that of the enclosing
+                    // group; later stages alternate within it.
+                    let odd = if first {
+                        (first_group + group) & 1 == 1
+                    } else {
+                        local_group & 1 == 1
+                    };
+                    for i in base..base + lag {
+                        sequency_butterfly(&mut x, i, lag, odd);
+                    }
+                }
+                local_offset = lag;
+                first = false;
+            }
+            for (i, &xi) in x.iter().enumerate().take(width) {
+                at!(v, j + i * stride) = xi;
+            }
+        }
+    }
+}
+
+// At or below this many elements, the recursive algorithm switches to the
+// iterative kernels, whose working set then fits in the L1 cache.
+const RECURSIVE_LEAF: usize = 1 << 12;
+
+// The recursive algorithm splits columns no narrower than this, so
+// butterflies over row segments stay long enough to vectorize.
+const RECURSIVE_MIN_COLUMNS: usize = 256;
+
+/// Apply the butterfly stages of `ordering` to `v`, bit-reversed for
+/// sequency, by cache-oblivious divide and conquer. The stages are split
+/// into those within blocks of about `sqrt(v.len())` elements and those
+/// across them, and both halves are split again, alternating between
+/// rows and columns, until a piece fits in the L1 cache. Every level of
+/// the memory hierarchy then holds whole pieces, so the data is swept
+/// about `log(n) / log(cache size)` times rather than once per pass. Each
+/// element receives the additions of [`hadamard`] or [`sequency_stages`]
+/// in the same order, so results are bit-identical to them. Sequency
+/// group parities are taken as in [`sequency_stages_from`] with `block`.
+pub(crate) fn stages_recursive<T>(v: &mut [T], ordering: Ordering, block: usize)
+where
+    T: WalshElement,
+{
+    recurse(v, v.len(), 1, 0..1, ordering, block);
+}
+
+// Apply the stages of `ordering` down the columns in `columns` of `rows`
+// rows starting `stride` elements apart in `v`, the `block`th block of
+// rows of a longer transform.
+fn recurse<T>(
+    v: &mut [T],
+    rows: usize,
+    stride: usize,
+    columns: Range<usize>,
+    ordering: Ordering,
+    block: usize,
+) where
+    T: WalshElement,
+{
+    if rows <= 1 {
+        return;
+    }
+    let width = columns.len();
+    if rows * width <= RECURSIVE_LEAF {
+        if stride == 1 {
+            let v = &mut v[..rows];
+            match ordering {
+                Ordering::Hadamard => hadamard_fused(v, 2),
+                Ordering::Sequency => sequency_stages_fused(v, 3, block),
+            }
+        } else {
+            let end = (rows - 1) * stride + columns.end;
+            stages_across_rows(&mut v[..end], stride, columns, ordering, block);
+        }
+        return;
+    }
+    if width > rows && width >= 2 * RECURSIVE_MIN_COLUMNS {
+        let middle = columns.start + width / 2;
+        recurse(v, rows, stride, columns.start..middle, ordering, block);
+        recurse(v, rows, stride, middle..columns.end, ordering, block);
+        return;
+    }
+    // Here `rows >= 4`, since two rows of more than `RECURSIVE_LEAF / 2`
+    // columns are split by columns above.
+    let inner = 1 << (rows.ilog2() / 2);
+    let outer = rows / inner;
+    let within = |v: &mut [T]| {
+        for c in 0..outer {
+            let v = &mut v[c * inner * stride..];
+            recurse(
+                v,
+                inner,
+                stride,
+                columns.clone(),
+                ordering,
+                block * outer + c,
+            );
+        }
+    };
+    let across = |v: &mut [T]| {
+        if columns == (0..stride) {
+            recurse(v, outer, stride * inner, 0..stride * inner, ordering, block);
+        } else {
+            for i in 0..inner {
+                let v = &mut v[i * stride..];
+                recurse(v, outer, stride * inner, columns.clone(), ordering, block);
+            }
+        }
+    };
+    match ordering {
+        Ordering::Hadamard => {
+            within(v);
+            across(v);
+        }
+        Ordering::Sequency => {
+            across(v);
+            within(v);
+        }
+    }
+}
+
+// The side of the square tiles swapped by the in-place transposes of the
+// four-step algorithm. Rows of a tile are a power of 2 apart and so share
+// cache sets; small tiles keep a pair of them from evicting each other.
+const TRANSPOSE_TILE: usize = 8;
+
+/// Apply the butterfly stages of `ordering` to `v`, bit-reversed for
+/// sequency, by the four-step algorithm: viewing `v` as a square matrix
+/// of side `sqrt(n)`, transform the rows, transpose in place, transform
+/// the rows again, which hold the original columns, and transpose back.
+/// Each transform is of a contiguous row that fits in cache, and the
+/// transposes are tiled, so the data is swept a fixed number of times.
+/// Lengths that are odd powers of 2 are split into two square halves with
+/// one butterfly stage between them. The stages are applied to every
+/// element in the same order as in [`hadamard`] or [`sequency_stages`],
+/// so results are bit-identical to them; sequency group parities are
+/// taken as in [`sequency_stages_from`] with `block`.
+pub(crate) fn stages_four_step<T>(v: &mut [T], ordering: Ordering, block: usize)
+where
+    T: WalshElement,
+{
+    let length = v.len();
+    if length.trailing_zeros() % 2 == 1 {
+        let half = length / 2;
+        if ordering == Ordering::Sequency {
+            butterfly_slices(v, 0, half, half, block & 1 == 1);
+        }
+        let (low, high) = v.split_at_mut(half);
+        stages_four_step(low, ordering, 2 * block);
+        stages_four_step(high, ordering, 2 * block + 1);
+        if ordering == Ordering::Hadamard {
+            butterfly_slices(v, 0, half, half, false);
+        }
+        return;
+    }
+    let side = 1 << (length.trailing_zeros() / 2);
+    let rows = |v: &mut [T], columns: bool| {
+        for (i, row) in v.chunks_exact_mut(side).enumerate() {
+            match ordering {
+                Ordering::Hadamard => hadamard_fused(row, 2),
+                // Columns are the groups of whole rows of this block, and
+                // rows are blocks of the next level down.
+                Ordering::Sequency if columns => sequency_stages_fused(row, 3, block),
+                Ordering::Sequency => sequency_stages_fused(row, 3, block * side + i),
+            }
+        }
+    };
+    match ordering {
+        Ordering::Hadamard => {
+            rows(v, false);
+            transpose_square(v, side);
+            rows(v, true);
+            transpose_square(v, side);
+        }
+        Ordering::Sequency => {
+            transpose_square(v, side);
+            rows(v, true);
+            transpose_square(v, side);
+            rows(v, false);
+        }
+    }
+}
+
+// Transpose the `side × side` row-major matrix `v` in place, a pair of
+// tiles at a time.
+fn transpose_square<T>(v: &mut [T], side: usize) {
+    let tile = TRANSPOSE_TILE.min(side);
+    for i0 in (0..side).step_by(tile) {
+        for j0 in (i0..side).step_by(tile) {
+            for i in i0..i0 + tile {
+                let start = if i0 == j0 { i + 1 } else { j0 };
+                for j in start..j0 + tile {
+                    v.swap(i * side + j, j * side + i);
+                }
+            }
+        }
+    }
+}
+
+// The bytes of data in a tile of the tiled algorithm, a fraction of a
+// typical L2 cache.
+const TILE_BYTES: usize = 1 << 18;
+
+// The width of the column strips in the levels of the tiled algorithm
+// above the first: long enough for the butterflies to vectorize, and few
+// enough rows per strip that they do not evict each other from the cache.
+const TILE_STRIP: usize = 512;
+
+/// Apply the butterfly stages of `ordering` to `v`, bit-reversed for
+/// sequency, tiled for the cache: the stages are grouped into levels,
+/// and each level is applied to one cache-sized tile at a time before
+/// moving on, so the data is swept once per level rather than once per
+/// stage. The first level holds the stages within contiguous tiles of
+/// about 256 KiB; each later level holds the stages across the rows of
+/// the previous one, applied to strips of 512 columns at a time. The
+/// stages are applied to every element in the same order as in
+/// [`hadamard`] or [`sequency_stages`], so results are bit-identical to
+/// them; sequency group parities are taken as in [`sequency_stages_from`]
+/// with `block`.
+pub(crate) fn stages_tiled<T>(v: &mut [T], ordering: Ordering, block: usize)
+where
+    T: WalshElement,
+{
+    let tile = (TILE_BYTES / std::mem::size_of::<T>().max(1)).next_power_of_two();
+    stages_tiled_by(v, ordering, block, tile);
+}
+
+/// As [`stages_tiled`], with tiles of `tile` elements, a power of 2.
+pub(crate) fn stages_tiled_by<T>(v: &mut [T], ordering: Ordering, block: usize, tile: usize)
+where
+    T: WalshElement,
+{
+    let length = v.len();
+    if length <= 1 {
+        return;
+    }
+    let tile = tile.max(2 * TILE_STRIP).min(length);
+    // Each level after the first spans `rows` rows of `width` elements.
+    // Every level at least doubles the width, so there are fewer levels
+    // than bits, and a fixed array keeps this path free of allocation.
+    let mut all_levels = [(0, 0); usize::BITS as usize];
+    let mut count = 0;
+    let mut width = tile;
+    while width < length {
+        let rows = (tile / TILE_STRIP).min(length / width);
+        all_levels[count] = (width, rows);
+        count += 1;
+        width *= rows;
+    }
+    let levels = &all_levels[..count];
+    let level = |v: &mut [T], (width, rows): (usize, usize)| {
+        let span = width * rows;
+        for (group, g) in v.chunks_exact_mut(span).enumerate() {
+            for strip in (0..width).step_by(TILE_STRIP) {
+                let columns = strip..strip + TILE_STRIP;
+                stages_across_rows(g, width, columns, ordering, block * (length / span) + group);
+            }
+        }
+    };
+    match ordering {
+        Ordering::Hadamard => {
+            for tile in v.chunks_exact_mut(tile) {
+                hadamard_fused(tile, 3);
+            }
+            for &l in levels {
+                level(v, l);
+            }
+        }
+        Ordering::Sequency => {
+            for &l in levels.iter().rev() {
+                level(v, l);
+            }
+            let tiles = length / tile;
+            for (i, t) in v.chunks_exact_mut(tile).enumerate() {
+                sequency_stages_fused(t, 3, block * tiles + i);
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Transforms whose matrix is a Kronecker power of a small kernel.
+
+use std::ops::{Add, Mul};
+
+use crate::Error;
+
+/// A transform by the `m`-fold Kronecker power `K ⊗ K ⊗ … ⊗ K` of a small
+/// `p × p` kernel matrix `K`, for inputs of length `p^m`.
+///
+/// The power is never formed: each of the `m` stages applies `K` across
+/// one base-`p` digit of the index, for O(*n* *m* *p*) work. The
+/// Hadamard transform is the case `K = [[1, 1], [1, −1]]`, and other
+/// kernels give, for example, the Arıkan polar transform or the
+/// [`chrestenson`](crate::chrestenson) transform.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Kronecker;
+///
+/// let walsh = Kronecker::new(2, vec![1, 1, 1, -1]).unwrap();
+/// let v = [0, 0, 0, 0, 0, 0, 1, 0];
+/// assert_eq!(walsh.apply(&v), Ok(fwt::hadamard(&v).unwrap()));
+///
+/// // The Arıkan polar transform over GF(2), on 0/1 integers.
+/// let arikan = Kronecker::new(2, vec![1, 0, 1, 1]).unwrap();
+/// let encoded = arikan.apply(&[1u8, 0, 1, 1]).unwrap();
+/// let bits: Vec<u8> = encoded.iter().map(|x| x % 2).collect();
+/// assert_eq!(bits, vec![1, 1, 0, 1]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kronecker<T> {
+    size: usize,
+    matrix: Vec<T>,
+}
+
+impl<T> Kronecker<T>
+where
+    T: Copy + Add<Output = T> + Mul<Output = T>,
+{
+    /// Create the transform for the `size × size` kernel given in
+    /// row-major order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidRadix`] if `size < 2`, or
+    /// [`Error::LengthMismatch`] if `matrix` does not hold `size * size`
+    /// entries, reporting `usize::MAX` as expected if that number
+    /// overflows.
+    pub fn new(size: usize, matrix: Vec<T>) -> Result<Kronecker<T>, Error> {
+        if size < 2 {
+            return Err(Error::InvalidRadix(size));
+        }
+        if size.checked_mul(size) != Some(matrix.len()) {
+            return Err(Error::LengthMismatch {
+                expected: size.saturating_mul(size),
+                found: matrix.len(),
+            });
+        }
+        Ok(Kronecker { size, matrix })
+    }
+
+    /// The kernel dimension `p`.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The kernel matrix in row-major order.
+    pub fn kernel(&self) -> &[T] {
+        &self.matrix
+    }
+
+    /// Return the transform of `input`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfRadix`] if the input length is not a
+    /// power of the kernel size.
+    pub fn apply(&self, input: &[T]) -> Result<Vec<T>, Error> {
+        let mut v = input.to_vec();
+        self.apply_in_place(&mut v)?;
+        Ok(v)
+    }
+
+    /// Replace the contents of `v` with its transform.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfRadix`] if the length of `v` is not a
+    /// power of the kernel size, in which case `v` is unchanged.
+    pub fn apply_in_place(&self, v: &mut [T]) -> Result<(), Error> {
+        let (n, p) = (v.len(), self.size);
+        if !is_power_of(n, p) {
+            return Err(Error::NotPowerOfRadix {
+                length: n,
+                radix: p,
+            });
+        }
+        if n == 1 {
+            return Ok(());
+        }
+        let mut digits = vec![v[0]; p];
+        let mut stride = 1;
+        while stride < n {
+            let span = stride * p;
+            for block in (0..n).step_by(span) {
+                for offset in block..block + stride {
+                    for (d, x) in digits.iter_mut().enumerate() {
+                        *x = v[offset + d * stride];
+                    }
+                    for (k, row) in self.matrix.chunks_exact(p).enumerate() {
+                        v[offset + k * stride] = row
+                            .iter()
+                            .zip(&digits)
+                            .skip(1)
+                            .fold(row[0] * digits[0], |acc, (&m, &x)| acc + m * x);
+                    }
+                }
+            }
+            stride = span;
+        }
+        Ok(())
+    }
+}
+
+// Whether `n` is `p^m` for some `m >= 0`.
+fn is_power_of(mut n: usize, p: usize) -> bool {
+    if n == 0 {
+        return false;
+    }
+    while n.is_multiple_of(p) {
+        n /= p;
+    }
+    n == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_explicit_kronecker_product() {
+        let kernel = vec![2, -1, 0, 1, 3, 1, 0, 1, -2];
+        let engine = Kronecker::new(3, kernel.clone()).unwrap();
+        let n = 9;
+        let v: Vec<i64> = (0..n as i64).map(|i| i * i - 4).collect();
+        let expected: Vec<i64> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| kernel[(i / 3) * 3 + j / 3] * kernel[(i % 3) * 3 + j % 3] * v[j])
+                    .sum()
+            })
+            .collect();
+        assert_eq!(engine.apply(&v), Ok(expected));
+        assert_eq!(engine.apply(&[7]), Ok(vec![7]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Kronecker::new(1, vec![1]), Err(Error::InvalidRadix(1)));
+        assert_eq!(
+            Kronecker::new(2, vec![1, 1, 1]),
+            Err(Error::LengthMismatch {
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(
+            Kronecker::new(1 << 33, vec![1]),
+            Err(Error::LengthMismatch {
+                expected: usize::MAX,
+                found: 1
+            })
+        );
+        let engine = Kronecker::new(2, vec![1.0, 1.0, 1.0, -1.0]).unwrap();
+        assert_eq!(
+            engine.apply(&[1.0; 6]),
+            Err(Error::NotPowerOfRadix {
+                length: 6,
+                radix: 2
+            })
+        );
+        assert!(engine.apply(&[]).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Convolutions over the subset lattice.
+//!
+//! An index `i` in `0..2^k` is treated as a subset of `{0, ..., k-1}`, with
+//! bit `b` set when element `b` is a member. The *AND* (subset) convolution
+//! of `a` and `b` is
+//!
+//! `c[k] = Σ a[i]·b[j]` over all pairs with `i & j == k`,
+//!
+//! and the *OR* (superset) convolution is the same sum over pairs with
+//! `i | j == k`. Both are computed in O(*n* log(*n*)) time by taking zeta
+//! transforms of the inputs, multiplying pointwise, and applying the
+//! corresponding Möbius transform, in the same way that the XOR convolution
+//! is computed with Walsh transforms. The zeta and Möbius transforms are
+//! also available on their own for computing subset aggregates directly.
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::power_of_2;
+
+/// Return the AND convolution of `a` and `b`, i.e., the vector whose
+/// `k`-th entry sums `a[i] * b[j]` over all `i & j == k`, or `None` if
+/// the inputs differ in length or their length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let a = [1, 2, 3, 4];
+/// let b = [5, 6, 7, 8];
+/// assert_eq!(
+///     fwt::and_convolution(&a, &b),
+///     Some(vec![103, 52, 73, 32])
+/// );
+/// assert_eq!(fwt::and_convolution(&a, &b[..2]), None);
+/// ```
+pub fn and_convolution<T>(a: &[T], b: &[T]) -> Option<Vec<T>>
+where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Copy,
+{
+    if a.len() != b.len() || !power_of_2(a.len()) {
+        return None;
+    }
+    let mut fa = a.to_vec();
+    let mut fb = b.to_vec();
+    superset_zeta(&mut fa);
+    superset_zeta(&mut fb);
+    for (x, &y) in fa.iter_mut().zip(fb.iter()) {
+        *x = *x * y;
+    }
+    superset_mobius(&mut fa);
+    Some(fa)
+}
+
+/// Return the OR convolution of `a` and `b`, i.e., the vector whose
+/// `k`-th entry sums `a[i] * b[j]` over all `i | j == k`, or `None` if
+/// the inputs differ in length or their length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let a = [1, 2, 3, 4];
+/// let b = [5, 6, 7, 8];
+/// assert_eq!(
+///     fwt::or_convolution(&a, &b),
+///     Some(vec![5, 28, 43, 184])
+/// );
+/// assert_eq!(fwt::or_convolution(&[1, 2, 3], &[4, 5, 6]), None);
+/// ```
+pub fn or_convolution<T>(a: &[T], b: &[T]) -> Option<Vec<T>>
+where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Copy,
+{
+    if a.len() != b.len() || !power_of_2(a.len()) {
+        return None;
+    }
+    let mut fa = a.to_vec();
+    let mut fb = b.to_vec();
+    subset_zeta(&mut fa);
+    subset_zeta(&mut fb);
+    for (x, &y) in fa.iter_mut().zip(fb.iter()) {
+        *x = *x * y;
+    }
+    subset_mobius(&mut fa);
+    Some(fa)
+}
+
+/// Return the zeta (sum-over-subsets) transform of `input_v`, whose
+/// `s`-th entry is the sum of `input_v[t]` over all `t` with `t & s == t`,
+/// or `None` if the input length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let input_v = [1, 2, 3, 4];
+/// assert_eq!(fwt::zeta_transform(&input_v), Some(vec![1, 3, 4, 10]));
+/// assert_eq!(fwt::zeta_transform(&[1, 2, 3]), None);
+/// ```
+pub fn zeta_transform<T>(input_v: &[T]) -> Option<Vec<T>>
+where
+    T: Add<Output = T> + Copy,
+{
+    if power_of_2(input_v.len()) {
+        let mut v = input_v.to_vec();
+        subset_zeta(&mut v);
+        Some(v)
+    } else {
+        None
+    }
+}
+
+/// Return the Möbius transform of `input_v`, the inverse of
+/// [`zeta_transform`], or `None` if the input length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let input_v = [1, 3, 4, 10];
+/// assert_eq!(fwt::mobius_transform(&input_v), Some(vec![1, 2, 3, 4]));
+/// ```
+pub fn mobius_transform<T>(input_v: &[T]) -> Option<Vec<T>>
+where
+    T: Sub<Output = T> + Copy,
+{
+    if power_of_2(input_v.len()) {
+        let mut v = input_v.to_vec();
+        subset_mobius(&mut v);
+        Some(v)
+    } else {
+        None
+    }
+}
+
+/// Return the superset-sum zeta transform of `input_v`, whose `s`-th
+/// entry is the sum of `input_v[t]` over all `t` with `t & s == s`, or
+/// `None` if the input length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let input_v = [1, 2, 3, 4];
+/// assert_eq!(fwt::superset_zeta_transform(&input_v), Some(vec![10, 6, 7, 4]));
+/// ```
+pub fn superset_zeta_transform<T>(input_v: &[T]) -> Option<Vec<T>>
+where
+    T: Add<Output = T> + Copy,
+{
+    if power_of_2(input_v.len()) {
+        let mut v = input_v.to_vec();
+        superset_zeta(&mut v);
+        Some(v)
+    } else {
+        None
+    }
+}
+
+/// Return the inverse of [`superset_zeta_transform`], or `None` if the
+/// input length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let input_v = [10, 6, 7, 4];
+/// assert_eq!(fwt::superset_mobius_transform(&input_v), Some(vec![1, 2, 3, 4]));
+/// ```
+pub fn superset_mobius_transform<T>(input_v: &[T]) -> Option<Vec<T>>
+where
+    T: Sub<Output = T> + Copy,
+{
+    if power_of_2(input_v.len()) {
+        let mut v = input_v.to_vec();
+        superset_mobius(&mut v);
+        Some(v)
+    } else {
+        None
+    }
+}
+
+// Sum over subsets: v[S] <- Σ v[T] for T ⊆ S.
+fn subset_zeta<T>(v: &mut [T])
+where
+    T: Add<Output = T> + Copy,
+{
+    let length = v.len();
+    let mut lag = 1;
+    while lag < length {
+        for j in 0..length {
+            if j & lag != 0 {
+                v[j] = v[j] + v[j ^ lag];
+            }
+        }
+        lag <<= 1;
+    }
+}
+
+// Inverse of `subset_zeta`.
+fn subset_mobius<T>(v: &mut [T])
+where
+    T: Sub<Output = T> + Copy,
+{
+    let length = v.len();
+    let mut lag = 1;
+    while lag < length {
+        for j in 0..length {
+            if j & lag != 0 {
+                v[j] = v[j] - v[j ^ lag];
+            }
+        }
+        lag <<= 1;
+    }
+}
+
+// Sum over supersets: v[S] <- Σ v[T] for T ⊇ S.
+fn superset_zeta<T>(v: &mut [T])
+where
+    T: Add<Output = T> + Copy,
+{
+    let length = v.len();
+    let mut lag = 1;
+    while lag < length {
+        for j in 0..length {
+            if j & lag == 0 {
+                v[j] = v[j] + v[j | lag];
+            }
+        }
+        lag <<= 1;
+    }
+}
+
+// Inverse of `superset_zeta`.
+fn superset_mobius<T>(v: &mut [T])
+where
+    T: Sub<Output = T> + Copy,
+{
+    let length = v.len();
+    let mut lag = 1;
+    while lag < length {
+        for j in 0..length {
+            if j & lag == 0 {
+                v[j] = v[j] - v[j | lag];
+            }
+        }
+        lag <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(a: &[i64], b: &[i64], op: fn(usize, usize) -> usize) -> Vec<i64> {
+        let mut c = vec![0; a.len()];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                c[op(i, j)] += a[i] * b[j];
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn test_and_convolution() {
+        let a: Vec<i64> = (0..16).map(|x| (x * 7 + 3) % 11 - 5).collect();
+        let b: Vec<i64> = (0..16).map(|x| (x * 5 + 1) % 13 - 6).collect();
+        let result = and_convolution(&a, &b).expect("input length not a power of 2");
+        assert_eq!(result, brute_force(&a, &b, |i, j| i & j));
+    }
+
+    #[test]
+    fn test_or_convolution() {
+        let a: Vec<i64> = (0..16).map(|x| (x * 7 + 3) % 11 - 5).collect();
+        let b: Vec<i64> = (0..16).map(|x| (x * 5 + 1) % 13 - 6).collect();
+        let result = or_convolution(&a, &b).expect("input length not a power of 2");
+        assert_eq!(result, brute_force(&a, &b, |i, j| i | j));
+        assert_eq!(or_convolution(&[2.0], &[3.0]), Some(vec![6.0]));
+        assert_eq!(or_convolution::<i32>(&[], &[]), None);
+    }
+
+    #[test]
+    fn test_zeta_mobius_round_trip() {
+        let a: Vec<i64> = (0..32).map(|x| (x * 7 + 3) % 11 - 5).collect();
+        let zeta = zeta_transform(&a).expect("input length not a power of 2");
+        for (s, &z) in zeta.iter().enumerate() {
+            let direct: i64 = (0..32).filter(|&t| t & s == t).map(|t| a[t]).sum();
+            assert_eq!(z, direct);
+        }
+        assert_eq!(mobius_transform(&zeta), Some(a.clone()));
+        let zeta = superset_zeta_transform(&a).expect("input length not a power of 2");
+        for (s, &z) in zeta.iter().enumerate() {
+            let direct: i64 = (0..32).filter(|&t| t & s == s).map(|t| a[t]).sum();
+            assert_eq!(z, direct);
+        }
+        assert_eq!(superset_mobius_transform(&zeta), Some(a));
+        assert_eq!(zeta_transform::<f64>(&[]), None);
+    }
+}
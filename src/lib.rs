@@ -1,4 +1,5 @@
 #![crate_name = "fwt"]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
 //! Walsh transforms are useful in a variety of applications, such as image or
 //! speech processing, filtering, and efficiently creating [very large statistical
@@ -15,13 +16,187 @@
 //! be a power of 2.
 //!
 //! Walsh transformations are computed solely using addition and subtraction.
-//! Consequently, the output type (float vs int) conforms to the input type.
+//! Consequently, the output type (float vs int) conforms to the input type,
+//! and any type implementing [`WalshElement`] can be transformed.
 //!
 //! Note that these transforms are their own inverse to within a scale
 //! factor of the input slice's length.
+//!
+//! Floating-point results of [`hadamard`] and [`sequency`] are bit-identical
+//! across platforms, since every butterfly is a single IEEE-754 addition or
+//! subtraction performed in a fixed order. See [`Determinism`] for how the
+//! same guarantee is requested from a [`Plan`].
+
+#[cfg(all(target_arch = "aarch64", not(feature = "simd")))]
+mod aarch64;
+mod agc;
+#[cfg(feature = "alloc-check")]
+pub mod alloc_check;
+mod approx;
+mod bands;
+mod boolean;
+mod cache;
+#[cfg(feature = "candle")]
+pub mod candle;
+mod chrestenson;
+mod chunked;
+mod codelet;
+mod complex;
+mod compress;
+mod convolver;
+#[cfg(feature = "cuda")]
+pub mod cuda;
+mod digits;
+mod dispatch;
+mod downmix;
+mod dyadic;
+mod element;
+mod error;
+#[cfg(feature = "mmap")]
+pub mod file;
+mod filter;
+mod fingerprint;
+pub mod format;
+mod functions;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+mod interval;
+mod kernel;
+mod kronecker;
+mod lattice;
+mod linalg;
+mod memory;
+mod mixer;
+mod modular;
+mod multidim;
+pub mod naive;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra;
+#[cfg(feature = "ndarray")]
+pub mod ndarray;
+mod normalize;
+mod ovsf;
+mod pair;
+mod papr;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod pilot;
+mod plan;
+mod planner;
+#[cfg(feature = "plotters")]
+pub mod plotters;
+mod process;
+mod quantize;
+mod randomness;
+mod rng;
+mod scan;
+mod selftest;
+#[cfg(feature = "simd")]
+mod simd;
+mod sketch;
+mod sliding;
+mod spectrum;
+mod splice;
+mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+mod stwt;
+mod surrogate;
+mod symmetry;
+mod text;
+mod transfer;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "simd")
+))]
+mod wasm;
+mod welch;
+#[cfg(all(target_arch = "x86_64", not(feature = "simd")))]
+mod x86;
 
-use std::ops::Add;
-use std::ops::Sub;
+pub use agc::BandAgc;
+pub use approx::{approx_eq, approx_eq_spectra, Tolerance};
+pub use bands::{BandAnalyzer, BandSummary};
+pub use boolean::walsh_spectrum_packed;
+pub use cache::{CacheStats, PlanCache};
+pub use chrestenson::{chrestenson, inverse_chrestenson};
+pub use chunked::{ChunkedFwt, ChunkedSpectra};
+pub use complex::Complex;
+pub use compress::{
+    compress_topk, progressive_order, reconstruct, Progression, ProgressiveDecoder, SparseSpectrum,
+};
+pub use convolver::PartitionedConvolver;
+pub use digits::{digit_signal, digit_spectra, digit_spectrum};
+pub use dispatch::{calibrate, Backend, Dispatcher, Measurement, ProfileStore, TuningProfile};
+pub use downmix::Downmixer;
+pub use dyadic::{
+    dyadic_autocorrelation, dyadic_convolution, dyadic_correlation, dyadic_correlation_with_plan,
+    dyadic_shift, dyadic_shift_spectrum, gibbs_derivative, gibbs_integral, logical_derivative,
+};
+pub use element::WalshElement;
+pub use error::Error;
+pub use filter::{
+    band_energies_2d, bandpass, bandpass_spectrum, denoise, equalize_2d, highpass, lowpass,
+    ThresholdMode,
+};
+pub use fingerprint::fingerprint;
+pub use functions::{cal, rademacher, rademacher_factors, sal, wal, walsh_row};
+pub use interval::Interval;
+pub use kronecker::Kronecker;
+pub use lattice::{
+    and_convolution, mobius_transform, or_convolution, superset_mobius_transform,
+    superset_zeta_transform, zeta_transform,
+};
+pub use memory::{estimate_memory, MemoryEstimate, MemoryOptions};
+pub use mixer::Mixer;
+pub use modular::{hadamard_mod, sequency_mod, xor_convolution_mod, Modulus};
+#[cfg(feature = "parallel")]
+pub use multidim::fwt2_parallel;
+pub use multidim::{
+    block_transform_2d, fwt2, fwt3, fwtn, fwtn_axes, ifwt2, inverse_block_transform_2d,
+    transform_cols, transform_rows,
+};
+pub use normalize::Normalize;
+pub use ovsf::{Code, CodeTree};
+pub use pair::transform_pair;
+pub use papr::{
+    low_papr_subset, papr, papr_statistics, to_db, walsh_carrier_sum, PaprStatistics,
+    MAX_ENUMERATED_CARRIERS,
+};
+pub use pilot::{estimate_channel, estimate_channel_matrix, pilot_sequences};
+pub use plan::{Algorithm, Determinism, Plan};
+pub use planner::{Planner, Wisdom, WisdomEntry};
+pub use process::DyadicProcess;
+pub use quantize::Quantizer;
+pub use randomness::{
+    sequence_report, walsh_spectral_test, Anomaly, AutocorrelationPeak, Balance, Runs,
+    SequenceReport, WalshTestReport,
+};
+pub use scan::{scan_2d, scan_order, unscan_2d, Scan};
+pub use selftest::{self_test, Check, SelfTestReport};
+pub use sketch::{ProductEstimate, Srht, SrhtSummary};
+pub use sliding::SlidingFwt;
+pub use spectrum::{
+    amplitude_spectrum, polar_spectrum, power_spectrum, sequency_power_spectrum, PolarSpectrum,
+};
+pub use splice::{crossfade, splice};
+pub use stwt::{Spectrogram, Stwt, Window};
+pub use surrogate::{surrogate_test, walsh_surrogate, SurrogateTest};
+pub use symmetry::{sequency_symmetric, Symmetry};
+pub use text::{to_ascii_plot, to_sparkline};
+pub use transfer::{estimate_transfer, estimate_transfer_with, TransferEstimate};
+pub use welch::Welch;
+
+/// The order in which the Walsh functions index a transform's output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ordering {
+    /// Walsh functions sorted by sequency, the number of sign changes.
+    Sequency,
+    /// Natural (Hadamard) ordering, as in the rows of a Sylvester
+    /// Hadamard matrix.
+    Hadamard,
+}
 
 /// Return the Manz sequency ordering transform of `input_v`, or
 /// `None` if the input length is not a power of 2.
@@ -39,44 +214,45 @@ use std::ops::Sub;
 /// ```
 pub fn sequency<T>(input_v: &[T]) -> Option<Vec<T>>
 where
-    T: Add<Output = T> + Sub<Output = T> + Copy + std::ops::AddAssign<T>,
+    T: WalshElement,
 {
-    let length = input_v.len();
-    let mut v = input_v.to_vec();
+    if power_of_2(input_v.len()) {
+        Some(kernel::sequency_gathered(input_v))
+    } else {
+        None
+    }
+}
 
-    if power_of_2(length) {
-        let mut j = 0;
-        for i in 0..(length - 2) {
-            if i < j {
-                (v[i], v[j]) = (v[j], v[i]);
-            }
-            let mut k = length >> 1;
-            while k <= j {
-                j -= k;
-                k >>= 1;
-            }
-            j += k;
-        }
-        let mut offset = length;
-        while offset > 1 {
-            let lag = offset >> 1;
-            let ngroups = length / offset;
-            for group in 0..ngroups {
-                for i in 0..lag {
-                    j = i + group * offset;
-                    let k = j + lag;
-                    if group & 1 == 1 {
-                        (v[j], v[k]) = (v[j] - v[k], v[j] + v[k]);
-                    } else {
-                        (v[j], v[k]) = (v[j] + v[k], v[j] - v[k]);
-                    }
-                }
+/// Replace the contents of `v` with its Manz sequency ordering
+/// transform, without allocating.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2, in which case `v` is unchanged.
+///
+/// # Example
+///
+/// ```
+/// let mut v = [0, 0, 0, 0, 0, 0, 1, 0];
+/// fwt::sequency_in_place(&mut v).expect("input length not a power of 2");
+/// assert_eq!(v, [1, -1, 1, -1, -1, 1, -1, 1]);
+/// ```
+pub fn sequency_in_place<T>(v: &mut [T]) -> Result<(), Error>
+where
+    T: WalshElement,
+{
+    if power_of_2(v.len()) {
+        no_alloc(|| {
+            if v.len() <= codelet::MAX_LENGTH {
+                return codelet::transform(v, Ordering::Sequency);
             }
-            offset = lag;
-        }
-        Some(v)
+            kernel::bit_reverse(v);
+            kernel::sequency_stages(v);
+        });
+        Ok(())
     } else {
-        None
+        Err(Error::NotPowerOfTwo(v.len()))
     }
 }
 
@@ -96,27 +272,83 @@ where
 /// ```
 pub fn hadamard<T>(input_v: &[T]) -> Option<Vec<T>>
 where
-    T: Add<Output = T> + Sub<Output = T> + Copy + std::ops::AddAssign<T>,
+    T: WalshElement,
 {
-    let length = input_v.len();
     let mut v = input_v.to_vec();
-    if power_of_2(length) {
-        let mut lag = 1;
-        while lag < length {
-            let offset = lag << 1;
-            let ngroups = length / offset;
-            for group in 0..ngroups {
-                for base in 0..lag {
-                    let j = base + group * offset;
-                    let k = j + lag;
-                    (v[j], v[k]) = (v[j] + v[k], v[j] - v[k]);
-                }
-            }
-            lag = offset;
-        }
-        Some(v)
+    hadamard_in_place(&mut v).ok()?;
+    Some(v)
+}
+
+/// Replace the contents of `v` with its Hadamard (natural) ordering
+/// transform, without allocating.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2, in which case `v` is unchanged.
+///
+/// # Example
+///
+/// ```
+/// let mut v = [0.0, 1.0, 0.0, 0.0];
+/// fwt::hadamard_in_place(&mut v).expect("input length not a power of 2");
+/// assert_eq!(v, [1.0, -1.0, 1.0, -1.0]);
+/// ```
+pub fn hadamard_in_place<T>(v: &mut [T]) -> Result<(), Error>
+where
+    T: WalshElement,
+{
+    if power_of_2(v.len()) {
+        no_alloc(|| kernel::hadamard(v));
+        Ok(())
     } else {
-        None
+        Err(Error::NotPowerOfTwo(v.len()))
+    }
+}
+
+/// Return the adjoint (transpose) of the transform with the given
+/// `ordering` applied to `grad`, or `None` if the input length is not a
+/// power of 2.
+///
+/// Both Walsh matrices are symmetric, so the adjoint is the transform
+/// itself. This function exists so that reverse-mode differentiation code
+/// can state its intent: if `y = W x`, the gradient with respect to `x`
+/// is `adjoint(dL/dy)`.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let x = [1.0, 2.0, 3.0, 4.0];
+/// let y = [0.5, -1.0, 0.0, 2.0];
+/// let wx = fwt::sequency(&x).unwrap();
+/// let wty = fwt::adjoint(&y, Ordering::Sequency).unwrap();
+/// let lhs: f64 = wx.iter().zip(&y).map(|(a, b)| a * b).sum();
+/// let rhs: f64 = x.iter().zip(&wty).map(|(a, b)| a * b).sum();
+/// assert_eq!(lhs, rhs);
+/// ```
+pub fn adjoint<T>(grad: &[T], ordering: Ordering) -> Option<Vec<T>>
+where
+    T: WalshElement,
+{
+    match ordering {
+        Ordering::Sequency => sequency(grad),
+        Ordering::Hadamard => hadamard(grad),
+    }
+}
+
+// Run `f`, checking in debug builds with the `alloc-check` feature that it
+// does not allocate.
+#[inline(always)]
+pub(crate) fn no_alloc<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(all(feature = "alloc-check", debug_assertions))]
+    {
+        alloc_check::assert_no_alloc(f)
+    }
+    #[cfg(not(all(feature = "alloc-check", debug_assertions)))]
+    {
+        f()
     }
 }
 
@@ -138,7 +370,8 @@ pub fn power_of_2(n: usize) -> bool {
 /// Scale a vector by its length. This is an appropriate scaling
 /// to yield an inversion from two calls to the same transform.
 /// Note that the result of scaling is `f64` even if the input
-/// `v` contains ints.
+/// `v` contains ints. Use [`scale_in_place`] to keep the element type,
+/// so that `f32` pipelines avoid widening.
 ///
 /// # Example
 ///
@@ -172,6 +405,44 @@ where
     }
 }
 
+/// Divide each element of `v` by the length of `v` in place, keeping its
+/// element type. Two transforms and a scaling are an inversion, as for
+/// [`scale`], without converting `f32` or integer data to `f64`. Integer
+/// elements must divide exactly.
+///
+/// # Errors
+///
+/// Returns [`Error::InexactScaling`] if an element cannot be divided
+/// exactly, in which case `v` is unchanged.
+///
+/// # Example
+///
+/// ```
+/// let input = [1.0f32, 2.0, 3.0, 4.0];
+/// let mut v = fwt::hadamard(&fwt::hadamard(&input).unwrap()).unwrap();
+/// fwt::scale_in_place(&mut v).unwrap();
+/// assert_eq!(v, input);
+/// let mut odd = [4, 6];
+/// assert_eq!(fwt::scale_in_place(&mut odd), Ok(()));
+/// assert_eq!(odd, [2, 3]);
+/// assert_eq!(fwt::scale_in_place(&mut odd), Err(fwt::Error::InexactScaling));
+/// ```
+pub fn scale_in_place<T>(v: &mut [T]) -> Result<(), Error>
+where
+    T: Normalize + Copy,
+{
+    let length = v.len();
+    if v.iter().any(|&x| x.normalize(length).is_none()) {
+        return Err(Error::InexactScaling);
+    }
+    no_alloc(|| {
+        for x in v.iter_mut() {
+            *x = x.normalize(length).expect("checked above");
+        }
+    });
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -319,4 +590,98 @@ mod tests {
         let v: Vec<i32> = [].to_vec();
         assert_eq!(sequency(&v), None);
     }
+
+    #[test]
+    fn test_length_one() {
+        assert_eq!(sequency(&[3]), Some(vec![3]));
+        assert_eq!(hadamard(&[3]), Some(vec![3]));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Dual {
+        value: f64,
+        deriv: f64,
+    }
+
+    impl std::ops::Add for Dual {
+        type Output = Dual;
+        fn add(self, rhs: Dual) -> Dual {
+            Dual {
+                value: self.value + rhs.value,
+                deriv: self.deriv + rhs.deriv,
+            }
+        }
+    }
+
+    impl std::ops::Sub for Dual {
+        type Output = Dual;
+        fn sub(self, rhs: Dual) -> Dual {
+            Dual {
+                value: self.value - rhs.value,
+                deriv: self.deriv - rhs.deriv,
+            }
+        }
+    }
+
+    impl WalshElement for Dual {}
+
+    #[test]
+    fn test_dual_numbers_match_adjoint() {
+        // Forward mode: seeding input i yields column i of the Jacobian,
+        // which must equal row i of the adjoint applied to unit vectors.
+        let x: Vec<f64> = (0..8).map(|i| i as f64 * 0.5 - 1.0).collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            for i in 0..8 {
+                let input: Vec<Dual> = x
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &value)| Dual {
+                        value,
+                        deriv: if i == j { 1.0 } else { 0.0 },
+                    })
+                    .collect();
+                let output = adjoint(&input, ordering).unwrap();
+                for (k, y) in output.iter().enumerate() {
+                    let mut unit = vec![0.0; 8];
+                    unit[k] = 1.0;
+                    assert_eq!(y.deriv, adjoint(&unit, ordering).unwrap()[i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequency_matches_in_place_bit_exact() {
+        for m in 0..=12 {
+            let n = 1usize << m;
+            let v: Vec<f64> = (0..n).map(|i| ((i * 7919) % 113) as f64 / 7.3 - 5.1).collect();
+            let mut expected = v.clone();
+            sequency_in_place(&mut expected).unwrap();
+            let result = sequency(&v).unwrap();
+            assert!(result.iter().zip(&expected).all(|(a, b)| a.to_bits() == b.to_bits()));
+        }
+    }
+
+    #[test]
+    fn test_scale_in_place_matches_scale() {
+        let input: Vec<f32> = (0..64).map(|i| ((i * 37) % 23) as f32 - 11.0).collect();
+        let mut v = sequency(&sequency(&input).unwrap()).unwrap();
+        let widened = scale(&v).unwrap();
+        scale_in_place(&mut v).unwrap();
+        assert_eq!(v, input);
+        assert!(v.iter().zip(&widened).all(|(&a, &b)| f64::from(a) == b));
+        let mut empty: [f32; 0] = [];
+        assert_eq!(scale_in_place(&mut empty), Ok(()));
+        let mut ints = [3, 6, 9];
+        assert_eq!(scale_in_place(&mut ints), Ok(()));
+        assert_eq!(ints, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_in_place_rejects_bad_length() {
+        let mut v = [1, 2, 3];
+        assert_eq!(hadamard_in_place(&mut v), Err(Error::NotPowerOfTwo(3)));
+        assert_eq!(sequency_in_place(&mut v), Err(Error::NotPowerOfTwo(3)));
+        assert_eq!(v, [1, 2, 3]);
+    }
 }
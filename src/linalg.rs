@@ -0,0 +1,132 @@
+//! Small dense linear algebra used by the fitting routines.
+
+/// Solve the square system `a x = b`, where `a` is `n × n` in row-major
+/// order, by Gaussian elimination with partial pivoting. Returns `None` if
+/// `a` is singular to working precision.
+pub(crate) fn solve(mut a: Vec<f64>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    debug_assert_eq!(a.len(), n * n);
+    let scale = a.iter().fold(0.0f64, |m, x| m.max(x.abs()));
+    let tolerance = scale * f64::EPSILON * n as f64;
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))?;
+        let magnitude = a[pivot * n + col].abs();
+        if magnitude.is_nan() || magnitude <= tolerance {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..n {
+                a.swap(pivot * n + k, col * n + k);
+            }
+            b.swap(pivot, col);
+        }
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for k in col..n {
+                a[row * n + k] -= factor * a[col * n + k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row * n + k] * b[k]).sum();
+        b[row] = (b[row] - sum) / a[row * n + row];
+    }
+    Some(b)
+}
+
+/// Return the `cols × cols` upper-triangular factor `R` of the QR
+/// decomposition of the `rows × cols` row-major matrix `a`, computed with
+/// Householder reflections, or `None` if `a` does not have full column
+/// rank to working precision.
+pub(crate) fn qr_r(mut a: Vec<f64>, rows: usize, cols: usize) -> Option<Vec<f64>> {
+    if rows < cols {
+        return None;
+    }
+    let scale = a.iter().fold(0.0f64, |m, x| m.max(x.abs()));
+    let tolerance = scale * f64::EPSILON * rows as f64;
+    for col in 0..cols {
+        let norm = (col..rows)
+            .map(|r| a[r * cols + col].powi(2))
+            .sum::<f64>()
+            .sqrt();
+        if norm.is_nan() || norm <= tolerance {
+            return None;
+        }
+        let alpha = if a[col * cols + col] > 0.0 {
+            -norm
+        } else {
+            norm
+        };
+        // v = x - alpha e1, applied as I - 2 v vᵀ / vᵀv.
+        let mut v: Vec<f64> = (col..rows).map(|r| a[r * cols + col]).collect();
+        v[0] -= alpha;
+        let vv: f64 = v.iter().map(|x| x * x).sum();
+        for c in col..cols {
+            let dot: f64 = v
+                .iter()
+                .enumerate()
+                .map(|(i, x)| x * a[(col + i) * cols + c])
+                .sum();
+            let factor = 2.0 * dot / vv;
+            for (i, x) in v.iter().enumerate() {
+                a[(col + i) * cols + c] -= factor * x;
+            }
+        }
+    }
+    a.truncate(cols * cols);
+    for r in 1..cols {
+        for c in 0..r {
+            a[r * cols + c] = 0.0;
+        }
+    }
+    Some(a)
+}
+
+/// Solve `z R = x` for the row vector `z`, where `R` is `n × n` upper
+/// triangular with a nonzero diagonal, by forward substitution.
+pub(crate) fn solve_upper_transposed(r: &[f64], x: &[f64]) -> Vec<f64> {
+    let n = x.len();
+    let mut z = vec![0.0; n];
+    for j in 0..n {
+        let sum: f64 = (0..j).map(|i| z[i] * r[i * n + j]).sum();
+        z[j] = (x[j] - sum) / r[j * n + j];
+    }
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let a = vec![0.0, 2.0, 1.0, 1.0, 1.0, 0.0, 2.0, 0.0, 3.0];
+        let x = solve(a, vec![7.0, 3.0, 11.0]).unwrap();
+        for (xi, expected) in x.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((xi - expected).abs() < 1e-12);
+        }
+        assert_eq!(solve(vec![1.0, 2.0, 2.0, 4.0], vec![1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn test_qr_r() {
+        // RᵀR = AᵀA for any QR factorization.
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 10.0, 1.0, 0.0, 1.0];
+        let r = qr_r(a.clone(), 4, 3).unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                let ata: f64 = (0..4).map(|k| a[k * 3 + i] * a[k * 3 + j]).sum();
+                let rtr: f64 = (0..3).map(|k| r[k * 3 + i] * r[k * 3 + j]).sum();
+                assert!((ata - rtr).abs() < 1e-9);
+            }
+        }
+        let z = solve_upper_transposed(&r, &[1.0, 2.0, 3.0]);
+        for j in 0..3 {
+            let zr: f64 = (0..3).map(|i| z[i] * r[i * 3 + j]).sum();
+            assert!((zr - [1.0, 2.0, 3.0][j]).abs() < 1e-12);
+        }
+        assert_eq!(qr_r(vec![1.0, 2.0, 2.0, 4.0], 2, 2), None);
+    }
+}
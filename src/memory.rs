@@ -0,0 +1,246 @@
+use crate::{power_of_2, Ordering};
+
+/// The configuration of a transform workload whose memory use is to be
+/// estimated with [`estimate_memory`].
+///
+/// # Example
+///
+/// ```
+/// use fwt::{MemoryOptions, Ordering};
+///
+/// let options = MemoryOptions::for_type::<f32>()
+///     .ordering(Ordering::Hadamard)
+///     .batch(16);
+/// let estimate = fwt::estimate_memory(1024, &options).unwrap();
+/// assert_eq!(estimate.buffer_bytes(), 16 * 1024 * 4);
+/// assert_eq!(estimate.plan_bytes(), 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryOptions {
+    element_size: usize,
+    ordering: Ordering,
+    batch: usize,
+    in_place: bool,
+    transposed: bool,
+    parallel_2d: bool,
+}
+
+impl MemoryOptions {
+    /// Options for a single out-of-place sequency transform of elements
+    /// of type `T`.
+    pub fn for_type<T>() -> MemoryOptions {
+        MemoryOptions {
+            element_size: std::mem::size_of::<T>(),
+            ordering: Ordering::Sequency,
+            batch: 1,
+            in_place: false,
+            transposed: false,
+            parallel_2d: false,
+        }
+    }
+
+    /// Set the ordering of the transforms.
+    pub fn ordering(self, ordering: Ordering) -> MemoryOptions {
+        MemoryOptions { ordering, ..self }
+    }
+
+    /// Set the number of signals transformed together.
+    pub fn batch(self, batch: usize) -> MemoryOptions {
+        MemoryOptions { batch, ..self }
+    }
+
+    /// Set whether the caller's buffer is transformed in place, so that no
+    /// output buffer is allocated.
+    pub fn in_place(self, in_place: bool) -> MemoryOptions {
+        MemoryOptions { in_place, ..self }
+    }
+
+    /// Set whether the batch is transformed by
+    /// [`Plan::execute_transposed`](crate::Plan::execute_transposed),
+    /// which copies groups of signals into a scratch buffer.
+    pub fn transposed(self, transposed: bool) -> MemoryOptions {
+        MemoryOptions { transposed, ..self }
+    }
+
+    /// Set whether the batch is the rows of a matrix given a 2D transform
+    /// by `fwt2_parallel`, of the `parallel` feature, which transposes the
+    /// whole matrix through a copy and needs a plan for its columns. The
+    /// batch must then be a power of 2.
+    pub fn parallel_2d(self, parallel_2d: bool) -> MemoryOptions {
+        MemoryOptions {
+            parallel_2d,
+            ..self
+        }
+    }
+}
+
+/// The peak memory a transform workload needs beyond its input, as
+/// reported by [`estimate_memory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    plan_bytes: usize,
+    buffer_bytes: usize,
+    scratch_bytes: usize,
+}
+
+impl MemoryEstimate {
+    /// Bytes held by the [`Plan`](crate::Plan), such as the permutation
+    /// table of a sequency transform.
+    pub fn plan_bytes(&self) -> usize {
+        self.plan_bytes
+    }
+
+    /// Bytes of output buffers allocated by the transforms.
+    pub fn buffer_bytes(&self) -> usize {
+        self.buffer_bytes
+    }
+
+    /// Bytes of scratch buffers and tables held while the transforms run
+    /// and freed before they return.
+    pub fn scratch_bytes(&self) -> usize {
+        self.scratch_bytes
+    }
+
+    /// The total peak number of bytes.
+    pub fn total(&self) -> usize {
+        self.plan_bytes + self.buffer_bytes + self.scratch_bytes
+    }
+}
+
+/// Estimate the peak number of bytes needed to transform signals of
+/// `length` elements under the given `options`, so that a service can
+/// refuse requests it could not complete. Returns `None` if `length` is
+/// not a power of 2, nor is the batch of a 2D transform, or the estimate
+/// overflows `usize`.
+///
+/// The estimate covers memory allocated by the crate; the caller's input
+/// buffers are not included.
+///
+/// # Example
+///
+/// ```
+/// use fwt::MemoryOptions;
+///
+/// let options = MemoryOptions::for_type::<f64>().in_place(true);
+/// let estimate = fwt::estimate_memory(1 << 20, &options).unwrap();
+/// assert_eq!(estimate.buffer_bytes(), 0);
+/// assert!(estimate.plan_bytes() > 0);
+/// assert_eq!(fwt::estimate_memory(1000, &options), None);
+/// ```
+pub fn estimate_memory(length: usize, options: &MemoryOptions) -> Option<MemoryEstimate> {
+    if !power_of_2(length) {
+        return None;
+    }
+    let plan_bytes = if options.parallel_2d {
+        if !power_of_2(options.batch) {
+            return None;
+        }
+        plan_bytes(length, options.ordering)
+            .checked_add(plan_bytes(options.batch, options.ordering))?
+    } else {
+        plan_bytes(length, options.ordering)
+    };
+    let elements = length.checked_mul(options.batch)?;
+    let buffer_bytes = if options.in_place {
+        0
+    } else {
+        elements.checked_mul(options.element_size)?
+    };
+    let mut scratch_bytes = 0usize;
+    if options.transposed && options.batch > 0 {
+        // The scratch holds one group of signals, and while the last,
+        // shorter group replaces it both are live.
+        let group = crate::multidim::transposed_group(length, options.element_size);
+        let mut signals = group.min(options.batch);
+        if options.batch > group && !options.batch.is_multiple_of(group) {
+            signals += options.batch % group;
+        }
+        let table = length.checked_mul(std::mem::size_of::<usize>())?;
+        let groups = signals
+            .checked_mul(length)?
+            .checked_mul(options.element_size)?;
+        scratch_bytes = table.checked_add(groups)?;
+    }
+    if options.parallel_2d {
+        let copy = elements.checked_mul(options.element_size)?;
+        scratch_bytes = scratch_bytes.checked_add(copy)?;
+    }
+    Some(MemoryEstimate {
+        plan_bytes,
+        buffer_bytes,
+        scratch_bytes,
+    })
+}
+
+// The bytes held by a plan for transforms of `length` elements.
+fn plan_bytes(length: usize, ordering: Ordering) -> usize {
+    match ordering {
+        Ordering::Sequency => {
+            // Indices whose bit pattern is a palindrome stay in place; the
+            // rest are exchanged in pairs.
+            let bits = length.trailing_zeros();
+            let palindromes = 1usize << bits.div_ceil(2);
+            (length - palindromes) / 2 * std::mem::size_of::<(usize, usize)>()
+        }
+        Ordering::Hadamard => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Plan;
+
+    #[test]
+    fn test_plan_bytes_match_plan() {
+        for log_length in 0..16 {
+            let length = 1 << log_length;
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let options = MemoryOptions::for_type::<f64>().ordering(ordering);
+                let estimate = estimate_memory(length, &options).unwrap();
+                let plan = Plan::new(length, ordering).unwrap();
+                assert_eq!(estimate.plan_bytes(), plan.heap_bytes());
+                assert_eq!(estimate.total(), plan.heap_bytes() + length * 8);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scratch_of_transposed_and_2d_layouts() {
+        // Groups of 16 signals of 256 `f64`s: 70 signals leave a last
+        // group of 6, live together with a full one.
+        let options = MemoryOptions::for_type::<f64>()
+            .batch(70)
+            .in_place(true)
+            .transposed(true);
+        let estimate = estimate_memory(256, &options).unwrap();
+        assert_eq!(estimate.buffer_bytes(), 0);
+        assert_eq!(estimate.scratch_bytes(), 256 * 8 + (16 + 6) * 256 * 8);
+        let whole = estimate_memory(256, &options.batch(32)).unwrap();
+        assert_eq!(whole.scratch_bytes(), 256 * 8 + 16 * 256 * 8);
+        let few = estimate_memory(256, &options.batch(3)).unwrap();
+        assert_eq!(few.scratch_bytes(), 256 * 8 + 3 * 256 * 8);
+        // A 512 × 256 matrix of `f32` is copied once, and its columns
+        // need a plan of their own.
+        let options = MemoryOptions::for_type::<f32>()
+            .batch(512)
+            .parallel_2d(true);
+        let estimate = estimate_memory(256, &options).unwrap();
+        let row_plan = Plan::new(256, Ordering::Sequency).unwrap();
+        let col_plan = Plan::new(512, Ordering::Sequency).unwrap();
+        assert_eq!(
+            estimate.plan_bytes(),
+            row_plan.heap_bytes() + col_plan.heap_bytes()
+        );
+        assert_eq!(estimate.buffer_bytes(), 512 * 256 * 4);
+        assert_eq!(estimate.scratch_bytes(), 512 * 256 * 4);
+        assert_eq!(estimate.total(), estimate.plan_bytes() + 2 * 512 * 256 * 4);
+        assert_eq!(estimate_memory(256, &options.batch(500)), None);
+    }
+
+    #[test]
+    fn test_overflow_is_reported() {
+        let options = MemoryOptions::for_type::<f64>().batch(usize::MAX);
+        assert_eq!(estimate_memory(2, &options), None);
+    }
+}
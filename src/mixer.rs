@@ -0,0 +1,166 @@
+//! Seeded, exactly invertible random mixing built from Hadamard
+//! transforms.
+
+use crate::rng::SplitMix64;
+use crate::{hadamard_in_place, power_of_2, Error};
+
+/// A fast approximate random rotation of `f64` vectors.
+///
+/// Each round flips the signs of a random subset of the entries, applies
+/// the orthonormal Hadamard transform, and randomly permutes the result.
+/// Every step is orthogonal, so mixing preserves Euclidean norms and
+/// [`unmix`](Mixer::unmix) undoes it exactly up to rounding. A few
+/// rounds spread every input entry across all outputs, which makes the
+/// mixer useful for data augmentation and for flattening vectors before
+/// quantization. The same seed always gives the same mixer.
+///
+/// # Example
+///
+/// ```
+/// let v = [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+/// // A single round spreads the energy of a spike evenly.
+/// let once = fwt::Mixer::new(8, 42, 1).unwrap().mix(&v).unwrap();
+/// assert!(once.iter().all(|x| (x.abs() - 8f64.sqrt().recip()).abs() < 1e-12));
+///
+/// let mixer = fwt::Mixer::new(8, 42, 3).unwrap();
+/// let mixed = mixer.mix(&v).unwrap();
+/// let back = mixer.unmix(&mixed).unwrap();
+/// assert!(back.iter().zip(&v).all(|(a, b)| (a - b).abs() < 1e-12));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mixer {
+    length: usize,
+    rounds: Vec<Round>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Round {
+    signs: Vec<bool>,
+    permutation: Vec<usize>,
+}
+
+impl Mixer {
+    /// Create a mixer for vectors of the given `length` with `rounds`
+    /// rounds, drawn from `seed`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn new(length: usize, seed: u64, rounds: usize) -> Result<Mixer, Error> {
+        if !power_of_2(length) {
+            return Err(Error::NotPowerOfTwo(length));
+        }
+        let mut rng = SplitMix64::new(seed);
+        let rounds = (0..rounds)
+            .map(|_| {
+                let signs = (0..length).map(|_| rng.sign()).collect();
+                let mut permutation: Vec<usize> = (0..length).collect();
+                rng.shuffle(&mut permutation);
+                Round { signs, permutation }
+            })
+            .collect();
+        Ok(Mixer { length, rounds })
+    }
+
+    /// The vector length this mixer applies to.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The number of rounds.
+    pub fn rounds(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// Return the mixed copy of `input`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `input` has the wrong length.
+    pub fn mix(&self, input: &[f64]) -> Result<Vec<f64>, Error> {
+        self.check(input)?;
+        let scale = (self.length as f64).sqrt().recip();
+        let mut v = input.to_vec();
+        let mut out = vec![0.0; self.length];
+        for round in &self.rounds {
+            for (x, &flip) in v.iter_mut().zip(&round.signs) {
+                if flip {
+                    *x = -*x;
+                }
+            }
+            hadamard_in_place(&mut v)?;
+            for (i, &p) in round.permutation.iter().enumerate() {
+                out[p] = v[i] * scale;
+            }
+            std::mem::swap(&mut v, &mut out);
+        }
+        Ok(v)
+    }
+
+    /// Return the vector whose mix is `input`, inverting [`mix`](Mixer::mix).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `input` has the wrong length.
+    pub fn unmix(&self, input: &[f64]) -> Result<Vec<f64>, Error> {
+        self.check(input)?;
+        let scale = (self.length as f64).sqrt().recip();
+        let mut v = input.to_vec();
+        let mut out = vec![0.0; self.length];
+        for round in self.rounds.iter().rev() {
+            for (i, &p) in round.permutation.iter().enumerate() {
+                out[i] = v[p] * scale;
+            }
+            hadamard_in_place(&mut out)?;
+            for (x, &flip) in out.iter_mut().zip(&round.signs) {
+                if flip {
+                    *x = -*x;
+                }
+            }
+            std::mem::swap(&mut v, &mut out);
+        }
+        Ok(v)
+    }
+
+    fn check(&self, input: &[f64]) -> Result<(), Error> {
+        if input.len() == self.length {
+            Ok(())
+        } else {
+            Err(Error::LengthMismatch {
+                expected: self.length,
+                found: input.len(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_norm() {
+        let mixer = Mixer::new(256, 9, 4).unwrap();
+        let v: Vec<f64> = (0..256)
+            .map(|i| (i as f64 * 0.3).sin() + 0.1 * i as f64)
+            .collect();
+        let mixed = mixer.mix(&v).unwrap();
+        let norm = |x: &[f64]| x.iter().map(|a| a * a).sum::<f64>().sqrt();
+        assert!((norm(&mixed) - norm(&v)).abs() < 1e-9 * norm(&v));
+        let back = mixer.unmix(&mixed).unwrap();
+        assert!(back.iter().zip(&v).all(|(a, b)| (a - b).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_seeds() {
+        assert_eq!(Mixer::new(16, 1, 2), Mixer::new(16, 1, 2));
+        assert_ne!(Mixer::new(16, 1, 2), Mixer::new(16, 2, 2));
+        let identity = Mixer::new(4, 0, 0).unwrap();
+        assert_eq!(
+            identity.mix(&[1.0, 2.0, 3.0, 4.0]),
+            Ok(vec![1.0, 2.0, 3.0, 4.0])
+        );
+        assert_eq!(Mixer::new(6, 0, 1), Err(Error::NotPowerOfTwo(6)));
+        assert!(identity.unmix(&[1.0]).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Walsh–Hadamard transforms in modular arithmetic.
+//!
+//! Butterflies only add and subtract, so each one is reduced modulo `p`
+//! with a single conditional correction. The multiplications needed by
+//! XOR convolutions (the pointwise product and the final scaling by the
+//! inverse of the length) use Barrett reduction, which replaces division
+//! by `p` with a multiplication by a precomputed reciprocal.
+
+use crate::{kernel, power_of_2, Error};
+
+/// A modulus `p` with `2 <= p < 2^32`, together with the precomputed
+/// constant for Barrett reduction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modulus {
+    p: u64,
+    // floor(2^64 / p)
+    reciprocal: u64,
+}
+
+impl Modulus {
+    /// Prepare `p` for modular arithmetic, or return `None` if `p` is
+    /// outside `2..2^32`.
+    pub fn new(p: u64) -> Option<Modulus> {
+        if (2..1 << 32).contains(&p) {
+            Some(Modulus {
+                p,
+                reciprocal: ((1u128 << 64) / p as u128) as u64,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The modulus.
+    pub fn value(&self) -> u64 {
+        self.p
+    }
+
+    /// Reduce any `x < 2^64` modulo `p`.
+    pub fn reduce(&self, x: u64) -> u64 {
+        let q = ((x as u128 * self.reciprocal as u128) >> 64) as u64;
+        let r = x - q * self.p;
+        if r >= self.p {
+            r - self.p
+        } else {
+            r
+        }
+    }
+
+    /// Return `a * b mod p` for reduced `a` and `b`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let m = fwt::Modulus::new(998_244_353).unwrap();
+    /// assert_eq!(m.mul(998_244_352, 998_244_352), 1);
+    /// ```
+    pub fn mul(&self, a: u64, b: u64) -> u64 {
+        self.reduce(a * b)
+    }
+
+    /// Return the multiplicative inverse of `a` modulo `p`, or `None` if
+    /// `a` and `p` are not coprime.
+    pub fn inverse(&self, a: u64) -> Option<u64> {
+        let (mut r0, mut r1) = (self.p as i64, self.reduce(a) as i64);
+        let (mut t0, mut t1) = (0i64, 1i64);
+        while r1 != 0 {
+            let q = r0 / r1;
+            (r0, r1) = (r1, r0 - q * r1);
+            (t0, t1) = (t1, t0 - q * t1);
+        }
+        if r0 == 1 {
+            Some(t0.rem_euclid(self.p as i64) as u64)
+        } else {
+            None
+        }
+    }
+}
+
+// Butterflies modulo p on reduced values. Since p < 2^32, sums never
+// overflow a u64.
+fn hadamard_stages_mod(v: &mut [u64], p: u64) {
+    let length = v.len();
+    let mut lag = 1;
+    while lag < length {
+        let offset = lag << 1;
+        for group in (0..length).step_by(offset) {
+            for j in group..group + lag {
+                let (a, b) = (v[j], v[j + lag]);
+                let sum = a + b;
+                v[j] = if sum >= p { sum - p } else { sum };
+                v[j + lag] = if a >= b { a - b } else { a + p - b };
+            }
+        }
+        lag = offset;
+    }
+}
+
+/// Return the Hadamard (natural) ordering transform of `input_v` with all
+/// arithmetic performed modulo `p`, or `None` if the input length is not
+/// a power of 2 or `p` is outside `2..2^32`. Inputs need not be reduced.
+///
+/// # Example
+///
+/// ```
+/// let p = 7;
+/// assert_eq!(fwt::hadamard_mod(&[1, 2, 3, 4], p), Some(vec![3, 5, 3, 0]));
+/// assert_eq!(fwt::hadamard_mod(&[1, 2, 3], p), None);
+/// ```
+pub fn hadamard_mod(input_v: &[u64], p: u64) -> Option<Vec<u64>> {
+    let m = Modulus::new(p)?;
+    if !power_of_2(input_v.len()) {
+        return None;
+    }
+    let mut v: Vec<u64> = input_v.iter().map(|&x| m.reduce(x)).collect();
+    hadamard_stages_mod(&mut v, p);
+    Some(v)
+}
+
+/// Return the Manz sequency ordering transform of `input_v` modulo `p`,
+/// under the same conditions as [`hadamard_mod`].
+///
+/// # Example
+///
+/// ```
+/// let v = [0, 1, 0, 0, 0, 0, 0, 0];
+/// assert_eq!(
+///     fwt::sequency_mod(&v, 5),
+///     Some(vec![1, 1, 1, 1, 4, 4, 4, 4])
+/// );
+/// ```
+pub fn sequency_mod(input_v: &[u64], p: u64) -> Option<Vec<u64>> {
+    let length = input_v.len();
+    let h = hadamard_mod(input_v, p)?;
+    Some(
+        (0..length)
+            .map(|s| h[kernel::sequency_to_hadamard(s, length)])
+            .collect(),
+    )
+}
+
+/// Return the XOR convolution of `a` and `b` modulo `p`, whose `k`-th
+/// entry is the sum of `a[i] * b[j]` over all `i ^ j == k`, reduced
+/// modulo `p`.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if the inputs differ in length,
+/// [`Error::NotPowerOfTwo`] if their length is not a power of 2, and
+/// [`Error::InvalidModulus`] if `p` is outside `2..2^32` or the length is
+/// not invertible modulo `p` (that is, `p` is even).
+///
+/// # Example
+///
+/// ```
+/// let p = 998_244_353;
+/// assert_eq!(
+///     fwt::xor_convolution_mod(&[1, 2, 3, 4], &[1, 0, 0, 1], p),
+///     Ok(vec![5, 5, 5, 5])
+/// );
+/// ```
+pub fn xor_convolution_mod(a: &[u64], b: &[u64], p: u64) -> Result<Vec<u64>, Error> {
+    let length = a.len();
+    if b.len() != length {
+        return Err(Error::LengthMismatch {
+            expected: length,
+            found: b.len(),
+        });
+    }
+    if !power_of_2(length) {
+        return Err(Error::NotPowerOfTwo(length));
+    }
+    let m = Modulus::new(p).ok_or(Error::InvalidModulus(p))?;
+    let scale = m
+        .inverse(m.reduce(length as u64))
+        .ok_or(Error::InvalidModulus(p))?;
+    let mut fa: Vec<u64> = a.iter().map(|&x| m.reduce(x)).collect();
+    let mut fb: Vec<u64> = b.iter().map(|&x| m.reduce(x)).collect();
+    hadamard_stages_mod(&mut fa, p);
+    hadamard_stages_mod(&mut fb, p);
+    for (x, &y) in fa.iter_mut().zip(&fb) {
+        *x = m.mul(*x, y);
+    }
+    hadamard_stages_mod(&mut fa, p);
+    for x in fa.iter_mut() {
+        *x = m.mul(*x, scale);
+    }
+    Ok(fa)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_integer_transform() {
+        let p = 1_000_000_007u64;
+        let v: Vec<i64> = (0..64).map(|x| (x * 7919 + 13) % 1000 - 500).collect();
+        let exact = crate::hadamard(&v).unwrap();
+        let reduced: Vec<u64> = v.iter().map(|&x| x.rem_euclid(p as i64) as u64).collect();
+        let modular = hadamard_mod(&reduced, p).unwrap();
+        for (e, m) in exact.iter().zip(&modular) {
+            assert_eq!(e.rem_euclid(p as i64) as u64, *m);
+        }
+        let exact = crate::sequency(&v).unwrap();
+        let modular = sequency_mod(&reduced, p).unwrap();
+        for (e, m) in exact.iter().zip(&modular) {
+            assert_eq!(e.rem_euclid(p as i64) as u64, *m);
+        }
+    }
+
+    #[test]
+    fn test_convolution_without_overflow() {
+        let p = 998_244_353u64;
+        let a: Vec<u64> = (0..32).map(|x| (x * 123_456_789) % p).collect();
+        let b: Vec<u64> = (0..32).map(|x| (x * 987_654_321 + 5) % p).collect();
+        let mut expected = vec![0u64; 32];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                let k = i ^ j;
+                expected[k] = ((expected[k] as u128 + x as u128 * y as u128) % p as u128) as u64;
+            }
+        }
+        assert_eq!(xor_convolution_mod(&a, &b, p), Ok(expected));
+        assert_eq!(
+            xor_convolution_mod(&a, &b, 1 << 20),
+            Err(Error::InvalidModulus(1 << 20))
+        );
+        assert_eq!(
+            xor_convolution_mod(&a, &b, 1),
+            Err(Error::InvalidModulus(1))
+        );
+    }
+
+    #[test]
+    fn test_barrett_reduction() {
+        let m = Modulus::new(4_294_967_291).unwrap();
+        for x in [
+            0,
+            1,
+            u64::MAX,
+            u64::MAX - 1,
+            1 << 63,
+            4_294_967_291,
+            4_294_967_290,
+        ] {
+            assert_eq!(m.reduce(x), x % 4_294_967_291);
+        }
+        assert_eq!(Modulus::new(1 << 32), None);
+        assert_eq!(m.inverse(0), None);
+        assert_eq!(m.mul(m.inverse(12345).unwrap(), 12345), 1);
+    }
+}
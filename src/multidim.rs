@@ -0,0 +1,750 @@
+//! Multidimensional transforms of row-major arrays.
+
+use crate::{kernel, no_alloc, Error, Normalize, Ordering, Plan, WalshElement};
+
+// The working set of one column tile of a strided axis transform.
+const TILE_BYTES: usize = 32 * 1024;
+// The narrowest tile, so that short contiguous runs are still used.
+const MIN_TILE: usize = 16;
+
+// The signals moved together by the transposes of batched transforms.
+const LANES: usize = 8;
+
+/// Return the 2D transform of the `rows × cols` row-major matrix `data`,
+/// applying the transform in the given ordering along every row and then
+/// along every column.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `data` does not hold
+/// `rows * cols` elements, or [`Error::NotPowerOfTwo`] if either
+/// dimension is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let image = [
+///     1, 1, 1, 1,
+///     1, 1, 1, 1,
+///     0, 0, 0, 0,
+///     0, 0, 0, 0,
+/// ];
+/// let spectrum = fwt::fwt2(&image, 4, 4, Ordering::Sequency).unwrap();
+/// // Only the first row varies, along the columns, with one sign change.
+/// assert_eq!(spectrum[0], 8);
+/// assert_eq!(spectrum[4], 8);
+/// assert_eq!(spectrum.iter().filter(|&&c| c != 0).count(), 2);
+/// ```
+pub fn fwt2<T>(data: &[T], rows: usize, cols: usize, ordering: Ordering) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    fwtn(data, &[rows, cols], ordering)
+}
+
+/// Return the 2D transform of the `rows × cols` row-major matrix `data`,
+/// as [`fwt2`] does, computed on the current rayon thread pool. The rows
+/// are transformed in parallel, the matrix is transposed in cache-sized
+/// tiles so the columns become contiguous rows, those are transformed in
+/// parallel, and the result is transposed back. Results are bit-identical
+/// to [`fwt2`] for any number of threads.
+///
+/// Enable with the `parallel` feature.
+///
+/// # Errors
+///
+/// Returns the errors of [`fwt2`].
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let image: Vec<f32> = (0..512 * 256).map(|i| (i % 13) as f32).collect();
+/// let spectrum = fwt::fwt2_parallel(&image, 512, 256, Ordering::Sequency).unwrap();
+/// assert_eq!(spectrum, fwt::fwt2(&image, 512, 256, Ordering::Sequency).unwrap());
+/// ```
+#[cfg(feature = "parallel")]
+pub fn fwt2_parallel<T>(
+    data: &[T],
+    rows: usize,
+    cols: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Send + Sync,
+{
+    check_shape(data, &[rows, cols])?;
+    let row_plan = Plan::new(cols, ordering)?;
+    let col_plan = Plan::new(rows, ordering)?;
+    let mut v = data.to_vec();
+    crate::parallel::transform_2d(&mut v, &row_plan, &col_plan);
+    Ok(v)
+}
+
+/// Return the `rows × cols` row-major matrix `data` with the transform in
+/// the given ordering applied along every row only, leaving the columns
+/// untouched. [`fwt2`] is this followed by [`transform_cols`].
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `data` does not hold
+/// `rows * cols` elements, or [`Error::NotPowerOfTwo`] if `cols` is not a
+/// power of 2. `rows` may be any size.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // Two channels of four samples each, transformed along time.
+/// let channels = [1, 1, 1, 1, 1, -1, 1, -1];
+/// let spectra = fwt::transform_rows(&channels, 2, 4, Ordering::Sequency).unwrap();
+/// assert_eq!(spectra, vec![4, 0, 0, 0, 0, 0, 0, 4]);
+/// ```
+pub fn transform_rows<T>(
+    data: &[T],
+    rows: usize,
+    cols: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    check_shape(data, &[rows, cols])?;
+    let mut v = data.to_vec();
+    transform_axis(&mut v, &[rows, cols], 1, &Plan::new(cols, ordering)?)?;
+    Ok(v)
+}
+
+/// Return the `rows × cols` row-major matrix `data` with the transform in
+/// the given ordering applied down every column only, leaving the rows
+/// untouched. The columns are transformed in place in the row-major
+/// layout, without transposing.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `data` does not hold
+/// `rows * cols` elements, or [`Error::NotPowerOfTwo`] if `rows` is not a
+/// power of 2. `cols` may be any size.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // Four time steps of three channels, transformed across time.
+/// let samples = [1, 2, 3, 1, 2, 3, 1, 2, 3, 1, 2, 3];
+/// let spectra = fwt::transform_cols(&samples, 4, 3, Ordering::Sequency).unwrap();
+/// assert_eq!(spectra, vec![4, 8, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+/// ```
+pub fn transform_cols<T>(
+    data: &[T],
+    rows: usize,
+    cols: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    check_shape(data, &[rows, cols])?;
+    let mut v = data.to_vec();
+    transform_axis(&mut v, &[rows, cols], 0, &Plan::new(rows, ordering)?)?;
+    Ok(v)
+}
+
+/// Return the N-dimensional transform of the row-major array `data` of
+/// the given `shape`, applying the transform in the given ordering along
+/// every axis in turn, last axis first.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `data` does not hold the product
+/// of the dimensions, or [`Error::NotPowerOfTwo`] if any dimension is
+/// not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // A 2 × 2 × 2 full factorial design: the spectrum holds the grand
+/// // total, the main effects, and the interactions.
+/// let responses = [0, 1, 2, 3, 4, 5, 6, 7];
+/// let effects = fwt::fwtn(&responses, &[2, 2, 2], Ordering::Hadamard).unwrap();
+/// assert_eq!(effects, vec![28, -4, -8, 0, -16, 0, 0, 0]);
+/// ```
+pub fn fwtn<T>(data: &[T], shape: &[usize], ordering: Ordering) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    check_shape(data, shape)?;
+    let mut v = data.to_vec();
+    for axis in (0..shape.len()).rev() {
+        transform_axis(&mut v, shape, axis, &Plan::new(shape[axis], ordering)?)?;
+    }
+    Ok(v)
+}
+
+/// Return the transform of the row-major array `data` of the given
+/// `shape` along only the listed `axes`, leaving the other axes
+/// untouched. Axes may be listed in any order; the result does not
+/// depend on it. Only the transformed axes need power-of-2 lengths.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `data` does not hold the product
+/// of the dimensions, [`Error::InvalidAxis`] if an axis is out of range
+/// or listed twice, or [`Error::NotPowerOfTwo`] if a transformed
+/// dimension is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // Three channels of two frames of four samples: transform over frames
+/// // and samples, but not across channels.
+/// let data: Vec<i32> = (0..24).collect();
+/// let spectra = fwt::fwtn_axes(&data, &[3, 2, 4], &[1, 2], Ordering::Sequency).unwrap();
+/// for (channel, spectrum) in data.chunks(8).zip(spectra.chunks(8)) {
+///     assert_eq!(spectrum, fwt::fwt2(channel, 2, 4, Ordering::Sequency).unwrap());
+/// }
+/// ```
+pub fn fwtn_axes<T>(
+    data: &[T],
+    shape: &[usize],
+    axes: &[usize],
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    check_shape(data, shape)?;
+    let mut selected = vec![false; shape.len()];
+    for &axis in axes {
+        match selected.get_mut(axis) {
+            Some(seen) if !*seen => *seen = true,
+            _ => return Err(Error::InvalidAxis(axis)),
+        }
+    }
+    let mut v = data.to_vec();
+    for axis in (0..shape.len()).rev().filter(|&a| selected[a]) {
+        transform_axis(&mut v, shape, axis, &Plan::new(shape[axis], ordering)?)?;
+    }
+    Ok(v)
+}
+
+/// Return the 3D transform of the volume `data`, stored with `x` varying
+/// fastest, then `y`, then `z` (the index of `(x, y, z)` is
+/// `(z * dy + y) * dx + x`), applying the transform in the given ordering
+/// along all three axes.
+///
+/// The `x` axis is transformed row by row. The `y` and `z` axes are
+/// transformed by butterflies that combine whole contiguous rows and
+/// planes, in cache-sized column tiles, so no axis is ever traversed with
+/// a stride and no transposes are needed. Results are bit-identical to
+/// transforming each line separately.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `data` does not hold
+/// `dx * dy * dz` elements, or [`Error::NotPowerOfTwo`] if any dimension
+/// is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let mut volume = vec![0.0; 4 * 2 * 8];
+/// volume[0] = 1.0;
+/// let spectrum = fwt::fwt3(&volume, 4, 2, 8, Ordering::Sequency).unwrap();
+/// assert!(spectrum.iter().all(|&c| c == 1.0));
+/// ```
+pub fn fwt3<T>(
+    data: &[T],
+    dx: usize,
+    dy: usize,
+    dz: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    fwtn(data, &[dz, dy, dx], ordering)
+}
+
+/// Return the inverse of [`fwt2`]: the 2D transform of the `rows × cols`
+/// row-major `spectrum` in the given ordering, divided by `rows * cols`.
+///
+/// # Errors
+///
+/// Returns the errors of [`fwt2`], and [`Error::InexactScaling`] if an
+/// integer result is not exactly divisible by `rows * cols`.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let image = [3, 1, 4, 1, 5, 9, 2, 6];
+/// let spectrum = fwt::fwt2(&image, 2, 4, Ordering::Sequency).unwrap();
+/// assert_eq!(fwt::ifwt2(&spectrum, 2, 4, Ordering::Sequency), Ok(image.to_vec()));
+/// ```
+pub fn ifwt2<T>(
+    spectrum: &[T],
+    rows: usize,
+    cols: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Normalize,
+{
+    let v = fwt2(spectrum, rows, cols, ordering)?;
+    normalize_all(v, rows * cols)
+}
+
+/// Return the 2D transform of every `block_size × block_size` block of the
+/// `width × height` row-major `image`, with each block's spectrum stored in
+/// place of the block, as in block-based image and video coding.
+///
+/// All blocks share one plan and are transformed in two passes over the
+/// image, one along rows and one down columns, rather than with a call
+/// per block. Each block's spectrum is identical to that of [`fwt2`]
+/// applied to the block alone.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if `block_size` is not a power of 2,
+/// [`Error::LengthMismatch`] if `width` or `height` is not a multiple of
+/// `block_size` (reporting the next multiple as expected), or if `image`
+/// does not hold `width * height` elements.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // Two 2 × 2 blocks side by side.
+/// let image = [
+///     1, 1, 5, 3,
+///     1, 1, 5, 3,
+/// ];
+/// let blocks = fwt::block_transform_2d(&image, 4, 2, 2, Ordering::Sequency).unwrap();
+/// assert_eq!(blocks, vec![4, 0, 16, 4, 0, 0, 0, 0]);
+/// assert_eq!(
+///     fwt::inverse_block_transform_2d(&blocks, 4, 2, 2, Ordering::Sequency),
+///     Ok(image.to_vec())
+/// );
+/// ```
+pub fn block_transform_2d<T>(
+    image: &[T],
+    width: usize,
+    height: usize,
+    block_size: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    let plan = Plan::new(block_size, ordering)?;
+    for extent in [width, height] {
+        if !extent.is_multiple_of(block_size) {
+            return Err(Error::LengthMismatch {
+                expected: extent.next_multiple_of(block_size),
+                found: extent,
+            });
+        }
+    }
+    let shape = [
+        height / block_size,
+        block_size,
+        width / block_size,
+        block_size,
+    ];
+    check_shape(image, &shape)?;
+    let mut v = image.to_vec();
+    transform_axis(&mut v, &shape, 3, &plan)?;
+    transform_axis(&mut v, &shape, 1, &plan)?;
+    Ok(v)
+}
+
+/// Return the inverse of [`block_transform_2d`]: the block transform of
+/// `blocks`, with every element divided by `block_size * block_size`.
+///
+/// # Errors
+///
+/// Returns the errors of [`block_transform_2d`], and
+/// [`Error::InexactScaling`] if an integer result is not exactly
+/// divisible by `block_size * block_size`.
+pub fn inverse_block_transform_2d<T>(
+    blocks: &[T],
+    width: usize,
+    height: usize,
+    block_size: usize,
+    ordering: Ordering,
+) -> Result<Vec<T>, Error>
+where
+    T: WalshElement + Normalize,
+{
+    let v = block_transform_2d(blocks, width, height, block_size, ordering)?;
+    normalize_all(v, block_size * block_size)
+}
+
+// Divide every element of `v` by `length`.
+pub(crate) fn normalize_all<T>(v: Vec<T>, length: usize) -> Result<Vec<T>, Error>
+where
+    T: Normalize,
+{
+    v.into_iter()
+        .map(|x| x.normalize(length).ok_or(Error::InexactScaling))
+        .collect()
+}
+
+// Check that `data` holds exactly the elements of an array of `shape`.
+pub(crate) fn check_shape<T>(data: &[T], shape: &[usize]) -> Result<(), Error> {
+    let expected = shape
+        .iter()
+        .try_fold(1usize, |acc, &d| acc.checked_mul(d))
+        .ok_or(Error::LengthMismatch {
+            expected: usize::MAX,
+            found: data.len(),
+        })?;
+    if data.len() == expected {
+        Ok(())
+    } else {
+        Err(Error::LengthMismatch {
+            expected,
+            found: data.len(),
+        })
+    }
+}
+
+// Transform every line of the row-major array `data` of `shape` that runs
+// along `axis`, using a plan for that axis's length.
+pub(crate) fn transform_axis<T>(
+    data: &mut [T],
+    shape: &[usize],
+    axis: usize,
+    plan: &Plan,
+) -> Result<(), Error>
+where
+    T: WalshElement,
+{
+    let length = shape[axis];
+    let stride: usize = shape[axis + 1..].iter().product();
+    if data.is_empty() {
+        return Ok(());
+    }
+    if stride == 1 {
+        for line in data.chunks_exact_mut(length) {
+            plan.execute(line)?;
+        }
+        return Ok(());
+    }
+    // Whole rows of `stride` elements are combined at once, in column
+    // tiles narrow enough that all `length` rows of a tile stay in cache
+    // through every stage.
+    let tile = (TILE_BYTES / std::mem::size_of::<T>().max(1) / length)
+        .clamp(MIN_TILE, stride.max(MIN_TILE));
+    for block in data.chunks_exact_mut(length * stride) {
+        if plan.ordering() == Ordering::Sequency {
+            kernel::bit_reverse_rows(block, stride);
+        }
+        for start in (0..stride).step_by(tile) {
+            let columns = start..(start + tile).min(stride);
+            no_alloc(|| kernel::stages_across_rows(block, stride, columns, plan.ordering(), 0));
+        }
+    }
+    Ok(())
+}
+
+// Transform each of the signals of `plan`'s length held back to back in
+// `v` a group at a time: the group is transposed so that each signal is a
+// column, in bit-reversed row order for the sequency ordering, the
+// columns are transformed together by butterflies of whole rows, one
+// vector lane per signal, and the group is transposed back. Groups fill
+// about one column tile of `transform_axis`.
+pub(crate) fn transform_batch_transposed<T>(v: &mut [T], plan: &Plan)
+where
+    T: WalshElement,
+{
+    let length = plan.length();
+    if v.is_empty() {
+        return;
+    }
+    let group = transposed_group(length, std::mem::size_of::<T>());
+    let rows: Vec<usize> = match plan.ordering() {
+        Ordering::Sequency => (0..length)
+            .map(|r| kernel::reverse_index(r, length))
+            .collect(),
+        Ordering::Hadamard => (0..length).collect(),
+    };
+    let mut scratch = Vec::new();
+    for signals in v.chunks_mut(group * length) {
+        let count = signals.len() / length;
+        if scratch.len() != signals.len() {
+            scratch = signals.to_vec();
+        }
+        // Move a few signals at a time, so that each row of the scratch
+        // is written and read in short contiguous runs.
+        for (first, block) in signals.chunks(length * LANES).enumerate() {
+            let start = first * LANES;
+            for (&r, src) in rows.iter().zip(0..) {
+                let row = &mut scratch[r * count + start..];
+                for (x, signal) in row.iter_mut().zip(block.chunks_exact(length)) {
+                    *x = signal[src];
+                }
+            }
+        }
+        no_alloc(|| kernel::stages_across_rows(&mut scratch, count, 0..count, plan.ordering(), 0));
+        for (first, block) in signals.chunks_mut(length * LANES).enumerate() {
+            let start = first * LANES;
+            for (r, row) in scratch.chunks_exact(count).enumerate() {
+                for (&x, signal) in row[start..].iter().zip(block.chunks_exact_mut(length)) {
+                    signal[r] = x;
+                }
+            }
+        }
+    }
+}
+
+// The number of signals of `length` elements of `element_size` bytes
+// that `transform_batch_transposed` transposes at a time.
+pub(crate) fn transposed_group(length: usize, element_size: usize) -> usize {
+    (TILE_BYTES / element_size.max(1) / length).max(MIN_TILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fwt2_is_separable() {
+        let (rows, cols) = (4, 8);
+        let data: Vec<i64> = (0..32).map(|i| (i * 7) % 5 - 2).collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let result = fwt2(&data, rows, cols, ordering).unwrap();
+            for r in 0..rows {
+                for c in 0..cols {
+                    let row_walsh = crate::walsh_row(rows, r, ordering).unwrap();
+                    let col_walsh = crate::walsh_row(cols, c, ordering).unwrap();
+                    let expected: i64 = (0..rows * cols)
+                        .map(|i| data[i] * (row_walsh[i / cols] * col_walsh[i % cols]) as i64)
+                        .sum();
+                    assert_eq!(result[r * cols + c], expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ifwt2_round_trip() {
+        let data: Vec<f64> = (0..64).map(|i| (i as f64 * 0.7).sin()).collect();
+        let spectrum = fwt2(&data, 8, 8, Ordering::Hadamard).unwrap();
+        let back = ifwt2(&spectrum, 8, 8, Ordering::Hadamard).unwrap();
+        assert!(back.iter().zip(&data).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert_eq!(
+            ifwt2(&[1, 0, 0, 0], 2, 2, Ordering::Sequency),
+            Err(Error::InexactScaling)
+        );
+    }
+
+    #[test]
+    fn test_fwtn_matches_flat_hadamard() {
+        // Hadamard ordering is a Kronecker product, so transforming every
+        // axis of any power-of-2 shape equals the flat transform.
+        let data: Vec<i32> = (0..64).map(|i| (i * 11) % 9 - 4).collect();
+        let flat = crate::hadamard(&data).unwrap();
+        for shape in [&[64][..], &[4, 16], &[2, 4, 8], &[2, 2, 2, 2, 2, 2]] {
+            assert_eq!(fwtn(&data, shape, Ordering::Hadamard).unwrap(), flat);
+        }
+        assert_eq!(fwtn(&[5], &[], Ordering::Sequency), Ok(vec![5]));
+        assert_eq!(
+            fwtn(&[1; 12], &[2, 3, 2], Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            fwtn(&[1; 12], &[2, 2, 2], Ordering::Sequency),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                found: 12
+            })
+        );
+    }
+
+    #[test]
+    fn test_fwtn_axes_selects_axes() {
+        let shape = [2, 4, 3, 8];
+        let data: Vec<i64> = (0..192).map(|i| (i * 7) % 11 - 5).collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let result = fwtn_axes(&data, &shape, &[3, 0], ordering).unwrap();
+            assert_eq!(
+                fwtn_axes(&data, &shape, &[0, 3], ordering),
+                Ok(result.clone())
+            );
+            // The last axis is each row of 8; the first splits the array
+            // into two halves of 96 whose elements pair up.
+            let rows = transform_rows(&data, 24, 8, ordering).unwrap();
+            let expected = transform_cols(&rows, 2, 96, ordering).unwrap();
+            assert_eq!(result, expected);
+            let all = fwtn_axes(&[1; 8], &[2, 2, 2], &[0, 1, 2], ordering);
+            assert_eq!(all, fwtn(&[1; 8], &[2, 2, 2], ordering));
+        }
+        assert_eq!(
+            fwtn_axes(&data, &shape, &[], Ordering::Sequency),
+            Ok(data.clone())
+        );
+        assert_eq!(
+            fwtn_axes(&data, &shape, &[2], Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            fwtn_axes(&data, &shape, &[4], Ordering::Sequency),
+            Err(Error::InvalidAxis(4))
+        );
+        assert_eq!(
+            fwtn_axes(&data, &shape, &[1, 1], Ordering::Sequency),
+            Err(Error::InvalidAxis(1))
+        );
+    }
+
+    #[test]
+    fn test_fwt3_matches_line_by_line() {
+        let (dx, dy, dz) = (8, 4, 32);
+        let data: Vec<f64> = (0..dx * dy * dz).map(|i| (i as f64 * 0.61).sin()).collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let transform = |line: &[f64]| match ordering {
+                Ordering::Sequency => crate::sequency(line).unwrap(),
+                Ordering::Hadamard => crate::hadamard(line).unwrap(),
+            };
+            let mut expected = data.clone();
+            for (length, stride) in [(dx, 1), (dy, dx), (dz, dx * dy)] {
+                for start in 0..expected.len() {
+                    if (start / stride) % length != 0 {
+                        continue;
+                    }
+                    let line: Vec<f64> =
+                        (0..length).map(|i| expected[start + i * stride]).collect();
+                    for (i, x) in transform(&line).into_iter().enumerate() {
+                        expected[start + i * stride] = x;
+                    }
+                }
+            }
+            let result = fwt3(&data, dx, dy, dz, ordering).unwrap();
+            assert!(result
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+        }
+        assert_eq!(
+            fwt3(&[1; 8], 2, 2, 2, Ordering::Hadamard),
+            Ok(vec![8, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert!(fwt3(&[1; 8], 2, 4, 2, Ordering::Hadamard).is_err());
+    }
+
+    #[test]
+    fn test_block_transform_2d_matches_fwt2_per_block() {
+        let (width, height) = (32, 16);
+        let image: Vec<i32> = (0..width * height)
+            .map(|i| (i * 13 % 29) as i32 - 14)
+            .collect();
+        for block_size in [1, 2, 4, 8, 16] {
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let result =
+                    block_transform_2d(&image, width, height, block_size, ordering).unwrap();
+                for by in (0..height).step_by(block_size) {
+                    for bx in (0..width).step_by(block_size) {
+                        let at = |r: usize, c: usize| (by + r) * width + bx + c;
+                        let block: Vec<i32> = (0..block_size * block_size)
+                            .map(|i| image[at(i / block_size, i % block_size)])
+                            .collect();
+                        let expected = fwt2(&block, block_size, block_size, ordering).unwrap();
+                        for (i, e) in expected.into_iter().enumerate() {
+                            assert_eq!(result[at(i / block_size, i % block_size)], e);
+                        }
+                    }
+                }
+                let back = inverse_block_transform_2d(&result, width, height, block_size, ordering);
+                assert_eq!(back, Ok(image.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_transform_2d_errors() {
+        let image = [0; 48];
+        assert_eq!(
+            block_transform_2d(&image, 8, 6, 3, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            block_transform_2d(&image, 8, 6, 4, Ordering::Sequency),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                found: 6
+            })
+        );
+        assert_eq!(
+            block_transform_2d(&image, 8, 8, 4, Ordering::Sequency),
+            Err(Error::LengthMismatch {
+                expected: 64,
+                found: 48
+            })
+        );
+        assert_eq!(
+            inverse_block_transform_2d(&[1, 0, 0, 0], 2, 2, 2, Ordering::Hadamard),
+            Err(Error::InexactScaling)
+        );
+    }
+
+    #[test]
+    fn test_row_and_column_transforms_compose() {
+        let (rows, cols) = (8, 4);
+        let data: Vec<i64> = (0..32).map(|i| (i * 5) % 7 - 3).collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let by_rows = transform_rows(&data, rows, cols, ordering).unwrap();
+            for (line, out) in data.chunks(cols).zip(by_rows.chunks(cols)) {
+                assert_eq!(crate::naive::transform(line, ordering).unwrap(), out);
+            }
+            let by_cols = transform_cols(&data, rows, cols, ordering).unwrap();
+            for c in 0..cols {
+                let column: Vec<i64> = (0..rows).map(|r| data[r * cols + c]).collect();
+                let expected = crate::naive::transform(&column, ordering).unwrap();
+                assert!((0..rows).all(|r| by_cols[r * cols + c] == expected[r]));
+            }
+            let both = transform_cols(&by_rows, rows, cols, ordering).unwrap();
+            assert_eq!(both, fwt2(&data, rows, cols, ordering).unwrap());
+            let swapped = transform_rows(&by_cols, rows, cols, ordering).unwrap();
+            assert_eq!(swapped, both);
+        }
+        assert_eq!(
+            transform_rows(&[1, 2, 3], 3, 1, Ordering::Sequency),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(
+            transform_cols(&[1; 6], 3, 2, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(3))
+        );
+    }
+
+    #[test]
+    fn test_fwt2_errors() {
+        assert_eq!(
+            fwt2(&[1; 6], 2, 4, Ordering::Hadamard),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                found: 6
+            })
+        );
+        assert_eq!(
+            fwt2(&[1; 6], 2, 3, Ordering::Hadamard),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(fwt2(&[1; 1], 1, 1, Ordering::Sequency), Ok(vec![1]));
+    }
+}
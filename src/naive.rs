@@ -0,0 +1,150 @@
+//! Direct O(n²) matrix-multiplication transforms.
+//!
+//! These evaluate every output coefficient as an explicit signed sum of
+//! the inputs, with each Walsh matrix entry computed independently of the
+//! butterfly schedule. They are far too slow for real work but make a
+//! trustworthy oracle for validating the fast paths, and are handy for
+//! tiny sizes and for teaching.
+
+use crate::kernel;
+use crate::{power_of_2, Ordering, WalshElement};
+
+/// Return the entry in row `row` and column `col` of the Walsh matrix of
+/// size `length` in the given ordering, as `1` or `-1`.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// assert_eq!(fwt::naive::walsh_entry(8, 3, 1, Ordering::Sequency), 1);
+/// assert_eq!(fwt::naive::walsh_entry(8, 3, 1, Ordering::Hadamard), -1);
+/// ```
+pub fn walsh_entry(length: usize, row: usize, col: usize, ordering: Ordering) -> i8 {
+    let row = match ordering {
+        Ordering::Sequency => kernel::sequency_to_hadamard(row, length),
+        Ordering::Hadamard => row,
+    };
+    if (row & col).count_ones().is_multiple_of(2) {
+        1
+    } else {
+        -1
+    }
+}
+
+/// Return the transform of `input_v` in the given ordering by direct
+/// matrix multiplication, or `None` if the input length is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let input_v = [0, 0, 0, 0, 0, 0, 1, 0];
+/// assert_eq!(
+///     fwt::naive::transform(&input_v, Ordering::Sequency),
+///     fwt::sequency(&input_v)
+/// );
+/// ```
+pub fn transform<T>(input_v: &[T], ordering: Ordering) -> Option<Vec<T>>
+where
+    T: WalshElement,
+{
+    let length = input_v.len();
+    if !power_of_2(length) {
+        return None;
+    }
+    // Column 0 of every Walsh matrix is all ones, so each sum can start
+    // from the first input without needing a zero of `T`.
+    let result = (0..length)
+        .map(|row| {
+            input_v
+                .iter()
+                .enumerate()
+                .skip(1)
+                .fold(input_v[0], |acc, (col, &x)| {
+                    if walsh_entry(length, row, col, ordering) > 0 {
+                        acc + x
+                    } else {
+                        acc - x
+                    }
+                })
+        })
+        .collect();
+    Some(result)
+}
+
+/// Return the sequency ordering transform of `input_v` by direct matrix
+/// multiplication, or `None` if the input length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let input_v = [0, 0, 0, 0, 0, 0, 1, 0];
+/// assert_eq!(
+///     fwt::naive::sequency(&input_v),
+///     Some(vec![1, -1, 1, -1, -1, 1, -1, 1])
+/// );
+/// ```
+pub fn sequency<T>(input_v: &[T]) -> Option<Vec<T>>
+where
+    T: WalshElement,
+{
+    transform(input_v, Ordering::Sequency)
+}
+
+/// Return the natural (Hadamard) ordering transform of `input_v` by direct
+/// matrix multiplication, or `None` if the input length is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// let input_v = [0, 0, 0, 0, 0, 0, 1, 0];
+/// assert_eq!(
+///     fwt::naive::hadamard(&input_v),
+///     Some(vec![1, 1, -1, -1, -1, -1, 1, 1])
+/// );
+/// ```
+pub fn hadamard<T>(input_v: &[T]) -> Option<Vec<T>>
+where
+    T: WalshElement,
+{
+    transform(input_v, Ordering::Hadamard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_fast_transforms() {
+        for m in 0..=7 {
+            let n = 1usize << m;
+            let v: Vec<i64> = (0..n as i64).map(|i| (i * 37) % 11 - 5).collect();
+            assert_eq!(sequency(&v), crate::sequency(&v));
+            assert_eq!(hadamard(&v), crate::hadamard(&v));
+        }
+    }
+
+    #[test]
+    fn test_sequency_rows_count_sign_changes() {
+        let n = 32;
+        for row in 0..n {
+            let changes = (1..n)
+                .filter(|&c| {
+                    walsh_entry(n, row, c, Ordering::Sequency)
+                        != walsh_entry(n, row, c - 1, Ordering::Sequency)
+                })
+                .count();
+            assert_eq!(changes, row);
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_length() {
+        assert_eq!(sequency::<i32>(&[]), None);
+        assert_eq!(hadamard(&[1, 2, 3]), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Walsh transforms of [`nalgebra`](::nalgebra) vectors and matrices.
+//!
+//! Every function returns the same container type it is given, so
+//! dynamically sized [`DVector`](::nalgebra::DVector) and
+//! [`DMatrix`](::nalgebra::DMatrix) values and statically sized
+//! [`SVector`](::nalgebra::SVector) and [`SMatrix`](::nalgebra::SMatrix)
+//! values all round-trip without conversion. Columns are contiguous in
+//! nalgebra's column-major storage and are transformed in place; rows
+//! are gathered into a scratch buffer.
+//!
+//! Enable with the `nalgebra` feature.
+
+use ::nalgebra::allocator::Allocator;
+use ::nalgebra::{DefaultAllocator, Dim, Matrix, OMatrix, OVector, Scalar, Storage, Vector};
+
+use crate::{Error, Ordering, Plan, WalshElement};
+
+/// Return the transform of the vector `v`.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use nalgebra::{DVector, Vector4};
+/// use fwt::Ordering;
+///
+/// let v = Vector4::new(1, 0, 0, 0);
+/// assert_eq!(fwt::nalgebra::transform_vector(&v, Ordering::Sequency), Ok(Vector4::new(1, 1, 1, 1)));
+///
+/// let d = DVector::from_vec(vec![0.0, 0.0, 1.0, 0.0]);
+/// let spectrum = fwt::nalgebra::transform_vector(&d, Ordering::Hadamard).unwrap();
+/// assert_eq!(spectrum.as_slice(), &[1.0, 1.0, -1.0, -1.0]);
+/// ```
+pub fn transform_vector<T, R, S>(
+    v: &Vector<T, R, S>,
+    ordering: Ordering,
+) -> Result<OVector<T, R>, Error>
+where
+    T: WalshElement + Scalar,
+    R: Dim,
+    S: Storage<T, R>,
+    DefaultAllocator: Allocator<R>,
+{
+    transform_columns(v, ordering)
+}
+
+/// Return `m` with every column transformed.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the number of rows is not a power
+/// of 2.
+pub fn transform_columns<T, R, C, S>(
+    m: &Matrix<T, R, C, S>,
+    ordering: Ordering,
+) -> Result<OMatrix<T, R, C>, Error>
+where
+    T: WalshElement + Scalar,
+    R: Dim,
+    C: Dim,
+    S: Storage<T, R, C>,
+    DefaultAllocator: Allocator<R, C>,
+{
+    let plan = Plan::new(m.nrows(), ordering)?;
+    let mut out = m.clone_owned();
+    // Owned storage is contiguous and column-major.
+    for column in out.as_mut_slice().chunks_exact_mut(plan.length()) {
+        plan.execute(column)?;
+    }
+    Ok(out)
+}
+
+/// Return `m` with every row transformed.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the number of columns is not a
+/// power of 2.
+pub fn transform_rows<T, R, C, S>(
+    m: &Matrix<T, R, C, S>,
+    ordering: Ordering,
+) -> Result<OMatrix<T, R, C>, Error>
+where
+    T: WalshElement + Scalar,
+    R: Dim,
+    C: Dim,
+    S: Storage<T, R, C>,
+    DefaultAllocator: Allocator<R, C>,
+{
+    let plan = Plan::new(m.ncols(), ordering)?;
+    let mut out = m.clone_owned();
+    let mut scratch = Vec::with_capacity(plan.length());
+    for i in 0..out.nrows() {
+        scratch.clear();
+        scratch.extend(out.row(i).iter().copied());
+        plan.execute(&mut scratch)?;
+        for (x, &y) in out.row_mut(i).iter_mut().zip(&scratch) {
+            *x = y;
+        }
+    }
+    Ok(out)
+}
+
+/// Return the 2D transform of `m`, transforming every row and every
+/// column.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if either dimension is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use nalgebra::Matrix2x4;
+/// use fwt::Ordering;
+///
+/// let m = Matrix2x4::new(3, 1, 4, 1, 5, 9, 2, 6);
+/// let spectrum = fwt::nalgebra::transform_matrix(&m, Ordering::Sequency).unwrap();
+/// let flat = fwt::fwt2(&[3, 1, 4, 1, 5, 9, 2, 6], 2, 4, Ordering::Sequency).unwrap();
+/// assert_eq!(spectrum, Matrix2x4::from_row_slice(&flat));
+/// ```
+pub fn transform_matrix<T, R, C, S>(
+    m: &Matrix<T, R, C, S>,
+    ordering: Ordering,
+) -> Result<OMatrix<T, R, C>, Error>
+where
+    T: WalshElement + Scalar,
+    R: Dim,
+    C: Dim,
+    S: Storage<T, R, C>,
+    DefaultAllocator: Allocator<R, C>,
+{
+    let columns = transform_columns(m, ordering)?;
+    transform_rows(&columns, ordering)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::nalgebra::{DMatrix, DVector, SVector};
+
+    #[test]
+    fn test_containers() {
+        let v = SVector::<i32, 8>::from_fn(|i, _| (i as i32 * 3) % 5);
+        let expected = crate::sequency(v.as_slice()).unwrap();
+        assert_eq!(
+            transform_vector(&v, Ordering::Sequency).unwrap().as_slice(),
+            &expected[..]
+        );
+        let odd = SVector::<i32, 3>::zeros();
+        assert_eq!(
+            transform_vector(&odd, Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(3))
+        );
+
+        let m = DMatrix::from_fn(4, 2, |r, c| (r * 2 + c) as f64);
+        let cols = transform_columns(&m, Ordering::Hadamard).unwrap();
+        assert_eq!(
+            cols.column(1).as_slice(),
+            &crate::hadamard(&[1.0, 3.0, 5.0, 7.0]).unwrap()[..]
+        );
+        let rows = transform_rows(&m, Ordering::Hadamard).unwrap();
+        assert_eq!(
+            rows.row(3).iter().copied().collect::<Vec<_>>(),
+            vec![13.0, -1.0]
+        );
+        assert_eq!(
+            transform_matrix(&DMatrix::<f64>::zeros(4, 3), Ordering::Hadamard),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        let d = DVector::from_vec(vec![1i64]);
+        assert_eq!(transform_vector(&d, Ordering::Hadamard), Ok(d.clone()));
+    }
+}
@@ -0,0 +1,131 @@
+//! Walsh transforms of [`ndarray`](::ndarray) arrays.
+//!
+//! The functions here transform an array in place along one [`Axis`] or
+//! along every axis, for any element type implementing
+//! [`WalshElement`]. Arrays keep their memory layout: lanes that are
+//! contiguous are transformed directly, and strided lanes are gathered
+//! into a scratch buffer, transformed, and written back.
+//!
+//! Enable with the `ndarray` feature.
+
+use ::ndarray::{ArrayBase, Axis, DataMut, Dimension};
+
+use crate::{Error, Ordering, Plan, WalshElement};
+
+/// Transform every lane of `array` along `axis` in place.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `axis` is not a
+/// power of 2, in which case `array` is unchanged.
+///
+/// # Panics
+///
+/// Panics if `axis` is out of bounds.
+///
+/// # Example
+///
+/// ```
+/// use ndarray::{array, Axis};
+/// use fwt::Ordering;
+///
+/// let mut a = array![[1, 0, 0, 0], [0, 1, 0, 0]];
+/// fwt::ndarray::transform_axis(&mut a, Axis(1), Ordering::Hadamard).unwrap();
+/// assert_eq!(a, array![[1, 1, 1, 1], [1, -1, 1, -1]]);
+/// fwt::ndarray::transform_axis(&mut a, Axis(0), Ordering::Hadamard).unwrap();
+/// assert_eq!(a, array![[2, 0, 2, 0], [0, 2, 0, 2]]);
+/// ```
+pub fn transform_axis<S, D, T>(
+    array: &mut ArrayBase<S, D>,
+    axis: Axis,
+    ordering: Ordering,
+) -> Result<(), Error>
+where
+    S: DataMut<Elem = T>,
+    D: Dimension,
+    T: WalshElement,
+{
+    let plan = Plan::new(array.len_of(axis), ordering)?;
+    let mut scratch = Vec::new();
+    for mut lane in array.lanes_mut(axis) {
+        if let Some(slice) = lane.as_slice_mut() {
+            plan.execute(slice)?;
+        } else {
+            scratch.clear();
+            scratch.extend(lane.iter().copied());
+            plan.execute(&mut scratch)?;
+            for (x, &y) in lane.iter_mut().zip(&scratch) {
+                *x = y;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Transform `array` in place along every axis, giving its
+/// N-dimensional transform.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if any axis length is not a power of
+/// 2, in which case `array` is unchanged.
+///
+/// # Example
+///
+/// ```
+/// use ndarray::Array3;
+/// use fwt::Ordering;
+///
+/// let mut a = Array3::from_elem((2, 4, 8), 1.0);
+/// fwt::ndarray::transform_all(&mut a, Ordering::Sequency).unwrap();
+/// assert_eq!(a[[0, 0, 0]], 64.0);
+/// assert_eq!(a.iter().filter(|&&x| x != 0.0).count(), 1);
+/// ```
+pub fn transform_all<S, D, T>(array: &mut ArrayBase<S, D>, ordering: Ordering) -> Result<(), Error>
+where
+    S: DataMut<Elem = T>,
+    D: Dimension,
+    T: WalshElement,
+{
+    if let Some(&bad) = array.shape().iter().find(|&&d| !crate::power_of_2(d)) {
+        return Err(Error::NotPowerOfTwo(bad));
+    }
+    for axis in (0..array.ndim()).rev() {
+        transform_axis(array, Axis(axis), ordering)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::ndarray::{Array, ShapeBuilder};
+
+    #[test]
+    fn test_matches_fwtn_in_any_layout() {
+        let shape = [4, 2, 8];
+        let data: Vec<i64> = (0..64).map(|i| (i * 5) % 9 - 4).collect();
+        let expected = crate::fwtn(&data, &shape, Ordering::Sequency).unwrap();
+        let standard = Array::from_shape_vec(shape, data.clone()).unwrap();
+        let mut fortran = Array::zeros(shape.f());
+        fortran.assign(&standard);
+        for mut array in [standard, fortran] {
+            transform_all(&mut array, Ordering::Sequency).unwrap();
+            assert_eq!(array.iter().copied().collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_views_and_errors() {
+        let mut a = Array::from_shape_vec((3, 4), (0..12).collect::<Vec<i32>>()).unwrap();
+        assert_eq!(
+            transform_all(&mut a, Ordering::Hadamard),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(a[[2, 3]], 11);
+        // A strided view transforms only the selected columns.
+        let mut view = a.slice_mut(::ndarray::s![.., ..;2]);
+        transform_axis(&mut view, Axis(1), Ordering::Hadamard).unwrap();
+        assert_eq!(a.row(0).to_vec(), vec![2, 1, -2, 3]);
+    }
+}
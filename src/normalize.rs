@@ -0,0 +1,38 @@
+/// Division of a transform result by the transform length.
+///
+/// Floating-point types divide directly. Integer types only succeed when
+/// the division is exact, so that an inverse transform of integer data
+/// never silently truncates.
+pub trait Normalize: Sized {
+    /// Return `self / length`, or `None` if that cannot be represented
+    /// exactly in `Self`.
+    fn normalize(self, length: usize) -> Option<Self>;
+}
+
+macro_rules! normalize_float {
+    ($($t:ty),*) => {$(
+        impl Normalize for $t {
+            fn normalize(self, length: usize) -> Option<Self> {
+                Some(self / length as $t)
+            }
+        }
+    )*};
+}
+
+macro_rules! normalize_int {
+    ($($t:ty),*) => {$(
+        impl Normalize for $t {
+            fn normalize(self, length: usize) -> Option<Self> {
+                let length = <$t>::try_from(length).ok()?;
+                if self % length == 0 {
+                    Some(self / length)
+                } else {
+                    None
+                }
+            }
+        }
+    )*};
+}
+
+normalize_float!(f32, f64);
+normalize_int!(i8, i16, i32, i64, i128, isize);
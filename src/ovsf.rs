@@ -0,0 +1,235 @@
+//! Orthogonal variable spreading factor (OVSF) code assignment.
+//!
+//! OVSF codes are Walsh functions arranged in a binary tree: the code
+//! `C(2s, 2k)` is `C(s, k)` followed by itself, and `C(2s, 2k + 1)` is
+//! `C(s, k)` followed by its negation. Codes of different lengths remain
+//! orthogonal as long as neither is an ancestor of the other in the tree,
+//! which lets users with different data rates share a channel. A
+//! [`CodeTree`] tracks which codes are in use and hands out codes that
+//! preserve that constraint.
+
+/// A single OVSF code, identified by its spreading factor (code length)
+/// and its index among the codes of that length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Code {
+    spreading_factor: usize,
+    index: usize,
+}
+
+impl Code {
+    /// Construct the code with the given spreading factor and index, or
+    /// `None` if the spreading factor is not a power of 2 or the index
+    /// is out of range.
+    pub fn new(spreading_factor: usize, index: usize) -> Option<Code> {
+        if crate::power_of_2(spreading_factor) && index < spreading_factor {
+            Some(Code {
+                spreading_factor,
+                index,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The length of the code in chips.
+    pub fn spreading_factor(&self) -> usize {
+        self.spreading_factor
+    }
+
+    /// The position of the code among codes of the same length.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return the ±1 chip sequence of the code.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let code = fwt::Code::new(4, 1).unwrap();
+    /// assert_eq!(code.chips(), vec![1, 1, -1, -1]);
+    /// ```
+    pub fn chips(&self) -> Vec<i8> {
+        let mut chips = vec![1i8];
+        let mut bit = self.spreading_factor >> 1;
+        while bit > 0 {
+            let negate = self.index & bit != 0;
+            let half = chips.len();
+            for i in 0..half {
+                let c = chips[i];
+                chips.push(if negate { -c } else { c });
+            }
+            bit >>= 1;
+        }
+        chips
+    }
+
+    /// Determine whether `self` is `other` or one of its ancestors in the
+    /// code tree, in which case the two codes are not orthogonal.
+    pub fn covers(&self, other: &Code) -> bool {
+        other.spreading_factor >= self.spreading_factor
+            && other.index / (other.spreading_factor / self.spreading_factor) == self.index
+    }
+
+    /// Determine whether two codes may be used simultaneously.
+    pub fn is_orthogonal_to(&self, other: &Code) -> bool {
+        !self.covers(other) && !other.covers(self)
+    }
+}
+
+/// Tracks the codes of an OVSF tree that are currently assigned.
+///
+/// # Example
+///
+/// ```
+/// let mut tree = fwt::CodeTree::new(8).unwrap();
+/// let voice = tree.assign(8).unwrap();
+/// let data = tree.assign(2).unwrap();
+/// assert!(voice.is_orthogonal_to(&data));
+/// assert_eq!(tree.used_capacity(), 0.625);
+/// assert_eq!(tree.available(2), 0);
+/// assert!(tree.release(&data));
+/// assert_eq!(tree.available(2), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CodeTree {
+    max_spreading_factor: usize,
+    assigned: Vec<Code>,
+}
+
+impl CodeTree {
+    /// Create an empty tree whose leaves have length
+    /// `max_spreading_factor`, or `None` if that is not a power of 2.
+    pub fn new(max_spreading_factor: usize) -> Option<CodeTree> {
+        if crate::power_of_2(max_spreading_factor) {
+            Some(CodeTree {
+                max_spreading_factor,
+                assigned: Vec::new(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The longest code length the tree supports.
+    pub fn max_spreading_factor(&self) -> usize {
+        self.max_spreading_factor
+    }
+
+    /// The codes currently in use, in order of assignment.
+    pub fn assigned(&self) -> &[Code] {
+        &self.assigned
+    }
+
+    /// Determine whether `code` could be assigned without violating
+    /// orthogonality with the codes already in use.
+    pub fn is_available(&self, code: &Code) -> bool {
+        code.spreading_factor <= self.max_spreading_factor
+            && self.assigned.iter().all(|c| c.is_orthogonal_to(code))
+    }
+
+    /// Assign the lowest-indexed free code with the given spreading
+    /// factor, or return `None` if there is no such code.
+    pub fn assign(&mut self, spreading_factor: usize) -> Option<Code> {
+        if spreading_factor > self.max_spreading_factor {
+            return None;
+        }
+        let code = (0..spreading_factor)
+            .filter_map(|index| Code::new(spreading_factor, index))
+            .find(|code| self.is_available(code))?;
+        self.assigned.push(code);
+        Some(code)
+    }
+
+    /// Assign a specific code, returning `false` if it conflicts with a
+    /// code already in use.
+    pub fn assign_code(&mut self, code: Code) -> bool {
+        if self.is_available(&code) {
+            self.assigned.push(code);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Release a previously assigned code, returning `false` if it was
+    /// not in use.
+    pub fn release(&mut self, code: &Code) -> bool {
+        match self.assigned.iter().position(|c| c == code) {
+            Some(i) => {
+                self.assigned.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The number of codes with the given spreading factor that could
+    /// currently be assigned.
+    pub fn available(&self, spreading_factor: usize) -> usize {
+        (0..spreading_factor)
+            .filter_map(|index| Code::new(spreading_factor, index))
+            .filter(|code| self.is_available(code))
+            .count()
+    }
+
+    /// The fraction of the tree's total capacity in use, where a code
+    /// with spreading factor `s` consumes `1/s` of the capacity.
+    pub fn used_capacity(&self) -> f64 {
+        self.assigned
+            .iter()
+            .map(|c| 1.0 / c.spreading_factor as f64)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chips_match_walsh_tree() {
+        let rows: Vec<Vec<i8>> = (0..4).map(|k| Code::new(4, k).unwrap().chips()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec![1, 1, 1, 1],
+                vec![1, 1, -1, -1],
+                vec![1, -1, 1, -1],
+                vec![1, -1, -1, 1],
+            ]
+        );
+        assert_eq!(Code::new(1, 0).unwrap().chips(), vec![1]);
+        assert_eq!(Code::new(6, 0), None);
+        assert_eq!(Code::new(4, 4), None);
+    }
+
+    #[test]
+    fn test_mixed_length_assignments_are_orthogonal() {
+        let mut tree = CodeTree::new(16).unwrap();
+        let codes: Vec<Code> = [4, 16, 8, 2, 16]
+            .iter()
+            .map(|&sf| tree.assign(sf).expect("tree full"))
+            .collect();
+        for (i, a) in codes.iter().enumerate() {
+            for b in &codes[i + 1..] {
+                let (short, long) = if a.spreading_factor() <= b.spreading_factor() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                let s = short.chips();
+                let dot: i32 = long
+                    .chips()
+                    .iter()
+                    .enumerate()
+                    .map(|(t, &c)| (c * s[t % s.len()]) as i32)
+                    .sum();
+                assert_eq!(dot, 0, "{:?} and {:?} are not orthogonal", a, b);
+            }
+        }
+        assert_eq!(tree.available(2), 0);
+        assert!(tree.assign(2).is_none());
+        assert!(!tree.assign_code(Code::new(8, 0).unwrap()));
+    }
+}
@@ -0,0 +1,218 @@
+//! Peak-to-average power ratio (PAPR) of multicarrier Walsh waveforms.
+//!
+//! A waveform built by summing several Walsh functions ("carriers"), each
+//! weighted by a data symbol, can have a large peak relative to its mean
+//! power. The functions here measure that ratio, summarize it over all
+//! BPSK (±1) symbol patterns for a set of carriers, and search for carrier
+//! subsets whose worst-case PAPR is low. Carriers are identified by their
+//! sequency index.
+
+use crate::{power_of_2, sequency};
+
+/// The largest number of carriers for which [`papr_statistics`] will
+/// enumerate symbol patterns.
+pub const MAX_ENUMERATED_CARRIERS: usize = 20;
+
+/// Return the ratio of peak to mean power of `signal`, or `None` if the
+/// signal is empty or has zero power.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(fwt::papr(&[1.0, -1.0, 1.0, -1.0]), Some(1.0));
+/// assert_eq!(fwt::papr(&[2.0, 0.0, 0.0, 0.0]), Some(4.0));
+/// assert_eq!(fwt::papr(&[0.0, 0.0]), None);
+/// ```
+pub fn papr(signal: &[f64]) -> Option<f64> {
+    if signal.is_empty() {
+        return None;
+    }
+    let powers = signal.iter().map(|x| x * x);
+    let peak = powers.clone().fold(0.0, f64::max);
+    let mean = powers.sum::<f64>() / signal.len() as f64;
+    if mean > 0.0 {
+        Some(peak / mean)
+    } else {
+        None
+    }
+}
+
+/// Express a power ratio in decibels.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(fwt::to_db(100.0), 20.0);
+/// ```
+pub fn to_db(ratio: f64) -> f64 {
+    10.0 * ratio.log10()
+}
+
+/// Return the length-`n` waveform `Σ amplitudes[i] · wal(carriers[i], ·)`,
+/// where `wal(k, ·)` is the Walsh function with sequency `k`. Returns
+/// `None` if `n` is not a power of 2, a carrier is out of range, or the
+/// two slices differ in length.
+///
+/// # Example
+///
+/// ```
+/// let w = fwt::walsh_carrier_sum(4, &[0, 1], &[1.0, 1.0]).unwrap();
+/// assert_eq!(w, vec![2.0, 2.0, 0.0, 0.0]);
+/// ```
+pub fn walsh_carrier_sum(n: usize, carriers: &[usize], amplitudes: &[f64]) -> Option<Vec<f64>> {
+    if !power_of_2(n) || carriers.len() != amplitudes.len() {
+        return None;
+    }
+    let mut spectrum = vec![0.0; n];
+    for (&k, &a) in carriers.iter().zip(amplitudes) {
+        *spectrum.get_mut(k)? += a;
+    }
+    // The sequency-ordered Walsh matrix is symmetric, so transforming a
+    // spectrum synthesizes the corresponding sum of Walsh functions.
+    sequency(&spectrum)
+}
+
+/// Summary of the PAPR of a set of Walsh carriers over every BPSK symbol
+/// pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaprStatistics {
+    values: Vec<f64>,
+}
+
+impl PaprStatistics {
+    /// The PAPR of each distinct symbol pattern, in ascending order.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// The smallest PAPR over all symbol patterns.
+    pub fn min(&self) -> f64 {
+        self.values[0]
+    }
+
+    /// The largest (worst-case) PAPR over all symbol patterns.
+    pub fn max(&self) -> f64 {
+        self.values[self.values.len() - 1]
+    }
+
+    /// The average PAPR over all symbol patterns.
+    pub fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    /// The complementary CDF: the fraction of symbol patterns whose PAPR
+    /// exceeds `threshold`.
+    pub fn ccdf(&self, threshold: f64) -> f64 {
+        let below = self.values.partition_point(|&v| v <= threshold);
+        (self.values.len() - below) as f64 / self.values.len() as f64
+    }
+}
+
+/// Compute the PAPR of the length-`n` sum of the given carriers for every
+/// ±1 symbol pattern. Patterns that differ only by a global sign have the
+/// same PAPR, so `2^(m-1)` patterns are evaluated for `m` carriers.
+/// Returns `None` if `carriers` is empty or longer than
+/// [`MAX_ENUMERATED_CARRIERS`], or under the conditions of
+/// [`walsh_carrier_sum`].
+///
+/// # Example
+///
+/// ```
+/// let stats = fwt::papr_statistics(8, &[1, 2]).unwrap();
+/// assert_eq!(stats.values(), &[2.0, 2.0]);
+/// assert_eq!(stats.ccdf(1.5), 1.0);
+/// ```
+pub fn papr_statistics(n: usize, carriers: &[usize]) -> Option<PaprStatistics> {
+    let m = carriers.len();
+    if m == 0 || m > MAX_ENUMERATED_CARRIERS {
+        return None;
+    }
+    let mut values = Vec::with_capacity(1 << (m - 1));
+    let mut symbols = vec![1.0; m];
+    for pattern in 0..(1usize << (m - 1)) {
+        for (i, s) in symbols.iter_mut().enumerate().skip(1) {
+            *s = if pattern & (1 << (i - 1)) == 0 { 1.0 } else { -1.0 };
+        }
+        let waveform = walsh_carrier_sum(n, carriers, &symbols)?;
+        values.push(papr(&waveform)?);
+    }
+    values.sort_by(f64::total_cmp);
+    Some(PaprStatistics { values })
+}
+
+/// Greedily choose `count` carriers out of the `n` Walsh functions of
+/// length `n` so that the worst-case PAPR over BPSK symbol patterns stays
+/// low. Starting from the DC carrier, each step adds the carrier that
+/// minimizes the resulting worst case, with ties going to the lowest
+/// sequency. Returns the chosen carriers in the order they were added, or
+/// `None` if `n` is not a power of 2 or `count` is zero, exceeds `n`, or
+/// exceeds [`MAX_ENUMERATED_CARRIERS`].
+///
+/// The search is heuristic and costs `O(count · n · 2^count · n log n)`,
+/// so it is intended for the small code sets typical of design studies.
+///
+/// # Example
+///
+/// ```
+/// let subset = fwt::low_papr_subset(8, 2).unwrap();
+/// assert_eq!(subset.len(), 2);
+/// let worst = fwt::papr_statistics(8, &subset).unwrap().max();
+/// assert!(worst <= 2.0);
+/// ```
+pub fn low_papr_subset(n: usize, count: usize) -> Option<Vec<usize>> {
+    if !power_of_2(n) || count == 0 || count > n || count > MAX_ENUMERATED_CARRIERS {
+        return None;
+    }
+    let mut chosen = vec![0];
+    while chosen.len() < count {
+        let mut best: Option<(f64, usize)> = None;
+        for candidate in 1..n {
+            if chosen.contains(&candidate) {
+                continue;
+            }
+            chosen.push(candidate);
+            let worst = papr_statistics(n, &chosen)?.max();
+            chosen.pop();
+            if best.is_none_or(|(b, _)| worst < b) {
+                best = Some((worst, candidate));
+            }
+        }
+        chosen.push(best?.1);
+    }
+    Some(chosen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_carrier_is_constant_envelope() {
+        for k in 0..16 {
+            let w = walsh_carrier_sum(16, &[k], &[3.0]).unwrap();
+            assert_eq!(papr(&w), Some(1.0));
+        }
+        assert_eq!(walsh_carrier_sum(16, &[16], &[1.0]), None);
+        assert_eq!(walsh_carrier_sum(12, &[1], &[1.0]), None);
+    }
+
+    #[test]
+    fn test_statistics_cover_all_patterns() {
+        // Three carriers whose sum always peaks at the full amplitude.
+        let stats = papr_statistics(4, &[0, 1, 2]).unwrap();
+        assert_eq!(stats.values().len(), 4);
+        assert_eq!(stats.max(), 3.0);
+        assert!(stats.min() <= stats.mean() && stats.mean() <= stats.max());
+        assert_eq!(stats.ccdf(stats.max()), 0.0);
+        assert_eq!(papr_statistics(4, &[]), None);
+    }
+
+    #[test]
+    fn test_greedy_search_beats_worst_choice() {
+        let subset = low_papr_subset(16, 4).unwrap();
+        let greedy = papr_statistics(16, &subset).unwrap().max();
+        let naive = papr_statistics(16, &[0, 1, 2, 3]).unwrap().max();
+        assert!(greedy <= naive);
+        assert_eq!(low_papr_subset(16, 0), None);
+    }
+}
@@ -0,0 +1,410 @@
+//! Multithreaded execution of plans with rayon.
+//!
+//! The slice is split into cache-sized blocks. Stages whose butterflies
+//! stay within a block run one block per task, and the remaining stages,
+//! which pair whole blocks, run one stage at a time with every pair of
+//! blocks a task. Every element receives exactly the additions of the
+//! serial kernels in the same order, so results are bit-identical to
+//! them for any number of threads.
+
+use std::sync::Arc;
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::{kernel, Algorithm, Ordering, Plan, WalshElement};
+
+// Bounds on the block size: small enough that a block stays in the L2
+// cache, large enough that tasks amortize their scheduling.
+const MIN_BLOCK: usize = 1 << 10;
+const MAX_BLOCK: usize = 1 << 15;
+
+// The width in bytes of the square tiles of the 2D transposes, so that
+// each tile is read and written a few cache lines per row and its source
+// rows stay in the L1 cache.
+const TRANSPOSE_BYTES: usize = 256;
+
+/// Transform `v` in parallel on the current rayon pool, first applying
+/// `swaps` for the sequency ordering.
+pub(crate) fn execute<T>(
+    v: &mut [T],
+    ordering: Ordering,
+    swaps: &[(usize, usize)],
+    algorithm: Algorithm,
+) where
+    T: WalshElement + Send,
+{
+    // Aim for several blocks per thread so the load balances.
+    let target = (v.len() / (4 * rayon::current_num_threads())).max(1);
+    let block = (1 << target.ilog2())
+        .clamp(MIN_BLOCK, MAX_BLOCK)
+        .min(v.len());
+    execute_blocked(v, ordering, swaps, algorithm, block);
+}
+
+/// The rayon pool parallel work runs on: the current one, or one given by
+/// the caller. Handles are equal when they name the same pool.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Pool(Option<Arc<ThreadPool>>);
+
+impl Pool {
+    pub(crate) fn new(pool: Arc<ThreadPool>) -> Pool {
+        Pool(Some(pool))
+    }
+
+    /// A dedicated pool of `threads` threads, or of rayon's default number
+    /// if `threads` is 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system cannot create the threads.
+    pub(crate) fn with_threads(threads: usize) -> Pool {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("the operating system can create the pool's threads");
+        Pool::new(Arc::new(pool))
+    }
+
+    pub(crate) fn get(&self) -> Option<&Arc<ThreadPool>> {
+        self.0.as_ref()
+    }
+
+    /// Run `f` on this pool, so that rayon's parallel iterators inside it
+    /// use this pool's threads.
+    pub(crate) fn install<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match &self.0 {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+}
+
+impl PartialEq for Pool {
+    fn eq(&self, other: &Pool) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for Pool {}
+
+fn execute_blocked<T>(
+    v: &mut [T],
+    ordering: Ordering,
+    swaps: &[(usize, usize)],
+    algorithm: Algorithm,
+    block: usize,
+) where
+    T: WalshElement + Send,
+{
+    let length = v.len();
+    if length == 0 {
+        return;
+    }
+    match ordering {
+        Ordering::Sequency => {
+            permute(v, swaps);
+            let mut offset = length;
+            while offset > block {
+                cross_block_stage(v, offset, block, true);
+                offset >>= 1;
+            }
+            v.par_chunks_mut(block)
+                .enumerate()
+                .for_each(|(i, b)| match algorithm {
+                    Algorithm::Radix2 => kernel::sequency_stages_from(b, block, i),
+                    Algorithm::Radix4 => kernel::sequency_stages_fused(b, 2, i),
+                    Algorithm::Radix8 => kernel::sequency_stages_fused(b, 3, i),
+                    Algorithm::Recursive => kernel::stages_recursive(b, Ordering::Sequency, i),
+                    Algorithm::FourStep => kernel::stages_four_step(b, Ordering::Sequency, i),
+                    Algorithm::Tiled => kernel::stages_tiled(b, Ordering::Sequency, i),
+                });
+        }
+        Ordering::Hadamard => {
+            v.par_chunks_mut(block).for_each(|b| match algorithm {
+                Algorithm::Radix2 => kernel::hadamard(b),
+                Algorithm::Radix4 => kernel::hadamard_fused(b, 2),
+                Algorithm::Radix8 => kernel::hadamard_fused(b, 3),
+                Algorithm::Recursive => kernel::stages_recursive(b, Ordering::Hadamard, 0),
+                Algorithm::FourStep => kernel::stages_four_step(b, Ordering::Hadamard, 0),
+                Algorithm::Tiled => kernel::stages_tiled(b, Ordering::Hadamard, 0),
+            });
+            let mut offset = block << 1;
+            while offset <= length {
+                cross_block_stage(v, offset, block, false);
+                offset <<= 1;
+            }
+        }
+    }
+}
+
+/// Replace the row-major matrix `v` with its 2D transform, with
+/// `row_plan` for its rows and `col_plan` for its columns: transform the
+/// rows in parallel, transpose into a scratch matrix, transform its rows,
+/// which hold the original columns, in parallel, and transpose back.
+pub(crate) fn transform_2d<T>(v: &mut [T], row_plan: &Plan, col_plan: &Plan)
+where
+    T: WalshElement + Send + Sync,
+{
+    let (rows, cols) = (col_plan.length(), row_plan.length());
+    v.par_chunks_mut(cols).for_each(|row| {
+        row_plan
+            .execute(row)
+            .expect("every row has the plan length");
+    });
+    let mut t = v.to_vec();
+    transpose(v, &mut t, rows, cols);
+    t.par_chunks_mut(rows).for_each(|col| {
+        col_plan
+            .execute(col)
+            .expect("every column has the plan length");
+    });
+    transpose(&t, v, cols, rows);
+}
+
+// Write the transpose of the `rows × cols` row-major matrix `src` to
+// `dst`, in parallel over bands of output rows one tile high, each filled
+// one square tile at a time.
+fn transpose<T>(src: &[T], dst: &mut [T], rows: usize, cols: usize)
+where
+    T: Copy + Send + Sync,
+{
+    let tile = (TRANSPOSE_BYTES / std::mem::size_of::<T>().max(1)).max(1);
+    dst.par_chunks_mut(tile * rows)
+        .enumerate()
+        .for_each(|(band, out)| {
+            let first = band * tile;
+            for r0 in (0..rows).step_by(tile) {
+                let tile_rows = r0..(r0 + tile).min(rows);
+                for (c, line) in out.chunks_exact_mut(rows).enumerate() {
+                    for r in tile_rows.clone() {
+                        line[r] = src[r * cols + first + c];
+                    }
+                }
+            }
+        });
+}
+
+// Apply the stage whose groups have `offset > block` elements, splitting
+// each pair of half-groups into blocks. Sequency groups of odd index use
+// crossed butterflies.
+fn cross_block_stage<T>(v: &mut [T], offset: usize, block: usize, sequency: bool)
+where
+    T: WalshElement + Send,
+{
+    let lag = offset >> 1;
+    v.par_chunks_mut(offset).enumerate().for_each(|(group, g)| {
+        let crossed = sequency && group & 1 == 1;
+        let (a, b) = g.split_at_mut(lag);
+        a.par_chunks_mut(block)
+            .zip(b.par_chunks_mut(block))
+            .for_each(|(x, y)| {
+                if crossed {
+                    T::butterfly_crossed(x, y);
+                } else {
+                    T::butterfly(x, y);
+                }
+            });
+    });
+}
+
+// A pointer to the start of a slice, shared between the threads applying
+// disjoint swaps.
+struct Shared<T>(*mut T);
+
+// SAFETY: the pointer is only used to access disjoint elements, which are
+// `Send`, from each thread.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+// Apply the exchanges in `swaps`, which must be in bounds and pairwise
+// disjoint, as the bit-reversal swaps of a plan are.
+fn permute<T: Send>(v: &mut [T], swaps: &[(usize, usize)]) {
+    let length = v.len();
+    let base = Shared(v.as_mut_ptr());
+    swaps.par_chunks(4096).for_each(|chunk| {
+        for &(i, j) in chunk {
+            assert!(i < length && j < length);
+            // SAFETY: both indices are in bounds, and no other swap
+            // touches either of them, so no element is accessed by two
+            // threads.
+            unsafe { std::ptr::swap(base.get().add(i), base.get().add(j)) };
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_matches_serial_bit_exact() {
+        for log_length in 0..12 {
+            let length = 1 << log_length;
+            let input: Vec<f64> = (0..length)
+                .map(|x| ((x * 37 + 11) % 101) as f64 / 7.3)
+                .collect();
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                for algorithm in [
+                    Algorithm::Radix2,
+                    Algorithm::Radix4,
+                    Algorithm::Radix8,
+                    Algorithm::Recursive,
+                    Algorithm::FourStep,
+                    Algorithm::Tiled,
+                ] {
+                    let plan = Plan::new(length, ordering)
+                        .unwrap()
+                        .with_algorithm(algorithm);
+                    let reference = plan.transform(&input).unwrap();
+                    let swaps: Vec<(usize, usize)> = match ordering {
+                        Ordering::Sequency => kernel::bit_reverse_swaps(length).collect(),
+                        Ordering::Hadamard => Vec::new(),
+                    };
+                    for log_block in 0..=log_length {
+                        let mut v = input.clone();
+                        execute_blocked(&mut v, ordering, &swaps, algorithm, 1 << log_block);
+                        assert!(
+                            v.iter()
+                                .zip(&reference)
+                                .all(|(a, b)| a.to_bits() == b.to_bits()),
+                            "{ordering:?} {algorithm:?} length {length} block {}",
+                            1 << log_block
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_transform_2d_matches_fwt2_bit_exact() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(3)
+            .build()
+            .unwrap();
+        for (rows, cols) in [(1, 1), (1, 64), (64, 1), (8, 32), (128, 16), (64, 64)] {
+            let input: Vec<f64> = (0..rows * cols)
+                .map(|x| ((x * 37 + 11) % 101) as f64 / 7.3)
+                .collect();
+            let mut t = vec![0.0; rows * cols];
+            transpose(&input, &mut t, rows, cols);
+            for (i, &x) in input.iter().enumerate() {
+                assert_eq!(t[(i % cols) * rows + i / cols], x);
+            }
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let result = pool
+                    .install(|| crate::fwt2_parallel(&input, rows, cols, ordering))
+                    .unwrap();
+                let expected = crate::fwt2(&input, rows, cols, ordering).unwrap();
+                assert!(result
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| a.to_bits() == b.to_bits()));
+            }
+        }
+        assert_eq!(
+            crate::fwt2_parallel(&[1; 6], 2, 3, Ordering::Hadamard),
+            Err(crate::Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            crate::fwt2_parallel(&[1; 6], 2, 4, Ordering::Hadamard),
+            Err(crate::Error::LengthMismatch {
+                expected: 8,
+                found: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_execute_on_pool() {
+        let length = 1 << 16;
+        let input: Vec<i64> = (0..length).map(|x| (x * 7919 % 1001) - 500).collect();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let plan = Plan::new(length as usize, ordering).unwrap();
+            let mut v = input.clone();
+            pool.install(|| plan.execute_parallel(&mut v)).unwrap();
+            assert_eq!(v, plan.transform(&input).unwrap());
+        }
+        let plan = Plan::new(8, Ordering::Sequency)
+            .unwrap()
+            .with_parallel_threshold(0);
+        assert_eq!(plan.parallel_threshold(), 0);
+        let mut v = [1, 2, 3, 4, 5, 6, 7, 8];
+        plan.execute_parallel(&mut v).unwrap();
+        assert_eq!(
+            v.to_vec(),
+            crate::sequency(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap()
+        );
+        assert_eq!(
+            plan.execute_parallel(&mut v[..4]),
+            Err(crate::Error::LengthMismatch {
+                expected: 8,
+                found: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_given_pools_and_thresholds() {
+        let length = 1 << 14;
+        let input: Vec<i64> = (0..length).map(|x| (x * 7919 % 1001) - 500).collect();
+        let plan = Plan::new(length as usize, Ordering::Sequency)
+            .unwrap()
+            .with_parallel_threshold(1 << 12)
+            .with_threads(2);
+        let expected = plan.transform(&input).unwrap();
+        let mut v = input.clone();
+        plan.execute_parallel(&mut v).unwrap();
+        assert_eq!(v, expected);
+        // Clones share the pool, and plans on other pools differ.
+        let clone = plan.clone();
+        assert!(Arc::ptr_eq(
+            clone.thread_pool().unwrap(),
+            plan.thread_pool().unwrap()
+        ));
+        assert_eq!(clone, plan);
+        assert_ne!(plan.clone().with_threads(2), plan);
+        assert!(Plan::new(8, Ordering::Sequency)
+            .unwrap()
+            .thread_pool()
+            .is_none());
+
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(3).build().unwrap());
+        let dispatcher = crate::Dispatcher::new()
+            .with_parallel_threshold(1 << 10)
+            .with_thread_pool(pool);
+        assert_eq!(dispatcher.parallel_threshold(), 1 << 10);
+        assert_eq!(
+            dispatcher.choose::<i64>(1 << 9, 2),
+            crate::Backend::Parallel
+        );
+        assert_eq!(
+            crate::Dispatcher::new().choose::<i64>(1 << 9, 2),
+            crate::Backend::Simd
+        );
+        let mut v = input.repeat(2);
+        let used = dispatcher
+            .execute(&mut v, length as usize, Ordering::Sequency)
+            .unwrap();
+        assert_eq!(used, crate::Backend::Parallel);
+        assert_eq!(v, expected.repeat(2));
+    }
+}
@@ -0,0 +1,104 @@
+//! Walsh pilot sequences for multi-antenna channel estimation.
+//!
+//! Each transmit antenna sends a different Walsh function as its pilot.
+//! Because the pilots are mutually orthogonal, the least-squares estimate
+//! of every antenna's channel gain is a single coefficient of the Walsh
+//! transform of the received pilot block, so all gains are recovered with
+//! one O(*n* log(*n*)) transform.
+
+use crate::{power_of_2, sequency, walsh_row, Ordering};
+
+/// Return `antennas` mutually orthogonal ±1 pilot sequences of the given
+/// `length`, one per transmit antenna. Antenna `a` is assigned the Walsh
+/// function with sequency `a`. Returns `None` if `length` is not a power
+/// of 2 or there are more antennas than Walsh functions of that length.
+///
+/// # Example
+///
+/// ```
+/// let pilots = fwt::pilot_sequences(2, 4).unwrap();
+/// assert_eq!(pilots, vec![vec![1, 1, 1, 1], vec![1, 1, -1, -1]]);
+/// assert_eq!(fwt::pilot_sequences(5, 4), None);
+/// ```
+pub fn pilot_sequences(antennas: usize, length: usize) -> Option<Vec<Vec<i8>>> {
+    if !power_of_2(length) || antennas > length {
+        return None;
+    }
+    (0..antennas)
+        .map(|a| walsh_row(length, a, Ordering::Sequency))
+        .collect()
+}
+
+/// Estimate the channel gain from each of `antennas` transmit antennas to
+/// a single receive antenna, given the samples received while the pilots
+/// from [`pilot_sequences`] were transmitted simultaneously. Returns the
+/// least-squares gains in antenna order, or `None` if the received block
+/// length is not a power of 2 or is shorter than `antennas`.
+///
+/// # Example
+///
+/// ```
+/// let pilots = fwt::pilot_sequences(2, 4).unwrap();
+/// let received: Vec<f64> = (0..4)
+///     .map(|t| 0.5 * pilots[0][t] as f64 - 2.0 * pilots[1][t] as f64)
+///     .collect();
+/// assert_eq!(fwt::estimate_channel(&received, 2), Some(vec![0.5, -2.0]));
+/// ```
+pub fn estimate_channel(received: &[f64], antennas: usize) -> Option<Vec<f64>> {
+    let length = received.len();
+    if antennas > length {
+        return None;
+    }
+    let spectrum = sequency(received)?;
+    Some(
+        spectrum[..antennas]
+            .iter()
+            .map(|&c| c / length as f64)
+            .collect(),
+    )
+}
+
+/// Estimate the full MIMO channel matrix from the pilot blocks received
+/// on each receive antenna. Entry `[r][a]` of the result is the gain from
+/// transmit antenna `a` to receive antenna `r`. Returns `None` under the
+/// conditions of [`estimate_channel`] for any receive antenna.
+pub fn estimate_channel_matrix(received: &[Vec<f64>], antennas: usize) -> Option<Vec<Vec<f64>>> {
+    received
+        .iter()
+        .map(|r| estimate_channel(r, antennas))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pilots_are_orthogonal() {
+        let pilots = pilot_sequences(8, 8).unwrap();
+        for (i, p) in pilots.iter().enumerate() {
+            for (j, q) in pilots.iter().enumerate() {
+                let dot: i32 = p.iter().zip(q).map(|(&x, &y)| (x * y) as i32).sum();
+                assert_eq!(dot, if i == j { 8 } else { 0 });
+            }
+        }
+        assert_eq!(pilot_sequences(1, 6), None);
+    }
+
+    #[test]
+    fn test_channel_matrix_recovery() {
+        let pilots = pilot_sequences(3, 16).unwrap();
+        let gains = [vec![1.0, -0.5, 0.25], vec![0.75, 2.0, -1.0]];
+        let received: Vec<Vec<f64>> = gains
+            .iter()
+            .map(|h| {
+                (0..16)
+                    .map(|t| (0..3).map(|a| h[a] * pilots[a][t] as f64).sum())
+                    .collect()
+            })
+            .collect();
+        let estimate = estimate_channel_matrix(&received, 3).unwrap();
+        assert_eq!(estimate, gains.to_vec());
+        assert_eq!(estimate_channel(&[1.0, 2.0], 3), None);
+    }
+}
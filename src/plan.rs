@@ -0,0 +1,790 @@
+use crate::{codelet, kernel, power_of_2, Error, Normalize, Ordering, WalshElement};
+
+/// How much freedom a [`Plan`] has to trade bit-exact reproducibility for
+/// speed.
+///
+/// Every butterfly in a Walsh transform is a single addition or
+/// subtraction, and Rust never fuses or reassociates floating-point
+/// operations on its own, so the result of a transform is fully
+/// determined by which pairs of values are combined and in what order.
+/// [`Determinism::Strict`] pins that schedule to the reference radix-2
+/// algorithm used by [`hadamard`](crate::hadamard) and
+/// [`sequency`](crate::sequency), so floating-point results are
+/// bit-identical on every platform with IEEE-754 arithmetic (this
+/// excludes the x87 unit of 32-bit x86 targets without SSE2) and for any
+/// thread count.
+///
+/// A strict plan ignores its [`Algorithm`], runs plain scalar
+/// butterflies instead of vectorized ones, and never divides a transform
+/// between threads or transposes a batch. [`Determinism::Fast`] permits
+/// all of these, and any kernel that combines values in a different
+/// order, whose floating-point results may differ from the reference in
+/// the last bits. Integer results are exact either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Determinism {
+    /// Allow any kernel that computes the transform correctly.
+    #[default]
+    Fast,
+    /// Only use kernels that reproduce the reference schedule exactly.
+    Strict,
+}
+
+/// The butterfly schedule a [`Plan`] uses.
+///
+/// Plans of at most 64 elements run fully unrolled kernels, whose cost
+/// would otherwise be mostly loop overhead, whatever their algorithm; the
+/// results are the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// One pass over the data per stage.
+    Radix2,
+    /// Two stages fused per pass, halving the number of sweeps over
+    /// memory. Results are bit-identical to [`Algorithm::Radix2`].
+    Radix4,
+    /// Three stages fused per pass, with a radix-4 or radix-2 pass for
+    /// any remaining stages, for transforms much larger than the cache.
+    /// Results are bit-identical to [`Algorithm::Radix2`].
+    Radix8,
+    /// Cache-oblivious divide and conquer, which recursively splits the
+    /// transform into pieces that fit in each level of the memory
+    /// hierarchy without being tuned to its sizes. Results are
+    /// bit-identical to [`Algorithm::Radix2`].
+    Recursive,
+    /// The four-step algorithm, which views the data as a square matrix
+    /// and transforms its rows, transposes it in place, transforms the
+    /// rows again and transposes back, for transforms far larger than the
+    /// cache. Results are bit-identical to [`Algorithm::Radix2`].
+    FourStep,
+    /// Stages grouped into levels that are each applied one cache-sized
+    /// tile at a time, so the data is swept once per level rather than
+    /// once per stage. Unlike [`Algorithm::Recursive`], the tiles are
+    /// sized for a typical L2 cache. Results are bit-identical to
+    /// [`Algorithm::Radix2`].
+    Tiled,
+}
+
+impl Algorithm {
+    /// The algorithm chosen automatically for transforms of `length`
+    /// elements.
+    pub fn for_length(length: usize) -> Algorithm {
+        if length >= RECURSIVE_THRESHOLD {
+            Algorithm::Recursive
+        } else {
+            Algorithm::Radix2
+        }
+    }
+}
+
+// Above this many elements the data no longer fits in a typical L2 cache,
+// and each pass over it is bound by memory bandwidth.
+const RECURSIVE_THRESHOLD: usize = 1 << 15;
+
+// Below this many elements a transform takes tens of microseconds, too
+// little to be worth dividing between threads.
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 1 << 16;
+
+/// A reusable description of a transform of a fixed length and ordering.
+///
+/// Creating a plan performs the setup work that does not depend on the
+/// data, such as computing the index permutation used by the sequency
+/// ordering, so that repeated transforms of the same size only pay for
+/// the butterflies.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, Plan};
+///
+/// let plan = Plan::new(8, Ordering::Sequency).expect("length not a power of 2");
+/// let mut v = [0, 0, 0, 0, 0, 0, 1, 0];
+/// plan.execute(&mut v).expect("length does not match the plan");
+/// assert_eq!(v, [1, -1, 1, -1, -1, 1, -1, 1]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    length: usize,
+    ordering: Ordering,
+    swaps: Vec<(usize, usize)>,
+    determinism: Determinism,
+    algorithm: Algorithm,
+    #[cfg(feature = "parallel")]
+    parallel_threshold: usize,
+    #[cfg(feature = "parallel")]
+    pool: crate::parallel::Pool,
+}
+
+impl Plan {
+    /// Create a plan for transforms of the given length and ordering.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn new(length: usize, ordering: Ordering) -> Result<Plan, Error> {
+        if !power_of_2(length) {
+            return Err(Error::NotPowerOfTwo(length));
+        }
+        let mut swaps: Vec<(usize, usize)> = match ordering {
+            Ordering::Sequency => kernel::bit_reverse_swaps(length).collect(),
+            Ordering::Hadamard => Vec::new(),
+        };
+        swaps.shrink_to_fit();
+        Ok(Plan {
+            length,
+            ordering,
+            swaps,
+            determinism: Determinism::default(),
+            algorithm: Algorithm::for_length(length),
+            #[cfg(feature = "parallel")]
+            parallel_threshold: PARALLEL_THRESHOLD,
+            #[cfg(feature = "parallel")]
+            pool: crate::parallel::Pool::default(),
+        })
+    }
+
+    /// Return this plan using the given [`Algorithm`] in place of the
+    /// automatic choice.
+    pub fn with_algorithm(self, algorithm: Algorithm) -> Plan {
+        Plan { algorithm, ..self }
+    }
+
+    /// The butterfly schedule this plan uses.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Return this plan with the given [`Determinism`] requirement.
+    ///
+    /// A strict plan runs the radix-2 algorithm whatever its
+    /// [`algorithm`](Self::algorithm), on a single thread.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::{Determinism, Ordering, Plan};
+    ///
+    /// let plan = Plan::new(4, Ordering::Hadamard)
+    ///     .unwrap()
+    ///     .with_determinism(Determinism::Strict);
+    /// assert_eq!(plan.determinism(), Determinism::Strict);
+    /// let input = [0.1, 0.2, 0.3, 0.4];
+    /// assert_eq!(plan.transform(&input), Ok(fwt::hadamard(&input).unwrap()));
+    /// ```
+    pub fn with_determinism(self, determinism: Determinism) -> Plan {
+        Plan {
+            determinism,
+            ..self
+        }
+    }
+
+    /// The reproducibility requirement this plan was created with.
+    pub fn determinism(&self) -> Determinism {
+        self.determinism
+    }
+
+    /// The length of the slices this plan transforms.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The ordering of the transforms this plan computes.
+    pub fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    /// The number of heap bytes owned by this plan.
+    pub fn heap_bytes(&self) -> usize {
+        self.swaps.capacity() * std::mem::size_of::<(usize, usize)>()
+    }
+
+    /// Replace the contents of `v` with its transform.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `v` does not have the length
+    /// the plan was created for.
+    pub fn execute<T>(&self, v: &mut [T]) -> Result<(), Error>
+    where
+        T: WalshElement,
+    {
+        if v.len() != self.length {
+            return Err(Error::LengthMismatch {
+                expected: self.length,
+                found: v.len(),
+            });
+        }
+        if self.determinism == Determinism::Strict {
+            return self.execute_strict(v);
+        }
+        crate::no_alloc(|| self.run(v, self.algorithm));
+        Ok(())
+    }
+
+    // Replace the contents of `v` with its transform as a strict plan
+    // computes it, whatever this plan's determinism.
+    pub(crate) fn execute_strict<T>(&self, v: &mut [T]) -> Result<(), Error>
+    where
+        T: WalshElement,
+    {
+        if v.len() != self.length {
+            return Err(Error::LengthMismatch {
+                expected: self.length,
+                found: v.len(),
+            });
+        }
+        let v = crate::dispatch::as_scalar(v);
+        crate::no_alloc(|| self.run(v, Algorithm::Radix2));
+        Ok(())
+    }
+
+    // Apply the transform to `v`, of the plan's length, with `algorithm`.
+    fn run<T: WalshElement>(&self, v: &mut [T], algorithm: Algorithm) {
+        match self.ordering {
+            _ if self.length <= codelet::MAX_LENGTH => codelet::transform(v, self.ordering),
+            Ordering::Sequency => {
+                #[cfg(not(feature = "unchecked"))]
+                for &(i, j) in &self.swaps {
+                    v.swap(i, j);
+                }
+                #[cfg(feature = "unchecked")]
+                {
+                    let p = v.as_mut_ptr();
+                    for &(i, j) in &self.swaps {
+                        // SAFETY: the swaps were built by `Plan::new` for
+                        // `self.length`, which is the length of `v`.
+                        unsafe { std::ptr::swap(p.add(i), p.add(j)) };
+                    }
+                }
+                match algorithm {
+                    Algorithm::Radix2 => kernel::sequency_stages(v),
+                    Algorithm::Radix4 => kernel::sequency_stages_fused(v, 2, 0),
+                    Algorithm::Radix8 => kernel::sequency_stages_fused(v, 3, 0),
+                    Algorithm::Recursive => kernel::stages_recursive(v, Ordering::Sequency, 0),
+                    Algorithm::FourStep => kernel::stages_four_step(v, Ordering::Sequency, 0),
+                    Algorithm::Tiled => kernel::stages_tiled(v, Ordering::Sequency, 0),
+                }
+            }
+            Ordering::Hadamard => match algorithm {
+                Algorithm::Radix2 => kernel::hadamard(v),
+                Algorithm::Radix4 => kernel::hadamard_fused(v, 2),
+                Algorithm::Radix8 => kernel::hadamard_fused(v, 3),
+                Algorithm::Recursive => kernel::stages_recursive(v, Ordering::Hadamard, 0),
+                Algorithm::FourStep => kernel::stages_four_step(v, Ordering::Hadamard, 0),
+                Algorithm::Tiled => kernel::stages_tiled(v, Ordering::Hadamard, 0),
+            },
+        }
+    }
+
+    /// Replace the contents of `v` with its inverse transform: the
+    /// transform divided by the length, in the element type of `v`, so
+    /// that `f32` data stays in single precision throughout without the
+    /// widening of [`scale`](crate::scale).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `v` does not have the length
+    /// the plan was created for, in which case `v` is unchanged, or
+    /// [`Error::InexactScaling`] if an integer coefficient is not
+    /// divisible by the length, in which case `v` holds the unscaled
+    /// transform.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::{Ordering, Plan};
+    ///
+    /// let plan = Plan::new(4, Ordering::Sequency).unwrap();
+    /// let input = [1.5f32, -2.0, 0.25, 8.0];
+    /// let mut v = plan.transform(&input).unwrap();
+    /// plan.execute_inverse(&mut v).unwrap();
+    /// assert_eq!(v, input);
+    /// ```
+    pub fn execute_inverse<T>(&self, v: &mut [T]) -> Result<(), Error>
+    where
+        T: WalshElement + Normalize,
+    {
+        self.execute(v)?;
+        crate::scale_in_place(v)
+    }
+
+    /// Replace each of the signals held back to back in `v`, which holds
+    /// any whole number of signals of the plan's length, with its
+    /// transform, transposing groups of signals so that each vector lane
+    /// carries one signal through every stage.
+    ///
+    /// Transforming short signals one at a time spends most of the time
+    /// in stages too short to fill a vector, and in loop overhead; with
+    /// one signal per lane every butterfly fills whole vectors whatever
+    /// the length, which suits batches of many short signals, such as
+    /// 10,000 signals of 64 elements. The results are the same as from
+    /// [`execute`](Plan::execute) on each signal. A scratch buffer of a
+    /// few tens of kilobytes is allocated per call. Data already laid out
+    /// with one signal per column is transformed without transposing by
+    /// [`transform_cols`](crate::transform_cols). A strict plan transforms
+    /// the signals one at a time instead, as by [`execute`](Plan::execute).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if the length of `v` is not a
+    /// multiple of the plan's length, reporting the next multiple as
+    /// expected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::{Ordering, Plan};
+    ///
+    /// let plan = Plan::new(4, Ordering::Sequency).unwrap();
+    /// let mut batch = [1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+    /// plan.execute_transposed(&mut batch).unwrap();
+    /// assert_eq!(batch, [1.0, 1.0, 1.0, 1.0, 4.0, 0.0, 0.0, 0.0]);
+    /// ```
+    pub fn execute_transposed<T>(&self, v: &mut [T]) -> Result<(), Error>
+    where
+        T: WalshElement,
+    {
+        if !v.len().is_multiple_of(self.length) {
+            return Err(Error::LengthMismatch {
+                expected: v.len().next_multiple_of(self.length),
+                found: v.len(),
+            });
+        }
+        if self.determinism == Determinism::Strict {
+            return v
+                .chunks_exact_mut(self.length)
+                .try_for_each(|s| self.execute(s));
+        }
+        crate::multidim::transform_batch_transposed(v, self);
+        Ok(())
+    }
+
+    /// Return this plan running [`execute_parallel`](Plan::execute_parallel)
+    /// on multiple threads only for lengths of at least `length`.
+    ///
+    /// Enable with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_threshold(self, length: usize) -> Plan {
+        Plan {
+            parallel_threshold: length,
+            ..self
+        }
+    }
+
+    /// The smallest length [`execute_parallel`](Plan::execute_parallel)
+    /// divides between threads, 2^16 by default.
+    ///
+    /// Enable with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn parallel_threshold(&self) -> usize {
+        self.parallel_threshold
+    }
+
+    /// Return this plan running [`execute_parallel`](Plan::execute_parallel)
+    /// on `pool` instead of the current rayon thread pool, for
+    /// applications that manage their own threads and would otherwise be
+    /// oversubscribed.
+    ///
+    /// Enable with the `parallel` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use fwt::{Ordering, Plan};
+    ///
+    /// let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+    /// let plan = Plan::new(1 << 12, Ordering::Hadamard)
+    ///     .unwrap()
+    ///     .with_parallel_threshold(1 << 10)
+    ///     .with_thread_pool(Arc::clone(&pool));
+    /// let mut v = vec![1.0; 1 << 12];
+    /// plan.execute_parallel(&mut v).unwrap();
+    /// assert_eq!(v[0], 4096.0);
+    /// assert!(plan.thread_pool().is_some_and(|p| Arc::ptr_eq(p, &pool)));
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(self, pool: std::sync::Arc<rayon::ThreadPool>) -> Plan {
+        Plan {
+            pool: crate::parallel::Pool::new(pool),
+            ..self
+        }
+    }
+
+    /// Return this plan running [`execute_parallel`](Plan::execute_parallel)
+    /// on a pool of its own with `threads` threads, or rayon's default
+    /// number if `threads` is 0. Clones of the plan share the pool.
+    ///
+    /// Enable with the `parallel` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system cannot create the threads.
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(self, threads: usize) -> Plan {
+        Plan {
+            pool: crate::parallel::Pool::with_threads(threads),
+            ..self
+        }
+    }
+
+    /// The thread pool given with [`with_thread_pool`](Plan::with_thread_pool)
+    /// or created by [`with_threads`](Plan::with_threads), or `None` if
+    /// the plan uses the current rayon pool.
+    ///
+    /// Enable with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(&self) -> Option<&std::sync::Arc<rayon::ThreadPool>> {
+        self.pool.get()
+    }
+
+    /// Replace the contents of `v` with its transform, computed on the
+    /// plan's thread pool if the plan's length is at least its
+    /// [`parallel_threshold`](Plan::parallel_threshold), and otherwise as
+    /// by [`execute`](Plan::execute). Results are bit-identical to
+    /// [`execute`](Plan::execute) for any number of threads. A strict plan
+    /// always runs on the calling thread.
+    ///
+    /// Enable with the `parallel` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `v` does not have the length
+    /// the plan was created for.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::{Ordering, Plan};
+    ///
+    /// let plan = Plan::new(1 << 20, Ordering::Sequency).unwrap();
+    /// let input: Vec<f32> = (0..1 << 20).map(|i| (i % 7) as f32).collect();
+    /// let mut v = input.clone();
+    /// plan.execute_parallel(&mut v).unwrap();
+    /// assert_eq!(v, plan.transform(&input).unwrap());
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn execute_parallel<T>(&self, v: &mut [T]) -> Result<(), Error>
+    where
+        T: WalshElement + Send,
+    {
+        self.execute_parallel_from(v, self.parallel_threshold)
+    }
+
+    // Run `execute_parallel` as if the parallel threshold were
+    // `threshold`.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn execute_parallel_from<T>(
+        &self,
+        v: &mut [T],
+        threshold: usize,
+    ) -> Result<(), Error>
+    where
+        T: WalshElement + Send,
+    {
+        if v.len() < threshold || self.determinism == Determinism::Strict {
+            return self.execute(v);
+        }
+        if v.len() != self.length {
+            return Err(Error::LengthMismatch {
+                expected: self.length,
+                found: v.len(),
+            });
+        }
+        self.pool
+            .install(|| crate::parallel::execute(v, self.ordering, &self.swaps, self.algorithm));
+        Ok(())
+    }
+
+    /// Return the transform of `input_v`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `input_v` does not have the
+    /// length the plan was created for.
+    pub fn transform<T>(&self, input_v: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: WalshElement,
+    {
+        let mut v = input_v.to_vec();
+        self.execute(&mut v)?;
+        Ok(v)
+    }
+
+    /// Return the transform of `input_v` converted to a wider element
+    /// type, such as raw 16-bit ADC or pixel samples into `i32`, where the
+    /// coefficients of a full-scale signal cannot overflow.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `input_v` does not have the
+    /// length the plan was created for.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::{Ordering, Plan};
+    ///
+    /// let plan = Plan::new(4, Ordering::Sequency).unwrap();
+    /// let samples: [i16; 4] = [i16::MAX; 4];
+    /// let spectrum: Vec<i32> = plan.transform_widened(&samples).unwrap();
+    /// assert_eq!(spectrum, [4 * i16::MAX as i32, 0, 0, 0]);
+    /// ```
+    pub fn transform_widened<T, U>(&self, input_v: &[T]) -> Result<Vec<U>, Error>
+    where
+        T: Copy,
+        U: WalshElement + From<T>,
+    {
+        let mut v: Vec<U> = input_v.iter().map(|&x| U::from(x)).collect();
+        self.execute(&mut v)?;
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hadamard, sequency};
+
+    #[test]
+    fn test_plan_matches_functions() {
+        let input: Vec<i32> = (0..32).map(|x| (x * 7 + 3) % 11 - 5).collect();
+        let plan = Plan::new(32, Ordering::Sequency).unwrap();
+        assert_eq!(plan.transform(&input).ok(), sequency(&input));
+        let plan = Plan::new(32, Ordering::Hadamard).unwrap();
+        assert_eq!(plan.transform(&input).ok(), hadamard(&input));
+    }
+
+    #[test]
+    fn test_16_bit_transforms() {
+        use std::num::Saturating;
+        let input: Vec<i16> = (0..64).map(|x| ((x * 7919) % 601 - 300) as i16).collect();
+        let wide: Vec<i32> = input.iter().map(|&x| x.into()).collect();
+        let plan = Plan::new(64, Ordering::Sequency).unwrap();
+        let expected = plan.transform(&wide).unwrap();
+        assert_eq!(
+            plan.transform_widened::<i16, i32>(&input).unwrap(),
+            expected
+        );
+        let narrow: Vec<i32> = plan
+            .transform(&input)
+            .unwrap()
+            .into_iter()
+            .map(i32::from)
+            .collect();
+        assert_eq!(narrow, expected);
+        // Saturating transforms clamp each butterfly instead of wrapping.
+        let loud = vec![Saturating(i16::MAX); 64];
+        let spectrum = plan.transform(&loud).unwrap();
+        assert_eq!(spectrum[0], Saturating(i16::MAX));
+        assert!(spectrum[1..].iter().all(|&x| x == Saturating(0)));
+        // Wrapping transforms wrap at every length, in the unrolled,
+        // vectorized and scalar paths alike.
+        use std::num::Wrapping;
+        for length in [8, 128, 1 << 10] {
+            let loud = vec![Wrapping(i16::MAX); length];
+            let wide = vec![i64::from(i16::MAX); length];
+            let expected: Vec<Wrapping<i16>> = hadamard(&wide)
+                .unwrap()
+                .into_iter()
+                .map(|x| Wrapping(x as i16))
+                .collect();
+            assert_eq!(hadamard(&loud).unwrap(), expected);
+            let loud = vec![Wrapping(i32::MAX); length];
+            let dc = Wrapping(i32::MAX) * Wrapping(length as i32);
+            assert_eq!(hadamard(&loud).unwrap()[0], dc);
+        }
+    }
+
+    #[test]
+    fn test_inverse_stays_in_element_type() {
+        let input: Vec<f32> = (0..256).map(|x| ((x * 37 + 11) % 101) as f32 / 8.0).collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let plan = Plan::new(256, ordering).unwrap();
+            let mut v = plan.transform(&input).unwrap();
+            plan.execute_inverse(&mut v).unwrap();
+            assert_eq!(v, input);
+        }
+        let plan = Plan::new(2, Ordering::Hadamard).unwrap();
+        let mut v = [1, 2];
+        assert_eq!(plan.execute_inverse(&mut v), Err(Error::InexactScaling));
+        assert_eq!(v, [3, -1]);
+        let mut v = [4, 2];
+        assert_eq!(plan.execute_inverse(&mut v), Ok(()));
+        assert_eq!(v, [3, 1]);
+    }
+
+    #[test]
+    fn test_transposed_batches_match_execute() {
+        for (length, batch) in [(1, 5), (4, 3), (128, 1), (256, 70), (1 << 11, 9)] {
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let plan = Plan::new(length, ordering).unwrap();
+                let input: Vec<f64> = (0..length * batch)
+                    .map(|i| ((i * 7919) % 101) as f64 / 7.3)
+                    .collect();
+                let mut expected = input.clone();
+                for signal in expected.chunks_exact_mut(length) {
+                    plan.execute(signal).unwrap();
+                }
+                let mut v = input.clone();
+                plan.execute_transposed(&mut v).unwrap();
+                assert!(v
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| a.to_bits() == b.to_bits()));
+            }
+        }
+        let plan = Plan::new(4, Ordering::Hadamard).unwrap();
+        let mut empty: [i32; 0] = [];
+        assert_eq!(plan.execute_transposed(&mut empty), Ok(()));
+        assert_eq!(
+            plan.execute_transposed(&mut [1, 2, 3, 4, 5, 6]),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                found: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_strict_plan_is_bit_exact() {
+        let input: Vec<f64> = (0..256)
+            .map(|x| ((x * 37 + 11) % 101) as f64 / 7.3)
+            .collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let plan = Plan::new(256, ordering)
+                .unwrap()
+                .with_determinism(Determinism::Strict);
+            let reference = match ordering {
+                Ordering::Sequency => sequency(&input),
+                Ordering::Hadamard => hadamard(&input),
+            }
+            .unwrap();
+            // The algorithm is ignored, and batches are not transposed.
+            for algorithm in [Algorithm::Radix4, Algorithm::Recursive, Algorithm::Tiled] {
+                let plan = plan.clone().with_algorithm(algorithm);
+                let result = plan.transform(&input).unwrap();
+                assert!(result
+                    .iter()
+                    .zip(&reference)
+                    .all(|(a, b)| a.to_bits() == b.to_bits()));
+                let mut batch = [input.clone(), input.clone()].concat();
+                plan.execute_transposed(&mut batch).unwrap();
+                assert!(batch
+                    .iter()
+                    .zip(reference.iter().cycle())
+                    .all(|(a, b)| a.to_bits() == b.to_bits()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fused_algorithms_are_bit_exact() {
+        for log_length in 0..16 {
+            let length = 1 << log_length;
+            let input: Vec<f64> = (0..length)
+                .map(|x| ((x * 37 + 11) % 101) as f64 / 7.3)
+                .collect();
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let radix2 = Plan::new(length, ordering)
+                    .unwrap()
+                    .with_algorithm(Algorithm::Radix2)
+                    .transform(&input)
+                    .unwrap();
+                for algorithm in [
+                    Algorithm::Radix4,
+                    Algorithm::Radix8,
+                    Algorithm::Recursive,
+                    Algorithm::FourStep,
+                    Algorithm::Tiled,
+                ] {
+                    let fused = Plan::new(length, ordering)
+                        .unwrap()
+                        .with_algorithm(algorithm)
+                        .transform(&input)
+                        .unwrap();
+                    assert!(radix2
+                        .iter()
+                        .zip(&fused)
+                        .all(|(a, b)| a.to_bits() == b.to_bits()));
+                }
+            }
+        }
+        assert_eq!(Algorithm::for_length(1 << 14), Algorithm::Radix2);
+        assert_eq!(Algorithm::for_length(1 << 16), Algorithm::Recursive);
+    }
+
+    #[test]
+    fn test_tiled_levels_are_bit_exact() {
+        // Small tiles give several levels of strips across rows.
+        for log_length in [0, 5, 8, 12, 15, 16] {
+            let length = 1 << log_length;
+            let input: Vec<f64> = (0..length)
+                .map(|x| ((x * 37 + 11) % 101) as f64 / 7.3)
+                .collect();
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let reference = Plan::new(length, ordering)
+                    .unwrap()
+                    .with_algorithm(Algorithm::Radix2)
+                    .transform(&input)
+                    .unwrap();
+                for tile in [1024, 4096] {
+                    let mut v = input.clone();
+                    if ordering == Ordering::Sequency {
+                        kernel::bit_reverse(&mut v);
+                    }
+                    kernel::stages_tiled_by(&mut v, ordering, 0, tile);
+                    assert!(
+                        v.iter()
+                            .zip(&reference)
+                            .all(|(a, b)| a.to_bits() == b.to_bits()),
+                        "{ordering:?} length {length} tile {tile}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_lengths_are_bit_exact() {
+        // Large enough for the recursive algorithm to split columns, and
+        // both odd and even powers of 2 for the four-step algorithm.
+        for log_length in [17, 20] {
+            let length = 1 << log_length;
+            let input: Vec<f32> = (0..length)
+                .map(|x| ((x * 37 + 11) % 101) as f32 / 7.3)
+                .collect();
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let plan = Plan::new(length, ordering).unwrap();
+                let radix2 = plan
+                    .clone()
+                    .with_algorithm(Algorithm::Radix2)
+                    .transform(&input)
+                    .unwrap();
+                for algorithm in [Algorithm::Recursive, Algorithm::FourStep, Algorithm::Tiled] {
+                    let result = plan
+                        .clone()
+                        .with_algorithm(algorithm)
+                        .transform(&input)
+                        .unwrap();
+                    assert!(radix2
+                        .iter()
+                        .zip(&result)
+                        .all(|(a, b)| a.to_bits() == b.to_bits()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        assert_eq!(Plan::new(0, Ordering::Hadamard), Err(Error::NotPowerOfTwo(0)));
+        let plan = Plan::new(4, Ordering::Sequency).unwrap();
+        assert_eq!(
+            plan.transform(&[1.0, 2.0]),
+            Err(Error::LengthMismatch {
+                expected: 4,
+                found: 2
+            })
+        );
+    }
+}
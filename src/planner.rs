@@ -0,0 +1,507 @@
+//! Kernel choices measured on the running machine, kept as wisdom.
+
+use std::any::type_name;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::dispatch::as_scalar;
+use crate::{codelet, Algorithm, Backend, Error, Ordering, Plan, WalshElement};
+
+// The least time spent timing each candidate kernel.
+const TUNING_TIME: Duration = Duration::from_millis(1);
+
+const ALGORITHMS: [Algorithm; 6] = [
+    Algorithm::Radix2,
+    Algorithm::Radix4,
+    Algorithm::Radix8,
+    Algorithm::Recursive,
+    Algorithm::FourStep,
+    Algorithm::Tiled,
+];
+
+// The planner shared by the whole program, which keeps the default
+// choices until given wisdom.
+static GLOBAL: LazyLock<Planner> = LazyLock::new(|| Planner::new().with_timing(false));
+
+// The backends a planner chooses between, in order of preference between
+// equal timings.
+#[cfg(feature = "parallel")]
+const BACKENDS: [Backend; 3] = [Backend::Simd, Backend::Scalar, Backend::Parallel];
+#[cfg(not(feature = "parallel"))]
+const BACKENDS: [Backend; 2] = [Backend::Simd, Backend::Scalar];
+
+/// The kernel a [`Planner`] chose for transforms of one element type,
+/// length and ordering.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WisdomEntry {
+    /// The element type, as named by [`std::any::type_name`].
+    pub element: String,
+    /// The length of the transforms.
+    pub length: usize,
+    /// The ordering of the transforms.
+    pub ordering: Ordering,
+    /// The butterfly schedule.
+    pub algorithm: Algorithm,
+    /// How the butterflies run: [`Backend::Simd`], [`Backend::Scalar`]
+    /// or [`Backend::Parallel`].
+    pub backend: Backend,
+}
+
+/// The kernel choices of a [`Planner`], exported to be imported by a
+/// later run of the program, as FFTW's wisdom is.
+///
+/// Store wisdom in the [`format`](crate::format) container with
+/// [`Artifact::wisdom`](crate::format::Artifact::wisdom). Choices depend
+/// on the machine they were measured on, so wisdom should only be
+/// imported on the same machine.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Wisdom {
+    entries: Vec<WisdomEntry>,
+}
+
+impl Wisdom {
+    /// Create wisdom without choices.
+    pub fn new() -> Wisdom {
+        Wisdom::default()
+    }
+
+    /// Add `entry`, replacing any earlier choice for the same element
+    /// type, length and ordering.
+    pub fn record(&mut self, entry: WisdomEntry) {
+        let same = |e: &WisdomEntry| {
+            (&e.element, e.length, e.ordering) == (&entry.element, entry.length, entry.ordering)
+        };
+        match self.entries.iter_mut().find(|e| same(e)) {
+            Some(e) => *e = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// The choices in the order they were first recorded.
+    pub fn entries(&self) -> &[WisdomEntry] {
+        &self.entries
+    }
+}
+
+/// Chooses the fastest kernel for each size by timing the candidates on
+/// the running machine, for transforms called many times on CPUs whose
+/// caches and vector units the heuristics of [`Plan::new`] may suit
+/// badly.
+///
+/// The first transform of each element type, length and ordering times
+/// every [`Algorithm`] with the vectorized butterflies, the scalar ones
+/// and, with the `parallel` feature, on the rayon thread pool or the one
+/// given with `with_thread_pool`, each for about a millisecond on a copy
+/// of the data, and keeps the fastest. Lengths of at most 64 elements
+/// always run unrolled kernels, so only the butterflies are timed for
+/// them. Later transforms of that size use
+/// the choice directly. [`export_wisdom`](Self::export_wisdom) returns
+/// the choices made so far, and [`import_wisdom`](Self::import_wisdom)
+/// adopts choices made earlier without timing them again.
+///
+/// Every choice computes the same transform; the algorithms are
+/// bit-identical, and the vectorized, scalar and parallel butterflies
+/// compute exactly the same values.
+///
+/// A planner is safe to share between threads. Call sites that do not
+/// keep their own can use the [`global`](Self::global) one, which reuses
+/// plans across the whole program.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, Planner};
+///
+/// let planner = Planner::new();
+/// let mut v = [1.0, 0.0, 0.0, 0.0];
+/// planner.execute(&mut v, Ordering::Sequency).unwrap();
+/// assert_eq!(v, [1.0; 4]);
+/// let wisdom = planner.export_wisdom();
+/// assert_eq!(wisdom.entries().len(), 1);
+///
+/// // A later run skips the timing.
+/// let later = Planner::new();
+/// later.import_wisdom(&wisdom);
+/// assert_eq!(
+///     later.choice::<f64>(4, Ordering::Sequency),
+///     planner.choice::<f64>(4, Ordering::Sequency)
+/// );
+/// ```
+#[derive(Debug)]
+pub struct Planner {
+    choices: Mutex<HashMap<Key, Tuned>>,
+    timing: bool,
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+type Key = (Cow<'static, str>, usize, Ordering);
+
+#[derive(Debug, Clone)]
+struct Tuned {
+    plan: Arc<Plan>,
+    backend: Backend,
+    // Whether the choice was timed or imported, rather than the default
+    // of an untimed planner.
+    measured: bool,
+}
+
+impl Tuned {
+    fn run<T: WalshElement + Send>(&self, v: &mut [T]) -> Result<(), Error> {
+        match self.backend {
+            Backend::Scalar => self.plan.execute(as_scalar(v)),
+            #[cfg(feature = "parallel")]
+            Backend::Parallel => self.plan.execute_parallel(v),
+            _ => self.plan.execute(v),
+        }
+    }
+}
+
+impl Default for Planner {
+    fn default() -> Planner {
+        Planner::new()
+    }
+}
+
+impl Planner {
+    /// Create a planner without choices.
+    pub fn new() -> Planner {
+        Planner {
+            choices: Mutex::new(HashMap::new()),
+            timing: true,
+            #[cfg(feature = "parallel")]
+            pool: None,
+        }
+    }
+
+    /// The planner shared by the whole program, created at first use,
+    /// for call sites that transform the same sizes repeatedly without
+    /// keeping a plan. It builds each plan once per element type, length
+    /// and ordering and reuses it from then on.
+    ///
+    /// The global planner does not time kernels, so that no call pays for
+    /// tuning unexpectedly: it uses the choices of [`Plan::new`], or those
+    /// [imported](Self::import_wisdom) into it. Create a planner of your
+    /// own to time them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fwt::{Ordering, Planner};
+    ///
+    /// for _ in 0..3 {
+    ///     let mut v = [0, 0, 0, 0, 0, 0, 1, 0];
+    ///     Planner::global().execute(&mut v, Ordering::Sequency).unwrap();
+    ///     assert_eq!(v, [1, -1, 1, -1, -1, 1, -1, 1]);
+    /// }
+    /// assert!(Planner::global().choice::<i32>(8, Ordering::Sequency).is_some());
+    /// ```
+    pub fn global() -> &'static Planner {
+        &GLOBAL
+    }
+
+    /// Return this planner timing the candidate kernels at the first
+    /// transform of each size if `timing`, the default, or otherwise
+    /// using the choices of [`Plan::new`] with the vectorized butterflies.
+    /// Untimed choices are not exported as wisdom.
+    pub fn with_timing(self, timing: bool) -> Planner {
+        Planner { timing, ..self }
+    }
+
+    /// Whether the planner times kernels before choosing them.
+    pub fn timing(&self) -> bool {
+        self.timing
+    }
+
+    /// Return this planner running the parallel kernels on `pool` instead
+    /// of the current rayon thread pool, both when timing them and once
+    /// chosen.
+    ///
+    /// Enable with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(self, pool: Arc<rayon::ThreadPool>) -> Planner {
+        Planner {
+            pool: Some(pool),
+            ..self
+        }
+    }
+
+    /// Replace the contents of `v` with its transform in the given
+    /// ordering, using the kernel chosen for its element type and length,
+    /// after timing the candidates if none is chosen yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a
+    /// power of 2.
+    pub fn execute<T>(&self, v: &mut [T], ordering: Ordering) -> Result<(), Error>
+    where
+        T: WalshElement + Send,
+    {
+        let key = (Cow::Borrowed(type_name::<T>()), v.len(), ordering);
+        let known = self.lock().get(&key).cloned();
+        let tuned = match known {
+            Some(tuned) => tuned,
+            None => {
+                // Time outside the lock, so other sizes are served
+                // meanwhile.
+                let tuned = if self.timing {
+                    tune(self, v, ordering)?
+                } else {
+                    let plan = Plan::new(v.len(), ordering)?;
+                    Tuned {
+                        measured: false,
+                        ..self.tuned(v.len(), ordering, plan.algorithm(), Backend::Simd)?
+                    }
+                };
+                self.lock().entry(key).or_insert(tuned).clone()
+            }
+        };
+        tuned.run(v)
+    }
+
+    /// The algorithm and backend chosen for transforms of `length`
+    /// elements of type `T` in the given ordering, or `None` if none is
+    /// chosen yet.
+    pub fn choice<T>(&self, length: usize, ordering: Ordering) -> Option<(Algorithm, Backend)> {
+        let key = (Cow::Borrowed(type_name::<T>()), length, ordering);
+        self.lock()
+            .get(&key)
+            .map(|tuned| (tuned.plan.algorithm(), tuned.backend))
+    }
+
+    /// The choices timed or imported so far, ordered by element type,
+    /// length and ordering.
+    pub fn export_wisdom(&self) -> Wisdom {
+        let mut entries: Vec<WisdomEntry> = self
+            .lock()
+            .iter()
+            .filter(|(_, tuned)| tuned.measured)
+            .map(|((element, length, ordering), tuned)| WisdomEntry {
+                element: element.to_string(),
+                length: *length,
+                ordering: *ordering,
+                algorithm: tuned.plan.algorithm(),
+                backend: tuned.backend,
+            })
+            .collect();
+        entries.sort_by_key(|e| {
+            let ordering = e.ordering == Ordering::Hadamard;
+            (e.element.clone(), e.length, ordering)
+        });
+        Wisdom { entries }
+    }
+
+    /// Adopt the choices in `wisdom`, replacing any made for the same
+    /// sizes. Entries whose length is not a power of 2 are ignored, and
+    /// backends this build cannot run are replaced by [`Backend::Simd`].
+    pub fn import_wisdom(&self, wisdom: &Wisdom) {
+        let mut choices = self.lock();
+        for e in &wisdom.entries {
+            if let Ok(tuned) = self.tuned(e.length, e.ordering, e.algorithm, e.backend) {
+                let key = (Cow::Owned(e.element.clone()), e.length, e.ordering);
+                choices.insert(key, tuned);
+            }
+        }
+    }
+
+    // The kernel for the given choice, with this planner's thread pool.
+    fn tuned(
+        &self,
+        length: usize,
+        ordering: Ordering,
+        algorithm: Algorithm,
+        backend: Backend,
+    ) -> Result<Tuned, Error> {
+        let plan = Plan::new(length, ordering)?.with_algorithm(algorithm);
+        #[cfg(feature = "parallel")]
+        let plan = match (backend, &self.pool) {
+            (Backend::Parallel, Some(pool)) => plan
+                .with_parallel_threshold(0)
+                .with_thread_pool(Arc::clone(pool)),
+            (Backend::Parallel, None) => plan.with_parallel_threshold(0),
+            _ => plan,
+        };
+        let backend = match backend {
+            Backend::Scalar | Backend::Parallel if BACKENDS.contains(&backend) => backend,
+            _ => Backend::Simd,
+        };
+        Ok(Tuned {
+            plan: Arc::new(plan),
+            backend,
+            measured: true,
+        })
+    }
+
+    // A panic while holding the lock cannot leave the choices
+    // inconsistent, since each update is a single insertion.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Key, Tuned>> {
+        self.choices.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Time every candidate kernel on copies of `v` and return the fastest.
+fn tune<T>(planner: &Planner, v: &[T], ordering: Ordering) -> Result<Tuned, Error>
+where
+    T: WalshElement + Send,
+{
+    let length = v.len();
+    let default = Plan::new(length, ordering)?.algorithm();
+    let algorithms: &[Algorithm] = if length <= codelet::MAX_LENGTH {
+        &[default]
+    } else {
+        &ALGORITHMS
+    };
+    let mut scratch = v.to_vec();
+    let mut best: Option<(f64, Tuned)> = None;
+    for &algorithm in algorithms {
+        for backend in BACKENDS {
+            let tuned = planner.tuned(length, ordering, algorithm, backend)?;
+            // Every run restores the data, so integers cannot overflow
+            // over repeated transforms; the copy costs every candidate
+            // the same. The first run warms the caches.
+            let mut run = || {
+                scratch.copy_from_slice(v);
+                tuned.run(&mut scratch)
+            };
+            run()?;
+            let start = Instant::now();
+            let mut runs = 0;
+            while runs < 1 || start.elapsed() < TUNING_TIME {
+                run()?;
+                runs += 1;
+            }
+            let nanos = start.elapsed().as_nanos() as f64 / runs as f64;
+            if best.as_ref().is_none_or(|(fastest, _)| nanos < *fastest) {
+                best = Some((nanos, tuned));
+            }
+        }
+    }
+    Ok(best.expect("there is at least one candidate").1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{self, Artifact};
+
+    #[test]
+    fn test_tuned_transforms_match_plan() {
+        let planner = Planner::new();
+        for length in [1, 16, 256, 1 << 12] {
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let plan = Plan::new(length, ordering).unwrap();
+                let input: Vec<i32> = (0..length as i32).map(|i| (i * 37) % 23 - 11).collect();
+                let float: Vec<f64> = input.iter().map(|&x| x as f64 * 0.37).collect();
+                // The second call uses the choice made by the first.
+                for _ in 0..2 {
+                    let mut v = input.clone();
+                    planner.execute(&mut v, ordering).unwrap();
+                    assert_eq!(v, plan.transform(&input).unwrap());
+                    let mut v = float.clone();
+                    planner.execute(&mut v, ordering).unwrap();
+                    assert_eq!(v, plan.transform(&float).unwrap());
+                }
+                assert!(planner.choice::<i32>(length, ordering).is_some());
+            }
+        }
+        assert_eq!(planner.export_wisdom().entries().len(), 16);
+        assert_eq!(planner.choice::<i64>(16, Ordering::Sequency), None);
+        assert_eq!(
+            planner.execute(&mut [1.0; 6], Ordering::Sequency),
+            Err(Error::NotPowerOfTwo(6))
+        );
+    }
+
+    #[test]
+    fn test_imported_wisdom_replaces_choices() {
+        let planner = Planner::new();
+        let mut wisdom = Wisdom::new();
+        for backend in [Backend::Simd, Backend::Scalar] {
+            wisdom.record(WisdomEntry {
+                element: type_name::<i32>().to_string(),
+                length: 256,
+                ordering: Ordering::Hadamard,
+                algorithm: Algorithm::Radix8,
+                backend,
+            });
+        }
+        wisdom.record(WisdomEntry {
+            element: type_name::<i32>().to_string(),
+            length: 100,
+            ordering: Ordering::Hadamard,
+            algorithm: Algorithm::Radix2,
+            backend: Backend::Gpu,
+        });
+        assert_eq!(wisdom.entries().len(), 2);
+        planner.import_wisdom(&wisdom);
+        assert_eq!(
+            planner.choice::<i32>(256, Ordering::Hadamard),
+            Some((Algorithm::Radix8, Backend::Scalar))
+        );
+        assert_eq!(planner.choice::<i32>(100, Ordering::Hadamard), None);
+        let input: Vec<i32> = (0..256).map(|i| (i * 29) % 11 - 5).collect();
+        let mut v = input.clone();
+        planner.execute(&mut v, Ordering::Hadamard).unwrap();
+        assert_eq!(v, crate::hadamard(&input).unwrap());
+        assert_eq!(planner.export_wisdom().entries(), &wisdom.entries()[..1]);
+    }
+
+    #[test]
+    fn test_wisdom_persists_in_container() {
+        let planner = Planner::new();
+        planner
+            .execute(&mut [1.0f32; 128], Ordering::Sequency)
+            .unwrap();
+        planner.execute(&mut [1i16; 2], Ordering::Hadamard).unwrap();
+        let wisdom = planner.export_wisdom();
+        let mut bytes = Vec::new();
+        format::write(&mut bytes, &Artifact::wisdom(&wisdom)).unwrap();
+        let restored = format::read(&mut bytes.as_slice()).unwrap().to_wisdom();
+        assert_eq!(restored.as_ref(), Some(&wisdom));
+        let later = Planner::new();
+        later.import_wisdom(&restored.unwrap());
+        for (length, ordering) in [(128, Ordering::Sequency), (2, Ordering::Hadamard)] {
+            assert_eq!(
+                later.choice::<f32>(length, ordering),
+                planner.choice::<f32>(length, ordering)
+            );
+        }
+        assert!(later.choice::<i16>(2, Ordering::Hadamard).is_some());
+    }
+
+    #[test]
+    fn test_global_planner_reuses_default_plans() {
+        let global = Planner::global();
+        assert!(std::ptr::eq(global, Planner::global()));
+        assert!(!global.timing());
+        let input: Vec<i64> = (0..1 << 16).map(|i| (i * 7919) % 101 - 50).collect();
+        let expected = crate::sequency(&input).unwrap();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let input = input.clone();
+                std::thread::spawn(move || {
+                    let mut v = input;
+                    Planner::global()
+                        .execute(&mut v, Ordering::Sequency)
+                        .unwrap();
+                    v
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+        assert_eq!(
+            global.choice::<i64>(1 << 16, Ordering::Sequency),
+            Some((Algorithm::for_length(1 << 16), Backend::Simd))
+        );
+        // Untimed choices are not wisdom.
+        let untimed = Planner::new().with_timing(false);
+        untimed.execute(&mut [1.0; 8], Ordering::Hadamard).unwrap();
+        assert!(untimed.choice::<f64>(8, Ordering::Hadamard).is_some());
+        assert!(untimed.export_wisdom().entries().is_empty());
+    }
+}
@@ -0,0 +1,314 @@
+//! Quick-look charts of spectra and Walsh functions drawn with
+//! [`plotters`](::plotters).
+//!
+//! Each function renders one chart to `path`, choosing SVG output when
+//! the file name ends in `.svg` and PNG output otherwise. The charts are
+//! meant for eyeballing results during development rather than for
+//! publication, so sizes, colours and labels are fixed.
+//!
+//! Enable with the `plotters` feature.
+
+use std::error::Error as StdError;
+use std::path::Path;
+
+use ::plotters::coord::Shift;
+use ::plotters::prelude::*;
+
+use crate::{walsh_row, Error, Ordering};
+
+/// The result of rendering a chart. Drawing errors come from the
+/// plotting backend, so they are boxed.
+pub type PlotResult = Result<(), Box<dyn StdError>>;
+
+const SIZE: (u32, u32) = (800, 480);
+
+// Draw with `$draw` on the backend selected by the extension of `$path`.
+macro_rules! render {
+    ($path:expr, $draw:ident($($arg:expr),*)) => {{
+        let path: &Path = $path.as_ref();
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")) {
+            $draw(SVGBackend::new(path, SIZE).into_drawing_area(), $($arg),*)
+        } else {
+            $draw(BitMapBackend::new(path, SIZE).into_drawing_area(), $($arg),*)
+        }
+    }};
+}
+
+/// Render the coefficients of a spectrum as a bar chart, one bar per
+/// coefficient, with negative coefficients drawn below the axis.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `coefficients` is empty, or the
+/// backend's error if the chart cannot be drawn or written.
+///
+/// # Example
+///
+/// ```no_run
+/// use fwt::Ordering;
+///
+/// let spectrum = fwt::sequency(&[1.0, 3.0, 2.0, 0.0, -1.0, 4.0, 2.0, 1.0]).unwrap();
+/// fwt::plotters::spectrum_chart("spectrum.svg", &spectrum, "Sequency spectrum").unwrap();
+/// ```
+pub fn spectrum_chart<P: AsRef<Path>>(path: P, coefficients: &[f64], title: &str) -> PlotResult {
+    if coefficients.is_empty() {
+        return Err(Error::LengthMismatch {
+            expected: 1,
+            found: 0,
+        }
+        .into());
+    }
+    render!(path, draw_spectrum(coefficients, title))
+}
+
+/// Render a sequence of spectra as a heatmap, with time (the index into
+/// `frames`) across and coefficient index up. Cells are shaded by
+/// magnitude in decibels relative to the largest magnitude, over a range
+/// of `dynamic_range` dB.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if `frames` is empty or the frames
+/// differ in length, or the backend's error if the chart cannot be drawn
+/// or written.
+///
+/// # Example
+///
+/// ```no_run
+/// let frames: Vec<Vec<f64>> = (0..32)
+///     .map(|t| fwt::sequency(&[t as f64, 1.0, 0.0, -1.0]).unwrap())
+///     .collect();
+/// fwt::plotters::spectrogram_heatmap("spectrogram.png", &frames, 60.0, "Spectrogram").unwrap();
+/// ```
+pub fn spectrogram_heatmap<P: AsRef<Path>>(
+    path: P,
+    frames: &[Vec<f64>],
+    dynamic_range: f64,
+    title: &str,
+) -> PlotResult {
+    let height = frames.first().map_or(0, Vec::len);
+    if height == 0 {
+        return Err(Error::LengthMismatch {
+            expected: 1,
+            found: 0,
+        }
+        .into());
+    }
+    if let Some(frame) = frames.iter().find(|f| f.len() != height) {
+        return Err(Error::LengthMismatch {
+            expected: height,
+            found: frame.len(),
+        }
+        .into());
+    }
+    render!(path, draw_spectrogram(frames, height, dynamic_range, title))
+}
+
+/// Render the Walsh functions with the given indices among those of
+/// length `length` in the given ordering, stacked one above another as
+/// step plots.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2, or
+/// [`Error::LengthMismatch`] if an index is not less than `length`, or the
+/// backend's error if the chart cannot be drawn or written.
+///
+/// # Example
+///
+/// ```no_run
+/// use fwt::Ordering;
+///
+/// fwt::plotters::walsh_function_plot("walsh.png", 16, &[0, 1, 2, 3, 15], Ordering::Sequency).unwrap();
+/// ```
+pub fn walsh_function_plot<P: AsRef<Path>>(
+    path: P,
+    length: usize,
+    indices: &[usize],
+    ordering: Ordering,
+) -> PlotResult {
+    if !length.is_power_of_two() {
+        return Err(Error::NotPowerOfTwo(length).into());
+    }
+    let rows = indices
+        .iter()
+        .map(|&k| {
+            walsh_row(length, k, ordering)
+                .map(|row| (k, row))
+                .ok_or(Error::LengthMismatch {
+                    expected: length,
+                    found: k,
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    render!(path, draw_walsh(&rows, length, ordering))
+}
+
+fn draw_spectrum<DB>(root: DrawingArea<DB, Shift>, coefficients: &[f64], title: &str) -> PlotResult
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let peak = coefficients.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+    let peak = if peak > 0.0 { peak * 1.05 } else { 1.0 };
+    let low = if coefficients.iter().any(|&c| c < 0.0) {
+        -peak
+    } else {
+        0.0
+    };
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 24))
+        .margin(12)
+        .x_label_area_size(32)
+        .y_label_area_size(56)
+        .build_cartesian_2d(0.0..coefficients.len() as f64, low..peak)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_desc("coefficient")
+        .draw()?;
+    chart.draw_series(coefficients.iter().enumerate().map(|(i, &c)| {
+        let x = i as f64;
+        Rectangle::new([(x + 0.1, 0.0), (x + 0.9, c)], BLUE.filled())
+    }))?;
+    root.present()?;
+    Ok(())
+}
+
+fn draw_spectrogram<DB>(
+    root: DrawingArea<DB, Shift>,
+    frames: &[Vec<f64>],
+    height: usize,
+    dynamic_range: f64,
+    title: &str,
+) -> PlotResult
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let peak = frames.iter().flatten().fold(0.0f64, |m, c| m.max(c.abs()));
+    let range = dynamic_range.max(f64::MIN_POSITIVE);
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 24))
+        .margin(12)
+        .x_label_area_size(32)
+        .y_label_area_size(56)
+        .build_cartesian_2d(0..frames.len(), 0..height)?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_desc("frame")
+        .y_desc("coefficient")
+        .draw()?;
+    chart.draw_series(frames.iter().enumerate().flat_map(|(t, frame)| {
+        frame.iter().enumerate().map(move |(k, &c)| {
+            let level = if peak > 0.0 && c != 0.0 {
+                (20.0 * (c.abs() / peak).log10() / range + 1.0).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let color = ViridisRGB::get_color(level);
+            Rectangle::new([(t, k), (t + 1, k + 1)], color.filled())
+        })
+    }))?;
+    root.present()?;
+    Ok(())
+}
+
+fn draw_walsh<DB>(
+    root: DrawingArea<DB, Shift>,
+    rows: &[(usize, Vec<i8>)],
+    length: usize,
+    ordering: Ordering,
+) -> PlotResult
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let caption = match ordering {
+        Ordering::Sequency => "Walsh functions (sequency order)",
+        Ordering::Hadamard => "Walsh functions (Hadamard order)",
+    };
+    let top = 3.0 * rows.len().max(1) as f64;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 24))
+        .margin(12)
+        .x_label_area_size(32)
+        .y_label_area_size(56)
+        .build_cartesian_2d(0.0..length as f64, 0.0..top)?;
+    chart
+        .configure_mesh()
+        .disable_y_mesh()
+        .y_labels(0)
+        .x_desc("sample")
+        .draw()?;
+    for (slot, (k, row)) in rows.iter().enumerate() {
+        let base = top - 3.0 * slot as f64 - 1.5;
+        let color = Palette99::pick(slot);
+        let steps = row.iter().enumerate().flat_map(|(i, &s)| {
+            let y = base + f64::from(s);
+            [(i as f64, y), (i as f64 + 1.0, y)]
+        });
+        chart
+            .draw_series(LineSeries::new(steps, color.stroke_width(2)))?
+            .label(format!("k = {}", k))
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 16, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("fwt-plotters-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_renders_svg_and_png() {
+        let spectrum = crate::sequency(&[1.0, 3.0, 2.0, 0.0, -1.0, 4.0, 2.0, 1.0]).unwrap();
+        let frames: Vec<Vec<f64>> = (0..8)
+            .map(|t| crate::sequency(&[t as f64, 1.0, 0.0, -1.0]).unwrap())
+            .collect();
+        for extension in ["svg", "png"] {
+            let path = scratch(&format!("spectrum.{}", extension));
+            spectrum_chart(&path, &spectrum, "spectrum").unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            std::fs::remove_file(&path).unwrap();
+
+            let path = scratch(&format!("spectrogram.{}", extension));
+            spectrogram_heatmap(&path, &frames, 60.0, "spectrogram").unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            std::fs::remove_file(&path).unwrap();
+
+            let path = scratch(&format!("walsh.{}", extension));
+            walsh_function_plot(&path, 8, &[0, 3, 7], Ordering::Sequency).unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            std::fs::remove_file(&path).unwrap();
+        }
+        let svg = scratch("header.svg");
+        spectrum_chart(&svg, &spectrum, "spectrum").unwrap();
+        assert!(std::fs::read_to_string(&svg).unwrap().contains("<svg"));
+        std::fs::remove_file(&svg).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let path = scratch("unused.svg");
+        assert!(spectrum_chart(&path, &[], "empty").is_err());
+        assert!(spectrogram_heatmap(&path, &[vec![1.0; 4], vec![1.0; 2]], 60.0, "ragged").is_err());
+        assert!(walsh_function_plot(&path, 6, &[0], Ordering::Sequency).is_err());
+        assert!(walsh_function_plot(&path, 8, &[8], Ordering::Sequency).is_err());
+        assert!(!path.exists());
+    }
+}
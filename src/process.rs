@@ -0,0 +1,201 @@
+//! Simulation of dyadic-stationary random processes.
+//!
+//! A process `x` of length `n` is dyadic stationary if the covariance of
+//! `x[i]` and `x[j]` depends only on `i ⊕ j`, the dyadic distance, as
+//! `R(i ⊕ j)`. The Walsh functions diagonalize such covariances, just as
+//! the Fourier basis diagonalizes shift-invariant ones: the sequency
+//! transform `X` of `x` has uncorrelated coefficients with
+//! `E[X[s]²] = n S[s]`, where the power spectrum `S` is the sequency
+//! transform of `R`. Conversely, scaling independent standard normal
+//! coefficients by `√S[s]` and transforming back yields a Gaussian process
+//! with exactly that spectrum, the Walsh-domain counterpart of spectral
+//! factorization.
+
+use crate::rng::SplitMix64;
+use crate::{Error, Ordering, Plan};
+
+/// A generator of zero-mean Gaussian dyadic-stationary processes with a
+/// given sequency power spectrum, for Monte Carlo studies of Walsh-domain
+/// estimators.
+///
+/// The spectrum `S` has one entry per sequency, scaled so that the
+/// sequency transform `X` of a realization satisfies `E[X[s]²] = n S[s]`.
+/// Its mean is the process variance, and a constant spectrum `σ²` gives
+/// white noise of variance `σ²`. Realizations are drawn from a seed, so
+/// an ensemble is reproducible across platforms.
+///
+/// # Example
+///
+/// ```
+/// // All power at sequency 1: every realization is a random multiple of
+/// // the Walsh function with one sign change.
+/// let mut spectrum = vec![0.0; 8];
+/// spectrum[1] = 8.0;
+/// let mut process = fwt::DyadicProcess::new(&spectrum, 42).unwrap();
+/// let x = process.sample();
+/// assert!(x[..4].iter().all(|&v| (v - x[0]).abs() < 1e-12));
+/// assert!(x[4..].iter().all(|&v| (v + x[0]).abs() < 1e-12));
+/// assert_eq!(process.autocorrelation()[0], 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DyadicProcess {
+    spectrum: Vec<f64>,
+    amplitudes: Vec<f64>,
+    plan: Plan,
+    rng: SplitMix64,
+}
+
+impl DyadicProcess {
+    /// Create a generator for processes with the sequency power spectrum
+    /// `spectrum`, drawing realizations from `seed`. Entries should be
+    /// non-negative; negative entries and NaNs contribute no power.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if the length of `spectrum` is not
+    /// a power of 2.
+    pub fn new(spectrum: &[f64], seed: u64) -> Result<DyadicProcess, Error> {
+        let plan = Plan::new(spectrum.len(), Ordering::Sequency)?;
+        let scale = (spectrum.len() as f64).sqrt().recip();
+        let amplitudes = spectrum
+            .iter()
+            .map(|&s| s.max(0.0).sqrt() * scale)
+            .collect();
+        Ok(DyadicProcess {
+            spectrum: spectrum.to_vec(),
+            amplitudes,
+            plan,
+            rng: SplitMix64::new(seed),
+        })
+    }
+
+    /// The length of each realization.
+    pub fn length(&self) -> usize {
+        self.spectrum.len()
+    }
+
+    /// The sequency power spectrum of the process.
+    pub fn spectrum(&self) -> &[f64] {
+        &self.spectrum
+    }
+
+    /// The dyadic autocorrelation `R(τ)` of the process, the covariance of
+    /// `x[i]` and `x[i ⊕ τ]`, which is the sequency transform of the
+    /// spectrum divided by its length. `R(0)` is the variance.
+    pub fn autocorrelation(&self) -> Vec<f64> {
+        let mut r: Vec<f64> = self.spectrum.iter().map(|&s| s.max(0.0)).collect();
+        self.plan
+            .execute(&mut r)
+            .expect("the plan matches the spectrum");
+        let scale = (r.len() as f64).recip();
+        for x in &mut r {
+            *x *= scale;
+        }
+        r
+    }
+
+    /// Draw the next realization.
+    pub fn sample(&mut self) -> Vec<f64> {
+        let mut x = vec![0.0; self.length()];
+        self.sample_into(&mut x)
+            .expect("the buffer has the process length");
+        x
+    }
+
+    /// Draw the next realization into `x`, without allocating.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if the length of `x` is not that
+    /// of the process.
+    pub fn sample_into(&mut self, x: &mut [f64]) -> Result<(), Error> {
+        if x.len() != self.length() {
+            return Err(Error::LengthMismatch {
+                expected: self.length(),
+                found: x.len(),
+            });
+        }
+        for (v, &a) in x.iter_mut().zip(&self.amplitudes) {
+            *v = a * self.rng.normal();
+        }
+        self.plan.execute(x)
+    }
+
+    /// Draw `count` realizations.
+    pub fn ensemble(&mut self, count: usize) -> Vec<Vec<f64>> {
+        (0..count).map(|_| self.sample()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensemble_has_the_spectrum() {
+        let spectrum: Vec<f64> = (0..16).map(|s| 16.0 / (1.0 + s as f64)).collect();
+        let mut process = DyadicProcess::new(&spectrum, 3).unwrap();
+        let count = 20_000;
+        let mut periodogram = [0.0; 16];
+        for x in process.ensemble(count) {
+            let transform = crate::sequency(&x).unwrap();
+            for (p, c) in periodogram.iter_mut().zip(&transform) {
+                *p += c * c / (16 * count) as f64;
+            }
+        }
+        for (p, s) in periodogram.iter().zip(&spectrum) {
+            // Each estimate is a mean of squared normals, with relative
+            // standard deviation √(2 / count) = 1%.
+            assert!((p / s - 1.0).abs() < 0.05, "{p} {s}");
+        }
+    }
+
+    #[test]
+    fn test_covariance_is_dyadic() {
+        let spectrum = [4.0, 0.0, 1.0, 3.0, 0.5, 2.0, 0.0, 1.5];
+        let mut process = DyadicProcess::new(&spectrum, 9).unwrap();
+        let r = process.autocorrelation();
+        assert!((r[0] - 1.5).abs() < 1e-12);
+        let count = 50_000;
+        let mut covariance = [[0.0; 8]; 8];
+        let mut x = [0.0; 8];
+        for _ in 0..count {
+            process.sample_into(&mut x).unwrap();
+            for (row, &xi) in covariance.iter_mut().zip(&x) {
+                for (c, &xj) in row.iter_mut().zip(&x) {
+                    *c += xi * xj / count as f64;
+                }
+            }
+        }
+        for (i, row) in covariance.iter().enumerate() {
+            for (j, &c) in row.iter().enumerate() {
+                assert!((c - r[i ^ j]).abs() < 0.05, "{i} {j} {c} {}", r[i ^ j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_seeds_and_errors() {
+        let spectrum = [1.0, -1.0, f64::NAN, 2.0];
+        let a = DyadicProcess::new(&spectrum, 5).unwrap().ensemble(3);
+        let b = DyadicProcess::new(&spectrum, 5).unwrap().ensemble(3);
+        assert_eq!(a, b);
+        assert_ne!(a[0], a[1]);
+        let process = DyadicProcess::new(&spectrum, 5).unwrap();
+        assert_eq!(process.length(), 4);
+        assert!(process.spectrum()[2].is_nan());
+        assert_eq!(process.autocorrelation()[0], 0.75);
+        assert_eq!(
+            DyadicProcess::new(&[1.0; 6], 0).unwrap_err(),
+            Error::NotPowerOfTwo(6)
+        );
+        let mut process = process;
+        assert_eq!(
+            process.sample_into(&mut [0.0; 8]),
+            Err(Error::LengthMismatch {
+                expected: 4,
+                found: 8
+            })
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Quantization of Walsh spectra for lossy coding.
+
+/// A scalar quantizer for sequency spectra, mapping each coefficient to
+/// an integer index and back, with a step size for each dyadic band of
+/// sequencies.
+///
+/// The bands are sequency 0, then `[1, 2)`, `[2, 4)`, `[4, 8)` and so
+/// on, so band `b > 0` holds the sequencies from `2^(b-1)` up to `2^b`.
+/// Coarser steps for the higher bands spend fewer bits on fine detail.
+/// Bands past the last step given use the last step.
+///
+/// The uniform quantizer rounds each coefficient to the nearest multiple
+/// of its step. The dead-zone quantizer truncates towards 0 instead, so
+/// that every coefficient smaller than a step maps to 0, and
+/// reconstructs nonzero indices at the middle of their intervals; its
+/// wider zero bin suits the many small coefficients of typical spectra.
+/// With a bit depth, indices are clamped to the symmetric range of that
+/// many bits, `±(2^(bits-1) - 1)`.
+///
+/// # Example
+///
+/// ```
+/// let quantizer = fwt::Quantizer::new(1.0).with_band_steps(&[1.0, 2.0, 4.0]);
+/// let spectrum = [10.2, -3.1, 5.0, 1.9, 7.0, -6.0, 2.5, 0.4];
+/// let indices = quantizer.quantize(&spectrum);
+/// assert_eq!(indices, [10, -2, 1, 0, 2, -2, 1, 0]);
+/// let dequantized = quantizer.dequantize(&indices);
+/// assert_eq!(dequantized, [10.0, -4.0, 4.0, 0.0, 8.0, -8.0, 4.0, 0.0]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantizer {
+    steps: Vec<f64>,
+    dead_zone: bool,
+    bits: Option<u32>,
+}
+
+impl Quantizer {
+    /// Create a uniform quantizer with the same `step` for every band and
+    /// no limit on the indices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is not positive and finite.
+    pub fn new(step: f64) -> Quantizer {
+        Quantizer {
+            steps: vec![checked_step(step)],
+            dead_zone: false,
+            bits: None,
+        }
+    }
+
+    /// Return this quantizer with `steps[b]` the step of band `b`, and
+    /// the last step for the bands after.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `steps` is empty or a step is not positive and finite.
+    pub fn with_band_steps(self, steps: &[f64]) -> Quantizer {
+        assert!(!steps.is_empty(), "at least one step is required");
+        Quantizer {
+            steps: steps.iter().map(|&s| checked_step(s)).collect(),
+            ..self
+        }
+    }
+
+    /// Return this quantizer with a dead zone around 0, or uniform.
+    pub fn with_dead_zone(self, dead_zone: bool) -> Quantizer {
+        Quantizer { dead_zone, ..self }
+    }
+
+    /// Return this quantizer clamping indices to `bits` bits, from 1 to
+    /// 64, or without a limit for `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is 0 or more than 64.
+    pub fn with_bits(self, bits: Option<u32>) -> Quantizer {
+        if let Some(bits) = bits {
+            assert!((1..=64).contains(&bits), "bit depth {bits} out of range");
+        }
+        Quantizer { bits, ..self }
+    }
+
+    /// Whether this quantizer has a dead zone.
+    pub fn dead_zone(&self) -> bool {
+        self.dead_zone
+    }
+
+    /// The bit depth of the indices, if limited.
+    pub fn bits(&self) -> Option<u32> {
+        self.bits
+    }
+
+    /// The step size for the coefficient of sequency `s`.
+    pub fn step(&self, s: usize) -> f64 {
+        let band = (usize::BITS - s.leading_zeros()) as usize;
+        self.steps[band.min(self.steps.len() - 1)]
+    }
+
+    /// Return the index of each coefficient of the sequency-ordered
+    /// `spectrum`.
+    pub fn quantize(&self, spectrum: &[f64]) -> Vec<i64> {
+        let limit = self.limit() as f64;
+        spectrum
+            .iter()
+            .enumerate()
+            .map(|(s, &c)| {
+                let x = c / self.step(s);
+                let q = if self.dead_zone { x.trunc() } else { x.round() };
+                q.clamp(-limit, limit) as i64
+            })
+            .collect()
+    }
+
+    /// Return the coefficients represented by the sequency-ordered
+    /// `indices`, an approximation of the spectrum they were quantized
+    /// from.
+    pub fn dequantize(&self, indices: &[i64]) -> Vec<f64> {
+        indices
+            .iter()
+            .enumerate()
+            .map(|(s, &q)| {
+                let q = q as f64;
+                let offset = if self.dead_zone && q != 0.0 {
+                    0.5f64.copysign(q)
+                } else {
+                    0.0
+                };
+                (q + offset) * self.step(s)
+            })
+            .collect()
+    }
+
+    // The largest index magnitude representable in the bit depth.
+    fn limit(&self) -> i64 {
+        match self.bits {
+            Some(bits) => ((1u64 << (bits - 1)) - 1) as i64,
+            None => i64::MAX,
+        }
+    }
+}
+
+fn checked_step(step: f64) -> f64 {
+    assert!(
+        step > 0.0 && step.is_finite(),
+        "step {step} is not positive"
+    );
+    step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantization_error_is_bounded_by_step() {
+        let spectrum: Vec<f64> = (0..64)
+            .map(|i| ((i * 7919) % 101) as f64 * 0.37 - 18.0)
+            .collect();
+        for dead_zone in [false, true] {
+            let quantizer = Quantizer::new(1.0)
+                .with_band_steps(&[0.5, 1.0, 2.0, 3.0])
+                .with_dead_zone(dead_zone);
+            let restored = quantizer.dequantize(&quantizer.quantize(&spectrum));
+            for (s, (r, c)) in restored.iter().zip(&spectrum).enumerate() {
+                let step = quantizer.step(s);
+                let bound = if dead_zone && c.abs() < step {
+                    step
+                } else {
+                    step / 2.0
+                };
+                assert!((r - c).abs() <= bound + 1e-12);
+            }
+        }
+        let steps = Quantizer::new(1.0).with_band_steps(&[1.0, 2.0, 4.0]);
+        let by_sequency: Vec<f64> = [0, 1, 2, 3, 4, 100].map(|s| steps.step(s)).to_vec();
+        assert_eq!(by_sequency, [1.0, 2.0, 4.0, 4.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    fn test_dead_zone_and_bit_depth() {
+        let spectrum = [0.9, -0.9, 1.2, -2.7, 40.0, -40.0];
+        let uniform = Quantizer::new(1.0);
+        assert_eq!(uniform.quantize(&spectrum), [1, -1, 1, -3, 40, -40]);
+        let dead_zone = uniform.clone().with_dead_zone(true);
+        assert!(dead_zone.dead_zone());
+        let indices = dead_zone.quantize(&spectrum);
+        assert_eq!(indices, [0, 0, 1, -2, 40, -40]);
+        assert_eq!(
+            dead_zone.dequantize(&indices),
+            [0.0, 0.0, 1.5, -2.5, 40.5, -40.5]
+        );
+        let four_bits = uniform.with_bits(Some(4));
+        assert_eq!(four_bits.bits(), Some(4));
+        assert_eq!(four_bits.quantize(&spectrum), [1, -1, 1, -3, 7, -7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not positive")]
+    fn test_rejects_non_positive_steps() {
+        Quantizer::new(1.0).with_band_steps(&[1.0, 0.0]);
+    }
+}
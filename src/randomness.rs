@@ -0,0 +1,468 @@
+//! Sequency-domain tests of random bit sequences.
+//!
+//! Mapping bits to ±1, every Walsh coefficient of a block of `n` fair,
+//! independent bits is a sum of `n` independent signs, approximately
+//! normal with mean 0 and variance `n`. Bias shows up in coefficient 0,
+//! periodic structure and correlation between bit positions in the
+//! coefficients whose Walsh functions match them.
+//!
+//! The squared coefficients of a block always sum to `n²`, whatever the
+//! bits, so their total says nothing; the spectral test instead looks at
+//! how that fixed energy is distributed over the sequencies, within and
+//! across blocks.
+//!
+//! [`sequence_report`] combines this spectral test with the classical
+//! companion statistics: balance, runs, and dyadic autocorrelation.
+
+use crate::stats::{chi_square_sf, normal_two_sided};
+use crate::{dyadic_autocorrelation, power_of_2, sequency_in_place, Error};
+
+/// A Walsh coefficient flagged by [`walsh_spectral_test`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anomaly {
+    /// The index of the block.
+    pub block: usize,
+    /// The sequency of the coefficient.
+    pub sequency: usize,
+    /// The coefficient divided by its standard deviation `√n`.
+    pub z: f64,
+    /// The two-sided p-value of the coefficient alone.
+    pub p_value: f64,
+}
+
+/// The result of [`walsh_spectral_test`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalshTestReport {
+    blocks: usize,
+    block_len: usize,
+    alpha: f64,
+    sequency_chi_squares: Vec<f64>,
+    chi_square: f64,
+    p_value: f64,
+    max_z: f64,
+    max_p_value: f64,
+    anomalies: Vec<Anomaly>,
+}
+
+impl WalshTestReport {
+    /// The number of complete blocks tested.
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// The block length.
+    pub fn block_len(&self) -> usize {
+        self.block_len
+    }
+
+    /// For each sequency, the sum over the blocks of its squared
+    /// standardized coefficient, which has a chi-square distribution with
+    /// one degree of freedom per block under the null hypothesis.
+    pub fn sequency_chi_squares(&self) -> &[f64] {
+        &self.sequency_chi_squares
+    }
+
+    /// The largest of the [`sequency_chi_squares`](Self::sequency_chi_squares),
+    /// large when some sequency holds more than its share of the energy
+    /// consistently across the blocks.
+    pub fn chi_square(&self) -> f64 {
+        self.chi_square
+    }
+
+    /// The p-value of [`chi_square`](Self::chi_square), adjusted for the
+    /// number of sequencies examined.
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// The largest standardized coefficient magnitude.
+    pub fn max_z(&self) -> f64 {
+        self.max_z
+    }
+
+    /// The p-value of [`max_z`](Self::max_z), adjusted for the number of
+    /// coefficients examined.
+    pub fn max_p_value(&self) -> f64 {
+        self.max_p_value
+    }
+
+    /// The coefficients whose p-values fall below the significance level
+    /// divided by the number of coefficients (a Bonferroni correction), in
+    /// block and sequency order.
+    pub fn anomalies(&self) -> &[Anomaly] {
+        &self.anomalies
+    }
+
+    /// Whether neither the chi-square nor the maximum test rejects at the
+    /// significance level.
+    pub fn passed(&self) -> bool {
+        self.p_value >= self.alpha && self.max_p_value >= self.alpha
+    }
+}
+
+/// Test `bits` for randomness by the Walsh spectra of consecutive blocks
+/// of `block_len` bits, at significance level `alpha`. A trailing partial
+/// block is ignored.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if `block_len` is not a power of 2,
+/// or [`Error::LengthMismatch`] if there is not a single complete block.
+///
+/// # Example
+///
+/// ```
+/// // A period-4 pattern concentrates all its energy in one coefficient.
+/// let bits: Vec<bool> = (0..4096).map(|i| i % 4 < 2).collect();
+/// let report = fwt::walsh_spectral_test(&bits, 256, 0.01).unwrap();
+/// assert!(!report.passed());
+/// assert!(report.anomalies().iter().all(|a| a.sequency == 127));
+/// ```
+pub fn walsh_spectral_test(
+    bits: &[bool],
+    block_len: usize,
+    alpha: f64,
+) -> Result<WalshTestReport, Error> {
+    if !power_of_2(block_len) {
+        return Err(Error::NotPowerOfTwo(block_len));
+    }
+    let blocks = bits.len() / block_len;
+    if blocks == 0 {
+        return Err(Error::LengthMismatch {
+            expected: block_len,
+            found: bits.len(),
+        });
+    }
+    let coefficients = (blocks * block_len) as f64;
+    let threshold = alpha / coefficients;
+    let scale = (block_len as f64).sqrt();
+    let mut spectrum = vec![0i64; block_len];
+    let mut sequency_chi_squares = vec![0.0; block_len];
+    let mut max_z = 0.0f64;
+    let mut anomalies = Vec::new();
+    for (block, chunk) in bits.chunks_exact(block_len).enumerate() {
+        for (s, &bit) in spectrum.iter_mut().zip(chunk) {
+            *s = if bit { -1 } else { 1 };
+        }
+        sequency_in_place(&mut spectrum)?;
+        for (sequency, &c) in spectrum.iter().enumerate() {
+            let z = c as f64 / scale;
+            sequency_chi_squares[sequency] += z * z;
+            max_z = max_z.max(z.abs());
+            let p_value = normal_two_sided(z);
+            if p_value < threshold {
+                anomalies.push(Anomaly {
+                    block,
+                    sequency,
+                    z,
+                    p_value,
+                });
+            }
+        }
+    }
+    // Šidák: the chance that at least one of the coefficients is as
+    // extreme, computed stably for small p.
+    let sidak = |p: f64, trials: f64| -(trials * (-p).ln_1p()).exp_m1();
+    let max_p_value = sidak(normal_two_sided(max_z), coefficients);
+    let chi_square = sequency_chi_squares.iter().copied().fold(0.0, f64::max);
+    let p_value = sidak(chi_square_sf(chi_square, blocks as f64), block_len as f64);
+    Ok(WalshTestReport {
+        blocks,
+        block_len,
+        alpha,
+        sequency_chi_squares,
+        chi_square,
+        p_value,
+        max_z,
+        max_p_value,
+        anomalies,
+    })
+}
+
+/// The balance of ones and zeros in a sequence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Balance {
+    /// The number of ones.
+    pub ones: usize,
+    /// The number of zeros.
+    pub zeros: usize,
+    /// `(ones − zeros) / √n`, approximately standard normal for fair bits.
+    pub z: f64,
+    /// The two-sided p-value of `z`.
+    pub p_value: f64,
+}
+
+/// The runs of identical bits in a sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Runs {
+    /// The total number of runs.
+    pub count: usize,
+    /// The length of the longest run.
+    pub longest: usize,
+    /// The number of runs of each length, where entry `i` counts runs of
+    /// length `i + 1`.
+    pub histogram: Vec<usize>,
+    /// The Wald–Wolfowitz statistic, the standardized number of runs given
+    /// the numbers of ones and zeros.
+    pub z: f64,
+    /// The two-sided p-value of `z`.
+    pub p_value: f64,
+}
+
+/// The dyadic autocorrelation of a sequence at one shift, summed over
+/// blocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutocorrelationPeak {
+    /// The dyadic shift `τ`, in `1..block_len`.
+    pub shift: usize,
+    /// The sum of `x[i] · x[i ^ τ]` over every block, with bits mapped to
+    /// ±1.
+    pub value: i64,
+    /// The value divided by its standard deviation under the null
+    /// hypothesis.
+    pub z: f64,
+    /// The two-sided p-value of `z` for this shift alone.
+    pub p_value: f64,
+}
+
+/// A combined sequence-quality report from [`sequence_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceReport {
+    /// The Walsh spectral test of the complete blocks.
+    pub spectral: WalshTestReport,
+    /// The balance of the whole sequence.
+    pub balance: Balance,
+    /// The runs in the whole sequence.
+    pub runs: Runs,
+    /// The shift with the largest dyadic autocorrelation magnitude, if
+    /// the blocks have more than one bit.
+    pub max_autocorrelation: Option<AutocorrelationPeak>,
+    /// The shifts whose autocorrelation is significant after a Bonferroni
+    /// correction for the number of shifts, strongest first.
+    pub autocorrelation_peaks: Vec<AutocorrelationPeak>,
+    alpha: f64,
+}
+
+impl SequenceReport {
+    /// Whether no test in the report rejects at the significance level.
+    pub fn passed(&self) -> bool {
+        self.spectral.passed()
+            && self.balance.p_value >= self.alpha
+            && self.runs.p_value >= self.alpha
+            && self.autocorrelation_peaks.is_empty()
+    }
+}
+
+/// Assess `bits` with the [`walsh_spectral_test`] on blocks of
+/// `block_len` bits together with balance, runs, and dyadic
+/// autocorrelation statistics, all at significance level `alpha`.
+///
+/// # Errors
+///
+/// As for [`walsh_spectral_test`].
+///
+/// # Example
+///
+/// ```
+/// // Alternating bits are perfectly balanced but have far too many runs.
+/// let bits: Vec<bool> = (0..1024).map(|i| i % 2 == 0).collect();
+/// let report = fwt::sequence_report(&bits, 64, 0.01).unwrap();
+/// assert_eq!(report.balance.z, 0.0);
+/// assert_eq!(report.runs.longest, 1);
+/// assert!(report.runs.p_value < 1e-10);
+/// assert_eq!(report.max_autocorrelation.unwrap().shift, 1);
+/// assert!(!report.passed());
+/// ```
+pub fn sequence_report(
+    bits: &[bool],
+    block_len: usize,
+    alpha: f64,
+) -> Result<SequenceReport, Error> {
+    let spectral = walsh_spectral_test(bits, block_len, alpha)?;
+    let n = bits.len() as f64;
+
+    let ones = bits.iter().filter(|&&b| b).count();
+    let zeros = bits.len() - ones;
+    let z = (ones as f64 - zeros as f64) / n.sqrt();
+    let balance = Balance {
+        ones,
+        zeros,
+        z,
+        p_value: normal_two_sided(z),
+    };
+
+    let mut histogram = Vec::new();
+    let mut run = 1;
+    for i in 1..=bits.len() {
+        if i < bits.len() && bits[i] == bits[i - 1] {
+            run += 1;
+        } else {
+            if histogram.len() < run {
+                histogram.resize(run, 0);
+            }
+            histogram[run - 1] += 1;
+            run = 1;
+        }
+    }
+    let count: usize = histogram.iter().sum();
+    let (n1, n0) = (ones as f64, zeros as f64);
+    let mean = 2.0 * n1 * n0 / n + 1.0;
+    let variance = (mean - 1.0) * (mean - 2.0) / (n - 1.0);
+    let z = if variance > 0.0 {
+        (count as f64 - mean) / variance.sqrt()
+    } else {
+        0.0
+    };
+    let runs = Runs {
+        count,
+        longest: histogram.len(),
+        histogram,
+        z,
+        p_value: normal_two_sided(z),
+    };
+
+    // Under the null hypothesis each shift pairs the bits of a block into
+    // block_len / 2 independent products, each counted twice.
+    let mut totals = vec![0i64; block_len];
+    let mut signs = vec![0i64; block_len];
+    for chunk in bits.chunks_exact(block_len) {
+        for (s, &bit) in signs.iter_mut().zip(chunk) {
+            *s = if bit { -1 } else { 1 };
+        }
+        for (t, r) in totals.iter_mut().zip(dyadic_autocorrelation(&signs)?) {
+            *t += r;
+        }
+    }
+    let deviation = (2.0 * (spectral.blocks() * block_len) as f64).sqrt();
+    let mut peaks: Vec<AutocorrelationPeak> = totals
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(shift, &value)| {
+            let z = value as f64 / deviation;
+            AutocorrelationPeak {
+                shift,
+                value,
+                z,
+                p_value: normal_two_sided(z),
+            }
+        })
+        .collect();
+    peaks.sort_by(|a, b| b.z.abs().total_cmp(&a.z.abs()).then(a.shift.cmp(&b.shift)));
+    let max_autocorrelation = peaks.first().copied();
+    let threshold = alpha / (block_len - 1).max(1) as f64;
+    peaks.retain(|p| p.p_value < threshold);
+
+    Ok(SequenceReport {
+        spectral,
+        balance,
+        runs,
+        max_autocorrelation,
+        autocorrelation_peaks: peaks,
+        alpha,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn test_random_bits_pass() {
+        let mut rng = SplitMix64::new(2024);
+        let bits: Vec<bool> = (0..1 << 16).map(|_| rng.sign()).collect();
+        let report = walsh_spectral_test(&bits, 1024, 0.001).unwrap();
+        assert_eq!(report.blocks(), 64);
+        assert!(report.passed());
+        assert!(report.anomalies().is_empty());
+        assert_eq!(report.sequency_chi_squares().len(), 1024);
+        assert!(report.p_value() > 0.01);
+    }
+
+    #[test]
+    fn test_repeated_blocks_fail() {
+        // Every block is the same random block: each block alone looks
+        // random, but its spectrum recurs in every block.
+        let mut rng = SplitMix64::new(31);
+        let block: Vec<bool> = (0..256).map(|_| rng.sign()).collect();
+        let bits: Vec<bool> = block.iter().copied().cycle().take(1 << 14).collect();
+        let single = walsh_spectral_test(&block, 256, 0.01).unwrap();
+        assert!(single.passed());
+        let report = walsh_spectral_test(&bits, 256, 0.01).unwrap();
+        assert!(report.p_value() < 1e-9);
+        assert!(!report.passed());
+        let strongest = (0..256)
+            .max_by(|&a, &b| {
+                let chi = report.sequency_chi_squares();
+                chi[a].total_cmp(&chi[b])
+            })
+            .unwrap();
+        assert_eq!(
+            report.chi_square(),
+            report.sequency_chi_squares()[strongest]
+        );
+    }
+
+    #[test]
+    fn test_biased_bits_fail() {
+        let mut rng = SplitMix64::new(5);
+        // Roughly 53% ones.
+        let bits: Vec<bool> = (0..1 << 16).map(|_| rng.below(100) < 53).collect();
+        let report = walsh_spectral_test(&bits, 4096, 0.01).unwrap();
+        assert!(!report.passed());
+        assert!(report
+            .anomalies()
+            .iter()
+            .all(|a| a.sequency == 0 && a.z < 0.0));
+    }
+
+    #[test]
+    fn test_sequence_report() {
+        let mut rng = SplitMix64::new(77);
+        let bits: Vec<bool> = (0..1 << 15).map(|_| rng.sign()).collect();
+        let report = sequence_report(&bits, 512, 0.001).unwrap();
+        assert!(report.passed());
+        assert_eq!(
+            report.runs.histogram.iter().sum::<usize>(),
+            report.runs.count
+        );
+        let covered: usize = report
+            .runs
+            .histogram
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i + 1) * c)
+            .sum();
+        assert_eq!(covered, bits.len());
+        // Copying each bit into its dyadic partner at shift 8 is invisible
+        // to balance but plain in the autocorrelation.
+        let mut paired = bits.clone();
+        for i in 0..paired.len() {
+            if i & 8 != 0 {
+                paired[i] = paired[i ^ 8];
+            }
+        }
+        let report = sequence_report(&paired, 512, 0.001).unwrap();
+        assert_eq!(
+            report.autocorrelation_peaks.first().map(|p| p.shift),
+            Some(8)
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            walsh_spectral_test(&[true; 10], 3, 0.01),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            walsh_spectral_test(&[true; 10], 16, 0.01),
+            Err(Error::LengthMismatch {
+                expected: 16,
+                found: 10
+            })
+        );
+    }
+}
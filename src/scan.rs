@@ -0,0 +1,236 @@
+//! Serialization orders for square blocks of 2D coefficients.
+
+use crate::{power_of_2, Error};
+
+/// An order in which to visit the coefficients of a square block, such
+/// as one produced by [`fwt2`](crate::fwt2) or
+/// [`block_transform_2d`](crate::block_transform_2d). Every order starts
+/// at the DC coefficient in the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scan {
+    /// Anti-diagonals of increasing total sequency, alternating in
+    /// direction, as in JPEG. With sequency-ordered coefficients this
+    /// visits low sequencies before high ones.
+    Zigzag,
+    /// The Morton (Z-order) curve, which interleaves the bits of the row
+    /// and column indices, so each aligned power-of-2 sub-block is
+    /// visited contiguously.
+    Morton,
+    /// The Hilbert curve, which visits each aligned power-of-2 sub-block
+    /// contiguously and only ever steps between adjacent coefficients.
+    Hilbert,
+}
+
+/// Return the row-major indices of a `size × size` block in the order
+/// given by `scan`: element `k` is the position of the `k`th coefficient
+/// visited.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if `size` is not a power of 2 and the
+/// scan is [`Scan::Morton`] or [`Scan::Hilbert`]. A zigzag scan accepts
+/// any size.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Scan;
+///
+/// assert_eq!(fwt::scan_order(3, Scan::Zigzag), Ok(vec![0, 1, 3, 6, 4, 2, 5, 7, 8]));
+/// assert_eq!(fwt::scan_order(2, Scan::Morton), Ok(vec![0, 1, 2, 3]));
+/// assert_eq!(fwt::scan_order(2, Scan::Hilbert), Ok(vec![0, 2, 3, 1]));
+/// ```
+pub fn scan_order(size: usize, scan: Scan) -> Result<Vec<usize>, Error> {
+    let count = size.checked_mul(size).ok_or(Error::LengthMismatch {
+        expected: usize::MAX,
+        found: size,
+    })?;
+    if scan != Scan::Zigzag && !power_of_2(size) {
+        return Err(Error::NotPowerOfTwo(size));
+    }
+    let order = match scan {
+        Scan::Zigzag => (0..(2 * size).saturating_sub(1))
+            .flat_map(|d| {
+                let first = d.saturating_sub(size - 1);
+                let last = d.min(size - 1);
+                let rows: Box<dyn Iterator<Item = usize>> = if d % 2 == 0 {
+                    Box::new((first..=last).rev())
+                } else {
+                    Box::new(first..=last)
+                };
+                rows.map(move |r| r * size + d - r)
+            })
+            .collect(),
+        Scan::Morton => (0..count)
+            .map(|k| {
+                let (row, col) = (compact_bits(k >> 1), compact_bits(k));
+                row * size + col
+            })
+            .collect(),
+        Scan::Hilbert => (0..count)
+            .map(|k| {
+                let (row, col) = hilbert_point(size, k);
+                row * size + col
+            })
+            .collect(),
+    };
+    Ok(order)
+}
+
+/// Return the coefficients of the `size × size` row-major block
+/// `coefficients` serialized in the order given by `scan`.
+///
+/// # Errors
+///
+/// Returns the errors of [`scan_order`], and [`Error::LengthMismatch`] if
+/// `coefficients` does not hold `size * size` elements.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, Scan};
+///
+/// let block = [9, 9, 9, 9, 9, 9, 9, 9, 1, 1, 1, 1, 1, 1, 1, 1];
+/// let spectrum = fwt::fwt2(&block, 4, 4, Ordering::Sequency).unwrap();
+/// let serialized = fwt::scan_2d(&spectrum, 4, Scan::Zigzag).unwrap();
+/// // The energy is gathered at the front of the scan.
+/// assert_eq!(&serialized[..3], &[80, 0, 64]);
+/// assert!(serialized[3..].iter().all(|&c| c == 0));
+/// assert_eq!(fwt::unscan_2d(&serialized, 4, Scan::Zigzag), Ok(spectrum));
+/// ```
+pub fn scan_2d<T: Copy>(coefficients: &[T], size: usize, scan: Scan) -> Result<Vec<T>, Error> {
+    let order = checked_order(coefficients.len(), size, scan)?;
+    Ok(order.into_iter().map(|i| coefficients[i]).collect())
+}
+
+/// Return the `size × size` row-major block whose serialization in the
+/// order given by `scan` is `serialized`, inverting [`scan_2d`].
+///
+/// # Errors
+///
+/// Returns the errors of [`scan_2d`].
+pub fn unscan_2d<T: Copy>(serialized: &[T], size: usize, scan: Scan) -> Result<Vec<T>, Error> {
+    let order = checked_order(serialized.len(), size, scan)?;
+    let mut block = serialized.to_vec();
+    for (&x, i) in serialized.iter().zip(order) {
+        block[i] = x;
+    }
+    Ok(block)
+}
+
+// The scan order, after checking that a block of `length` elements is
+// `size × size`.
+fn checked_order(length: usize, size: usize, scan: Scan) -> Result<Vec<usize>, Error> {
+    let order = scan_order(size, scan)?;
+    if order.len() == length {
+        Ok(order)
+    } else {
+        Err(Error::LengthMismatch {
+            expected: order.len(),
+            found: length,
+        })
+    }
+}
+
+// Gather the even-numbered bits of `k` into the low bits of the result.
+fn compact_bits(k: usize) -> usize {
+    (0..usize::BITS / 2).fold(0, |acc, b| acc | ((k >> (2 * b)) & 1) << b)
+}
+
+// The (row, column) of step `k` along the Hilbert curve filling a
+// `size × size` block, starting at the top left and ending at the top
+// right.
+fn hilbert_point(size: usize, k: usize) -> (usize, usize) {
+    let (mut row, mut col) = (0, 0);
+    let mut t = k;
+    let mut s = 1;
+    while s < size {
+        let rx = (t >> 1) & 1;
+        let ry = (t ^ rx) & 1;
+        if ry == 0 {
+            if rx == 1 {
+                row = s - 1 - row;
+                col = s - 1 - col;
+            }
+            std::mem::swap(&mut row, &mut col);
+        }
+        col += s * rx;
+        row += s * ry;
+        t >>= 2;
+        s <<= 1;
+    }
+    (row, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orders_are_permutations() {
+        for size in [1, 2, 4, 8, 16] {
+            for scan in [Scan::Zigzag, Scan::Morton, Scan::Hilbert] {
+                let mut order = scan_order(size, scan).unwrap();
+                assert_eq!(order[0], 0);
+                order.sort_unstable();
+                assert!(order.into_iter().eq(0..size * size));
+            }
+        }
+        let mut order = scan_order(5, Scan::Zigzag).unwrap();
+        order.sort_unstable();
+        assert!(order.into_iter().eq(0..25));
+    }
+
+    #[test]
+    fn test_hilbert_steps_are_adjacent() {
+        let size = 16;
+        let order = scan_order(size, Scan::Hilbert).unwrap();
+        for pair in order.windows(2) {
+            let (r0, c0) = (pair[0] / size, pair[0] % size);
+            let (r1, c1) = (pair[1] / size, pair[1] % size);
+            assert_eq!(r0.abs_diff(r1) + c0.abs_diff(c1), 1);
+        }
+        // Each quadrant is visited contiguously.
+        let quadrant = |i: usize| (i / size >= 8, i % size >= 8);
+        for chunk in order.chunks(64) {
+            assert!(chunk.iter().all(|&i| quadrant(i) == quadrant(chunk[0])));
+        }
+    }
+
+    #[test]
+    fn test_morton_and_zigzag_layout() {
+        assert_eq!(
+            scan_order(4, Scan::Morton),
+            Ok(vec![0, 1, 4, 5, 2, 3, 6, 7, 8, 9, 12, 13, 10, 11, 14, 15])
+        );
+        assert_eq!(
+            scan_order(4, Scan::Zigzag),
+            Ok(vec![0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15])
+        );
+        assert_eq!(scan_order(0, Scan::Zigzag), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_scan_round_trip_and_errors() {
+        let block: Vec<u16> = (0..64).collect();
+        for scan in [Scan::Zigzag, Scan::Morton, Scan::Hilbert] {
+            let serialized = scan_2d(&block, 8, scan).unwrap();
+            assert_eq!(unscan_2d(&serialized, 8, scan), Ok(block.clone()));
+        }
+        assert_eq!(
+            scan_2d(&block, 6, Scan::Morton),
+            Err(Error::NotPowerOfTwo(6))
+        );
+        assert_eq!(
+            scan_2d(&block, 4, Scan::Hilbert),
+            Err(Error::LengthMismatch {
+                expected: 16,
+                found: 64
+            })
+        );
+        assert_eq!(
+            unscan_2d(&block[..9], 3, Scan::Zigzag).map(|b| b.len()),
+            Ok(9)
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Runtime known-answer checks.
+
+use std::fmt;
+
+use crate::{
+    dyadic_convolution, hadamard, hadamard_mod, sequency, walsh_spectrum_packed, Normalize,
+    Ordering, Plan, WalshElement,
+};
+
+/// The outcome of one check performed by [`self_test`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    name: String,
+    passed: bool,
+}
+
+impl Check {
+    /// A short description of what was checked.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the check produced the expected answer.
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+}
+
+/// The results of [`self_test`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    /// Whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+
+    /// Every check that was performed, in order.
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|c| !c.passed)
+    }
+
+    fn record(&mut self, name: String, passed: bool) {
+        self.checks.push(Check { name, passed });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed = self.failures().count();
+        writeln!(
+            f,
+            "{} of {} checks passed",
+            self.checks.len() - failed,
+            self.checks.len()
+        )?;
+        for check in self.failures() {
+            writeln!(f, "FAILED: {}", check.name)?;
+        }
+        Ok(())
+    }
+}
+
+// Rows of the length-8 Walsh matrices, from which every unit-vector
+// transform can be checked.
+const SEQUENCY_8: [[i8; 8]; 8] = [
+    [1, 1, 1, 1, 1, 1, 1, 1],
+    [1, 1, 1, 1, -1, -1, -1, -1],
+    [1, 1, -1, -1, -1, -1, 1, 1],
+    [1, 1, -1, -1, 1, 1, -1, -1],
+    [1, -1, -1, 1, 1, -1, -1, 1],
+    [1, -1, -1, 1, -1, 1, 1, -1],
+    [1, -1, 1, -1, -1, 1, -1, 1],
+    [1, -1, 1, -1, 1, -1, 1, -1],
+];
+
+const HADAMARD_8: [[i8; 8]; 8] = [
+    [1, 1, 1, 1, 1, 1, 1, 1],
+    [1, -1, 1, -1, 1, -1, 1, -1],
+    [1, 1, -1, -1, 1, 1, -1, -1],
+    [1, -1, -1, 1, 1, -1, -1, 1],
+    [1, 1, 1, 1, -1, -1, -1, -1],
+    [1, -1, 1, -1, -1, 1, -1, 1],
+    [1, 1, -1, -1, -1, -1, 1, 1],
+    [1, -1, -1, 1, -1, 1, 1, -1],
+];
+
+fn known_answers<T>(report: &mut SelfTestReport, type_name: &str)
+where
+    T: WalshElement + From<i8> + PartialEq,
+{
+    for (ordering, table) in [
+        (Ordering::Sequency, &SEQUENCY_8),
+        (Ordering::Hadamard, &HADAMARD_8),
+    ] {
+        let passed = (0..8).all(|i| {
+            let unit: Vec<T> = (0..8).map(|j| T::from((i == j) as i8)).collect();
+            let expected: Vec<T> = table[i].iter().map(|&x| T::from(x)).collect();
+            let result = match ordering {
+                Ordering::Sequency => sequency(&unit),
+                Ordering::Hadamard => hadamard(&unit),
+            };
+            result == Some(expected)
+        });
+        report.record(
+            format!("{:?} known answers ({})", ordering, type_name),
+            passed,
+        );
+    }
+}
+
+fn round_trips<T>(report: &mut SelfTestReport, type_name: &str)
+where
+    T: WalshElement + Normalize + From<i8> + PartialEq,
+{
+    for length in [1, 2, 64, 1024] {
+        let input: Vec<T> = (0..length)
+            .map(|i| T::from(((i * 37 + 11) % 101) as i8 - 50))
+            .collect();
+        for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+            let passed = Plan::new(length, ordering)
+                .and_then(|plan| plan.transform(&input).and_then(|v| plan.transform(&v)))
+                .is_ok_and(|v| {
+                    v.into_iter()
+                        .map(|x| x.normalize(length))
+                        .eq(input.iter().map(|&x| Some(x)))
+                });
+            report.record(
+                format!(
+                    "{:?} round trip, length {} ({})",
+                    ordering, length, type_name
+                ),
+                passed,
+            );
+        }
+    }
+}
+
+/// Run a battery of known-answer checks on the transforms and report the
+/// results.
+///
+/// The checks cover both orderings, several element types and lengths,
+/// round trips through plans, and the derived operations (dyadic
+/// convolution, modular transforms, and bit-packed Boolean spectra). They
+/// take well under a millisecond in optimized builds, so deployments that
+/// must verify numerical libraries at startup can call this
+/// unconditionally.
+///
+/// # Example
+///
+/// ```
+/// let report = fwt::self_test();
+/// assert!(report.passed(), "{}", report);
+/// ```
+pub fn self_test() -> SelfTestReport {
+    let mut report = SelfTestReport { checks: Vec::new() };
+    known_answers::<i32>(&mut report, "i32");
+    known_answers::<i64>(&mut report, "i64");
+    known_answers::<f32>(&mut report, "f32");
+    known_answers::<f64>(&mut report, "f64");
+    round_trips::<i32>(&mut report, "i32");
+    round_trips::<f64>(&mut report, "f64");
+
+    let convolution = dyadic_convolution(&[1, 2, 3, 4], &[1, 0, 0, 1]);
+    report.record(
+        "dyadic convolution".to_string(),
+        convolution == Ok(vec![5, 5, 5, 5]),
+    );
+
+    let modular = hadamard_mod(&[1, 2, 3, 4], 7);
+    report.record(
+        "Hadamard transform modulo 7".to_string(),
+        modular == Some(vec![3, 5, 3, 0]),
+    );
+
+    let packed = walsh_spectrum_packed(&[0b1000], 2);
+    report.record(
+        "packed Boolean spectrum".to_string(),
+        packed == Some(vec![2, 2, 2, -2]),
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = self_test();
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.failures().count(), 0);
+        assert!(report.checks().len() > 20);
+        assert!(report.to_string().starts_with(&format!(
+            "{} of {} checks passed",
+            report.checks().len(),
+            report.checks().len()
+        )));
+    }
+}
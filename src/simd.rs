@@ -0,0 +1,68 @@
+//! Portable SIMD butterflies for the primitive element types, built on
+//! the nightly `std::simd` module.
+
+use std::ops::{Add, Sub};
+use std::simd::{Simd, SimdElement};
+
+/// Butterfly `a` with `b` as in
+/// [`WalshElement::butterfly`](crate::WalshElement::butterfly), or with
+/// exchanged outputs if `crossed`, `N` lanes at a time. Lane-wise
+/// additions and subtractions are exactly the scalar ones, so results
+/// match the scalar loop bit for bit.
+pub(crate) fn butterfly<T, const N: usize>(a: &mut [T], b: &mut [T], crossed: bool)
+where
+    T: SimdElement + Add<Output = T> + Sub<Output = T>,
+    Simd<T, N>: Add<Output = Simd<T, N>> + Sub<Output = Simd<T, N>>,
+{
+    let mut a_chunks = a.chunks_exact_mut(N);
+    let mut b_chunks = b.chunks_exact_mut(N);
+    for (x, y) in (&mut a_chunks).zip(&mut b_chunks) {
+        let (p, q) = (Simd::<T, N>::from_slice(x), Simd::<T, N>::from_slice(y));
+        let (sum, difference) = (p + q, p - q);
+        if crossed {
+            difference.copy_to_slice(x);
+            sum.copy_to_slice(y);
+        } else {
+            sum.copy_to_slice(x);
+            difference.copy_to_slice(y);
+        }
+    }
+    let rest = a_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(b_chunks.into_remainder());
+    for (x, y) in rest {
+        (*x, *y) = if crossed {
+            (*x - *y, *x + *y)
+        } else {
+            (*x + *y, *x - *y)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_scalar_butterflies() {
+        for length in [0, 1, 3, 4, 7, 8, 9, 17, 64] {
+            let a: Vec<f64> = (0..length).map(|i| (i as f64 * 0.37).sin()).collect();
+            let b: Vec<f64> = (0..length).map(|i| (i as f64 * 1.91).cos()).collect();
+            for crossed in [false, true] {
+                let (mut x, mut y) = (a.clone(), b.clone());
+                butterfly::<f64, 4>(&mut x, &mut y, crossed);
+                for i in 0..length {
+                    let (s, d) = (a[i] + b[i], a[i] - b[i]);
+                    let (p, q) = if crossed { (d, s) } else { (s, d) };
+                    assert_eq!((x[i].to_bits(), y[i].to_bits()), (p.to_bits(), q.to_bits()));
+                }
+            }
+            let mut x: Vec<i32> = (0..length as i32).collect();
+            let mut y: Vec<i32> = (0..length as i32).map(|i| 3 * i - 5).collect();
+            butterfly::<i32, 8>(&mut x, &mut y, false);
+            assert!((0..length as i32)
+                .all(|i| x[i as usize] == 4 * i - 5 && y[i as usize] == 5 - 2 * i));
+        }
+    }
+}
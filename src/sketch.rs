@@ -0,0 +1,578 @@
+//! Subsampled randomized Hadamard transform (SRHT) sketches.
+//!
+//! An SRHT maps the `n` rows of a tall matrix to `k` rows as
+//! `S = √(1/k) · R H D`, where `D` flips the signs of random rows, `H` is
+//! the Hadamard transform (after zero-padding `n` to a power of 2) and `R`
+//! keeps `k` random rows. The transform spreads every input row across
+//! all outputs, so uniform row sampling afterwards preserves inner
+//! products: `E[(SA)ᵀ(SB)] = AᵀB`.
+
+use crate::rng::SplitMix64;
+use crate::{hadamard_in_place, linalg, Error};
+
+/// A seeded SRHT sketching operator for matrices with a fixed number of
+/// rows. Matrices are row-major `f64` slices.
+///
+/// # Example
+///
+/// ```
+/// let sketch = fwt::Srht::new(1000, 64, 7).unwrap();
+/// let column: Vec<f64> = (0..1000).map(|i| (i as f64).sin()).collect();
+/// let sketched = sketch.sketch(&column, 1).unwrap();
+/// assert_eq!(sketched.len(), 64);
+/// // Norms are preserved approximately.
+/// let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+/// assert!((norm(&sketched) / norm(&column) - 1.0).abs() < 0.3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Srht {
+    rows: usize,
+    padded: usize,
+    seed: u64,
+    signs: Vec<bool>,
+    samples: Vec<usize>,
+}
+
+impl Srht {
+    /// Create a sketch reducing `rows` rows to `sketch_rows`, drawn from
+    /// `seed`. The same arguments always give the same sketch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `sketch_rows` is 0 or exceeds
+    /// `rows` rounded up to a power of 2.
+    pub fn new(rows: usize, sketch_rows: usize, seed: u64) -> Result<Srht, Error> {
+        let padded = rows.next_power_of_two();
+        if sketch_rows == 0 || sketch_rows > padded {
+            return Err(Error::LengthMismatch {
+                expected: padded,
+                found: sketch_rows,
+            });
+        }
+        let mut rng = SplitMix64::new(seed);
+        let signs = (0..rows).map(|_| rng.sign()).collect();
+        let mut samples: Vec<usize> = (0..padded).collect();
+        rng.shuffle(&mut samples);
+        samples.truncate(sketch_rows);
+        samples.sort_unstable();
+        Ok(Srht {
+            rows,
+            padded,
+            seed,
+            signs,
+            samples,
+        })
+    }
+
+    /// The number of rows of the matrices this sketch applies to.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of rows of a sketched matrix.
+    pub fn sketch_rows(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The seed the sketch was drawn from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Return the `sketch_rows × cols` sketch of the `rows × cols`
+    /// row-major `matrix`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `matrix` does not hold
+    /// `rows * cols` elements.
+    pub fn sketch(&self, matrix: &[f64], cols: usize) -> Result<Vec<f64>, Error> {
+        if matrix.len() != self.rows * cols {
+            return Err(Error::LengthMismatch {
+                expected: self.rows * cols,
+                found: matrix.len(),
+            });
+        }
+        Ok(self.sketch_block(matrix, 0, cols))
+    }
+
+    /// Return the summary of the block of consecutive rows `block`,
+    /// starting at row `first_row` of a `rows × cols` matrix. Because the
+    /// sketch is linear, summaries of disjoint blocks can be computed
+    /// separately, for example by distributed workers, and combined with
+    /// [`SrhtSummary::merge`] into the sketch of the whole matrix.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `block` is not a whole number
+    /// of rows or extends past the last row.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let srht = fwt::Srht::new(6, 4, 11).unwrap();
+    /// let matrix = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0];
+    /// let mut summary = srht.summarize(&matrix[..4], 0, 2).unwrap();
+    /// summary.merge(&srht.summarize(&matrix[4..], 2, 2).unwrap()).unwrap();
+    /// assert!(summary.is_complete());
+    /// let whole = srht.sketch(&matrix, 2).unwrap();
+    /// assert!(summary.sketch().iter().zip(&whole).all(|(a, b)| (a - b).abs() < 1e-12));
+    /// ```
+    pub fn summarize(
+        &self,
+        block: &[f64],
+        first_row: usize,
+        cols: usize,
+    ) -> Result<SrhtSummary, Error> {
+        let block_rows = block.len().checked_div(cols).unwrap_or(0);
+        if block_rows * cols != block.len() || first_row + block_rows > self.rows {
+            return Err(Error::LengthMismatch {
+                expected: (self.rows.saturating_sub(first_row)) * cols,
+                found: block.len(),
+            });
+        }
+        Ok(SrhtSummary {
+            rows: self.rows,
+            sketch_rows: self.samples.len(),
+            seed: self.seed,
+            cols,
+            rows_seen: block_rows,
+            sketch: self.sketch_block(block, first_row, cols),
+        })
+    }
+
+    // The sketch of the matrix that is zero outside the rows of `block`,
+    // which start at `first_row`.
+    fn sketch_block(&self, block: &[f64], first_row: usize, cols: usize) -> Vec<f64> {
+        let scale = (self.samples.len() as f64).sqrt().recip();
+        let k = self.samples.len();
+        let block_rows = block.len().checked_div(cols).unwrap_or(0);
+        let mut out = vec![0.0; k * cols];
+        let mut column = vec![0.0; self.padded];
+        for c in 0..cols {
+            column.fill(0.0);
+            for r in 0..block_rows {
+                let x = block[r * cols + c];
+                column[first_row + r] = if self.signs[first_row + r] { -x } else { x };
+            }
+            hadamard_in_place(&mut column).expect("padded length is a power of 2");
+            for (i, &s) in self.samples.iter().enumerate() {
+                out[i * cols + c] = column[s] * scale;
+            }
+        }
+        out
+    }
+
+    /// Estimate the product `AᵀB` of two tall matrices with `rows` rows,
+    /// `a` having `a_cols` columns and `b` having `b_cols`, from their
+    /// sketches.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if either matrix has the wrong
+    /// number of elements.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let n = 4096;
+    /// let a: Vec<f64> = (0..n * 2).map(|i| ((i * 37 % 101) as f64 - 50.0) / 50.0).collect();
+    /// let b: Vec<f64> = (0..n).map(|i| a[2 * i] + 0.5 * ((i * 13 % 7) as f64)).collect();
+    /// let estimate = fwt::Srht::new(n, 512, 1).unwrap().approx_product(&a, 2, &b, 1).unwrap();
+    /// let exact: f64 = (0..n).map(|i| a[2 * i] * b[i]).sum();
+    /// assert!((estimate.product()[0] - exact).abs() < 3.0 * estimate.error_estimate());
+    /// ```
+    pub fn approx_product(
+        &self,
+        a: &[f64],
+        a_cols: usize,
+        b: &[f64],
+        b_cols: usize,
+    ) -> Result<ProductEstimate, Error> {
+        let sa = self.sketch(a, a_cols)?;
+        let sb = self.sketch(b, b_cols)?;
+        let k = self.samples.len();
+        let mut product = vec![0.0; a_cols * b_cols];
+        for i in 0..k {
+            let row_a = &sa[i * a_cols..(i + 1) * a_cols];
+            let row_b = &sb[i * b_cols..(i + 1) * b_cols];
+            for (p, &x) in row_a.iter().enumerate() {
+                for (q, &y) in row_b.iter().enumerate() {
+                    product[p * b_cols + q] += x * y;
+                }
+            }
+        }
+        // Each sketch row contributes k times its outer product to an
+        // unbiased estimate, and the product is their mean, so the spread
+        // of the contributions gives its standard error.
+        let mut variance = 0.0;
+        for i in 0..k {
+            let row_a = &sa[i * a_cols..(i + 1) * a_cols];
+            let row_b = &sb[i * b_cols..(i + 1) * b_cols];
+            for (p, &x) in row_a.iter().enumerate() {
+                for (q, &y) in row_b.iter().enumerate() {
+                    let deviation = k as f64 * x * y - product[p * b_cols + q];
+                    variance += deviation * deviation;
+                }
+            }
+        }
+        let error_estimate = if k > 1 {
+            (variance / (k * (k - 1)) as f64).sqrt()
+        } else {
+            f64::INFINITY
+        };
+        Ok(ProductEstimate {
+            product,
+            rows: a_cols,
+            cols: b_cols,
+            error_estimate,
+        })
+    }
+
+    /// Approximate the statistical leverage scores of the rows of the
+    /// `rows × cols` row-major `matrix`: the squared row norms of an
+    /// orthonormal basis for its column space, which sum to `cols`.
+    ///
+    /// The sketch `SA` preserves the column space geometry, so with
+    /// `SA = QR` the matrix `AR⁻¹` is nearly orthonormal and its squared
+    /// row norms approximate the leverage scores. This costs one sketch,
+    /// one small QR factorization and a triangular solve per row, instead
+    /// of an `n × d` factorization.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `matrix` has the wrong number
+    /// of elements, or [`Error::Singular`] if the sketch does not have
+    /// full column rank (for example, because it has fewer rows than
+    /// `cols`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // A row far from the others dominates the column space.
+    /// let mut a: Vec<f64> = (0..512 * 2).map(|i| ((i * 7 % 13) as f64 - 6.0) / 6.0).collect();
+    /// a[0] = 100.0;
+    /// let scores = fwt::Srht::new(512, 64, 5).unwrap().leverage_scores(&a, 2).unwrap();
+    /// assert!(scores[0] > 0.9);
+    /// assert!((scores.iter().sum::<f64>() - 2.0).abs() < 0.5);
+    /// ```
+    pub fn leverage_scores(&self, matrix: &[f64], cols: usize) -> Result<Vec<f64>, Error> {
+        let sketched = self.sketch(matrix, cols)?;
+        let r = linalg::qr_r(sketched, self.samples.len(), cols).ok_or(Error::Singular)?;
+        Ok(matrix
+            .chunks_exact(cols.max(1))
+            .map(|row| {
+                linalg::solve_upper_transposed(&r, row)
+                    .iter()
+                    .map(|z| z * z)
+                    .sum()
+            })
+            .collect())
+    }
+}
+
+/// The SRHT sketch of some of the rows of a matrix, from
+/// [`Srht::summarize`]. Summaries of disjoint row blocks sketched with the
+/// same [`Srht`] add up to the sketch of their union, so they can be
+/// merged in any order and grouping, and can be persisted or sent between
+/// processes with [`format`](crate::format).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrhtSummary {
+    rows: usize,
+    sketch_rows: usize,
+    seed: u64,
+    cols: usize,
+    rows_seen: usize,
+    sketch: Vec<f64>,
+}
+
+impl SrhtSummary {
+    /// Reassemble a summary from its parts, as stored by
+    /// [`format`](crate::format).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `sketch` does not hold
+    /// `sketch_rows * cols` values, reporting `usize::MAX` as expected if
+    /// that number overflows, or more rows were seen than exist.
+    pub fn from_parts(
+        srht: &Srht,
+        cols: usize,
+        rows_seen: usize,
+        sketch: Vec<f64>,
+    ) -> Result<SrhtSummary, Error> {
+        let values = srht.sketch_rows().checked_mul(cols);
+        if values != Some(sketch.len()) {
+            return Err(Error::LengthMismatch {
+                expected: values.unwrap_or(usize::MAX),
+                found: sketch.len(),
+            });
+        }
+        if rows_seen > srht.rows() {
+            return Err(Error::LengthMismatch {
+                expected: srht.rows(),
+                found: rows_seen,
+            });
+        }
+        Ok(SrhtSummary {
+            rows: srht.rows(),
+            sketch_rows: srht.sketch_rows(),
+            seed: srht.seed(),
+            cols,
+            rows_seen,
+            sketch,
+        })
+    }
+
+    /// Add the rows summarized by `other` into this summary.
+    ///
+    /// The summaries must cover disjoint rows; overlapping rows would be
+    /// counted twice, which the summaries cannot detect.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IncompatibleSketches`] if the summaries come from
+    /// different sketches or matrices of different widths, or
+    /// [`Error::LengthMismatch`] if together they cover more rows than
+    /// the matrix has.
+    pub fn merge(&mut self, other: &SrhtSummary) -> Result<(), Error> {
+        let compatible = (self.rows, self.sketch_rows, self.seed, self.cols)
+            == (other.rows, other.sketch_rows, other.seed, other.cols);
+        if !compatible {
+            return Err(Error::IncompatibleSketches);
+        }
+        if self.rows_seen + other.rows_seen > self.rows {
+            return Err(Error::LengthMismatch {
+                expected: self.rows,
+                found: self.rows_seen + other.rows_seen,
+            });
+        }
+        for (a, b) in self.sketch.iter_mut().zip(&other.sketch) {
+            *a += b;
+        }
+        self.rows_seen += other.rows_seen;
+        Ok(())
+    }
+
+    /// Rebuild the [`Srht`] this summary was computed with.
+    pub fn srht(&self) -> Srht {
+        Srht::new(self.rows, self.sketch_rows, self.seed)
+            .expect("parameters came from a valid sketch")
+    }
+
+    /// The number of rows of the summarized matrix.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of rows of the sketch.
+    pub fn sketch_rows(&self) -> usize {
+        self.sketch_rows
+    }
+
+    /// The seed of the sketch.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number of columns of the summarized matrix.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The number of matrix rows included so far.
+    pub fn rows_seen(&self) -> usize {
+        self.rows_seen
+    }
+
+    /// Whether every row of the matrix has been included.
+    pub fn is_complete(&self) -> bool {
+        self.rows_seen == self.rows
+    }
+
+    /// The `sketch_rows × cols` sketch, row-major.
+    pub fn sketch(&self) -> &[f64] {
+        &self.sketch
+    }
+}
+
+/// An approximate matrix product from [`Srht::approx_product`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductEstimate {
+    product: Vec<f64>,
+    rows: usize,
+    cols: usize,
+    error_estimate: f64,
+}
+
+impl ProductEstimate {
+    /// The estimated product, row-major.
+    pub fn product(&self) -> &[f64] {
+        &self.product
+    }
+
+    /// The dimensions of the product.
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// The standard error of the estimate in the Frobenius norm, from the
+    /// spread of the sketch rows' contributions (infinite for a single
+    /// row). Sampling without replacement makes it slightly conservative.
+    pub fn error_estimate(&self) -> f64 {
+        self.error_estimate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_sketch_is_orthogonal() {
+        // Keeping every row of a padded length makes S orthogonal, so the
+        // product is exact up to rounding.
+        let rows = 64;
+        let sketch = Srht::new(rows, rows, 3).unwrap();
+        let a: Vec<f64> = (0..rows * 3).map(|i| (i as f64).cos()).collect();
+        let b: Vec<f64> = (0..rows * 2).map(|i| (i as f64 * 0.5).sin()).collect();
+        let estimate = sketch.approx_product(&a, 3, &b, 2).unwrap();
+        assert_eq!(estimate.shape(), (3, 2));
+        for p in 0..3 {
+            for q in 0..2 {
+                let exact: f64 = (0..rows).map(|r| a[r * 3 + p] * b[r * 2 + q]).sum();
+                assert!((estimate.product()[p * 2 + q] - exact).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_is_within_estimate() {
+        let rows = 3000;
+        let a: Vec<f64> = (0..rows * 4)
+            .map(|i| ((i * 7919 % 1009) as f64 / 504.5) - 1.0)
+            .collect();
+        let exact: Vec<f64> = (0..16)
+            .map(|pq| {
+                (0..rows)
+                    .map(|r| a[r * 4 + pq / 4] * a[r * 4 + pq % 4])
+                    .sum()
+            })
+            .collect();
+        for seed in 0..5 {
+            let estimate = Srht::new(rows, 256, seed)
+                .unwrap()
+                .approx_product(&a, 4, &a, 4)
+                .unwrap();
+            let error: f64 = estimate
+                .product()
+                .iter()
+                .zip(&exact)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f64>()
+                .sqrt();
+            assert!(error < 3.0 * estimate.error_estimate());
+        }
+    }
+
+    #[test]
+    fn test_leverage_scores_match_exact() {
+        // Exact scores from the QR factorization of the matrix itself.
+        let (rows, cols) = (256, 3);
+        let a: Vec<f64> = (0..rows * cols)
+            .map(|i| ((i * 7919 % 211) as f64 - 105.0) / (1.0 + (i % 17) as f64))
+            .collect();
+        let r = linalg::qr_r(a.clone(), rows, cols).unwrap();
+        let exact: Vec<f64> = a
+            .chunks_exact(cols)
+            .map(|row| {
+                linalg::solve_upper_transposed(&r, row)
+                    .iter()
+                    .map(|z| z * z)
+                    .sum()
+            })
+            .collect();
+        assert!((exact.iter().sum::<f64>() - cols as f64).abs() < 1e-9);
+        let full = Srht::new(rows, rows, 1)
+            .unwrap()
+            .leverage_scores(&a, cols)
+            .unwrap();
+        assert!(full.iter().zip(&exact).all(|(x, y)| (x - y).abs() < 1e-9));
+        let approx = Srht::new(rows, 64, 1)
+            .unwrap()
+            .leverage_scores(&a, cols)
+            .unwrap();
+        let error: f64 = approx.iter().zip(&exact).map(|(x, y)| (x - y).abs()).sum();
+        assert!(error < 0.5 * cols as f64);
+        assert_eq!(
+            Srht::new(rows, 2, 1).unwrap().leverage_scores(&a, cols),
+            Err(Error::Singular)
+        );
+    }
+
+    #[test]
+    fn test_merge_is_order_independent() {
+        let (rows, cols) = (100, 3);
+        let matrix: Vec<f64> = (0..rows * cols).map(|i| (i as f64 * 0.1).sin()).collect();
+        let srht = Srht::new(rows, 16, 4).unwrap();
+        let blocks = [(0, 30), (30, 45), (45, 100)];
+        let summaries: Vec<SrhtSummary> = blocks
+            .iter()
+            .map(|&(a, b)| {
+                srht.summarize(&matrix[a * cols..b * cols], a, cols)
+                    .unwrap()
+            })
+            .collect();
+        let whole = srht.sketch(&matrix, cols).unwrap();
+        for order in [[0, 1, 2], [2, 0, 1], [1, 2, 0]] {
+            let mut merged = summaries[order[0]].clone();
+            assert!(!merged.is_complete());
+            merged.merge(&summaries[order[1]]).unwrap();
+            merged.merge(&summaries[order[2]]).unwrap();
+            assert!(merged.is_complete());
+            assert!(merged
+                .sketch()
+                .iter()
+                .zip(&whole)
+                .all(|(a, b)| (a - b).abs() < 1e-9));
+        }
+        let mut merged = summaries[0].clone();
+        let other = Srht::new(rows, 16, 5)
+            .unwrap()
+            .summarize(&matrix[..cols], 0, cols)
+            .unwrap();
+        assert_eq!(merged.merge(&other), Err(Error::IncompatibleSketches));
+        let all = srht.summarize(&matrix, 0, cols).unwrap();
+        assert!(merged.merge(&all).is_err());
+        assert!(srht.summarize(&matrix[..4], 0, cols).is_err());
+        assert!(srht.summarize(&matrix[..30], 95, cols).is_err());
+        assert_eq!(all.srht(), srht);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Srht::new(100, 0, 0).is_err());
+        assert_eq!(
+            Srht::new(100, 129, 0),
+            Err(Error::LengthMismatch {
+                expected: 128,
+                found: 129
+            })
+        );
+        let sketch = Srht::new(10, 4, 0).unwrap();
+        assert_eq!(
+            sketch.sketch(&[1.0; 9], 1),
+            Err(Error::LengthMismatch {
+                expected: 10,
+                found: 9
+            })
+        );
+        assert_eq!(
+            SrhtSummary::from_parts(&sketch, usize::MAX, 0, vec![0.0; 4]).unwrap_err(),
+            Error::LengthMismatch {
+                expected: usize::MAX,
+                found: 4
+            }
+        );
+    }
+}
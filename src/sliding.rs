@@ -0,0 +1,197 @@
+//! The spectrum of a window sliding over a stream, updated sample by
+//! sample.
+
+use crate::{kernel, no_alloc, power_of_2, Error, Ordering, WalshElement};
+
+/// Maintains the transform of the latest `n` samples of a stream,
+/// updating it in O(n) operations as each sample arrives instead of the
+/// O(n log n) of transforming the window again.
+///
+/// The transform of a window is the butterfly of the transforms of its
+/// two halves, and the transform of its earlier half was computed as the
+/// later half of the window half a window ago. The slider keeps the
+/// transforms of the latest windows of every power-of-2 length below
+/// `n`, so that each sample costs one butterfly per length, `2n`
+/// additions and subtractions in all. Every spectrum is built from the
+/// samples in its window alone, so unlike sliding Fourier transforms
+/// floating-point errors do not accumulate over the stream, and integer
+/// spectra are exact. The price is memory: the history holds about n²/3
+/// elements, so windows of a few thousand samples are practical.
+///
+/// The window starts filled with zeros.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, SlidingFwt};
+///
+/// let mut sliding = SlidingFwt::new(4, Ordering::Sequency).unwrap();
+/// for x in [1, 2, 3, 4] {
+///     sliding.push(x);
+/// }
+/// assert_eq!(sliding.spectrum(), fwt::sequency(&[1, 2, 3, 4]).unwrap());
+/// assert_eq!(sliding.push(5), fwt::sequency(&[2, 3, 4, 5]).unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SlidingFwt<T> {
+    ordering: Ordering,
+    // For each length 2^j below the window length, the transforms of the
+    // latest 2^j windows of that length, one per slot, the window ending
+    // at sample t in slot t mod 2^j.
+    history: Vec<Vec<T>>,
+    // The number of samples pushed, modulo the window length.
+    time: usize,
+    // Where the spectrum of each output coefficient is in Hadamard order.
+    gather: Vec<usize>,
+    current: Vec<T>,
+    next: Vec<T>,
+    spectrum: Vec<T>,
+}
+
+impl<T: WalshElement + Default> SlidingFwt<T> {
+    /// Create a slider over windows of `length` samples, producing
+    /// spectra in the given ordering.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `length` is not a power of 2.
+    pub fn new(length: usize, ordering: Ordering) -> Result<SlidingFwt<T>, Error> {
+        if !power_of_2(length) {
+            return Err(Error::NotPowerOfTwo(length));
+        }
+        let history = (0..length.ilog2())
+            .map(|j| vec![T::default(); 1 << (2 * j)])
+            .collect();
+        let gather = (0..length)
+            .map(|s| match ordering {
+                Ordering::Sequency => kernel::sequency_to_hadamard(s, length),
+                Ordering::Hadamard => s,
+            })
+            .collect();
+        Ok(SlidingFwt {
+            ordering,
+            history,
+            time: 0,
+            gather,
+            current: vec![T::default(); length],
+            next: vec![T::default(); length],
+            spectrum: vec![T::default(); length],
+        })
+    }
+
+    /// Empty the window, as if the slider were new.
+    pub fn reset(&mut self) {
+        for level in &mut self.history {
+            level.fill(T::default());
+        }
+        self.spectrum.fill(T::default());
+        self.time = 0;
+    }
+}
+
+impl<T: WalshElement> SlidingFwt<T> {
+    /// The number of samples in the window.
+    pub fn length(&self) -> usize {
+        self.spectrum.len()
+    }
+
+    /// The ordering of the spectra.
+    pub fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    /// The spectrum of the current window.
+    pub fn spectrum(&self) -> &[T] {
+        &self.spectrum
+    }
+
+    /// Slide the window by one sample, dropping its oldest sample and
+    /// appending `sample`, and return the spectrum of the new window.
+    /// This does not allocate.
+    pub fn push(&mut self, sample: T) -> &[T] {
+        no_alloc(|| {
+            self.current[0] = sample;
+            for (j, level) in self.history.iter_mut().enumerate() {
+                let half = 1 << j;
+                let slot = &mut level[(self.time % half) * half..][..half];
+                // The earlier half of the window of length 2 * half is the
+                // window of length half that ended half samples ago.
+                let (earlier, later) = self.next[..2 * half].split_at_mut(half);
+                earlier.copy_from_slice(slot);
+                later.copy_from_slice(&self.current[..half]);
+                slot.copy_from_slice(&self.current[..half]);
+                T::butterfly(earlier, later);
+                std::mem::swap(&mut self.current, &mut self.next);
+            }
+            for (y, &h) in self.spectrum.iter_mut().zip(&self.gather) {
+                *y = self.current[h];
+            }
+            self.time = (self.time + 1) % self.spectrum.len();
+        });
+        &self.spectrum
+    }
+
+    /// Slide the window over every sample of `samples` in turn, and
+    /// return the spectrum of the final window.
+    pub fn extend(&mut self, samples: &[T]) -> &[T] {
+        for &x in samples {
+            self.push(x);
+        }
+        &self.spectrum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Plan;
+
+    #[test]
+    fn test_matches_transform_of_each_window() {
+        let stream: Vec<i64> = (0..300).map(|i| (i * 7919) % 101 - 50).collect();
+        for length in [1, 2, 8, 64] {
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let plan = Plan::new(length, ordering).unwrap();
+                let mut sliding = SlidingFwt::new(length, ordering).unwrap();
+                let mut padded = vec![0; length];
+                padded.extend_from_slice(&stream);
+                for (t, &x) in stream.iter().enumerate() {
+                    let window = &padded[t + 1..t + 1 + length];
+                    assert_eq!(sliding.push(x), plan.transform(window).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_floating_point_does_not_drift() {
+        let length = 32;
+        let plan = Plan::new(length, Ordering::Hadamard).unwrap();
+        let mut sliding = SlidingFwt::new(length, Ordering::Hadamard).unwrap();
+        let stream: Vec<f64> = (0..100_000)
+            .map(|i| (i as f64 * 0.37).sin() * 1e3)
+            .collect();
+        sliding.extend(&stream);
+        let expected = plan.transform(&stream[stream.len() - length..]).unwrap();
+        for (a, b) in sliding.spectrum().iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_reset_and_errors() {
+        let mut sliding = SlidingFwt::new(4, Ordering::Hadamard).unwrap();
+        sliding.extend(&[3, 1, 4, 1, 5]);
+        sliding.reset();
+        assert_eq!(sliding.spectrum(), [0; 4]);
+        assert_eq!(sliding.push(2), [2, -2, -2, 2]);
+        assert_eq!(
+            (sliding.length(), sliding.ordering()),
+            (4, Ordering::Hadamard)
+        );
+        assert_eq!(
+            SlidingFwt::<f64>::new(12, Ordering::Sequency).unwrap_err(),
+            Error::NotPowerOfTwo(12)
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Normalized power spectra of signals.
+
+use crate::{Error, Ordering, Plan};
+
+/// Return the power of each Walsh component of `v` in the given ordering,
+/// the square of its coefficient divided by the square of the length.
+///
+/// The powers are those of the time-domain components: by Parseval's
+/// theorem they sum to the mean square of `v`, so a constant signal of
+/// value `a` has power `a²` at sequency 0 whatever its length.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let power = fwt::power_spectrum(&[3, 1, 3, 1], Ordering::Sequency).unwrap();
+/// assert_eq!(power, [4.0, 0.0, 0.0, 1.0]);
+/// assert_eq!(power.iter().sum::<f64>(), (9.0 + 1.0 + 9.0 + 1.0) / 4.0);
+/// ```
+pub fn power_spectrum<T>(v: &[T], ordering: Ordering) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let scale = (v.len() as f64).powi(2).recip();
+    Ok(coefficients(v, ordering)?
+        .into_iter()
+        .map(|c| c * c * scale)
+        .collect())
+}
+
+/// Return the amplitude of each Walsh component of `v` in the given
+/// ordering, the absolute value of its coefficient divided by the
+/// length, so that a component `a · wal(k, t)` has amplitude `|a|`. The
+/// amplitudes are the square roots of the [`power_spectrum`].
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// let amplitude = fwt::amplitude_spectrum(&[3, 1, 3, 1], Ordering::Hadamard).unwrap();
+/// assert_eq!(amplitude, [2.0, 1.0, 0.0, 0.0]);
+/// ```
+pub fn amplitude_spectrum<T>(v: &[T], ordering: Ordering) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let scale = (v.len() as f64).recip();
+    Ok(coefficients(v, ordering)?
+        .into_iter()
+        .map(|c| c.abs() * scale)
+        .collect())
+}
+
+/// Return the power of `v` at each sequency `k` from 0 to `n/2`, where
+/// `n` is the length of `v`, combining the powers of the pair
+/// `cal(k, t)` and `sal(k, t)`, the Walsh counterpart of a one-sided
+/// Fourier power spectrum.
+///
+/// Sequency 0 holds only `cal(0, t)` and sequency `n/2` only
+/// `sal(n/2, t)`; every other sequency holds the sum of the
+/// [`power_spectrum`] entries `2k - 1` and `2k`. The powers still sum to
+/// the mean square of `v`. A signal of 1 sample has only sequency 0.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{cal, sal};
+///
+/// // Equal parts of cal(1, t) and sal(1, t) combine at sequency 1.
+/// let v: Vec<f64> = (0..8)
+///     .map(|i| (i as f64 + 0.5) / 8.0)
+///     .map(|t| f64::from(cal(1, t) + sal(1, t)))
+///     .collect();
+/// let power = fwt::sequency_power_spectrum(&v).unwrap();
+/// assert_eq!(power, [0.0, 2.0, 0.0, 0.0, 0.0]);
+/// ```
+pub fn sequency_power_spectrum<T>(v: &[T]) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let power = power_spectrum(v, Ordering::Sequency)?;
+    let n = power.len();
+    let mut combined = Vec::with_capacity(n / 2 + 1);
+    combined.push(power[0]);
+    combined.extend(power[1..].chunks(2).map(|pair| pair.iter().sum::<f64>()));
+    Ok(combined)
+}
+
+/// The magnitudes and phases of a signal's sequencies, returned by
+/// [`polar_spectrum`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolarSpectrum {
+    /// The amplitude at each sequency `k`, `√(a² + b²)` for the component
+    /// `a · cal(k, t) + b · sal(k, t)`.
+    pub magnitude: Vec<f64>,
+    /// The angle at each sequency `k`, `atan2(b, a)` in radians, in
+    /// `[-π, π]`: 0 for a pure `cal(k, t)`, `π/2` for a pure `sal(k, t)`,
+    /// and π for a negative `cal(k, t)`.
+    pub phase: Vec<f64>,
+}
+
+/// Fold the sequency spectrum of `v` into a magnitude and a phase-like
+/// angle at each sequency `k` from 0 to `n/2`, where `n` is the length of
+/// `v`, by treating `cal(k, t)` and `sal(k, t)` as the Walsh counterparts
+/// of the cosine and sine of a Fourier harmonic.
+///
+/// The magnitudes are the square roots of the
+/// [`sequency_power_spectrum`]. Unlike a Fourier phase, the angle does
+/// not describe a shift in time, since shifting a Walsh function does
+/// not rotate it into its pair, but it is the customary presentation of
+/// the balance between the even and odd parts of each sequency.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{cal, sal};
+/// use std::f64::consts::FRAC_PI_4;
+///
+/// let v: Vec<f64> = (0..8)
+///     .map(|i| (i as f64 + 0.5) / 8.0)
+///     .map(|t| f64::from(3 * cal(2, t) + 3 * sal(2, t)))
+///     .collect();
+/// let polar = fwt::polar_spectrum(&v).unwrap();
+/// assert!((polar.magnitude[2] - 18f64.sqrt()).abs() < 1e-12);
+/// assert!((polar.phase[2] - FRAC_PI_4).abs() < 1e-12);
+/// assert_eq!(polar.magnitude[1], 0.0);
+/// ```
+pub fn polar_spectrum<T>(v: &[T]) -> Result<PolarSpectrum, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let spectrum = coefficients(v, Ordering::Sequency)?;
+    let scale = (v.len() as f64).recip();
+    // Pair each cal coefficient with its sal, which is 0 at sequency 0
+    // and whose cal is 0 at sequency n/2.
+    let pairs = std::iter::once((spectrum[0], 0.0)).chain(
+        spectrum[1..]
+            .chunks(2)
+            .map(|pair| (pair.get(1).copied().unwrap_or(0.0), pair[0])),
+    );
+    let (magnitude, phase) = pairs
+        .map(|(cal, sal)| (cal.hypot(sal) * scale, sal.atan2(cal)))
+        .unzip();
+    Ok(PolarSpectrum { magnitude, phase })
+}
+
+// The unnormalized transform of `v` in `f64`.
+pub(crate) fn coefficients<T>(v: &[T], ordering: Ordering) -> Result<Vec<f64>, Error>
+where
+    T: Copy + Into<f64>,
+{
+    let plan = Plan::new(v.len(), ordering)?;
+    let mut spectrum: Vec<f64> = v.iter().map(|&x| x.into()).collect();
+    plan.execute(&mut spectrum)?;
+    Ok(spectrum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cal, sal};
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    fn signal(length: usize) -> Vec<f64> {
+        (0..length)
+            .map(|i| ((i * 7919) % 101) as f64 - 50.0)
+            .collect()
+    }
+
+    // The midpoints of `n` equal intervals of [0, 1).
+    fn times(n: usize) -> impl Iterator<Item = f64> {
+        (0..n).map(move |i| (i as f64 + 0.5) / n as f64)
+    }
+
+    #[test]
+    fn test_powers_sum_to_mean_square() {
+        for length in [1, 2, 16, 256] {
+            let v = signal(length);
+            let mean_square = v.iter().map(|x| x * x).sum::<f64>() / length as f64;
+            for ordering in [Ordering::Sequency, Ordering::Hadamard] {
+                let total: f64 = power_spectrum(&v, ordering).unwrap().iter().sum();
+                assert!((total - mean_square).abs() < 1e-9 * mean_square);
+            }
+            let combined = sequency_power_spectrum(&v).unwrap();
+            assert_eq!(combined.len(), length / 2 + 1);
+            let total: f64 = combined.iter().sum();
+            assert!((total - mean_square).abs() < 1e-9 * mean_square);
+        }
+    }
+
+    #[test]
+    fn test_pairs_combine_cal_and_sal() {
+        let n = 16;
+        for k in 1..n / 2 {
+            let v: Vec<i32> = times(n)
+                .map(|t| 3 * i32::from(cal(k, t)) - 4 * i32::from(sal(k, t)))
+                .collect();
+            let mut expected = vec![0.0; n / 2 + 1];
+            expected[k] = 25.0;
+            assert_eq!(sequency_power_spectrum(&v).unwrap(), expected);
+        }
+        let v: Vec<i32> = times(n).map(|t| 2 * i32::from(sal(n / 2, t))).collect();
+        assert_eq!(sequency_power_spectrum(&v).unwrap()[n / 2], 4.0);
+    }
+
+    #[test]
+    fn test_polar_spectrum_matches_power() {
+        let v = signal(64);
+        let polar = polar_spectrum(&v).unwrap();
+        let power = sequency_power_spectrum(&v).unwrap();
+        assert_eq!(polar.phase.len(), power.len());
+        for (m, p) in polar.magnitude.iter().zip(&power) {
+            assert!((m * m - p).abs() < 1e-9);
+        }
+        let n = 8;
+        let v: Vec<i32> = times(n).map(|t| -2 * i32::from(cal(1, t))).collect();
+        let polar = polar_spectrum(&v).unwrap();
+        assert_eq!((polar.magnitude[1], polar.phase[1]), (2.0, PI));
+        let v: Vec<i32> = times(n).map(|t| i32::from(sal(n / 2, t))).collect();
+        let polar = polar_spectrum(&v).unwrap();
+        assert_eq!(
+            (polar.magnitude[n / 2], polar.phase[n / 2]),
+            (1.0, FRAC_PI_2)
+        );
+        assert_eq!(polar_spectrum(&[-5]).unwrap().phase, [PI]);
+    }
+
+    #[test]
+    fn test_amplitudes_and_errors() {
+        let v = signal(32);
+        let power = power_spectrum(&v, Ordering::Hadamard).unwrap();
+        let amplitude = amplitude_spectrum(&v, Ordering::Hadamard).unwrap();
+        for (p, a) in power.iter().zip(&amplitude) {
+            assert!((a * a - p).abs() < 1e-9);
+        }
+        assert_eq!(
+            power_spectrum(&[1.0; 6], Ordering::Sequency).unwrap_err(),
+            Error::NotPowerOfTwo(6)
+        );
+        assert_eq!(
+            sequency_power_spectrum::<f32>(&[]).unwrap_err(),
+            Error::NotPowerOfTwo(0)
+        );
+        assert_eq!(
+            polar_spectrum::<f32>(&[0.0; 3]).unwrap_err(),
+            Error::NotPowerOfTwo(3)
+        );
+    }
+}
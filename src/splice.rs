@@ -0,0 +1,160 @@
+//! Click-free splicing of signals by crossfading in the sequency domain.
+//!
+//! A plain time-domain crossfade uses one ramp for every component of the
+//! signal, so either the slow components change abruptly or the fast ones
+//! smear across the whole overlap. Here both blocks are split into octave
+//! sequency bands and each band is crossfaded with its own linear ramp,
+//! spanning the whole overlap for the lowest bands and proportionally
+//! less for higher ones, roughly one period of the band's fastest Walsh
+//! function.
+
+use crate::{power_of_2, sequency, Error};
+
+/// Return the band-wise crossfade from block `a` into block `b`, which
+/// must have the same power-of-2 length. The result starts close to `a`,
+/// ends close to `b`, and equals `a` wherever `a` and `b` agree.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if the blocks differ in length, or
+/// [`Error::NotPowerOfTwo`] if their length is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// let a = [1.0; 8];
+/// let b = [3.0; 8];
+/// let faded = fwt::crossfade(&a, &b).unwrap();
+/// assert!(faded[0] < 1.5 && faded[7] > 2.5);
+/// assert!(faded.windows(2).all(|w| w[0] <= w[1]));
+/// ```
+pub fn crossfade(a: &[f64], b: &[f64]) -> Result<Vec<f64>, Error> {
+    if a.len() != b.len() {
+        return Err(Error::LengthMismatch {
+            expected: a.len(),
+            found: b.len(),
+        });
+    }
+    let n = a.len();
+    let spectrum_a = sequency(a).ok_or(Error::NotPowerOfTwo(n))?;
+    let spectrum_b = sequency(b).ok_or(Error::NotPowerOfTwo(n))?;
+    let mut out = vec![0.0; n];
+    let mut start = 0;
+    while start < n {
+        // Bands are {0, 1}, [2, 4), [4, 8), ... with the ramp shortening
+        // as the sequency doubles.
+        let end = (start * 2).max(2).min(n);
+        let width = (2 * n / end).min(n);
+        let band_a = band_component(&spectrum_a, start, end);
+        let band_b = band_component(&spectrum_b, start, end);
+        let first = (n - width) / 2;
+        for (i, x) in out.iter_mut().enumerate() {
+            let r = ((i as f64 - first as f64 + 1.0) / (width as f64 + 1.0)).clamp(0.0, 1.0);
+            *x += (1.0 - r) * band_a[i] + r * band_b[i];
+        }
+        start = end;
+    }
+    Ok(out)
+}
+
+/// Join `a` and `b` so that the last `overlap` samples of `a` crossfade
+/// band-wise into the first `overlap` samples of `b`, as by [`crossfade`].
+/// The result has `a.len() + b.len() - overlap` samples.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if `overlap` is not a power of 2, or
+/// [`Error::LengthMismatch`] if either signal is shorter than `overlap`.
+///
+/// # Example
+///
+/// ```
+/// let a = vec![1.0; 12];
+/// let b = vec![-1.0; 10];
+/// let joined = fwt::splice(&a, &b, 4).unwrap();
+/// assert_eq!(joined.len(), 18);
+/// assert_eq!(joined[..8], a[..8]);
+/// assert_eq!(joined[12..], b[4..]);
+/// ```
+pub fn splice(a: &[f64], b: &[f64], overlap: usize) -> Result<Vec<f64>, Error> {
+    if !power_of_2(overlap) {
+        return Err(Error::NotPowerOfTwo(overlap));
+    }
+    for signal in [a, b] {
+        if signal.len() < overlap {
+            return Err(Error::LengthMismatch {
+                expected: overlap,
+                found: signal.len(),
+            });
+        }
+    }
+    let head = a.len() - overlap;
+    let mut out = Vec::with_capacity(head + b.len());
+    out.extend_from_slice(&a[..head]);
+    out.extend(crossfade(&a[head..], &b[..overlap])?);
+    out.extend_from_slice(&b[overlap..]);
+    Ok(out)
+}
+
+// The time-domain component of the sequency coefficients in start..end.
+fn band_component(spectrum: &[f64], start: usize, end: usize) -> Vec<f64> {
+    let n = spectrum.len();
+    let mut band = vec![0.0; n];
+    band[start..end].copy_from_slice(&spectrum[start..end]);
+    sequency(&band)
+        .expect("length is a power of 2")
+        .into_iter()
+        .map(|x| x / n as f64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossfade_of_identical_blocks_is_identity() {
+        let a: Vec<f64> = (0..64).map(|i| ((i * 29) % 17) as f64 - 8.0).collect();
+        let faded = crossfade(&a, &a).unwrap();
+        for (x, y) in faded.iter().zip(&a) {
+            assert!((x - y).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_high_bands_switch_faster() {
+        // The fastest Walsh function fades over two samples, the constant
+        // over the whole block.
+        let n = 32;
+        let fast: Vec<f64> = crate::walsh_row(n, n - 1, crate::Ordering::Sequency)
+            .unwrap()
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        let faded = crossfade(&fast, &vec![0.0; n]).unwrap();
+        assert!(faded[..n / 2 - 1].iter().zip(&fast).all(|(x, y)| x == y));
+        assert!(faded[n / 2 + 1..].iter().all(|&x| x == 0.0));
+        let slow = crossfade(&vec![1.0; n], &vec![0.0; n]).unwrap();
+        assert!(slow.windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(crossfade(&[1.0; 4], &[1.0; 8]).is_err());
+        assert_eq!(
+            crossfade(&[1.0; 3], &[1.0; 3]),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            splice(&[1.0; 8], &[1.0; 8], 3),
+            Err(Error::NotPowerOfTwo(3))
+        );
+        assert_eq!(
+            splice(&[1.0; 8], &[1.0; 2], 4),
+            Err(Error::LengthMismatch {
+                expected: 4,
+                found: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! An asynchronous stream adapter that frames sample chunks into
+//! power-of-2 blocks and yields their spectra.
+//!
+//! Transforms run on the [`blocking`] thread pool, so they never stall the
+//! executor, and the adapter works with any async runtime. Upstream is only
+//! polled while fewer than [`max_in_flight`](SpectrumStream::max_in_flight)
+//! transforms are pending and no complete frame is waiting, so a slow
+//! consumer throttles ingestion instead of growing a buffer.
+//!
+//! Enable with the `stream` feature.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use blocking::{unblock, Task};
+use futures_core::Stream;
+
+use crate::{Error, Ordering, Plan, WalshElement};
+
+/// A [`Stream`] of spectra of consecutive frames of the samples from an
+/// upstream stream of chunks.
+///
+/// Chunks may have any length; samples are concatenated and split into
+/// frames of the plan's length, and a final partial frame is padded with
+/// zeros. Spectra are yielded in frame order. The upstream stream must be
+/// [`Unpin`]; pin other streams with [`Box::pin`].
+///
+/// # Example
+///
+/// ```
+/// use fwt::stream::SpectrumStream;
+/// use fwt::Ordering;
+/// use futures_lite::{future, stream, StreamExt};
+///
+/// let chunks = stream::iter(vec![vec![1, 0, 0], vec![0, 1, 1, 1, 1, 5]]);
+/// let spectra = SpectrumStream::new(chunks, 4, Ordering::Sequency).unwrap();
+/// let spectra: Vec<Vec<i32>> = future::block_on(spectra.collect());
+/// // The last frame is padded with zeros.
+/// assert_eq!(spectra, vec![vec![1, 1, 1, 1], vec![4, 0, 0, 0], vec![5, 5, 5, 5]]);
+/// ```
+pub struct SpectrumStream<S, T> {
+    input: Option<S>,
+    plan: Arc<Plan>,
+    samples: Vec<T>,
+    pending: VecDeque<Task<Vec<T>>>,
+    max_in_flight: usize,
+}
+
+impl<S, T> SpectrumStream<S, T>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<[T]>,
+    T: WalshElement + Default + Send + 'static,
+{
+    /// Create a stream of the spectra of `frame_length`-sample frames of
+    /// `input`, with up to one transform in flight per available CPU.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `frame_length` is not a power
+    /// of 2.
+    pub fn new(input: S, frame_length: usize, ordering: Ordering) -> Result<Self, Error> {
+        Ok(SpectrumStream {
+            input: Some(input),
+            plan: Arc::new(Plan::new(frame_length, ordering)?),
+            samples: Vec::new(),
+            pending: VecDeque::new(),
+            max_in_flight: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        })
+    }
+
+    /// Return this stream allowing at most `transforms` frames to be
+    /// transformed concurrently, at least 1.
+    pub fn with_max_in_flight(self, transforms: usize) -> Self {
+        SpectrumStream {
+            max_in_flight: transforms.max(1),
+            ..self
+        }
+    }
+
+    /// The number of samples in each frame.
+    pub fn frame_length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The ordering of the spectra.
+    pub fn ordering(&self) -> Ordering {
+        self.plan.ordering()
+    }
+
+    /// The largest number of frames transformed concurrently.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    // Start transforming buffered frames while there is capacity,
+    // including a padded final frame once the input has ended.
+    fn dispatch(&mut self) {
+        let length = self.frame_length();
+        while self.pending.len() < self.max_in_flight
+            && (self.samples.len() >= length || (self.input.is_none() && !self.samples.is_empty()))
+        {
+            let take = length.min(self.samples.len());
+            let mut frame: Vec<T> = self.samples.drain(..take).collect();
+            frame.resize(length, T::default());
+            let plan = Arc::clone(&self.plan);
+            self.pending.push_back(unblock(move || {
+                plan.execute(&mut frame)
+                    .expect("frames have the plan's length");
+                frame
+            }));
+        }
+    }
+}
+
+impl<S, T> Stream for SpectrumStream<S, T>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<[T]>,
+    T: WalshElement + Default + Send + 'static,
+{
+    type Item = Vec<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<T>>> {
+        let this = self.get_mut();
+        loop {
+            this.dispatch();
+            if let Some(task) = this.pending.front_mut() {
+                if let Poll::Ready(spectrum) = Pin::new(task).poll(cx) {
+                    this.pending.pop_front();
+                    return Poll::Ready(Some(spectrum));
+                }
+            }
+            let hungry =
+                this.pending.len() < this.max_in_flight && this.samples.len() < this.frame_length();
+            if let (true, Some(input)) = (hungry, this.input.as_mut()) {
+                match Pin::new(input).poll_next(cx) {
+                    Poll::Ready(Some(chunk)) => {
+                        this.samples.extend_from_slice(chunk.as_ref());
+                        continue;
+                    }
+                    Poll::Ready(None) => {
+                        this.input = None;
+                        continue;
+                    }
+                    Poll::Pending => {}
+                }
+            }
+            return if this.input.is_none() && this.pending.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.frame_length();
+        let buffered = self.pending.len() + self.samples.len().div_ceil(length);
+        match &self.input {
+            None => (buffered, Some(buffered)),
+            Some(_) => (self.pending.len() + self.samples.len() / length, None),
+        }
+    }
+}
+
+// No field is ever pinned, so the samples need not be `Unpin`.
+impl<S: Unpin, T> Unpin for SpectrumStream<S, T> {}
+
+impl<S, T> fmt::Debug for SpectrumStream<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpectrumStream")
+            .field("plan", &self.plan)
+            .field("buffered_samples", &self.samples.len())
+            .field("in_flight", &self.pending.len())
+            .field("max_in_flight", &self.max_in_flight)
+            .field("input_ended", &self.input.is_none())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::{future, stream, StreamExt};
+    use std::sync::atomic::{AtomicUsize, Ordering as Atomic};
+
+    #[test]
+    fn test_frames_match_plan() {
+        let samples: Vec<f64> = (0..1000).map(|i| (i as f64 * 0.37).sin()).collect();
+        let chunks: Vec<Vec<f64>> = samples.chunks(37).map(|c| c.to_vec()).collect();
+        let spectra = SpectrumStream::new(stream::iter(chunks), 64, Ordering::Hadamard)
+            .unwrap()
+            .with_max_in_flight(3);
+        assert_eq!(spectra.max_in_flight(), 3);
+        let spectra: Vec<Vec<f64>> = future::block_on(spectra.collect());
+        assert_eq!(spectra.len(), 16);
+        let plan = Plan::new(64, Ordering::Hadamard).unwrap();
+        for (k, spectrum) in spectra.iter().enumerate() {
+            let mut frame = samples[k * 64..]
+                .iter()
+                .take(64)
+                .copied()
+                .collect::<Vec<_>>();
+            frame.resize(64, 0.0);
+            plan.execute(&mut frame).unwrap();
+            assert_eq!(spectrum, &frame);
+        }
+    }
+
+    #[test]
+    fn test_backpressure_limits_upstream() {
+        // Count the chunks pulled from upstream; without a consumer
+        // polling, only enough for the in-flight frames may be taken.
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&pulled);
+        let chunks = stream::iter(0..100).map(move |_| {
+            counter.fetch_add(1, Atomic::SeqCst);
+            vec![1i32; 8]
+        });
+        let mut spectra = SpectrumStream::new(chunks, 8, Ordering::Sequency)
+            .unwrap()
+            .with_max_in_flight(2);
+        let first = future::block_on(spectra.next()).unwrap();
+        assert_eq!(first[0], 8);
+        assert!(pulled.load(Atomic::SeqCst) <= 3);
+        assert_eq!(future::block_on(spectra.count()), 99);
+        assert_eq!(pulled.load(Atomic::SeqCst), 100);
+    }
+
+    #[test]
+    fn test_empty_input_and_errors() {
+        let empty = stream::iter(Vec::<Vec<i32>>::new());
+        let spectra = SpectrumStream::new(empty, 4, Ordering::Sequency).unwrap();
+        assert_eq!(spectra.size_hint(), (0, None));
+        assert!(future::block_on(spectra.collect::<Vec<_>>()).is_empty());
+        let empty = stream::iter(Vec::<Vec<i32>>::new());
+        assert_eq!(
+            SpectrumStream::new(empty, 6, Ordering::Sequency).err(),
+            Some(Error::NotPowerOfTwo(6))
+        );
+    }
+}
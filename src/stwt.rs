@@ -0,0 +1,302 @@
+//! Short-time Walsh transforms of signals framed in time.
+
+use crate::{Error, Ordering, Plan};
+
+/// A taper applied to each frame before it is transformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Window {
+    /// No taper: every sample has weight 1.
+    #[default]
+    Rectangular,
+    /// The periodic Hann window, `sin²(π i / n)`.
+    Hann,
+    /// The periodic Hamming window, `0.54 - 0.46 cos(2π i / n)`.
+    Hamming,
+}
+
+impl Window {
+    /// Return the weights of this window over `length` samples. A window
+    /// of one sample has weight 1, as in SciPy, rather than the 0 of the
+    /// Hann formula.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let hann = fwt::Window::Hann.coefficients(4);
+    /// assert!(hann.iter().zip([0.0, 0.5, 1.0, 0.5]).all(|(w, e)| (w - e).abs() < 1e-12));
+    /// ```
+    pub fn coefficients(self, length: usize) -> Vec<f64> {
+        if length == 1 {
+            return vec![1.0];
+        }
+        let angle = |i: usize| std::f64::consts::TAU * i as f64 / length as f64;
+        (0..length)
+            .map(|i| match self {
+                Window::Rectangular => 1.0,
+                Window::Hann => 0.5 - 0.5 * angle(i).cos(),
+                Window::Hamming => 0.54 - 0.46 * angle(i).cos(),
+            })
+            .collect()
+    }
+}
+
+/// A short-time Walsh transform, which cuts a signal into frames,
+/// optionally tapers them, and transforms each one, giving a
+/// time–sequency matrix: a Walsh spectrogram.
+///
+/// Successive frames start [`hop`](Self::hop) samples apart, by default
+/// the frame length. Only whole frames are transformed, so samples after
+/// the last whole frame are ignored; pad the signal to keep them.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{Ordering, Stwt};
+///
+/// let stwt = Stwt::new(4, Ordering::Sequency).unwrap().with_hop(2);
+/// let spectrogram = stwt.transform(&[1, 1, 1, 1, -1, 1, -1, 1]);
+/// assert_eq!(spectrogram.frames(), 3);
+/// assert_eq!(spectrogram.frame(0), [4.0, 0.0, 0.0, 0.0]);
+/// // The last frame alternates in sign, with 3 sign changes.
+/// assert_eq!(spectrogram.frame(2), [0.0, 0.0, 0.0, -4.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stwt {
+    plan: Plan,
+    hop: usize,
+    window: Window,
+    weights: Vec<f64>,
+}
+
+impl Stwt {
+    /// Create a transform of frames of `frame_length` samples, in the
+    /// given ordering, without overlap or taper.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `frame_length` is not a power
+    /// of 2.
+    pub fn new(frame_length: usize, ordering: Ordering) -> Result<Stwt, Error> {
+        Ok(Stwt {
+            plan: Plan::new(frame_length, ordering)?,
+            hop: frame_length,
+            window: Window::Rectangular,
+            weights: vec![1.0; frame_length],
+        })
+    }
+
+    /// Return this transform starting successive frames `hop` samples
+    /// apart, at least 1.
+    pub fn with_hop(self, hop: usize) -> Stwt {
+        Stwt {
+            hop: hop.max(1),
+            ..self
+        }
+    }
+
+    /// Return this transform tapering each frame with `window`.
+    pub fn with_window(self, window: Window) -> Stwt {
+        Stwt {
+            weights: window.coefficients(self.frame_length()),
+            window,
+            ..self
+        }
+    }
+
+    /// The length of each frame.
+    pub fn frame_length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The number of samples between the starts of successive frames.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// The taper applied to each frame.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// The ordering of the spectra.
+    pub fn ordering(&self) -> Ordering {
+        self.plan.ordering()
+    }
+
+    /// The number of whole frames in a signal of `samples` samples.
+    pub fn frames(&self, samples: usize) -> usize {
+        match samples.checked_sub(self.frame_length()) {
+            Some(rest) => rest / self.hop + 1,
+            None => 0,
+        }
+    }
+
+    /// Return the spectrogram of `signal`, with one row per frame.
+    pub fn transform<T>(&self, signal: &[T]) -> Spectrogram
+    where
+        T: Copy + Into<f64>,
+    {
+        let length = self.frame_length();
+        let frames = self.frames(signal.len());
+        let mut data = Vec::with_capacity(frames * length);
+        for f in 0..frames {
+            let frame = &signal[f * self.hop..][..length];
+            data.extend(frame.iter().zip(&self.weights).map(|(&x, w)| x.into() * w));
+            self.plan
+                .execute(&mut data[f * length..])
+                .expect("frames have the plan's length");
+        }
+        Spectrogram {
+            frame_length: length,
+            hop: self.hop,
+            ordering: self.ordering(),
+            window_power: self.weights.iter().map(|w| w * w).sum(),
+            data,
+        }
+    }
+}
+
+/// The time–sequency matrix produced by [`Stwt::transform`], one row of
+/// transform coefficients per frame, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrogram {
+    frame_length: usize,
+    hop: usize,
+    ordering: Ordering,
+    // The sum of the squared window weights.
+    window_power: f64,
+    data: Vec<f64>,
+}
+
+impl Spectrogram {
+    /// The number of frames, the rows of the matrix.
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.frame_length
+    }
+
+    /// The length of each frame, the columns of the matrix.
+    pub fn frame_length(&self) -> usize {
+        self.frame_length
+    }
+
+    /// The number of samples between the starts of successive frames.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// The ordering of each frame's spectrum.
+    pub fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    /// The spectrum of frame `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`frames`](Self::frames).
+    pub fn frame(&self, index: usize) -> &[f64] {
+        &self.data[index * self.frame_length..][..self.frame_length]
+    }
+
+    /// The index of the first sample of frame `index` in the signal.
+    pub fn frame_start(&self, index: usize) -> usize {
+        index * self.hop
+    }
+
+    /// The matrix, frame by frame.
+    pub fn as_slice(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Return the power of each coefficient, in a matrix of the same
+    /// shape: its square divided by the frame length and the sum of the
+    /// squared window weights. Without a taper this is the
+    /// [`power_spectrum`](crate::power_spectrum) of each frame, and with
+    /// one each row sums to the frame's window-weighted mean square.
+    pub fn power(&self) -> Vec<f64> {
+        let scale = (self.frame_length as f64 * self.window_power).recip();
+        self.data.iter().map(|c| c * c * scale).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(length: usize) -> Vec<f64> {
+        (0..length)
+            .map(|i| ((i * 7919) % 101) as f64 - 50.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_frames_match_plan() {
+        let input = signal(100);
+        let plan = Plan::new(16, Ordering::Hadamard).unwrap();
+        for hop in [16, 5, 40] {
+            let stwt = Stwt::new(16, Ordering::Hadamard).unwrap().with_hop(hop);
+            let spectrogram = stwt.transform(&input);
+            assert_eq!(spectrogram.frames(), (100 - 16) / hop + 1);
+            assert_eq!(spectrogram.as_slice().len(), spectrogram.frames() * 16);
+            for f in 0..spectrogram.frames() {
+                let start = spectrogram.frame_start(f);
+                let expected = plan.transform(&input[start..start + 16]).unwrap();
+                assert_eq!(spectrogram.frame(f), expected);
+            }
+        }
+        assert_eq!(stwt_frames(8, 3, 7), 0);
+        assert_eq!(stwt_frames(8, 3, 8), 1);
+        assert_eq!(stwt_frames(8, 3, 11), 2);
+    }
+
+    fn stwt_frames(length: usize, hop: usize, samples: usize) -> usize {
+        Stwt::new(length, Ordering::Sequency)
+            .unwrap()
+            .with_hop(hop)
+            .frames(samples)
+    }
+
+    #[test]
+    fn test_window_tapers_frames() {
+        let input = signal(64);
+        let stwt = Stwt::new(32, Ordering::Sequency)
+            .unwrap()
+            .with_hop(16)
+            .with_window(Window::Hann);
+        assert_eq!(stwt.window(), Window::Hann);
+        let spectrogram = stwt.transform(&input);
+        let weights = Window::Hann.coefficients(32);
+        let plan = Plan::new(32, Ordering::Sequency).unwrap();
+        for f in 0..spectrogram.frames() {
+            let tapered: Vec<f64> = input[16 * f..][..32]
+                .iter()
+                .zip(&weights)
+                .map(|(x, w)| x * w)
+                .collect();
+            assert_eq!(spectrogram.frame(f), plan.transform(&tapered).unwrap());
+        }
+        let hamming = Window::Hamming.coefficients(8);
+        assert!((hamming[0] - 0.08).abs() < 1e-12 && (hamming[4] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_power_normalization() {
+        let input = signal(128);
+        let spectrogram = Stwt::new(32, Ordering::Sequency).unwrap().transform(&input);
+        let power = spectrogram.power();
+        for (f, row) in power.chunks(32).enumerate() {
+            let expected = crate::power_spectrum(&input[32 * f..][..32], Ordering::Sequency);
+            for (p, e) in row.iter().zip(expected.unwrap()) {
+                assert!((p - e).abs() < 1e-9);
+            }
+        }
+        assert_eq!(
+            Stwt::new(12, Ordering::Sequency).unwrap_err(),
+            Error::NotPowerOfTwo(12)
+        );
+        assert_eq!(
+            Stwt::new(4, Ordering::Sequency).unwrap().with_hop(0).hop(),
+            1
+        );
+    }
+}
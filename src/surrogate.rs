@@ -0,0 +1,210 @@
+//! Surrogate signals with the Walsh power spectrum of a reference, and
+//! the surrogate-data hypothesis test built on them.
+
+use crate::rng::SplitMix64;
+use crate::{hadamard_in_place, power_of_2, Error};
+
+/// Return a surrogate of `reference`: a signal with exactly the same
+/// Walsh coefficient magnitudes, and so the same power in every sequency,
+/// but with the signs of all coefficients except the mean drawn at random
+/// from `seed`.
+///
+/// Surrogates share the reference's mean, energy and sequency content
+/// while destroying any structure carried by the coefficient signs, which
+/// makes them null-hypothesis samples for the surrogate-data method. The
+/// same seed always gives the same surrogate.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `reference` is not a
+/// power of 2.
+///
+/// # Example
+///
+/// ```
+/// let reference = [4.0, 1.0, 3.0, 0.0, 2.0, 2.0, 5.0, 7.0];
+/// let surrogate = fwt::walsh_surrogate(&reference, 7).unwrap();
+///
+/// let magnitudes = |v: &[f64]| -> Vec<f64> {
+///     fwt::sequency(v).unwrap().iter().map(|c: &f64| c.abs()).collect()
+/// };
+/// let (a, b) = (magnitudes(&reference), magnitudes(&surrogate));
+/// assert!(a.iter().zip(&b).all(|(x, y)| (x - y).abs() < 1e-9));
+/// assert_eq!(fwt::walsh_surrogate(&reference, 7).unwrap(), surrogate);
+/// ```
+pub fn walsh_surrogate(reference: &[f64], seed: u64) -> Result<Vec<f64>, Error> {
+    let mut v = reference.to_vec();
+    hadamard_in_place(&mut v)?;
+    let mut rng = SplitMix64::new(seed);
+    for c in v.iter_mut().skip(1) {
+        if rng.sign() {
+            *c = -*c;
+        }
+    }
+    hadamard_in_place(&mut v)?;
+    let scale = (v.len() as f64).recip();
+    for x in &mut v {
+        *x *= scale;
+    }
+    Ok(v)
+}
+
+/// The outcome of [`surrogate_test`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurrogateTest {
+    statistic: f64,
+    surrogate_statistics: Vec<f64>,
+}
+
+impl SurrogateTest {
+    /// The statistic of the original signal.
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    /// The statistic of each surrogate, in generation order.
+    pub fn surrogate_statistics(&self) -> &[f64] {
+        &self.surrogate_statistics
+    }
+
+    /// The one-sided rank p-value for the original statistic being
+    /// unusually large: `(1 + k) / (m + 1)`, where `k` of the `m`
+    /// surrogates reach at least the original statistic.
+    pub fn p_value(&self) -> f64 {
+        self.rank_p_value(|s| s >= self.statistic)
+    }
+
+    /// The two-sided rank p-value for the original statistic being
+    /// unusually large or small: twice the smaller one-sided p-value,
+    /// capped at 1.
+    pub fn two_sided_p_value(&self) -> f64 {
+        let lower = self.rank_p_value(|s| s <= self.statistic);
+        (2.0 * self.p_value().min(lower)).min(1.0)
+    }
+
+    fn rank_p_value(&self, extreme: impl Fn(f64) -> bool) -> f64 {
+        let k = self
+            .surrogate_statistics
+            .iter()
+            .filter(|&&s| extreme(s))
+            .count();
+        (1 + k) as f64 / (self.surrogate_statistics.len() + 1) as f64
+    }
+}
+
+/// Test whether `signal` has structure beyond its Walsh power spectrum,
+/// by comparing `statistic` on the signal with its values on `surrogates`
+/// surrogates generated by [`walsh_surrogate`] with seeds drawn from
+/// `seed`.
+///
+/// Under the null hypothesis that the signal is indistinguishable from
+/// its surrogates, the original statistic is equally likely to take any
+/// rank among them, so the smallest attainable p-value is
+/// `1 / (surrogates + 1)`: 19 surrogates allow a one-sided test at the 5%
+/// level, and 39 a two-sided one.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `signal` is not a
+/// power of 2.
+///
+/// # Example
+///
+/// ```
+/// // A time-reversal asymmetry statistic, which sign randomization
+/// // destroys.
+/// let asymmetry = |x: &[f64]| -> f64 { x.windows(2).map(|w| (w[1] - w[0]).powi(3)).sum() };
+/// // The chaotic logistic map is strongly asymmetric in time.
+/// let mut x = 0.3;
+/// let signal: Vec<f64> = (0..256)
+///     .map(|_| {
+///         x = 4.0 * x * (1.0 - x);
+///         x
+///     })
+///     .collect();
+/// let test = fwt::surrogate_test(&signal, 99, 1, asymmetry).unwrap();
+/// assert!(test.two_sided_p_value() <= 0.05);
+/// assert_eq!(test.surrogate_statistics().len(), 99);
+/// ```
+pub fn surrogate_test<F>(
+    signal: &[f64],
+    surrogates: usize,
+    seed: u64,
+    statistic: F,
+) -> Result<SurrogateTest, Error>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    if !power_of_2(signal.len()) {
+        return Err(Error::NotPowerOfTwo(signal.len()));
+    }
+    let mut rng = SplitMix64::new(seed);
+    let surrogate_statistics = (0..surrogates)
+        .map(|_| walsh_surrogate(signal, rng.next_u64()).map(|s| statistic(&s)))
+        .collect::<Result<Vec<f64>, Error>>()?;
+    Ok(SurrogateTest {
+        statistic: statistic(signal),
+        surrogate_statistics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surrogate_preserves_spectrum_and_mean() {
+        let reference: Vec<f64> = (0..64).map(|i| ((i * i) % 17) as f64 - 5.0).collect();
+        let spectrum = crate::hadamard(&reference).unwrap();
+        let mean = reference.iter().sum::<f64>() / 64.0;
+        let mut differs = false;
+        for seed in 0..8 {
+            let surrogate = walsh_surrogate(&reference, seed).unwrap();
+            let s = crate::hadamard(&surrogate).unwrap();
+            assert!(s
+                .iter()
+                .zip(&spectrum)
+                .all(|(a, b)| (a.abs() - b.abs()).abs() < 1e-9));
+            assert!((s[0] - spectrum[0]).abs() < 1e-9);
+            assert!((surrogate.iter().sum::<f64>() / 64.0 - mean).abs() < 1e-12);
+            differs |= surrogate
+                .iter()
+                .zip(&reference)
+                .any(|(a, b)| (a - b).abs() > 1e-9);
+        }
+        assert!(differs);
+        assert_ne!(
+            walsh_surrogate(&reference, 1).unwrap(),
+            walsh_surrogate(&reference, 2).unwrap()
+        );
+        assert_eq!(walsh_surrogate(&[3.0], 0), Ok(vec![3.0]));
+        assert_eq!(walsh_surrogate(&[1.0; 6], 0), Err(Error::NotPowerOfTwo(6)));
+    }
+
+    #[test]
+    fn test_surrogate_test_p_values() {
+        let signal: Vec<f64> = (0..64).map(|i| ((i * 5) % 13) as f64).collect();
+        // The energy is identical for every surrogate, so every rank ties.
+        let energy = |x: &[f64]| -> f64 { x.iter().map(|v| v * v).sum::<f64>().round() };
+        let test = surrogate_test(&signal, 19, 3, energy).unwrap();
+        assert_eq!(test.p_value(), 1.0);
+        assert_eq!(test.two_sided_p_value(), 1.0);
+
+        let test = SurrogateTest {
+            statistic: 10.0,
+            surrogate_statistics: vec![1.0, 2.0, 3.0, 10.0],
+        };
+        assert_eq!(test.p_value(), 0.4);
+        assert_eq!(test.two_sided_p_value(), 0.8);
+        let first = |x: &[f64]| -> f64 { x[0] };
+        let a = surrogate_test(&signal, 5, 9, first).unwrap();
+        assert_eq!(a, surrogate_test(&signal, 5, 9, first).unwrap());
+        assert_eq!(a.statistic(), signal[0]);
+
+        assert_eq!(surrogate_test(&signal, 0, 0, first).unwrap().p_value(), 1.0);
+        assert_eq!(
+            surrogate_test(&[1.0; 3], 0, 0, first),
+            Err(Error::NotPowerOfTwo(3))
+        );
+    }
+}
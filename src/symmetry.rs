@@ -0,0 +1,107 @@
+//! Transforms of signals with even or odd dyadic symmetry.
+//!
+//! A signal of length `n` is even if `v[n - 1 - i] == v[i]` and odd if
+//! `v[n - 1 - i] == -v[i]` for every `i`. Mirroring about the centre maps
+//! the Walsh function of sequency `s` to itself times `(−1)^s`, so an even
+//! signal has only even-sequency (`cal`) coefficients and an odd signal
+//! only odd-sequency (`sal`) ones. On the first half of the interval both
+//! `wal(2k, t)` and `wal(2k + 1, t)` equal `wal(k, 2t)`, so the surviving
+//! coefficients are twice the half-length transform of the first half of
+//! the signal: half the work and half the output of a full transform.
+
+use crate::{kernel, power_of_2, Error, WalshElement};
+
+/// The dyadic symmetry of a signal about its centre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// `v[n - 1 - i] == v[i]`.
+    Even,
+    /// `v[n - 1 - i] == -v[i]`.
+    Odd,
+}
+
+/// Return the non-redundant sequency coefficients of a signal `v` with
+/// the given symmetry: coefficient `2k` of the full transform at index
+/// `k` for an even signal, or coefficient `2k + 1` for an odd one. Only
+/// the first half of `v` is read; the symmetry is assumed, not checked.
+///
+/// # Errors
+///
+/// Returns [`Error::NotPowerOfTwo`] if the length of `v` is not a power
+/// of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Symmetry;
+///
+/// let even = [1, 2, 3, 4, 4, 3, 2, 1];
+/// let full = fwt::sequency(&even).unwrap();
+/// let half = fwt::sequency_symmetric(&even, Symmetry::Even).unwrap();
+/// assert_eq!(half, vec![full[0], full[2], full[4], full[6]]);
+///
+/// let odd = [1, 2, 3, 4, -4, -3, -2, -1];
+/// let full = fwt::sequency(&odd).unwrap();
+/// let half = fwt::sequency_symmetric(&odd, Symmetry::Odd).unwrap();
+/// assert_eq!(half, vec![full[1], full[3], full[5], full[7]]);
+/// ```
+pub fn sequency_symmetric<T>(v: &[T], symmetry: Symmetry) -> Result<Vec<T>, Error>
+where
+    T: WalshElement,
+{
+    let n = v.len();
+    if !power_of_2(n) {
+        return Err(Error::NotPowerOfTwo(n));
+    }
+    if n == 1 {
+        // The lone coefficient has sequency 0, which only an even
+        // signal can carry.
+        return Ok(match symmetry {
+            Symmetry::Even => v.to_vec(),
+            Symmetry::Odd => Vec::new(),
+        });
+    }
+    let mut half = kernel::sequency_gathered(&v[..n / 2]);
+    for x in half.iter_mut() {
+        *x = *x + *x;
+    }
+    Ok(half)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_full_transform() {
+        for m in 0..=8 {
+            let n = 1usize << m;
+            let half: Vec<i64> = (0..n as i64 / 2).map(|i| (i * 13) % 7 - 3).collect();
+            let mirrored = half.iter().rev();
+            let mut even: Vec<i64> = half.iter().chain(mirrored.clone()).copied().collect();
+            let mut odd: Vec<i64> = half.iter().copied().chain(mirrored.map(|x| -x)).collect();
+            if n == 1 {
+                (even, odd) = (vec![5], vec![0]);
+            }
+            let full_even = crate::sequency(&even).unwrap();
+            let full_odd = crate::sequency(&odd).unwrap();
+            let expected_even: Vec<i64> = full_even.iter().step_by(2).copied().collect();
+            let expected_odd: Vec<i64> = full_odd.iter().skip(1).step_by(2).copied().collect();
+            assert_eq!(
+                sequency_symmetric(&even, Symmetry::Even).unwrap(),
+                expected_even
+            );
+            assert_eq!(
+                sequency_symmetric(&odd, Symmetry::Odd).unwrap(),
+                expected_odd
+            );
+            // The redundant coefficients really are zero.
+            assert!(full_even.iter().skip(1).step_by(2).all(|&c| c == 0));
+            assert!(full_odd.iter().step_by(2).all(|&c| c == 0));
+        }
+        assert_eq!(
+            sequency_symmetric(&[1, 2, 3], Symmetry::Even),
+            Err(Error::NotPowerOfTwo(3))
+        );
+    }
+}
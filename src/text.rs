@@ -0,0 +1,121 @@
+//! Plain-text renderings of spectra and band energies, for terminals and
+//! log files.
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Return a one-line sparkline of `values`, one Unicode block character
+/// per value, scaled so the smallest value is the lowest block and the
+/// largest the highest. Values that are not finite are shown as spaces;
+/// if all finite values are equal, every block is the lowest.
+///
+/// # Example
+///
+/// ```
+/// let energies = [0.0, 1.0, 4.0, 9.0, 16.0, 9.0, 4.0, 1.0];
+/// assert_eq!(fwt::to_sparkline(&energies), "▁▁▃▅█▅▃▁");
+/// assert_eq!(fwt::to_sparkline(&[1.0, f64::NAN, 3.0]), "▁ █");
+/// ```
+pub fn to_sparkline(values: &[f64]) -> String {
+    let (lo, hi) = finite_range(values);
+    values
+        .iter()
+        .map(|&v| {
+            if !v.is_finite() {
+                ' '
+            } else if hi > lo {
+                let level = ((v - lo) / (hi - lo) * 7.0).round() as usize;
+                BLOCKS[level.min(7)]
+            } else {
+                BLOCKS[0]
+            }
+        })
+        .collect()
+}
+
+/// Return a bar chart of `values` drawn in ASCII, `height` lines tall
+/// with one column per value, followed by a baseline. Bars rise from
+/// zero, so negative values hang below the zero level; the top and
+/// bottom lines are labelled with the values they reach. Values that are
+/// not finite are left blank.
+///
+/// # Example
+///
+/// ```
+/// let plot = fwt::to_ascii_plot(&[4.0, 2.0, -2.0, 1.0], 4);
+/// let lines: Vec<&str> = plot.lines().collect();
+/// assert_eq!(lines, [" 4|#   ", "  |##  ", "  |## #", "-2|  # ", "  +----"]);
+/// ```
+pub fn to_ascii_plot(values: &[f64], height: usize) -> String {
+    let (lo, hi) = finite_range(values);
+    let (lo, hi) = (lo.min(0.0), hi.max(0.0));
+    let height = height.max(1);
+    let (top, bottom) = (label(hi), label(lo));
+    let margin = top.len().max(bottom.len());
+    let step = (hi - lo) / height as f64;
+    let mut plot = String::new();
+    for row in 0..height {
+        let label = match row {
+            0 => &top,
+            r if r == height - 1 => &bottom,
+            _ => "",
+        };
+        plot.push_str(&format!("{:>margin$}|", label));
+        // The level at the centre of this line.
+        let y = hi - (row as f64 + 0.5) * step;
+        for &v in values {
+            let filled = v.is_finite() && ((0.0 < y && y <= v) || (v <= y && y < 0.0));
+            plot.push(if filled { '#' } else { ' ' });
+        }
+        plot.push('\n');
+    }
+    plot.push_str(&format!("{:>margin$}+{}\n", "", "-".repeat(values.len())));
+    plot
+}
+
+// Format `x` with at most three decimal places and no trailing zeros.
+fn label(x: f64) -> String {
+    let text = format!("{:.3}", x);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+// The smallest and largest finite values, or zeros if there are none.
+fn finite_range(values: &[f64]) -> (f64, f64) {
+    values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold(None, |range, &v| match range {
+            None => Some((v, v)),
+            Some((lo, hi)) => Some((v.min(lo), v.max(hi))),
+        })
+        .unwrap_or((0.0, 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_levels() {
+        let ramp: Vec<f64> = (0..8).map(f64::from).collect();
+        assert_eq!(to_sparkline(&ramp), "▁▂▃▄▅▆▇█");
+        assert_eq!(to_sparkline(&[2.0, 2.0]), "▁▁");
+        assert_eq!(to_sparkline(&[]), "");
+        assert_eq!(to_sparkline(&[f64::INFINITY, -1.0, 1.0]), " ▁█");
+    }
+
+    #[test]
+    fn test_ascii_plot_shape() {
+        let plot = to_ascii_plot(&[1.0, 0.0, 3.0, f64::NAN], 3);
+        let lines: Vec<&str> = plot.lines().collect();
+        assert_eq!(lines, ["3|  # ", " |  # ", "0|# # ", " +----"]);
+        let negative = to_ascii_plot(&[-1.0, -2.0], 2);
+        assert_eq!(negative, " 0|##\n-2| #\n  +--\n");
+        assert_eq!(to_ascii_plot(&[], 0), "0|\n +\n");
+        assert!(to_ascii_plot(&[0.1 + 0.2, 1.0 / 3.0], 1).starts_with("0.333|"));
+    }
+}
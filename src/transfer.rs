@@ -0,0 +1,303 @@
+//! Estimation of the transfer functions of dyadic-invariant systems.
+//!
+//! A linear system is dyadic invariant if it commutes with dyadic shifts,
+//! so that its output is the [dyadic convolution](crate::dyadic_convolution)
+//! `y = h ⊛ x` of its input with an impulse response `h`. Walsh
+//! transforms turn the convolution into a product, `Y[s] = H[s] X[s]` for
+//! every sequency `s`, so the transfer function `H` is estimated
+//! coefficient by coefficient from a measured input and output, as the
+//! Fourier transfer function is for shift-invariant systems.
+
+use crate::{Error, Ordering, Plan};
+
+// The default regularization of `estimate_transfer`, relative to the mean
+// input power per coefficient.
+const DEFAULT_REGULARIZATION: f64 = 1e-9;
+
+/// A dyadic transfer function estimated by [`estimate_transfer`] or
+/// [`estimate_transfer_with`], with indicators of its quality.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferEstimate {
+    response: Vec<f64>,
+    coherence: Vec<f64>,
+    fit: f64,
+    blocks: usize,
+}
+
+impl TransferEstimate {
+    /// The estimated transfer function `H`, indexed by sequency.
+    pub fn response(&self) -> &[f64] {
+        &self.response
+    }
+
+    /// The impulse response `h` of the system, the inverse sequency
+    /// transform of [`response`](Self::response).
+    pub fn impulse_response(&self) -> Vec<f64> {
+        let mut h = self.response.clone();
+        Plan::new(h.len(), Ordering::Sequency)
+            .and_then(|plan| plan.execute(&mut h))
+            .expect("the block length is a power of 2");
+        let scale = (h.len() as f64).recip();
+        for x in &mut h {
+            *x *= scale;
+        }
+        h
+    }
+
+    /// The squared coherence of input and output at each sequency,
+    /// `Sxy² / (Sxx Syy)` from the cross- and auto-powers summed over the
+    /// blocks, between 0 and 1. Values near 1 mean the output at that
+    /// sequency is explained by a fixed gain on the input, and low values
+    /// reveal noise or nonlinearity. It is 0 where either power is 0, and
+    /// with a single block it is 1 wherever it is defined, so assessing
+    /// coherence needs several blocks.
+    pub fn coherence(&self) -> &[f64] {
+        &self.coherence
+    }
+
+    /// The fraction of the output energy reproduced by applying the
+    /// estimated transfer function to the input, between 0 and 1.
+    pub fn fit(&self) -> f64 {
+        self.fit
+    }
+
+    /// The number of blocks averaged.
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+}
+
+/// Estimate the dyadic transfer function of the system that produced
+/// `output` from `input`, treating the whole record as one block, with a
+/// small default regularization of `1e-9` times the mean input power per
+/// coefficient. See [`estimate_transfer_with`].
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if the lengths of `input` and
+/// `output` differ, or [`Error::NotPowerOfTwo`] if their length is not a
+/// power of 2.
+///
+/// # Example
+///
+/// ```
+/// // A system that averages each sample with its dyadic neighbour 1.
+/// let h = [0.5, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+/// let x = [3.0, 1.0, -2.0, 4.0, 0.5, 2.0, 1.0, -1.0];
+/// let y = fwt::dyadic_convolution(&x, &h).unwrap();
+/// let estimate = fwt::estimate_transfer(&x, &y).unwrap();
+/// for (a, b) in estimate.impulse_response().iter().zip(&h) {
+///     assert!((a - b).abs() < 1e-6);
+/// }
+/// assert!(estimate.fit() > 0.999_999);
+/// ```
+pub fn estimate_transfer(input: &[f64], output: &[f64]) -> Result<TransferEstimate, Error> {
+    estimate_transfer_with(input, output, input.len(), DEFAULT_REGULARIZATION)
+}
+
+/// Estimate the dyadic transfer function of the system that produced
+/// `output` from `input`, averaging over the complete blocks of
+/// `block_len` samples.
+///
+/// With `X` and `Y` the sequency transforms of corresponding input and
+/// output blocks, the estimate at each sequency is
+/// `H = Sxy / (Sxx + λ)`, where `Sxy` is the sum over blocks of `X Y`,
+/// `Sxx` that of `X²`, and `λ` is `regularization` times the mean of
+/// `Sxx` over all sequencies. For `λ = 0` this is the least-squares gain
+/// at each sequency; a positive `regularization` shrinks the gains of
+/// sequencies where the input has little power, which noise would
+/// otherwise dominate, towards 0. Sequencies where both `Sxy` and
+/// `Sxx + λ` are 0 get a gain of 0. `regularization` should be
+/// non-negative.
+///
+/// # Errors
+///
+/// Returns [`Error::LengthMismatch`] if the lengths of `input` and
+/// `output` differ or are less than `block_len`, or
+/// [`Error::NotPowerOfTwo`] if `block_len` is not a power of 2.
+///
+/// # Example
+///
+/// ```
+/// use fwt::Ordering;
+///
+/// // Noise added at sequency 15, with a different level in each block,
+/// // lowers the coherence there only.
+/// let mut h = vec![0.0; 16];
+/// (h[0], h[1]) = (1.0, 0.5);
+/// let x: Vec<f64> = (0..1024).map(|i| ((i * 7919) % 61) as f64 - 30.0).collect();
+/// let noise = fwt::walsh_row(16, 15, Ordering::Sequency).unwrap();
+/// let y: Vec<f64> = x
+///     .chunks(16)
+///     .enumerate()
+///     .flat_map(|(k, block)| {
+///         let level = 20.0 * (((k * 31) % 13) as f64 - 6.0);
+///         let y = fwt::dyadic_convolution(block, &h).unwrap();
+///         y.into_iter().zip(&noise).map(move |(v, &n)| v + level * n as f64)
+///     })
+///     .collect();
+/// let estimate = fwt::estimate_transfer_with(&x, &y, 16, 0.0).unwrap();
+/// assert_eq!(estimate.blocks(), 64);
+/// assert!(estimate.coherence()[..15].iter().all(|&c| c > 0.999));
+/// assert!(estimate.coherence()[15] < 0.5);
+/// ```
+pub fn estimate_transfer_with(
+    input: &[f64],
+    output: &[f64],
+    block_len: usize,
+    regularization: f64,
+) -> Result<TransferEstimate, Error> {
+    if output.len() != input.len() {
+        return Err(Error::LengthMismatch {
+            expected: input.len(),
+            found: output.len(),
+        });
+    }
+    let plan = Plan::new(block_len, Ordering::Sequency)?;
+    let blocks = input.len() / block_len;
+    if blocks == 0 {
+        return Err(Error::LengthMismatch {
+            expected: block_len,
+            found: input.len(),
+        });
+    }
+    let mut sxx = vec![0.0; block_len];
+    let mut syy = vec![0.0; block_len];
+    let mut sxy = vec![0.0; block_len];
+    let mut x = vec![0.0; block_len];
+    let mut y = vec![0.0; block_len];
+    for (a, b) in input
+        .chunks_exact(block_len)
+        .zip(output.chunks_exact(block_len))
+    {
+        x.copy_from_slice(a);
+        y.copy_from_slice(b);
+        plan.execute(&mut x)?;
+        plan.execute(&mut y)?;
+        for s in 0..block_len {
+            sxx[s] += x[s] * x[s];
+            syy[s] += y[s] * y[s];
+            sxy[s] += x[s] * y[s];
+        }
+    }
+    let lambda = regularization * sxx.iter().sum::<f64>() / block_len as f64;
+    let mut response = Vec::with_capacity(block_len);
+    let mut coherence = Vec::with_capacity(block_len);
+    let mut residual = 0.0;
+    for s in 0..block_len {
+        let denominator = sxx[s] + lambda;
+        let gain = if denominator == 0.0 {
+            0.0
+        } else {
+            sxy[s] / denominator
+        };
+        let power = sxx[s] * syy[s];
+        coherence.push(if power == 0.0 {
+            0.0
+        } else {
+            (sxy[s] * sxy[s] / power).min(1.0)
+        });
+        residual += (syy[s] - 2.0 * gain * sxy[s] + gain * gain * sxx[s]).max(0.0);
+        response.push(gain);
+    }
+    let energy: f64 = syy.iter().sum();
+    let fit = if energy == 0.0 {
+        1.0
+    } else {
+        (1.0 - residual / energy).clamp(0.0, 1.0)
+    };
+    Ok(TransferEstimate {
+        response,
+        coherence,
+        fit,
+        blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn test_recovers_impulse_response() {
+        let mut rng = SplitMix64::new(1);
+        let h: Vec<f64> = (0..32).map(|_| rng.normal()).collect();
+        let x: Vec<f64> = (0..32 * 8).map(|_| rng.normal()).collect();
+        let y: Vec<f64> = x
+            .chunks(32)
+            .flat_map(|block| crate::dyadic_convolution(block, &h).unwrap())
+            .collect();
+        let estimate = estimate_transfer_with(&x, &y, 32, 0.0).unwrap();
+        for (a, b) in estimate.impulse_response().iter().zip(&h) {
+            assert!((a - b).abs() < 1e-9, "{a} {b}");
+        }
+        assert!(estimate.coherence().iter().all(|&c| c > 1.0 - 1e-9));
+        assert!(estimate.fit() > 1.0 - 1e-9);
+        let spectrum = crate::sequency(&h).unwrap();
+        for (a, b) in estimate.response().iter().zip(&spectrum) {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_regularization_and_noise() {
+        // The input has no power at sequency 3, so the gain there is
+        // undetermined: regularized to 0, with coherence 0.
+        let mut x = vec![0.0; 8];
+        x[0] = 2.0;
+        x[5] = 1.0;
+        let mut x_spectrum = crate::sequency(&x).unwrap();
+        x_spectrum[3] = 0.0;
+        let x: Vec<f64> = crate::sequency(&x_spectrum)
+            .unwrap()
+            .iter()
+            .map(|v| v / 8.0)
+            .collect();
+        let y = crate::dyadic_convolution(&x, &[1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5]).unwrap();
+        let estimate = estimate_transfer(&x, &y).unwrap();
+        assert_eq!(estimate.response()[3], 0.0);
+        assert_eq!(estimate.coherence()[3], 0.0);
+        assert_eq!(estimate.blocks(), 1);
+
+        // Independent noise on the output lowers the fit and coherence.
+        let mut rng = SplitMix64::new(2);
+        let x: Vec<f64> = (0..4096).map(|_| rng.normal()).collect();
+        let y: Vec<f64> = x.iter().map(|v| 0.5 * v + rng.normal()).collect();
+        let estimate = estimate_transfer_with(&x, &y, 16, 0.0).unwrap();
+        assert!(estimate.response().iter().all(|g| (g - 0.5).abs() < 0.25));
+        let mean = estimate.coherence().iter().sum::<f64>() / 16.0;
+        assert!((mean - 0.2).abs() < 0.1, "{mean}");
+        assert!((estimate.fit() - 0.2).abs() < 0.1);
+        // Strong regularization shrinks every gain.
+        let shrunk = estimate_transfer_with(&x, &y, 16, 1.0).unwrap();
+        for (a, b) in shrunk.response().iter().zip(estimate.response()) {
+            assert!(a.abs() < b.abs());
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            estimate_transfer(&[1.0; 4], &[1.0; 8]),
+            Err(Error::LengthMismatch {
+                expected: 4,
+                found: 8
+            })
+        );
+        assert_eq!(
+            estimate_transfer(&[1.0; 6], &[1.0; 6]),
+            Err(Error::NotPowerOfTwo(6))
+        );
+        assert_eq!(
+            estimate_transfer_with(&[1.0; 4], &[1.0; 4], 8, 0.0),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                found: 4
+            })
+        );
+        let silent = estimate_transfer(&[0.0; 4], &[0.0; 4]).unwrap();
+        assert_eq!(silent.response(), [0.0; 4]);
+        assert_eq!(silent.fit(), 1.0);
+    }
+}
@@ -0,0 +1,93 @@
+//! SIMD128 butterflies for `i16`, `Saturating<i16>`, `i32`, `f32` and
+//! `f64` on wasm32.
+//!
+//! WebAssembly has no run-time feature detection: a module that uses
+//! SIMD128 fails to validate on engines without it. These kernels are
+//! therefore only compiled when the target feature is enabled at build
+//! time, for example with `RUSTFLAGS="-C target-feature=+simd128"`.
+
+use std::arch::wasm32::*;
+use std::num::Saturating;
+
+use crate::WalshElement;
+
+// Define butterflies over `$lanes`-element vectors of `$t` using the given
+// add and subtract intrinsics, finishing the tail with scalar arithmetic.
+macro_rules! vector_butterfly {
+    ($($name:ident, $t:ty, $lanes:literal, $add:ident, $sub:ident;)*) => {$(
+        pub(crate) fn $name(a: &mut [$t], b: &mut [$t], crossed: bool) {
+            let length = a.len().min(b.len());
+            let split = length - length % $lanes;
+            for i in (0..split).step_by($lanes) {
+                // SAFETY: `i + $lanes <= split <= length`, so both
+                // unaligned accesses are in bounds.
+                unsafe {
+                    let pa = a.as_mut_ptr().add(i) as *mut v128;
+                    let pb = b.as_mut_ptr().add(i) as *mut v128;
+                    let (x, y) = (v128_load(pa), v128_load(pb));
+                    let (sum, difference) = ($add(x, y), $sub(x, y));
+                    let (p, q) = if crossed { (difference, sum) } else { (sum, difference) };
+                    v128_store(pa, p);
+                    v128_store(pb, q);
+                }
+            }
+            scalar(&mut a[split..length], &mut b[split..length], crossed);
+        }
+    )*};
+}
+
+vector_butterfly! {
+    butterfly_f64, f64, 2, f64x2_add, f64x2_sub;
+    butterfly_f32, f32, 4, f32x4_add, f32x4_sub;
+    butterfly_i32, i32, 4, i32x4_add, i32x4_sub;
+    butterfly_i16, i16, 8, i16x8_add, i16x8_sub;
+    butterfly_saturating_i16, Saturating<i16>, 8, i16x8_add_sat, i16x8_sub_sat;
+}
+
+fn scalar<T: WalshElement>(a: &mut [T], b: &mut [T], crossed: bool) {
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        (*x, *y) = if crossed {
+            (*x - *y, *x + *y)
+        } else {
+            (*x + *y, *x - *y)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check a kernel against the scalar loop on lengths around the
+    // vector width.
+    fn check<T>(kernel: fn(&mut [T], &mut [T], bool), make: fn(usize) -> T)
+    where
+        T: WalshElement + PartialEq + std::fmt::Debug,
+    {
+        for length in [0, 1, 3, 4, 5, 8, 17, 100] {
+            let a: Vec<T> = (0..length).map(make).collect();
+            let b: Vec<T> = (0..length).map(|i| make(3 * i + 1)).collect();
+            for crossed in [false, true] {
+                let (mut x, mut y) = (a.clone(), b.clone());
+                kernel(&mut x, &mut y, crossed);
+                let (mut p, mut q) = (a.clone(), b.clone());
+                scalar(&mut p, &mut q, crossed);
+                assert_eq!((x, y), (p, q));
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        check(butterfly_f64, |i| (i as f64 * 0.731).sin() * 1e3);
+        check(butterfly_f32, |i| (i as f32 * 0.731).sin() * 1e3);
+        check(butterfly_i32, |i| (i as i32 * 7919) % 1000 - 500);
+        check(butterfly_i16, |i| (i as i16 * 37) % 1000 - 500);
+        check(butterfly_saturating_i16, |i| {
+            Saturating((i as i16).wrapping_mul(7919))
+        });
+        let (mut a, mut b) = (vec![i32::MAX; 4], vec![1; 4]);
+        butterfly_i32(&mut a, &mut b, false);
+        assert_eq!(a, vec![i32::MIN; 4]);
+    }
+}
@@ -0,0 +1,262 @@
+//! Averaged periodograms of long signals.
+
+use crate::{Error, Ordering, Plan, Window};
+
+/// An estimator of the sequency power spectrum of a long stationary
+/// signal by Welch's method, the Walsh counterpart of
+/// `scipy.signal.welch`.
+///
+/// The signal is cut into overlapping segments, each segment has its
+/// mean removed and is tapered by a window, and the power spectra of the
+/// segments are averaged. Averaging trades sequency resolution, fixed by
+/// the segment length, for a lower variance than the spectrum of the
+/// whole signal. The defaults follow SciPy: a Hann window, segments
+/// overlapping by half their length, and constant detrending.
+///
+/// The power of each coefficient is its square divided by the segment
+/// length and the sum of the squared window weights, so that the
+/// estimate sums to the average window-weighted mean square of the
+/// detrended segments; without a window or detrending, segments that do
+/// not overlap give the mean of their
+/// [`power_spectrum`](crate::power_spectrum)s. Samples after the last
+/// whole segment are ignored.
+///
+/// # Example
+///
+/// ```
+/// use fwt::{cal, Ordering, Welch};
+///
+/// // cal(3, t) repeated over 16 segments of 8 samples, in noise.
+/// let noise = |i: usize| ((i * 37 % 11) as f64 - 5.0) / 50.0;
+/// let signal: Vec<f64> = (0..128)
+///     .map(|i| f64::from(cal(3, (i % 8) as f64 / 8.0 + 1.0 / 16.0)) + noise(i))
+///     .collect();
+/// let welch = Welch::new(8, Ordering::Sequency).unwrap().with_overlap(0);
+/// let power = welch.estimate(&signal).unwrap();
+/// let peak = (0..8).max_by(|&a, &b| power[a].total_cmp(&power[b])).unwrap();
+/// assert_eq!(peak, 6);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Welch {
+    plan: Plan,
+    overlap: usize,
+    window: Window,
+    weights: Vec<f64>,
+    detrend: bool,
+}
+
+impl Welch {
+    /// Create an estimator averaging segments of `segment_length`
+    /// samples, in the given ordering, with SciPy's defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPowerOfTwo`] if `segment_length` is not a
+    /// power of 2.
+    pub fn new(segment_length: usize, ordering: Ordering) -> Result<Welch, Error> {
+        Ok(Welch {
+            plan: Plan::new(segment_length, ordering)?,
+            overlap: segment_length / 2,
+            window: Window::Hann,
+            weights: Window::Hann.coefficients(segment_length),
+            detrend: true,
+        })
+    }
+
+    /// Return this estimator overlapping successive segments by
+    /// `overlap` samples, at most one less than the segment length.
+    pub fn with_overlap(self, overlap: usize) -> Welch {
+        Welch {
+            overlap: overlap.min(self.segment_length() - 1),
+            ..self
+        }
+    }
+
+    /// Return this estimator tapering each segment with `window`.
+    pub fn with_window(self, window: Window) -> Welch {
+        Welch {
+            weights: window.coefficients(self.segment_length()),
+            window,
+            ..self
+        }
+    }
+
+    /// Return this estimator removing the mean of each segment before
+    /// tapering it, or not.
+    pub fn with_detrend(self, detrend: bool) -> Welch {
+        Welch { detrend, ..self }
+    }
+
+    /// The length of each segment.
+    pub fn segment_length(&self) -> usize {
+        self.plan.length()
+    }
+
+    /// The number of samples shared by successive segments.
+    pub fn overlap(&self) -> usize {
+        self.overlap
+    }
+
+    /// The taper applied to each segment.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// Whether each segment has its mean removed.
+    pub fn detrend(&self) -> bool {
+        self.detrend
+    }
+
+    /// The ordering of the estimate.
+    pub fn ordering(&self) -> Ordering {
+        self.plan.ordering()
+    }
+
+    /// The number of whole segments in a signal of `samples` samples.
+    pub fn segments(&self, samples: usize) -> usize {
+        match samples.checked_sub(self.segment_length()) {
+            Some(rest) => rest / (self.segment_length() - self.overlap) + 1,
+            None => 0,
+        }
+    }
+
+    /// Return the averaged power spectrum of `signal`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LengthMismatch`] if `signal` is shorter than a
+    /// segment.
+    pub fn estimate<T>(&self, signal: &[T]) -> Result<Vec<f64>, Error>
+    where
+        T: Copy + Into<f64>,
+    {
+        let length = self.segment_length();
+        let segments = self.segments(signal.len());
+        if segments == 0 {
+            return Err(Error::LengthMismatch {
+                expected: length,
+                found: signal.len(),
+            });
+        }
+        let hop = length - self.overlap;
+        let mut power = vec![0.0; length];
+        let mut segment = vec![0.0; length];
+        for s in 0..segments {
+            for (y, &x) in segment.iter_mut().zip(&signal[s * hop..][..length]) {
+                *y = x.into();
+            }
+            if self.detrend {
+                let mean = segment.iter().sum::<f64>() / length as f64;
+                segment.iter_mut().for_each(|y| *y -= mean);
+            }
+            for (y, w) in segment.iter_mut().zip(&self.weights) {
+                *y *= w;
+            }
+            self.plan.execute(&mut segment)?;
+            for (p, c) in power.iter_mut().zip(&segment) {
+                *p += c * c;
+            }
+        }
+        let window_power: f64 = self.weights.iter().map(|w| w * w).sum();
+        let scale = (length as f64 * window_power * segments as f64).recip();
+        power.iter_mut().for_each(|p| *p *= scale);
+        Ok(power)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(length: usize) -> Vec<f64> {
+        (0..length)
+            .map(|i| ((i * 7919) % 101) as f64 - 50.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_segments_average_power_spectra() {
+        let input = signal(100);
+        let welch = Welch::new(16, Ordering::Hadamard)
+            .unwrap()
+            .with_overlap(0)
+            .with_window(Window::Rectangular)
+            .with_detrend(false);
+        assert_eq!(welch.segments(100), 6);
+        let mut expected = vec![0.0; 16];
+        for s in 0..6 {
+            let power = crate::power_spectrum(&input[16 * s..][..16], Ordering::Hadamard);
+            for (e, p) in expected.iter_mut().zip(power.unwrap()) {
+                *e += p / 6.0;
+            }
+        }
+        for (p, e) in welch.estimate(&input).unwrap().iter().zip(&expected) {
+            assert!((p - e).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_defaults_detrend_and_overlap() {
+        let welch = Welch::new(32, Ordering::Sequency).unwrap();
+        assert_eq!(
+            (welch.overlap(), welch.window(), welch.detrend()),
+            (16, Window::Hann, true)
+        );
+        assert_eq!(welch.segments(32), 1);
+        assert_eq!(welch.segments(80), 4);
+        // An offset is removed entirely by detrending.
+        let input = signal(256);
+        let shifted: Vec<f64> = input.iter().map(|x| x + 1e3).collect();
+        let power = welch.estimate(&input).unwrap();
+        for (a, b) in power.iter().zip(welch.estimate(&shifted).unwrap()) {
+            assert!((a - b).abs() < 1e-6 * a.abs().max(1.0));
+        }
+        // The estimate sums to the window-weighted mean square.
+        let total: f64 = power.iter().sum();
+        let weights = Window::Hann.coefficients(32);
+        let window_power: f64 = weights.iter().map(|w| w * w).sum();
+        let segments = welch.segments(256);
+        let expected: f64 = (0..segments)
+            .map(|s| {
+                let segment = &input[16 * s..][..32];
+                let mean = segment.iter().sum::<f64>() / 32.0;
+                segment
+                    .iter()
+                    .zip(&weights)
+                    .map(|(x, w)| ((x - mean) * w).powi(2))
+                    .sum::<f64>()
+                    / window_power
+            })
+            .sum::<f64>()
+            / segments as f64;
+        assert!((total - expected).abs() < 1e-9 * expected);
+    }
+
+    #[test]
+    fn test_errors() {
+        let welch = Welch::new(16, Ordering::Sequency).unwrap().with_overlap(99);
+        assert_eq!(welch.overlap(), 15);
+        assert_eq!(
+            welch.estimate(&[0.0; 10]).unwrap_err(),
+            Error::LengthMismatch {
+                expected: 16,
+                found: 10
+            }
+        );
+        assert_eq!(
+            Welch::new(24, Ordering::Sequency).unwrap_err(),
+            Error::NotPowerOfTwo(24)
+        );
+        // Segments of one sample keep their weight, and detrending
+        // leaves no power.
+        let single = Welch::new(1, Ordering::Sequency).unwrap();
+        assert_eq!(single.estimate(&[1.0, 2.0, 3.0]), Ok(vec![0.0]));
+        let plain = single.with_detrend(false);
+        assert_eq!(plain.estimate(&[1.0, 2.0, 3.0, 2.0]), Ok(vec![4.5]));
+        let frames = crate::Stwt::new(1, Ordering::Sequency)
+            .unwrap()
+            .with_window(Window::Hann)
+            .transform(&[2.0, -3.0]);
+        assert_eq!(frames.power(), [4.0, 9.0]);
+    }
+}
@@ -0,0 +1,210 @@
+//! AVX2 and AVX-512 butterflies for `i32`, `f32` and `f64`, and AVX2
+//! butterflies for `i16` and `Saturating<i16>`, on x86_64, selected at
+//! run time from the features of the running CPU, with the scalar loop as
+//! the fallback.
+
+use std::arch::x86_64::*;
+use std::num::Saturating;
+
+use crate::WalshElement;
+
+/// The widest vector instructions the butterflies use on this CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Level {
+    Scalar,
+    Avx2,
+    Avx512,
+}
+
+/// Detect the widest vector instructions available. Detection results
+/// are cached by the standard library, so this is cheap to call.
+pub(crate) fn level() -> Level {
+    if is_x86_feature_detected!("avx512f") {
+        Level::Avx512
+    } else if is_x86_feature_detected!("avx2") {
+        Level::Avx2
+    } else {
+        Level::Scalar
+    }
+}
+
+// Define unsafe butterflies over `$lanes`-element vectors of `$t` using
+// the given load, store, add and subtract intrinsics, finishing the tail
+// with scalar arithmetic.
+macro_rules! vector_butterfly {
+    ($($name:ident, $feature:literal, $t:ty, $lanes:literal, $v:ty,
+       $load:ident, $store:ident, $add:ident, $sub:ident;)*) => {$(
+        /// # Safety
+        ///
+        /// The CPU must support the target feature.
+        #[target_feature(enable = $feature)]
+        unsafe fn $name(a: &mut [$t], b: &mut [$t], crossed: bool) {
+            let length = a.len().min(b.len());
+            let split = length - length % $lanes;
+            for i in (0..split).step_by($lanes) {
+                // SAFETY: `i + $lanes <= split <= length`, so both
+                // unaligned accesses are in bounds.
+                let (pa, pb) = (a.as_mut_ptr().add(i), b.as_mut_ptr().add(i));
+                let x = $load(pa as *const $v);
+                let y = $load(pb as *const $v);
+                let (sum, difference) = ($add(x, y), $sub(x, y));
+                let (p, q) = if crossed { (difference, sum) } else { (sum, difference) };
+                $store(pa as *mut $v, p);
+                $store(pb as *mut $v, q);
+            }
+            scalar(&mut a[split..length], &mut b[split..length], crossed);
+        }
+    )*};
+}
+
+vector_butterfly! {
+    f64_avx2, "avx2", f64, 4, __m256d,
+        _mm256_loadu_pd_v, _mm256_storeu_pd_v, _mm256_add_pd, _mm256_sub_pd;
+    f32_avx2, "avx2", f32, 8, __m256,
+        _mm256_loadu_ps_v, _mm256_storeu_ps_v, _mm256_add_ps, _mm256_sub_ps;
+    i32_avx2, "avx2", i32, 8, __m256i,
+        _mm256_loadu_si256, _mm256_storeu_si256, _mm256_add_epi32, _mm256_sub_epi32;
+    f64_avx512, "avx512f", f64, 8, __m512d,
+        _mm512_loadu_pd_v, _mm512_storeu_pd_v, _mm512_add_pd, _mm512_sub_pd;
+    f32_avx512, "avx512f", f32, 16, __m512,
+        _mm512_loadu_ps_v, _mm512_storeu_ps_v, _mm512_add_ps, _mm512_sub_ps;
+    i32_avx512, "avx512f", i32, 16, __m512i,
+        _mm512_loadu_si512, _mm512_storeu_si512, _mm512_add_epi32, _mm512_sub_epi32;
+    i16_avx2, "avx2", i16, 16, __m256i,
+        _mm256_loadu_si256, _mm256_storeu_si256, _mm256_add_epi16, _mm256_sub_epi16;
+    saturating_i16_avx2, "avx2", Saturating<i16>, 16, __m256i,
+        _mm256_loadu_si256, _mm256_storeu_si256, _mm256_adds_epi16, _mm256_subs_epi16;
+}
+
+// The floating-point loads and stores take element pointers; these
+// wrappers give them the vector-pointer signature of the integer ones.
+macro_rules! vector_pointer {
+    ($($load:ident, $store:ident, $intrinsic_load:ident, $intrinsic_store:ident, $t:ty, $v:ty, $feature:literal;)*) => {$(
+        #[target_feature(enable = $feature)]
+        unsafe fn $load(p: *const $v) -> $v {
+            $intrinsic_load(p as *const $t)
+        }
+
+        #[target_feature(enable = $feature)]
+        unsafe fn $store(p: *mut $v, x: $v) {
+            $intrinsic_store(p as *mut $t, x)
+        }
+    )*};
+}
+
+vector_pointer! {
+    _mm256_loadu_pd_v, _mm256_storeu_pd_v, _mm256_loadu_pd, _mm256_storeu_pd, f64, __m256d, "avx2";
+    _mm256_loadu_ps_v, _mm256_storeu_ps_v, _mm256_loadu_ps, _mm256_storeu_ps, f32, __m256, "avx2";
+    _mm512_loadu_pd_v, _mm512_storeu_pd_v, _mm512_loadu_pd, _mm512_storeu_pd, f64, __m512d, "avx512f";
+    _mm512_loadu_ps_v, _mm512_storeu_ps_v, _mm512_loadu_ps, _mm512_storeu_ps, f32, __m512, "avx512f";
+}
+
+fn scalar<T: WalshElement>(a: &mut [T], b: &mut [T], crossed: bool) {
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        (*x, *y) = if crossed {
+            (*x - *y, *x + *y)
+        } else {
+            (*x + *y, *x - *y)
+        };
+    }
+}
+
+// Define the run-time dispatching butterfly for one element type.
+macro_rules! dispatch {
+    ($($name:ident, $t:ty, $avx2:ident, $avx512:ident;)*) => {$(
+        pub(crate) fn $name(a: &mut [$t], b: &mut [$t], crossed: bool) {
+            match level() {
+                // SAFETY: the level was detected on this CPU.
+                Level::Avx512 => unsafe { $avx512(a, b, crossed) },
+                Level::Avx2 => unsafe { $avx2(a, b, crossed) },
+                Level::Scalar => scalar(a, b, crossed),
+            }
+        }
+    )*};
+}
+
+dispatch! {
+    butterfly_f64, f64, f64_avx2, f64_avx512;
+    butterfly_f32, f32, f32_avx2, f32_avx512;
+    butterfly_i32, i32, i32_avx2, i32_avx512;
+}
+
+// Define the run-time dispatching butterfly for a 16-bit element type,
+// whose AVX-512 instructions need the separate BW extension, so only
+// AVX2 is used.
+macro_rules! dispatch_avx2 {
+    ($($name:ident, $t:ty, $avx2:ident;)*) => {$(
+        pub(crate) fn $name(a: &mut [$t], b: &mut [$t], crossed: bool) {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: AVX2 was detected on this CPU.
+                unsafe { $avx2(a, b, crossed) }
+            } else {
+                scalar(a, b, crossed)
+            }
+        }
+    )*};
+}
+
+dispatch_avx2! {
+    butterfly_i16, i16, i16_avx2;
+    butterfly_saturating_i16, Saturating<i16>, saturating_i16_avx2;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check a kernel against the scalar loop on lengths around the
+    // vector widths.
+    fn check<T>(kernel: unsafe fn(&mut [T], &mut [T], bool), make: fn(usize) -> T)
+    where
+        T: WalshElement + PartialEq + std::fmt::Debug,
+    {
+        for length in [0, 1, 7, 8, 15, 16, 17, 33, 100] {
+            let a: Vec<T> = (0..length).map(make).collect();
+            let b: Vec<T> = (0..length).map(|i| make(3 * i + 1)).collect();
+            for crossed in [false, true] {
+                let (mut x, mut y) = (a.clone(), b.clone());
+                // SAFETY: only called for kernels the CPU supports.
+                unsafe { kernel(&mut x, &mut y, crossed) };
+                let (mut p, mut q) = (a.clone(), b.clone());
+                scalar(&mut p, &mut q, crossed);
+                assert_eq!((x, y), (p, q));
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        let float = |i: usize| (i as f64 * 0.731).sin() * 1e3;
+        let single = |i: usize| (i as f32 * 0.731).sin() * 1e3;
+        let integer = |i: usize| (i as i32 * 7919) % 1000 - 500;
+        if is_x86_feature_detected!("avx2") {
+            check(f64_avx2, float);
+            check(f32_avx2, single);
+            check(i32_avx2, integer);
+            check(i16_avx2, |i| (i as i16 * 37) % 1000 - 500);
+            check(saturating_i16_avx2, |i| {
+                Saturating((i as i16).wrapping_mul(7919))
+            });
+        }
+        if is_x86_feature_detected!("avx512f") {
+            check(f64_avx512, float);
+            check(f32_avx512, single);
+            check(i32_avx512, integer);
+        }
+        let (mut a, mut b) = (vec![1.5f64; 9], vec![0.5f64; 9]);
+        butterfly_f64(&mut a, &mut b, true);
+        assert_eq!((a[8], b[8]), (1.0, 2.0));
+        let (mut a, mut b) = (vec![Saturating(i16::MAX); 17], vec![Saturating(1); 17]);
+        butterfly_saturating_i16(&mut a, &mut b, true);
+        assert_eq!(
+            (a[0], b[0]),
+            (Saturating(i16::MAX - 1), Saturating(i16::MAX))
+        );
+        assert_eq!(
+            (a[16], b[16]),
+            (Saturating(i16::MAX - 1), Saturating(i16::MAX))
+        );
+    }
+}